@@ -0,0 +1,63 @@
+//! Preference for terminal emulators working well with screen readers.
+//!
+//! GPU-accelerated terminal emulators (e.g. kitty, Alacritty) expose little or nothing of their contents through
+//! AT-SPI, so screen readers like Orca cannot read them. Users relying on a screen reader can prefer the terminal
+//! emulators known to work with them, with [`prefer_accessible_terminals`] or `PreferAccessible=true` in the
+//! configuration file of this crate.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::{DetectionMethod, ExecutionSyntax, SkipReason, TerminalEmulator, probes::ProbeResult};
+
+/// Terminal emulators known to work well with Orca and AT-SPI, and their execution syntax.
+pub(crate) const ACCESSIBLE_TERMINALS: &[(&str, ExecutionSyntax)] = &[
+    ("gnome-terminal", ExecutionSyntax::DoubleDash),
+    ("ptyxis", ExecutionSyntax::DoubleDash),
+    ("mate-terminal", ExecutionSyntax::SingleE),
+    ("kgx", ExecutionSyntax::DoubleDash),
+];
+
+/// Whether accessible terminal emulators are preferred.
+static PREFER: AtomicBool = AtomicBool::new(false);
+
+/// Prefers the terminal emulators known to work well with screen readers (gnome-terminal, Ptyxis, MATE Terminal and
+/// GNOME Console) during detection.
+///
+/// Disabled by default, unless `PreferAccessible=true` is set in the configuration file of this crate. When enabled,
+/// [`detect`](crate::detect) returns an installed accessible terminal emulator before the ones found in the desktop
+/// settings and hardcoded lists. `TERMINAL_EMULATOR` and the configured default terminal emulator still take
+/// precedence.
+pub fn prefer_accessible_terminals(enabled: bool) {
+    PREFER.store(enabled, Ordering::Relaxed);
+}
+
+/// Returns `true` if accessible terminal emulators are preferred, through [`prefer_accessible_terminals`] or the
+/// configuration file of this crate.
+pub fn accessible_terminals_preferred() -> bool {
+    #[cfg(feature = "config-file")]
+    if crate::config::prefers_accessible(crate::privilege::environment_trusted()) {
+        return true;
+    }
+
+    PREFER.load(Ordering::Relaxed)
+}
+
+/// Detects the first installed terminal emulator known to work well with screen readers.
+///
+/// Unlike the other detection methods, it is only used by [`detect`](crate::detect) if accessible terminal emulators
+/// are preferred, see [`prefer_accessible_terminals`].
+pub fn probe_accessible_terminals() -> Option<TerminalEmulator> {
+    crate::failures::prefer_working(
+        crate::probes::find_list(ACCESSIBLE_TERMINALS, DetectionMethod::Accessibility),
+        |terminal| &terminal.path,
+    )
+}
+
+/// Runs [`probe_accessible_terminals`] if accessible terminal emulators are preferred, returning why it found nothing.
+pub(crate) fn check_if_preferred() -> ProbeResult {
+    if !accessible_terminals_preferred() {
+        return Err(SkipReason::NotConfigured);
+    }
+
+    probe_accessible_terminals().ok_or(SkipReason::NoneInstalled)
+}
@@ -0,0 +1,100 @@
+//! Detection logic provided by other crates.
+//!
+//! A [`DetectionBackend`] registered with [`Detector::with_backend`](crate::Detector::with_backend) runs alongside the
+//! built-in detection methods, so company-internal terminal emulators or unusual setups can be detected without
+//! forking this crate.
+
+use std::{
+    fmt::{self, Debug, Formatter},
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use crate::{DetectionMethod, Environment, SkipReason, TerminalEmulator, probes::ProbeResult};
+
+/// Detection logic registered with [`Detector::with_backend`](crate::Detector::with_backend).
+///
+/// Backends build the terminal emulators they find with [`TerminalEmulator::new`], which are then reported as found by
+/// [`DetectionMethod::Backend`] with the name of the backend.
+pub trait DetectionBackend: Send + Sync {
+    /// Returns the name of the backend (e.g. `acme-terminal`), used to report and disable it.
+    fn name(&self) -> &'static str;
+
+    /// Returns the priority of the backend.
+    ///
+    /// Backends with a positive priority run before the built-in detection methods, the other ones after them. Among
+    /// backends, higher priorities run first, and equal priorities run in the order they were registered. Defaults to
+    /// `0`.
+    fn priority(&self) -> i32 {
+        0
+    }
+
+    /// Detects a terminal emulator, reading the environment from `env`.
+    fn detect(&self, env: &Environment) -> Option<TerminalEmulator>;
+}
+
+#[derive(Clone)]
+/// A backend registered with a [`Detector`](crate::Detector).
+pub(crate) struct Backend(pub(crate) Arc<dyn DetectionBackend>);
+
+impl Backend {
+    /// Returns the detection method reporting this backend.
+    pub(crate) fn method(&self) -> DetectionMethod {
+        DetectionMethod::Backend(self.0.name())
+    }
+
+    /// Runs the backend against the active environment snapshot.
+    pub(crate) fn run(&self) -> ProbeResult {
+        let mut terminal = self
+            .0
+            .detect(&crate::environment::current())
+            .ok_or(SkipReason::NotConfigured)?;
+        terminal.method = self.method();
+
+        Ok(terminal)
+    }
+}
+
+impl Debug for Backend {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Backend")
+            .field("name", &self.0.name())
+            .field("priority", &self.0.priority())
+            .finish()
+    }
+}
+
+impl PartialEq for Backend {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Backend {}
+
+impl Hash for Backend {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.name().hash(state);
+    }
+}
+
+/// Places the entries made by `entry` for `backends` around `builtins`, following their priorities.
+pub(crate) fn around<T>(
+    builtins: Vec<T>,
+    backends: &[Backend],
+    entry: impl Fn(&Backend) -> T,
+) -> Vec<T> {
+    let mut backends: Vec<&Backend> = backends.iter().collect();
+    backends.sort_by_key(|backend| std::cmp::Reverse(backend.0.priority()));
+
+    let (before, after): (Vec<&Backend>, Vec<&Backend>) = backends
+        .into_iter()
+        .partition(|backend| backend.0.priority() > 0);
+
+    before
+        .into_iter()
+        .map(&entry)
+        .chain(builtins)
+        .chain(after.into_iter().map(&entry))
+        .collect()
+}
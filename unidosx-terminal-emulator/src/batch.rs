@@ -0,0 +1,50 @@
+//! Launching many commands at once.
+//!
+//! Spawning many terminal emulators simultaneously overwhelms some compositors, which drop or misplace the windows.
+//! [`launch_batch`] staggers the launches instead, spreading them evenly so no more than a given number of windows
+//! start opening at the same time.
+
+use std::{
+    io, thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{CommandSpec, LaunchBackend, LaunchResult, Launcher, launcher, tagging};
+
+/// The period within which at most `max_concurrent` launches of a batch start.
+const BATCH_WINDOW: Duration = Duration::from_millis(500);
+
+/// Launches each command of `specs` through `launcher`, starting at most `max_concurrent` of them every half second.
+///
+/// The launches are evenly staggered (e.g. one every 125 milliseconds with `max_concurrent` set to 4), so windows open
+/// one after the other instead of all at once. A `max_concurrent` of 0 is treated as 1. The working directory and
+/// environment changes of each spec apply to the spawned terminal emulator, and each launch is tagged and recorded
+/// like [`Launcher::launch`].
+///
+/// Blocks until every command is launched, returning the result of each launch in the order of `specs`. A failed launch
+/// does not stop the batch.
+pub fn launch_batch(
+    launcher: &Launcher,
+    specs: &[CommandSpec],
+    max_concurrent: usize,
+) -> Vec<io::Result<LaunchResult>> {
+    let interval = BATCH_WINDOW / u32::try_from(max_concurrent.max(1)).unwrap_or(u32::MAX);
+    let backend = LaunchBackend::from(launcher);
+    let mut next_start = Instant::now();
+
+    specs
+        .iter()
+        .map(|spec| {
+            thread::sleep(next_start.saturating_duration_since(Instant::now()));
+            next_start = Instant::now() + interval;
+
+            let requested_at = SystemTime::now();
+            let mut invocation = launcher.build_invocation(&spec.program, &spec.args);
+            invocation.cwd = spec.cwd.clone();
+            invocation.env.extend(spec.env.iter().cloned());
+            let graphical = launcher::needs_graphical_session(backend, &invocation.program);
+
+            launcher::spawn(tagging::tag(invocation), backend, graphical, requested_at)
+        })
+        .collect()
+}
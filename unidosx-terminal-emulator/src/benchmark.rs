@@ -0,0 +1,102 @@
+//! Cold-start benchmarking of the installed terminal emulators.
+//!
+//! Startup latency varies a lot between terminal emulators and machines. [`benchmark_candidates`] measures it on the
+//! current machine, and the results are used by [`SelectionStrategy::Fastest`](crate::SelectionStrategy::Fastest) to
+//! pick the fastest terminal emulator.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{Mutex, MutexGuard},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{TerminalEmulator, selection::installed_terminals};
+
+/// How often a benchmarked process is checked for its exit.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// The startup time measured for each terminal emulator during the current process, by executable path.
+static RESULTS: Mutex<BTreeMap<PathBuf, Duration>> = Mutex::new(BTreeMap::new());
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// The cold-start time measured for a terminal emulator.
+pub struct CandidateBenchmark {
+    /// The benchmarked terminal emulator.
+    pub terminal: TerminalEmulator,
+    /// The time it took to run `--version` and exit, or `None` if it failed or did not exit within the timeout.
+    pub startup: Option<Duration>,
+}
+
+/// Locks the measured startup times, recovering them if a thread panicked while holding them.
+fn results() -> MutexGuard<'static, BTreeMap<PathBuf, Duration>> {
+    RESULTS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Measures the cold-start time of every installed terminal emulator, fastest first.
+///
+/// Each terminal emulator is run with `--version`, one at a time, and killed if it does not exit within `timeout`.
+/// The results are remembered during the current process and used by
+/// [`SelectionStrategy::Fastest`](crate::SelectionStrategy::Fastest).
+pub fn benchmark_candidates(timeout: Duration) -> Vec<CandidateBenchmark> {
+    let mut benchmarks: Vec<CandidateBenchmark> = installed_terminals()
+        .into_iter()
+        .map(|terminal| {
+            let startup = measure(&terminal.path, timeout);
+
+            if let Some(startup) = startup {
+                record(&terminal.path, startup);
+            }
+
+            CandidateBenchmark { terminal, startup }
+        })
+        .collect();
+
+    benchmarks.sort_by_key(|benchmark| (benchmark.startup.is_none(), benchmark.startup));
+    benchmarks
+}
+
+/// Returns the startup time measured for the terminal emulator at `path` during the current process, if any.
+pub(crate) fn startup_time(path: &Path) -> Option<Duration> {
+    results().get(path).copied()
+}
+
+/// Returns `true` if no terminal emulator was benchmarked during the current process.
+pub(crate) fn is_empty() -> bool {
+    results().is_empty()
+}
+
+/// Remembers the startup time measured for the terminal emulator at `path`.
+pub(crate) fn record(path: &Path, startup: Duration) {
+    results().insert(path.to_path_buf(), startup);
+}
+
+/// Runs `path --version`, returning the time it took to exit successfully.
+fn measure(path: &Path, timeout: Duration) -> Option<Duration> {
+    let started_at = Instant::now();
+    let mut child = Command::new(path)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+
+    loop {
+        if let Some(status) = child.try_wait().ok()? {
+            return status.success().then(|| started_at.elapsed());
+        }
+
+        if started_at.elapsed() >= timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            return None;
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+}
@@ -0,0 +1,648 @@
+//! An `xdg-terminal-exec` implementation backed by the detection of this crate.
+//!
+//! Usage: `xdg-terminal-exec [--dir=PATH] [-e | --] [command [args...]]`. Without a command, the shell of the user is
+//! opened. Other `--option=value` options of the specification are accepted and ignored.
+//!
+//! The terminal emulator is launched like [`Launcher::launch_with_options`] launches it, so it gets the session
+//! variables, and starts on the host from inside a Flatpak sandbox, and is waited for. Exits with 0 once it exited
+//! successfully, 2 if none is found, 3 if it fails to start or exits unsuccessfully, 4 on platforms without terminal
+//! emulator detection and 64 if the command line is invalid, so scripts can branch on the result. `--quiet` silences the error messages.
+//!
+//! With `--pipe-stdin`, the standard input is piped into the command (e.g. `generate_report | xdg-terminal-exec
+//! --pipe-stdin less`), which would otherwise read from the terminal of its window. The command is then started as a
+//! child and the standard input copied to it until its end.
+//!
+//! For packaging, `xdg-terminal-exec --completions=bash|zsh|fish` prints a shell completion script and
+//! `xdg-terminal-exec --man-page` prints the manual page, in roff.
+
+use std::{
+    env,
+    ffi::OsString,
+    fmt::{self, Display, Formatter},
+    io::{self, Write},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+#[cfg(unix)]
+use std::time::Duration;
+
+#[cfg(unix)]
+use unidosx_terminal_emulator::TerminalEmulator;
+use unidosx_terminal_emulator::{LaunchOptions, Launcher, detect};
+
+/// The exit status when the command line is invalid (`EX_USAGE`).
+const USAGE: u8 = 64;
+
+/// The exit status when no terminal emulator is found.
+const NO_TERMINAL: u8 = 2;
+
+/// The exit status when the terminal emulator fails to start.
+const LAUNCH_FAILED: u8 = 3;
+
+/// The exit status on platforms without terminal emulator detection.
+const UNSUPPORTED_PLATFORM: u8 = 4;
+
+/// How long the terminal emulator may take to start the command reading the piped standard input.
+#[cfg(unix)]
+const PIPE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The shells [`completion`] writes completion scripts for.
+const SHELLS: &[&str] = &["bash", "zsh", "fish"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The value of an option, given after `=`.
+enum Value {
+    /// A directory.
+    Path,
+    /// One of [`SHELLS`].
+    Shell,
+}
+
+impl Value {
+    /// Returns the name of the value in the usage and the manual page.
+    fn name(self) -> &'static str {
+        match self {
+            Self::Path => "path",
+            Self::Shell => "shell",
+        }
+    }
+}
+
+/// The options, with their value if they take one and their description, from which the parser, the completion
+/// scripts and the manual page are derived. Other `--option=value` options are accepted and ignored.
+const OPTIONS: &[(&str, Option<Value>, &str)] = &[
+    (
+        "--dir",
+        Some(Value::Path),
+        "Run the command in this directory",
+    ),
+    (
+        "--pipe-stdin",
+        None,
+        "Pipe the standard input into the command, which reads from the terminal of its window otherwise",
+    ),
+    (
+        "--quiet",
+        None,
+        "Do not print error messages, only report errors through the exit status",
+    ),
+    (
+        "--completions",
+        Some(Value::Shell),
+        "Print the completion script of the shell (bash, zsh or fish) and exit",
+    ),
+    ("--man-page", None, "Print the manual page and exit"),
+];
+
+/// The description of `-e` and `--`, which end the options.
+const END_OF_OPTIONS: &str = "Run the command that follows";
+
+/// Returns the completion script of `shell`, to install as `share/bash-completion/completions/xdg-terminal-exec`,
+/// `share/zsh/site-functions/_xdg-terminal-exec` or `share/fish/vendor_completions.d/xdg-terminal-exec.fish`.
+fn completion(shell: &str) -> Option<String> {
+    match shell {
+        "bash" => Some(bash_completion()),
+        "zsh" => Some(zsh_completion()),
+        "fish" => Some(fish_completion()),
+        _ => None,
+    }
+}
+
+/// Returns the bash completion script.
+fn bash_completion() -> String {
+    let mut values = String::new();
+    let mut words = Vec::new();
+
+    for &(name, value, _) in OPTIONS {
+        match value {
+            Some(value) => {
+                let reply = match value {
+                    Value::Path => String::from("compgen -d -- \"$cur\""),
+                    Value::Shell => format!("compgen -W '{}' -- \"$cur\"", SHELLS.join(" ")),
+                };
+                values.push_str(&format!(
+                    "        {name}) COMPREPLY=($({reply})); return ;;\n"
+                ));
+                words.push(format!("{name}="));
+            }
+            None => words.push(name.to_owned()),
+        }
+    }
+
+    words.extend(["-e".to_owned(), "--".to_owned()]);
+
+    format!(
+        r#"_xdg_terminal_exec() {{
+    local cur=${{COMP_WORDS[COMP_CWORD]}} prev=${{COMP_WORDS[COMP_CWORD-1]}} option= i
+
+    # COMP_WORDBREAKS splits --dir=PATH into --dir, = and PATH.
+    if [[ $cur == = ]]; then
+        option=$prev cur=
+    elif [[ $prev == = ]]; then
+        option=${{COMP_WORDS[COMP_CWORD-2]}}
+    fi
+
+    case $option in
+{values}    esac
+
+    for ((i = 1; i < COMP_CWORD; i++)); do
+        case ${{COMP_WORDS[i]}} in
+            -e | --) ((i == COMP_CWORD - 1)) && COMPREPLY=($(compgen -c -- "$cur")); return ;;
+            --* | =) ;;
+            *) [[ ${{COMP_WORDS[i-1]}} == = ]] || return ;;
+        esac
+    done
+
+    case $cur in
+        -*) COMPREPLY=($(compgen -W '{words}' -- "$cur")); [[ ${{COMPREPLY[*]}} == *= ]] && compopt -o nospace ;;
+        *) COMPREPLY=($(compgen -c -- "$cur")) ;;
+    esac
+}}
+complete -o default -F _xdg_terminal_exec xdg-terminal-exec
+"#,
+        words = words.join(" ")
+    )
+}
+
+/// Returns the zsh completion script.
+fn zsh_completion() -> String {
+    let mut script = String::from("#compdef xdg-terminal-exec\n\n_arguments -S \\\n");
+
+    for &(name, value, help) in OPTIONS {
+        let help = lowercase_first(help);
+
+        match value {
+            Some(value) => {
+                let action = match value {
+                    Value::Path => String::from("_files -/"),
+                    Value::Shell => format!("({})", SHELLS.join(" ")),
+                };
+                script.push_str(&format!(
+                    "    '{name}=-[{help}]:{}:{action}' \\\n",
+                    value.name()
+                ));
+            }
+            None => script.push_str(&format!("    '{name}[{help}]' \\\n")),
+        }
+    }
+
+    script.push_str(&format!(
+        "    '-e[{}]' \\\n    '(-):command: _command_names -e' \\\n    '*::arguments: _normal'\n",
+        lowercase_first(END_OF_OPTIONS)
+    ));
+    script
+}
+
+/// Returns the fish completion script.
+fn fish_completion() -> String {
+    let mut script = String::new();
+
+    for &(name, value, help) in OPTIONS {
+        let values = match value {
+            Some(Value::Path) => String::from(" -r -a '(__fish_complete_directories)'"),
+            Some(Value::Shell) => format!(" -x -a '{}'", SHELLS.join(" ")),
+            None => String::new(),
+        };
+        script.push_str(&format!(
+            "complete -c xdg-terminal-exec -l {}{values} -d '{help}'\n",
+            name.trim_start_matches('-')
+        ));
+    }
+
+    script.push_str(&format!(
+        "complete -c xdg-terminal-exec -s e -d '{END_OF_OPTIONS}'\n\
+         complete -c xdg-terminal-exec -x -a '(__fish_complete_subcommand)'\n"
+    ));
+    script
+}
+
+/// Returns `text` with its first letter in lowercase, as zsh completions describe options.
+fn lowercase_first(text: &str) -> String {
+    let mut chars = text.chars();
+
+    chars
+        .next()
+        .map(|first| first.to_lowercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+/// Escapes the dashes of `text` for roff.
+fn roff(text: &str) -> String {
+    text.replace('-', r"\-")
+}
+
+/// Returns the manual page, in roff.
+fn man_page() -> String {
+    let mut synopsis = String::new();
+    let mut options = String::new();
+
+    for &(name, value, help) in OPTIONS {
+        match value {
+            Some(value) => {
+                let value = value.name();
+                synopsis.push_str(&format!("[\\fB{}=\\fI{value}\\fR]\n", roff(name)));
+                options.push_str(&format!(".TP\n.BI {}= {value}\n{help}.\n", roff(name)));
+            }
+            None => {
+                synopsis.push_str(&format!("[\\fB{}\\fR]\n", roff(name)));
+                options.push_str(&format!(".TP\n.B {}\n{help}.\n", roff(name)));
+            }
+        }
+    }
+
+    format!(
+        r#".TH XDG-TERMINAL-EXEC 1 "" "unidosx-terminal-emulator {version}"
+.SH NAME
+xdg-terminal-exec \- run a command in the default terminal emulator
+.SH SYNOPSIS
+.B xdg-terminal-exec
+{synopsis}[\fB\-e\fR | \fB\-\-\fR]
+[\fIcommand\fR [\fIargs\fR...]]
+.SH DESCRIPTION
+Opens a new window of the default terminal emulator running \fIcommand\fR with \fIargs\fR, or the shell of the user
+(\fBSHELL\fR) without a command.
+.PP
+The default terminal emulator is detected from the environment, the configuration of the desktop environment and the
+installed terminal emulators. Other \fB\-\-option=value\fR options of the xdg-terminal-exec specification are accepted
+and ignored.
+.SH OPTIONS
+{options}.TP
+.BR \-e ", " \-\-
+{END_OF_OPTIONS}, ending the options.
+.SH ENVIRONMENT
+.TP
+.B TERMINAL_EMULATOR
+The terminal emulator to use, taking precedence over the detection.
+.SH EXIT STATUS
+.TP
+.B 0
+The terminal emulator ran and exited successfully.
+.TP
+.B 2
+No terminal emulator was found.
+.TP
+.B 3
+The terminal emulator failed to start or exited unsuccessfully.
+.TP
+.B 4
+Terminal emulators cannot be detected on this platform.
+.TP
+.B 64
+The command line is invalid (e.g. an unsupported shell for \fB\-\-completions\fR).
+"#,
+        version = env!("CARGO_PKG_VERSION")
+    )
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// What the command line asks for.
+enum Action {
+    /// Prints a completion script or the manual page and exits.
+    Print(String),
+    /// Runs `command` in a new terminal window.
+    Launch {
+        /// The working directory of the command.
+        dir: Option<PathBuf>,
+        /// Whether the standard input is piped into the command.
+        pipe_stdin: bool,
+        /// The command and its arguments, the shell of the user if empty.
+        command: Vec<OsString>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The parsed command line.
+struct Cli {
+    /// Whether the error messages are silenced.
+    quiet: bool,
+    /// What to do, or why the command line is invalid.
+    action: Result<Action, Failure>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Why `xdg-terminal-exec` failed, telling its exit status.
+enum Failure {
+    /// The command line is invalid.
+    Usage(String),
+    /// No terminal emulator was found.
+    NoTerminal,
+    /// The terminal emulator failed to start or exited unsuccessfully.
+    LaunchFailed(String),
+    /// The platform does not support what was asked.
+    UnsupportedPlatform(String),
+}
+
+impl Failure {
+    /// Returns the exit status reporting the failure.
+    fn exit_status(&self) -> u8 {
+        match self {
+            Self::Usage(_) => USAGE,
+            Self::NoTerminal => NO_TERMINAL,
+            Self::LaunchFailed(_) => LAUNCH_FAILED,
+            Self::UnsupportedPlatform(_) => UNSUPPORTED_PLATFORM,
+        }
+    }
+}
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoTerminal => write!(f, "no terminal emulator found"),
+            Self::Usage(message)
+            | Self::LaunchFailed(message)
+            | Self::UnsupportedPlatform(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+/// Parses the command line arguments, without the program name.
+///
+/// Options are read until `-e`, `--` or the first argument that is not an option. `--quiet` applies wherever it
+/// appears among them.
+fn parse<I: IntoIterator<Item = OsString>>(args: I) -> Cli {
+    let mut args = args.into_iter().peekable();
+    let mut dir = None;
+    let mut quiet = false;
+    let mut pipe_stdin = false;
+    let mut print = None;
+
+    while let Some(arg) = args.peek().and_then(|arg| arg.to_str()) {
+        if arg == "-e" || arg == "--" {
+            args.next();
+            break;
+        }
+
+        if !arg.starts_with("--") {
+            break;
+        }
+
+        let (name, value) = match arg.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (arg, None),
+        };
+
+        // Options not listed in `OPTIONS`, or without their value, are ignored.
+        let known = OPTIONS
+            .iter()
+            .any(|&(option, takes, _)| option == name && takes.is_some() == value.is_some());
+
+        match (name, value) {
+            _ if !known => {}
+            ("--dir", Some(path)) => dir = Some(PathBuf::from(path)),
+            ("--pipe-stdin", _) => pipe_stdin = true,
+            ("--quiet", _) => quiet = true,
+            ("--completions", Some(shell)) => {
+                print.get_or_insert(completion(shell).ok_or_else(|| {
+                    Failure::Usage(format!(
+                        "unsupported shell {shell}, expected bash, zsh or fish"
+                    ))
+                }));
+            }
+            ("--man-page", _) => {
+                print.get_or_insert(Ok(man_page()));
+            }
+            _ => unreachable!("{name} is listed in OPTIONS but not parsed"),
+        }
+
+        args.next();
+    }
+
+    let action = match print {
+        Some(text) => text.map(Action::Print),
+        None => Ok(Action::Launch {
+            dir,
+            pipe_stdin,
+            command: args.collect(),
+        }),
+    };
+
+    Cli { quiet, action }
+}
+
+fn main() -> ExitCode {
+    let cli = parse(env::args_os().skip(1));
+    let result = cli.action.and_then(run);
+
+    ExitCode::from(exit_status(result, cli.quiet, &mut io::stderr()))
+}
+
+/// Returns the exit status of `result`, writing the error message to `stderr` unless `quiet`.
+fn exit_status(result: Result<(), Failure>, quiet: bool, stderr: &mut dyn Write) -> u8 {
+    match result {
+        Ok(()) => 0,
+        Err(failure) => {
+            if !quiet {
+                let _ = writeln!(stderr, "xdg-terminal-exec: {failure}");
+            }
+
+            failure.exit_status()
+        }
+    }
+}
+
+/// Performs `action`.
+fn run(action: Action) -> Result<(), Failure> {
+    let (dir, pipe_stdin, mut command) = match action {
+        Action::Print(text) => {
+            print!("{text}");
+            return Ok(());
+        }
+        Action::Launch {
+            dir,
+            pipe_stdin,
+            command,
+        } => (dir, pipe_stdin, command),
+    };
+
+    if command.is_empty() {
+        command.push(env::var_os("SHELL").unwrap_or_else(|| "sh".into()));
+    }
+
+    if cfg!(not(any(unix, windows))) {
+        return Err(Failure::UnsupportedPlatform(String::from(
+            "terminal emulators cannot be detected on this platform",
+        )));
+    }
+
+    #[cfg(windows)]
+    let terminal = Some(detect());
+    #[cfg(not(windows))]
+    let terminal = detect();
+
+    let terminal = terminal.ok_or(Failure::NoTerminal)?;
+
+    // Server-based terminal emulators (e.g. gnome-terminal) ignore their own working directory, so it is also passed
+    // through their flag.
+    let options = match dir {
+        Some(dir) => LaunchOptions::new().current_dir(dir),
+        None => LaunchOptions::new(),
+    };
+
+    if pipe_stdin {
+        #[cfg(unix)]
+        return launch_piped(&terminal, &command, options);
+
+        #[cfg(not(unix))]
+        return Err(Failure::UnsupportedPlatform(String::from(
+            "the standard input cannot be piped on this platform",
+        )));
+    }
+
+    // Launched like any other launch of the crate: the session variables are passed on, a missing graphical session
+    // is reported, and the terminal emulator is started on the host from inside a Flatpak sandbox.
+    let status = Launcher::new(&terminal)
+        .launch_with_options(&command[0], &command[1..], &options)
+        .and_then(|mut launched| launched.child().wait())
+        .map_err(|error| {
+            Failure::LaunchFailed(format!("{}: {error}", terminal.path().display()))
+        })?;
+
+    if !status.success() {
+        return Err(Failure::LaunchFailed(format!(
+            "{} exited with {status}",
+            terminal.path().display()
+        )));
+    }
+
+    Ok(())
+}
+
+/// Launches `command` in `terminal` with `options`, and copies the standard input to it.
+#[cfg(unix)]
+fn launch_piped(
+    terminal: &TerminalEmulator,
+    command: &[OsString],
+    options: LaunchOptions,
+) -> Result<(), Failure> {
+    let options = options.pipe_stdin(true);
+    let launched = Launcher::new(terminal)
+        .launch_with_options(&command[0], &command[1..], &options)
+        .map_err(|error| {
+            Failure::LaunchFailed(format!("{}: {error}", terminal.path().display()))
+        })?;
+
+    match launched
+        .open_stdin(Some(PIPE_TIMEOUT))
+        .and_then(|mut stdin| io::copy(&mut io::stdin().lock(), &mut stdin))
+    {
+        Ok(_) => Ok(()),
+        // The command exited before reading everything (e.g. `less` quit early).
+        Err(error) if error.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        Err(error) => Err(Failure::LaunchFailed(format!(
+            "{}: {error}",
+            command[0].to_string_lossy()
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses `args` given as strings.
+    fn parse_args(args: &[&str]) -> Cli {
+        parse(args.iter().map(OsString::from))
+    }
+
+    #[test]
+    fn options_are_parsed_until_the_command() {
+        assert_eq!(
+            parse_args(&["--dir=/tmp", "--pipe-stdin", "--unknown=1", "-e", "--quiet"]),
+            Cli {
+                quiet: false,
+                action: Ok(Action::Launch {
+                    dir: Some(PathBuf::from("/tmp")),
+                    pipe_stdin: true,
+                    command: vec![OsString::from("--quiet")],
+                }),
+            }
+        );
+        assert_eq!(
+            parse_args(&["--man-page"]).action,
+            Ok(Action::Print(man_page()))
+        );
+        assert_eq!(
+            parse_args(&["--completions=fish"]).action,
+            Ok(Action::Print(fish_completion()))
+        );
+    }
+
+    #[test]
+    fn every_option_is_parsed_completed_and_documented() {
+        let (bash, zsh, fish, man_page) = (
+            bash_completion(),
+            zsh_completion(),
+            fish_completion(),
+            man_page(),
+        );
+
+        for &(name, value, _) in OPTIONS {
+            let (arg, word) = match value {
+                Some(_) => (format!("{name}=bash"), format!("{name}=")),
+                None => (name.to_owned(), name.to_owned()),
+            };
+            assert_ne!(parse_args(&[&arg]), parse_args(&[]), "{name} is not parsed");
+
+            assert!(
+                bash.split(['\'', ' ']).any(|bash_word| bash_word == word),
+                "{name}"
+            );
+            assert!(zsh.contains(&format!("'{name}")), "{name}");
+            assert!(
+                fish.contains(&format!("-l {} ", name.trim_start_matches('-'))),
+                "{name}"
+            );
+            assert!(man_page.contains(&roff(name)), "{name}");
+        }
+
+        for shell in SHELLS {
+            assert!(completion(shell).is_some());
+        }
+    }
+
+    #[test]
+    fn failures_have_documented_exit_statuses() {
+        let cli = parse_args(&["--completions=tcsh", "--quiet"]);
+        assert!(cli.quiet);
+        assert!(matches!(cli.action, Err(Failure::Usage(_))));
+
+        let mut stderr = Vec::new();
+        assert_eq!(exit_status(cli.action.map(drop), true, &mut stderr), USAGE);
+        assert!(stderr.is_empty());
+
+        assert_eq!(exit_status(Ok(()), false, &mut stderr), 0);
+        assert_eq!(
+            exit_status(Err(Failure::NoTerminal), false, &mut stderr),
+            NO_TERMINAL
+        );
+        assert_eq!(
+            String::from_utf8(stderr).unwrap(),
+            "xdg-terminal-exec: no terminal emulator found\n"
+        );
+        assert_eq!(Failure::LaunchFailed(String::new()).exit_status(), 3);
+        assert_eq!(Failure::UnsupportedPlatform(String::new()).exit_status(), 4);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn launches_report_their_outcome() {
+        use unidosx_terminal_emulator::{Environment, RootFilesystem, with_filesystem};
+
+        let launch = |terminal: &str| {
+            Environment::default()
+                .var("PATH", "/usr/bin:/bin")
+                .var("DISPLAY", ":0")
+                .var("TERMINAL_EMULATOR", terminal)
+                .scope(|| run(parse_args(&["sh"]).action.unwrap()))
+        };
+
+        assert_eq!(launch("true"), Ok(()));
+        assert!(matches!(launch("false"), Err(Failure::LaunchFailed(_))));
+
+        let nothing = with_filesystem(RootFilesystem::new("/nonexistent"), || {
+            Environment::default().scope(|| run(parse_args(&["sh"]).action.unwrap()))
+        });
+        assert_eq!(nothing, Err(Failure::NoTerminal));
+    }
+}
@@ -0,0 +1,332 @@
+//! Persistent cache of the detected terminal emulator.
+//!
+//! Detection runs external programs (e.g. `gsettings`) and reads many settings files, which is noticeable for
+//! short-lived command line tools. [`detect_cached`] stores the result in `unidosx/terminal.json` in the XDG cache
+//! directory, with a fingerprint of the environment variables and settings files it depends on, and reuses it until
+//! one of them changes.
+//!
+//! With the `watch` feature, the result is also kept in memory and the settings directories are watched, so
+//! long-running applications neither read the cache file again nor miss a settings change.
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    io,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+    time::UNIX_EPOCH,
+};
+
+use crate::{
+    DetectionMethod, ExecutionSyntax, TerminalEmulator, detect, environment, has_launch_failed,
+    json, privilege::environment_trusted, probes::lookup_known,
+};
+
+/// The number of detections answered from the cache, in memory or on disk, during the current process.
+static CACHE_HITS: AtomicU64 = AtomicU64::new(0);
+
+/// The version of the cache file format, older cache files are ignored.
+const CACHE_VERSION: u64 = 2;
+
+/// The environment variables the detection methods depend on.
+const FINGERPRINT_VARS: &[&str] = &[
+    "TERMINAL_EMULATOR",
+    "PATH",
+    "HOME",
+    "XDG_CURRENT_DESKTOP",
+    "XDG_CONFIG_HOME",
+    "XDG_CONFIG_DIRS",
+    "XDG_DATA_HOME",
+    "XDG_DATA_DIRS",
+    "DISPLAY",
+    "WAYLAND_DISPLAY",
+];
+
+/// The settings files read by the detection methods, relative to the XDG configuration directories.
+const SETTINGS_FILES: &[&str] = &[
+    "unidosx/terminal.conf",
+    "kdeglobals",
+    "xfce4/helpers.rc",
+    "lxqt/session.conf",
+    "xdg-terminals.list",
+    "dconf/user",
+];
+
+/// The names of the terminal emulators detected outside of the hardcoded lists, to restore them from the cache file.
+const LAUNCHER_NAMES: &[&str] = &["xdg-terminal-exec", "x-terminal-emulator", "Terminal"];
+
+/// Every detection method, to parse them back from the cache file.
+const METHODS: &[DetectionMethod] = &[
+    DetectionMethod::Windows,
+    DetectionMethod::EnvironmentVariable,
+    DetectionMethod::ConfigFile,
+    DetectionMethod::Accessibility,
+    DetectionMethod::TerminalApp,
+    DetectionMethod::XdgTerminalExec,
+    DetectionMethod::XTerminalEmulator,
+    DetectionMethod::GnomeSettings,
+    DetectionMethod::KdeSettings,
+    DetectionMethod::XfceSettings,
+    DetectionMethod::LxqtSettings,
+    DetectionMethod::HardcodedDesktopEnv,
+    DetectionMethod::HardcodedModern,
+    DetectionMethod::HardcodedTraditional,
+    DetectionMethod::HardcodedExtended,
+    DetectionMethod::ControllingTty,
+    DetectionMethod::HostEnvironment,
+    DetectionMethod::ProcessAncestry,
+];
+
+/// Returns the path of the cache file (`$XDG_CACHE_HOME/unidosx/terminal.json`), or `None` if neither
+/// `XDG_CACHE_HOME` nor `HOME` is set.
+pub fn detection_cache_path() -> Option<PathBuf> {
+    crate::xdg::cache_home().map(|cache| cache.join("unidosx/terminal.json"))
+}
+
+/// Detects the default terminal emulator like [`detect`], reusing the result of a previous run if the environment and
+/// settings files it depends on did not change.
+///
+/// The cache is invalidated when one of the relevant environment variables (e.g. `TERMINAL_EMULATOR`, `PATH`,
+/// `XDG_CURRENT_DESKTOP`) or the modification time of a settings file (e.g. `kdeglobals`, `xdg-terminals.list`, the
+/// dconf database) changes, or when the cached terminal emulator is no longer installed or failed to launch.
+///
+/// With the `watch` feature, the result is also kept in memory until a settings file changes, detected through inotify
+/// on Linux and by comparing modification times on other platforms.
+///
+/// The cache is not used in a privileged context, see [`is_privileged_context`](crate::is_privileged_context).
+pub fn detect_cached() -> Option<TerminalEmulator> {
+    environment::snapshot(|| {
+        if !environment_trusted() {
+            return detect();
+        }
+
+        #[cfg(feature = "watch")]
+        let key = environment_key();
+        #[cfg(feature = "watch")]
+        let generation = crate::watch::generation();
+        #[cfg(feature = "watch")]
+        if let Some(terminal) = crate::watch::lookup(key, fingerprint)
+            .filter(|terminal| terminal.path.exists() && !has_launch_failed(&terminal.path))
+        {
+            CACHE_HITS.fetch_add(1, Ordering::Relaxed);
+            return Some(terminal);
+        }
+        #[cfg(feature = "watch")]
+        crate::watch::watch(&settings_dirs());
+
+        let path = detection_cache_path();
+        let fingerprint = fingerprint();
+
+        if let Some(terminal) = path
+            .as_deref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|contents| parse_cache(&contents, fingerprint))
+            .filter(|terminal| terminal.path.exists() && !has_launch_failed(&terminal.path))
+        {
+            #[cfg(feature = "watch")]
+            crate::watch::store(key, generation, fingerprint, &terminal);
+
+            CACHE_HITS.fetch_add(1, Ordering::Relaxed);
+            return Some(terminal);
+        }
+
+        let terminal = detect()?;
+
+        if let Some(path) = path {
+            let _ = write_cache(&path, &terminal, fingerprint);
+        }
+
+        #[cfg(feature = "watch")]
+        crate::watch::store(key, generation, fingerprint, &terminal);
+
+        Some(terminal)
+    })
+}
+
+/// Returns the number of detections [`detect_cached`] answered from the cache during the current process.
+#[cfg(all(unix, feature = "daemon"))]
+pub(crate) fn cache_hits() -> u64 {
+    CACHE_HITS.load(Ordering::Relaxed)
+}
+
+/// Removes the cache file.
+pub fn clear_detection_cache() -> io::Result<()> {
+    match detection_cache_path().map(fs::remove_file) {
+        Some(Err(error)) if error.kind() != io::ErrorKind::NotFound => Err(error),
+        _ => Ok(()),
+    }
+}
+
+/// Computes the fingerprint of the environment variables and preferences the detection methods depend on.
+pub(crate) fn environment_key() -> u64 {
+    let mut hasher = DefaultHasher::new();
+
+    for var in FINGERPRINT_VARS {
+        environment::var_os(var).hash(&mut hasher);
+    }
+
+    crate::accessible_terminals_preferred().hash(&mut hasher);
+    crate::unknown_terminals_probed().hash(&mut hasher);
+
+    hasher.finish()
+}
+
+/// Returns the directories holding the settings files the detection methods depend on.
+#[cfg(feature = "watch")]
+pub(crate) fn settings_dirs() -> Vec<PathBuf> {
+    let config_dirs = crate::xdg::config_dirs();
+    let mut dirs = config_dirs.clone();
+
+    for dir in &config_dirs {
+        dirs.extend(
+            SETTINGS_FILES
+                .iter()
+                .filter_map(|file| Path::new(file).parent())
+                .filter(|parent| !parent.as_os_str().is_empty())
+                .map(|parent| dir.join(parent)),
+        );
+    }
+
+    dirs.extend(
+        crate::xdg::data_dirs()
+            .into_iter()
+            .map(|dir| dir.join("xdg-terminal-exec")),
+    );
+    #[cfg(feature = "config-file")]
+    dirs.extend(
+        Path::new(crate::config::SYSTEM_CONFIG_PATH)
+            .parent()
+            .map(Path::to_path_buf),
+    );
+    dirs
+}
+
+/// Computes the fingerprint of the environment variables and settings files the detection methods depend on.
+pub(crate) fn fingerprint() -> u64 {
+    let mut hasher = DefaultHasher::new();
+
+    environment_key().hash(&mut hasher);
+
+    let desktops = environment::var("XDG_CURRENT_DESKTOP").unwrap_or_default();
+    let desktop_lists: Vec<String> = desktops
+        .split(':')
+        .filter(|desktop| !desktop.is_empty())
+        .map(|desktop| format!("{}-xdg-terminals.list", desktop.to_ascii_lowercase()))
+        .collect();
+
+    for dir in crate::xdg::config_dirs() {
+        // The directory itself changes when a settings file is created or removed.
+        modified_millis(&dir).hash(&mut hasher);
+
+        for file in SETTINGS_FILES
+            .iter()
+            .copied()
+            .chain(desktop_lists.iter().map(String::as_str))
+        {
+            modified_millis(&dir.join(file)).hash(&mut hasher);
+        }
+    }
+
+    for dir in crate::xdg::data_dirs() {
+        modified_millis(&dir.join("xdg-terminal-exec")).hash(&mut hasher);
+    }
+
+    #[cfg(feature = "config-file")]
+    modified_millis(Path::new(crate::config::SYSTEM_CONFIG_PATH)).hash(&mut hasher);
+
+    hasher.finish()
+}
+
+/// Returns the modification time of `path` in milliseconds since the UNIX epoch, or `None` if it does not exist.
+fn modified_millis(path: &Path) -> Option<u128> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_millis())
+}
+
+/// Writes the cache file, replacing it atomically.
+fn write_cache(path: &Path, terminal: &TerminalEmulator, fingerprint: u64) -> io::Result<()> {
+    let contents = to_json(terminal, fingerprint)
+        .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidData))?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let temporary = path.with_extension(format!("json.{}", std::process::id()));
+
+    fs::write(&temporary, contents + "\n")?;
+    fs::rename(&temporary, path)
+}
+
+/// Serializes `terminal` with `fingerprint` as a single-line JSON object, read back by [`parse_cache`].
+///
+/// Returns `None` if the path of the terminal emulator is not valid Unicode.
+pub(crate) fn to_json(terminal: &TerminalEmulator, fingerprint: u64) -> Option<String> {
+    Some(format!(
+        "{{\"version\": {CACHE_VERSION}, \"fingerprint\": {fingerprint}, \"path\": {}, \"command_line\": {}, \
+         \"execution_syntax\": {}, \"method\": {}, \"raw\": {}}}",
+        json::string(terminal.path.to_str()?),
+        json::string(&terminal.command_line),
+        json::string(&terminal.execution_syntax.id()),
+        json::string(&terminal.method.to_string()),
+        terminal
+            .raw
+            .as_deref()
+            .map_or(String::from("null"), json::string),
+    ))
+}
+
+/// Parses the cache file, returning `None` if it is invalid or its fingerprint differs from `fingerprint`.
+pub(crate) fn parse_cache(contents: &str, fingerprint: u64) -> Option<TerminalEmulator> {
+    from_fields(&json::parse_flat_object(contents)?, fingerprint)
+}
+
+/// Reads a terminal emulator from the fields of an object written by [`to_json`], returning `None` if they are invalid
+/// or the fingerprint differs from `fingerprint`.
+pub(crate) fn from_fields(
+    fields: &[(String, json::Value)],
+    fingerprint: u64,
+) -> Option<TerminalEmulator> {
+    let field = |name: &str| {
+        fields
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value)
+    };
+    let string = |name: &str| match field(name)? {
+        json::Value::String(value) => Some(value.as_str()),
+        _ => None,
+    };
+
+    if field("version")? != &json::Value::Number(CACHE_VERSION)
+        || field("fingerprint")? != &json::Value::Number(fingerprint)
+    {
+        return None;
+    }
+
+    let path = PathBuf::from(string("path")?);
+    let execution_syntax = ExecutionSyntax::from_id(string("execution_syntax")?)?;
+    let method = string("method")?;
+    let method = *METHODS.iter().find(|known| known.to_string() == method)?;
+    let command_line = string("command_line")?;
+    let command_line = lookup_known(command_line)
+        .map(|(name, _)| name)
+        .or_else(|| {
+            LAUNCHER_NAMES
+                .iter()
+                .copied()
+                .find(|&name| name == command_line)
+        })
+        .unwrap_or("");
+    let raw = string("raw").map(str::to_owned);
+
+    Some(TerminalEmulator {
+        command_line: command_line.into(),
+        execution_syntax,
+        path,
+        method,
+        raw,
+    })
+}
@@ -0,0 +1,153 @@
+//! Cancellation of the commands run by the detection methods.
+//!
+//! Some detection methods run commands (e.g. `gsettings`), which can hang when the session bus does not answer. A
+//! [`CancellationToken`] installed with [`CancellationToken::scope`] bounds them: once it is cancelled, possibly from
+//! another thread, or its deadline has passed, the running commands are killed and reaped instead of being left
+//! behind, and the detection methods running them are skipped.
+
+#[cfg(any(test, feature = "gnome-settings"))]
+use std::process::{Command, Output, Stdio};
+use std::{
+    cell::RefCell,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+#[cfg(any(test, feature = "gnome-settings"))]
+use crate::SkipReason;
+
+#[cfg(any(test, feature = "gnome-settings"))]
+/// How often a running command is checked for completion and cancellation.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+thread_local! {
+    /// The token cancelling the commands run on the current thread, if any.
+    static ACTIVE: RefCell<Option<CancellationToken>> = const { RefCell::new(None) };
+}
+
+#[derive(Debug, Clone, Default)]
+/// Cancels the commands run by the detection methods, see [`CancellationToken::scope`].
+///
+/// Clones share the same cancellation, so a token can be cancelled from another thread than the one detecting.
+pub struct CancellationToken {
+    /// Whether [`CancellationToken::cancel`] was called on the token or one of its clones.
+    cancelled: Arc<AtomicBool>,
+    /// The instant after which the commands are cancelled, if any.
+    deadline: Option<Instant>,
+}
+
+impl CancellationToken {
+    /// Creates a token without a deadline, only cancelled by [`CancellationToken::cancel`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the instant after which the commands are cancelled.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Sets the deadline to `timeout` from now (e.g. the time budget of a GUI frame).
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.with_deadline(Instant::now() + timeout)
+    }
+
+    /// Cancels the commands running in the scopes of this token and its clones, on every thread.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if [`CancellationToken::cancel`] was called on this token or one of its clones.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Returns `true` if the deadline of this token has passed.
+    pub fn is_expired(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// Runs `f` with the commands run by the detection methods on the current thread cancelled by this token.
+    ///
+    /// Detection methods whose command was cancelled are skipped with `SkipReason::Cancelled`, or
+    /// `SkipReason::Timeout` once the deadline has passed. Scopes can be nested, the innermost token is used.
+    pub fn scope<R, F: FnOnce() -> R>(&self, f: F) -> R {
+        /// Restores the previous token, even if `f` panics.
+        struct Restore(Option<CancellationToken>);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                ACTIVE.with(|active| *active.borrow_mut() = self.0.take());
+            }
+        }
+
+        let previous = ACTIVE.with(|active| active.replace(Some(self.clone())));
+        let _restore = Restore(previous);
+
+        f()
+    }
+}
+
+#[cfg(any(test, feature = "gnome-settings"))]
+/// Returns why the commands on the current thread must not run anymore, if they were cancelled.
+pub(crate) fn check() -> Result<(), SkipReason> {
+    ACTIVE.with(|active| match &*active.borrow() {
+        Some(token) if token.is_cancelled() => Err(SkipReason::Cancelled),
+        Some(token) if token.is_expired() => Err(SkipReason::Timeout),
+        _ => Ok(()),
+    })
+}
+
+#[cfg(any(test, feature = "gnome-settings"))]
+/// Runs `command` like [`Command::output`], killing and reaping it if the token of the current thread is cancelled.
+///
+/// The output is only read once the command exits, which is fine for the short answers of the settings commands.
+pub(crate) fn output(command: &mut Command) -> Result<Output, SkipReason> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let spawn_error = |error: std::io::Error| match error.kind() {
+        std::io::ErrorKind::NotFound => SkipReason::BinaryNotFound {
+            name: program.clone(),
+        },
+        _ => SkipReason::CommandFailed {
+            detail: error.to_string(),
+        },
+    };
+
+    check()?;
+
+    if ACTIVE.with(|active| active.borrow().is_none()) {
+        return command.output().map_err(spawn_error);
+    }
+
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(spawn_error)?;
+
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => return child.wait_with_output().map_err(spawn_error),
+            Ok(None) => {}
+            Err(error) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(spawn_error(error));
+            }
+        }
+
+        if let Err(reason) = check() {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(reason);
+        }
+
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
@@ -0,0 +1,149 @@
+//! What known terminal emulators support, so callers can adapt their launch strategy.
+//!
+//! The launch options a terminal emulator takes as flags come from the flag tables of [`LaunchOptions`]; the other
+//! capabilities from the tables below. Terminal emulators missing from a table are assumed not to support it.
+
+use std::path::Path;
+
+use crate::{lookup::executable_name, options};
+
+#[cfg(doc)]
+use crate::LaunchOptions;
+
+/// Terminal emulators opening tabs in their windows.
+const TABBED_TERMINALS: &[&str] = &[
+    "gnome-terminal",
+    "kgx",
+    "ptyxis",
+    "konsole",
+    "xfce4-terminal",
+    "mate-terminal",
+    "lxterminal",
+    "qterminal",
+    "deepin-terminal",
+    "io.elementary.terminal",
+    "kitty",
+    "wezterm",
+    "ghostty",
+    "rio",
+    "hyper",
+    "tabby",
+    "blackbox",
+    "warp",
+    "extraterm",
+    "terminator",
+    "tilix",
+    "guake",
+    "yakuake",
+    "tilda",
+    "terminology",
+    "sakura",
+    "roxterm",
+    "Terminal",
+    "wt",
+    "WindowsTerminal",
+];
+
+/// Terminal emulators talking to Wayland compositors directly, without XWayland.
+const WAYLAND_NATIVE_TERMINALS: &[&str] = &[
+    "gnome-terminal",
+    "kgx",
+    "ptyxis",
+    "konsole",
+    "xfce4-terminal",
+    "mate-terminal",
+    "lxterminal",
+    "qterminal",
+    "deepin-terminal",
+    "io.elementary.terminal",
+    "kitty",
+    "alacritty",
+    "wezterm",
+    "ghostty",
+    "foot",
+    "rio",
+    "contour",
+    "blackbox",
+    "terminator",
+    "tilix",
+    "yakuake",
+    "terminology",
+    "cool-retro-term",
+    "sakura",
+    "roxterm",
+];
+
+/// Known terminal emulators approximating 24-bit colors with a palette, or ignoring them.
+const PALETTE_TERMINALS: &[&str] = &[
+    "xterm", "rxvt", "urxvt", "aterm", "eterm", "mrxvt", "fbterm", "kmscon", "linux", "Terminal",
+];
+
+/// Terminal emulators setting the clipboard on the OSC 52 escape sequence by default.
+const OSC52_TERMINALS: &[&str] = &[
+    "kitty",
+    "alacritty",
+    "wezterm",
+    "ghostty",
+    "foot",
+    "rio",
+    "contour",
+    "st",
+    "mlterm",
+    "tabby",
+    "iTerm2",
+    "wt",
+    "WindowsTerminal",
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+/// What a terminal emulator supports, see [`TerminalEmulator::capabilities`](crate::TerminalEmulator::capabilities).
+///
+/// The `supports_*` fields tell whether a [`LaunchOptions`] is passed as a flag of the terminal emulator. Options it
+/// takes no flag for still work on Linux, BSD and macOS through the `sh -c` wrapper, but are then applied inside the
+/// window (e.g. the title is set by an escape sequence the command may override).
+pub struct TerminalCapabilities {
+    /// Whether the window is held open after the command exits by a flag, see [`LaunchOptions::hold`].
+    pub supports_hold: bool,
+    /// Whether the window title is set by a flag, see [`LaunchOptions::title`].
+    pub supports_title: bool,
+    /// Whether the working directory is set by a flag, see [`LaunchOptions::current_dir`].
+    pub supports_working_directory: bool,
+    /// Whether a flag keeps the terminal emulator running until the command exits, see [`LaunchOptions::wait`].
+    /// Terminal emulators without a server process do so without a flag.
+    pub supports_wait: bool,
+    /// Whether the terminal emulator opens tabs in its windows.
+    pub supports_tabs: bool,
+    /// Whether the terminal emulator talks to Wayland compositors directly, without XWayland.
+    pub wayland_native: bool,
+    /// Whether the terminal emulator displays 24-bit colors, instead of approximating them with a palette.
+    pub truecolor: bool,
+    /// Whether the terminal emulator sets the clipboard on the OSC 52 escape sequence, see
+    /// [`copy_to_clipboard`](crate::copy_to_clipboard).
+    pub clipboard: bool,
+}
+
+/// Returns the capabilities of the terminal emulator at `path`.
+///
+/// Unknown terminal emulators take no flag, have no tabs and are not Wayland-native, but are assumed to display 24-bit
+/// colors as most current terminal emulators do.
+pub(crate) fn of(path: &Path) -> TerminalCapabilities {
+    let (_, cwd_flag, title_flag, hold_flag) = options::known_flags(path);
+    let listed = |names: &[&str]| {
+        executable_name(path).is_some_and(|name| {
+            names
+                .iter()
+                .any(|known| crate::lookup::same_name(known, name))
+        })
+    };
+
+    TerminalCapabilities {
+        supports_hold: hold_flag.is_some(),
+        supports_title: title_flag.is_some(),
+        supports_working_directory: cwd_flag.is_some(),
+        supports_wait: options::wait_flag(path).is_some(),
+        supports_tabs: listed(TABBED_TERMINALS),
+        wayland_native: listed(WAYLAND_NATIVE_TERMINALS),
+        truecolor: !listed(PALETTE_TERMINALS),
+        clipboard: listed(OSC52_TERMINALS),
+    }
+}
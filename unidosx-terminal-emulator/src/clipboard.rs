@@ -0,0 +1,134 @@
+//! Copying text to the clipboard from terminal applications.
+//!
+//! Terminal emulators supporting the OSC 52 escape sequence set the clipboard of the desktop they run on, also for
+//! applications running over SSH. Elsewhere, the clipboard command of the session is used: `wl-copy` on Wayland,
+//! `xclip` or `xsel` on X11, `pbcopy` on macOS and `clip` on Windows.
+
+use std::{
+    io::{self, IsTerminal, Write},
+    path::PathBuf,
+    process::Stdio,
+};
+
+use crate::{CommandSpec, detect_current, environment, lookup::find_executable};
+
+/// The clipboard commands, their arguments and the environment variable they need, in order of preference.
+const CLIPBOARD_COMMANDS: &[(&str, &[&str], Option<&str>)] = &[
+    ("wl-copy", &[], Some("WAYLAND_DISPLAY")),
+    ("xclip", &["-selection", "clipboard"], Some("DISPLAY")),
+    ("xsel", &["--clipboard", "--input"], Some("DISPLAY")),
+    ("pbcopy", &[], None),
+    ("clip", &[], None),
+];
+
+/// The environment variables set in SSH sessions.
+const SSH_VARS: &[&str] = &["SSH_TTY", "SSH_CONNECTION"];
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// How text was copied to the clipboard, see [`copy_to_clipboard`].
+pub enum ClipboardMethod {
+    /// Through the OSC 52 escape sequence, written to the terminal.
+    Osc52,
+    /// Through the clipboard command at this path (e.g. `wl-copy`).
+    Command(PathBuf),
+}
+
+/// Copies `text` to the clipboard, returning how.
+///
+/// The OSC 52 escape sequence is written to the terminal when the current terminal emulator supports it (see
+/// [`TerminalCapabilities::clipboard`](crate::TerminalCapabilities::clipboard)), or in SSH sessions, where it is the
+/// only way to reach the clipboard of the local desktop. Terminal emulators do not acknowledge it, so the copy may
+/// still be refused (e.g. when disabled in their settings). Otherwise, `text` is piped into the first clipboard command
+/// found for the session.
+///
+/// Fails with [`io::ErrorKind::NotFound`] if neither is available, or with the error of the clipboard command.
+pub fn copy_to_clipboard(text: &str) -> io::Result<ClipboardMethod> {
+    if io::stdout().is_terminal() && (osc52_supported() || in_ssh_session()) {
+        write_to_terminal(osc52(text).as_bytes())?;
+
+        return Ok(ClipboardMethod::Osc52);
+    }
+
+    let (program, args) = CLIPBOARD_COMMANDS
+        .iter()
+        .filter(|(_, _, var)| var.is_none_or(|var| environment::var_os(var).is_some()))
+        .find_map(|&(name, args, _)| find_executable(name).map(|program| (program, args)))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no clipboard command found"))?;
+
+    let mut child = CommandSpec::new(&program)
+        .args(args)
+        .to_command()
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()?;
+
+    // Dropped before waiting, so the command sees the end of its input.
+    let written = child
+        .stdin
+        .take()
+        .map_or(Ok(()), |mut stdin| stdin.write_all(text.as_bytes()));
+    let status = child.wait()?;
+    written?;
+
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "{} exited with {status}",
+            program.display()
+        )));
+    }
+
+    Ok(ClipboardMethod::Command(program))
+}
+
+/// Returns `true` if the terminal emulator hosting the current process supports OSC 52.
+fn osc52_supported() -> bool {
+    detect_current().is_some_and(|current| current.terminal.capabilities().clipboard)
+}
+
+/// Returns `true` if the current process runs in an SSH session.
+fn in_ssh_session() -> bool {
+    SSH_VARS
+        .iter()
+        .any(|var| environment::var_os(var).is_some_and(|value| !value.is_empty()))
+}
+
+/// Writes `bytes` to the terminal of the current process, even if the standard output is redirected on UNIX.
+fn write_to_terminal(bytes: &[u8]) -> io::Result<()> {
+    #[cfg(unix)]
+    if let Ok(mut tty) = std::fs::OpenOptions::new().write(true).open("/dev/tty") {
+        return tty.write_all(bytes).and_then(|()| tty.flush());
+    }
+
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(bytes)?;
+    stdout.flush()
+}
+
+/// Returns the OSC 52 escape sequence setting the clipboard to `text`.
+pub(crate) fn osc52(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", base64(text.as_bytes()))
+}
+
+/// Encodes `bytes` in standard base64, with padding.
+pub(crate) fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, &byte)| {
+            group | u32::from(byte) << (16 - 8 * i)
+        });
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(char::from(
+                    ALPHABET[(group >> (18 - 6 * i) & 0x3f) as usize],
+                ));
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
@@ -0,0 +1,186 @@
+//! Inspectable description of a command.
+
+use std::{
+    ffi::{OsStr, OsString},
+    fmt::{self, Display, Formatter},
+    path::{Path, PathBuf},
+    process::{Command, Output, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::shell::quote_if_needed;
+
+/// The `CREATE_NEW_CONSOLE` process creation flag from the Win32 API.
+#[cfg(windows)]
+const CREATE_NEW_CONSOLE: u32 = 0x0000_0010;
+
+/// How often a command run by [`CommandSpec::output_within`] is checked for its exit.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// The exact program, arguments, working directory and environment changes of a command.
+///
+/// Unlike [`Command`], it can be cloned, compared, hashed and inspected, and is converted into a [`Command`] only when
+/// it is time to spawn it. Building one never spawns anything, allowing launches to be logged, previewed or asserted in
+/// tests.
+pub struct CommandSpec {
+    /// The program to execute.
+    pub program: OsString,
+    /// The arguments passed to the program.
+    pub args: Vec<OsString>,
+    /// The working directory, `None` if inherited from the current process.
+    pub cwd: Option<PathBuf>,
+    /// The environment variables changed from the current process, `None` values are removed.
+    pub env: Vec<(OsString, Option<OsString>)>,
+    /// Whether the program gets a console of its own. Only used on Windows.
+    pub new_console: bool,
+}
+
+impl CommandSpec {
+    /// Creates a command specification for `program` without arguments.
+    pub fn new<S: AsRef<OsStr>>(program: S) -> Self {
+        Self {
+            program: program.as_ref().to_owned(),
+            ..Default::default()
+        }
+    }
+
+    /// Adds an argument.
+    pub fn arg<S: AsRef<OsStr>>(mut self, arg: S) -> Self {
+        self.args.push(arg.as_ref().to_owned());
+        self
+    }
+
+    /// Adds multiple arguments.
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.args
+            .extend(args.into_iter().map(|arg| arg.as_ref().to_owned()));
+        self
+    }
+
+    /// Sets the working directory.
+    pub fn current_dir<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.cwd = Some(dir.as_ref().to_owned());
+        self
+    }
+
+    /// Sets an environment variable.
+    pub fn env<K: AsRef<OsStr>, V: AsRef<OsStr>>(mut self, key: K, value: V) -> Self {
+        self.env
+            .push((key.as_ref().to_owned(), Some(value.as_ref().to_owned())));
+        self
+    }
+
+    /// Removes an environment variable.
+    pub fn env_remove<K: AsRef<OsStr>>(mut self, key: K) -> Self {
+        self.env.push((key.as_ref().to_owned(), None));
+        self
+    }
+
+    /// Converts the specification into a [`Command`] ready to be spawned.
+    pub fn to_command(&self) -> Command {
+        let mut cmd = Command::new(&self.program);
+        cmd.args(&self.args);
+
+        if let Some(cwd) = &self.cwd {
+            cmd.current_dir(cwd);
+        }
+
+        for (key, value) in &self.env {
+            match value {
+                Some(value) => cmd.env(key, value),
+                None => cmd.env_remove(key),
+            };
+        }
+
+        #[cfg(windows)]
+        if self.new_console {
+            use std::os::windows::process::CommandExt;
+
+            cmd.creation_flags(CREATE_NEW_CONSOLE);
+        }
+
+        cmd
+    }
+
+    /// Runs the command without input, capturing its output, and kills it if it does not exit within `timeout`.
+    ///
+    /// Returns `None` if the command could not be spawned or did not exit in time. Only suited to commands printing
+    /// little, such as `--version`, as the output is read once the command exited.
+    pub(crate) fn output_within(&self, timeout: Duration) -> Option<Output> {
+        let started_at = Instant::now();
+        let mut child = self
+            .to_command()
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .ok()?;
+
+        while child.try_wait().ok()?.is_none() {
+            if started_at.elapsed() >= timeout {
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+
+            thread::sleep(POLL_INTERVAL);
+        }
+
+        child.wait_with_output().ok()
+    }
+}
+
+impl From<CommandSpec> for Command {
+    fn from(spec: CommandSpec) -> Self {
+        spec.to_command()
+    }
+}
+
+impl From<&Command> for CommandSpec {
+    fn from(cmd: &Command) -> Self {
+        Self {
+            program: cmd.get_program().to_owned(),
+            args: cmd.get_args().map(ToOwned::to_owned).collect(),
+            cwd: cmd.get_current_dir().map(ToOwned::to_owned),
+            env: cmd
+                .get_envs()
+                .map(|(key, value)| (key.to_owned(), value.map(ToOwned::to_owned)))
+                .collect(),
+            new_console: false,
+        }
+    }
+}
+
+impl Display for CommandSpec {
+    /// Formats the command as a shell command line.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Some(cwd) = &self.cwd {
+            write!(f, "cd {} && ", quote_if_needed(cwd.as_os_str()))?;
+        }
+
+        if !self.env.is_empty() {
+            write!(f, "env ")?;
+        }
+
+        for (key, value) in &self.env {
+            match value {
+                Some(value) => write!(f, "{}={} ", key.to_string_lossy(), quote_if_needed(value))?,
+                None => write!(f, "-u {} ", key.to_string_lossy())?,
+            }
+        }
+
+        write!(f, "{}", quote_if_needed(&self.program))?;
+
+        for arg in &self.args {
+            write!(f, " {}", quote_if_needed(arg))?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,198 @@
+//! Wayland compositor identification and integration.
+
+use std::{
+    fmt::{self, Display, Formatter},
+    path::{Path, PathBuf},
+};
+
+use crate::{CommandSpec, environment};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// Wayland compositors.
+pub enum Compositor {
+    /// sway, controlled through `swaymsg exec`.
+    Sway,
+    /// Hyprland, controlled through `hyprctl dispatch exec`.
+    Hyprland,
+    /// niri, controlled through `niri msg action spawn`.
+    Niri,
+    /// river, controlled through `riverctl spawn`.
+    River,
+    /// KWin, the KDE Plasma compositor.
+    KWin,
+    /// Mutter, the GNOME compositor.
+    Mutter,
+    /// Wayfire.
+    Wayfire,
+    /// labwc.
+    Labwc,
+}
+
+/// Detects the running Wayland compositor.
+///
+/// The environment variables set by the compositors are checked first, validating that their IPC sockets are still
+/// alive, then the desktop names from `XDG_CURRENT_DESKTOP` and `DESKTOP_SESSION`. If the environment was scrubbed
+/// (e.g. under `sudo`), the IPC sockets in `XDG_RUNTIME_DIR` are probed as a last resort.
+pub fn detect_compositor() -> Option<Compositor> {
+    from_ipc_env()
+        .or_else(|| {
+            ["XDG_CURRENT_DESKTOP", "DESKTOP_SESSION"]
+                .into_iter()
+                .filter_map(environment::var)
+                .find_map(|desktop| compositor_from_desktop(&desktop))
+        })
+        .or_else(from_runtime_dir)
+}
+
+/// Detects the compositor from the IPC socket environment variables, ignoring stale sockets.
+fn from_ipc_env() -> Option<Compositor> {
+    if environment::var_os("SWAYSOCK").is_some_and(|socket| is_socket(Path::new(&socket))) {
+        return Some(Compositor::Sway);
+    }
+
+    if let Some(signature) = environment::var_os("HYPRLAND_INSTANCE_SIGNATURE") {
+        let alive = hyprland_dirs()
+            .into_iter()
+            .any(|dir| is_socket(&dir.join(&signature).join(".socket.sock")));
+
+        if alive {
+            return Some(Compositor::Hyprland);
+        }
+    }
+
+    if environment::var_os("NIRI_SOCKET").is_some_and(|socket| is_socket(Path::new(&socket))) {
+        return Some(Compositor::Niri);
+    }
+
+    None
+}
+
+/// Detects the compositor by probing the IPC sockets in `XDG_RUNTIME_DIR`.
+fn from_runtime_dir() -> Option<Compositor> {
+    let runtime_dir = crate::xdg::absolute(environment::var_os("XDG_RUNTIME_DIR"))?;
+    let entries = runtime_dir.read_dir().ok()?;
+
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let Some(name) = name.to_str() else {
+            continue;
+        };
+
+        let compositor = if name.starts_with("sway-ipc.") && name.ends_with(".sock") {
+            Compositor::Sway
+        } else if name.starts_with("niri.") && name.ends_with(".sock") {
+            Compositor::Niri
+        } else {
+            continue;
+        };
+
+        if is_socket(&entry.path()) {
+            return Some(compositor);
+        }
+    }
+
+    let hyprland = runtime_dir.join("hypr").read_dir().ok()?;
+
+    hyprland
+        .flatten()
+        .any(|instance| is_socket(&instance.path().join(".socket.sock")))
+        .then_some(Compositor::Hyprland)
+}
+
+/// Returns the directories containing the Hyprland instance directories.
+fn hyprland_dirs() -> Vec<PathBuf> {
+    let runtime_dir = crate::xdg::absolute(environment::var_os("XDG_RUNTIME_DIR"));
+
+    runtime_dir
+        .map(|dir| dir.join("hypr"))
+        .into_iter()
+        .chain(Some(PathBuf::from("/tmp/hypr")))
+        .collect()
+}
+
+/// Maps a desktop name list (e.g. `XDG_CURRENT_DESKTOP`) to a compositor.
+pub(crate) fn compositor_from_desktop(desktop: &str) -> Option<Compositor> {
+    desktop
+        .split(':')
+        .find_map(|name| match name.to_ascii_lowercase().as_str() {
+            "sway" => Some(Compositor::Sway),
+            "hyprland" => Some(Compositor::Hyprland),
+            "niri" => Some(Compositor::Niri),
+            "river" => Some(Compositor::River),
+            "kde" | "plasma" | "plasmawayland" => Some(Compositor::KWin),
+            "gnome" | "gnome-classic" | "ubuntu" => Some(Compositor::Mutter),
+            "wayfire" => Some(Compositor::Wayfire),
+            "labwc" => Some(Compositor::Labwc),
+            _ => None,
+        })
+}
+
+/// Returns `true` if the path points to a UNIX socket.
+fn is_socket(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+
+        path.metadata()
+            .is_ok_and(|metadata| metadata.file_type().is_socket())
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        false
+    }
+}
+
+impl Compositor {
+    /// Returns the program used to make the compositor spawn commands, if the compositor supports it.
+    pub fn control_program(&self) -> Option<&'static str> {
+        match self {
+            Self::Sway => Some("swaymsg"),
+            Self::Hyprland => Some("hyprctl"),
+            Self::Niri => Some("niri"),
+            Self::River => Some("riverctl"),
+            Self::KWin | Self::Mutter | Self::Wayfire | Self::Labwc => None,
+        }
+    }
+
+    /// Builds the command asking the compositor to spawn `spec`, using the control program at `control`.
+    ///
+    /// The compositor spawns the command with its own environment and working directory, so the ones of `spec` are
+    /// applied through a shell command line. Returns `None` if the compositor can not spawn commands.
+    pub fn spawn(&self, control: &Path, spec: &CommandSpec) -> Option<CommandSpec> {
+        let control = CommandSpec::new(control);
+
+        match self {
+            // These compositors run the command through `sh -c`.
+            Self::Sway => Some(control.args(["exec", "--"]).arg(spec.to_string())),
+            Self::Hyprland => Some(control.args(["dispatch", "exec"]).arg(spec.to_string())),
+            Self::River => Some(control.arg("spawn").arg(spec.to_string())),
+            Self::Niri => {
+                let control = control.args(["msg", "action", "spawn", "--"]);
+
+                if spec.cwd.is_none() && spec.env.is_empty() {
+                    Some(control.arg(&spec.program).args(&spec.args))
+                } else {
+                    Some(control.args(["sh", "-c"]).arg(spec.to_string()))
+                }
+            }
+            Self::KWin | Self::Mutter | Self::Wayfire | Self::Labwc => None,
+        }
+    }
+}
+
+impl Display for Compositor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Sway => write!(f, "sway"),
+            Self::Hyprland => write!(f, "Hyprland"),
+            Self::Niri => write!(f, "niri"),
+            Self::River => write!(f, "river"),
+            Self::KWin => write!(f, "KWin"),
+            Self::Mutter => write!(f, "Mutter"),
+            Self::Wayfire => write!(f, "Wayfire"),
+            Self::Labwc => write!(f, "labwc"),
+        }
+    }
+}
@@ -0,0 +1,185 @@
+//! The configuration file of this crate.
+//!
+//! Stores the default terminal emulator chosen by the user (e.g. through [`first_run`](crate::first_run)) in
+//! `unidosx/terminal.conf` in the XDG configuration directory:
+//!
+//! ```ini
+//! [Terminal]
+//! Exec=/usr/bin/kitty
+//! ExecutionSyntax=command
+//! ```
+//!
+//! Administrators can set a system-wide default in the same format, in `unidosx/terminal.conf` in the system XDG
+//! configuration directories (`$XDG_CONFIG_DIRS`, `/etc/xdg` by default) or in [`SYSTEM_CONFIG_PATH`]. The user
+//! configuration file takes precedence over them.
+//!
+//! `ExecutionSyntax` also accepts a custom flag, `flag:-x` or `single-flag:--command=` if the command must be given as
+//! a single argument, see [`ExecutionSyntax::CustomFlag`].
+//!
+//! `PreferAccessible=true` prefers the terminal emulators working well with screen readers, see
+//! [`prefer_accessible_terminals`](crate::prefer_accessible_terminals).
+//!
+//! For kiosk and enterprise deployments, `Locked=true` in [`SYSTEM_CONFIG_PATH`] locks the terminal emulator to the
+//! one it sets: `TERMINAL_EMULATOR`, the user configuration file and the other detection methods are ignored by
+//! [`detect`](crate::detect), and [`set_default_terminal`] fails. The lock is not read from `$XDG_CONFIG_DIRS`, which
+//! users control.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{ExecutionSyntax, TerminalEmulator, filesystem, ini::Ini};
+
+/// The section holding the default terminal emulator.
+const SECTION: &str = "Terminal";
+
+/// The system-wide configuration file, read after the XDG configuration directories.
+pub(crate) const SYSTEM_CONFIG_PATH: &str = "/etc/unidosx/terminal.conf";
+
+/// Returns the path of the configuration file (`$XDG_CONFIG_HOME/unidosx/terminal.conf`), or `None` if neither
+/// `XDG_CONFIG_HOME` nor `HOME` is set.
+pub fn default_terminal_config_path() -> Option<PathBuf> {
+    crate::xdg::config_home().map(|config| config.join("unidosx/terminal.conf"))
+}
+
+/// Returns `true` if the system-wide configuration file locks the terminal emulator, ignoring user overrides.
+pub fn is_terminal_locked() -> bool {
+    filesystem::read_to_string(Path::new(SYSTEM_CONFIG_PATH))
+        .is_ok_and(|contents| parse_terminal_lock(&contents))
+}
+
+/// Saves `terminal` as the default terminal emulator in the configuration file, replacing the previous one. The other
+/// keys and sections of the configuration file (e.g. `PreferAccessible`) and its comments are kept.
+///
+/// The saved terminal emulator is detected by [`probe_config_file`](crate::probe_config_file). Fails with
+/// [`io::ErrorKind::PermissionDenied`] if the terminal emulator is locked, see [`is_terminal_locked`].
+pub fn set_default_terminal(terminal: &TerminalEmulator) -> io::Result<()> {
+    if is_terminal_locked() {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("the terminal emulator is locked by {SYSTEM_CONFIG_PATH}"),
+        ));
+    }
+
+    let path = default_terminal_config_path().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "neither XDG_CONFIG_HOME nor HOME is set",
+        )
+    })?;
+    let exec = terminal.path.to_str().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the terminal emulator path is not valid Unicode",
+        )
+    })?;
+
+    let contents = fs::read_to_string(&path).unwrap_or_default();
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::write(
+        &path,
+        update_terminal_config(&contents, exec, &terminal.execution_syntax.id()),
+    )
+}
+
+/// Sets `Exec` and `ExecutionSyntax` in the configuration file contents, keeping the other keys, sections and comments.
+///
+/// The `[Terminal]` section is appended if missing, so keys before the first section header stay outside of it.
+pub(crate) fn update_terminal_config(contents: &str, exec: &str, syntax: &str) -> String {
+    let keys = format!("Exec={exec}\nExecutionSyntax={syntax}\n");
+    let mut updated = String::new();
+    let mut in_section = false;
+    let mut written = false;
+
+    for line in contents.lines() {
+        let trimmed = line.trim();
+
+        if let Some(header) = trimmed.strip_prefix('[') {
+            in_section = header
+                .split_once(']')
+                .is_some_and(|(name, _)| name.trim() == SECTION);
+            updated.push_str(line);
+            updated.push('\n');
+
+            if in_section && !written {
+                updated.push_str(&keys);
+                written = true;
+            }
+
+            continue;
+        }
+
+        let key = trimmed.split_once('=').map(|(key, _)| key.trim());
+        if in_section && matches!(key, Some("Exec" | "ExecutionSyntax")) {
+            continue;
+        }
+
+        updated.push_str(line);
+        updated.push('\n');
+    }
+
+    if !written {
+        if !updated.is_empty() {
+            updated.push('\n');
+        }
+        updated.push_str(&format!("[{SECTION}]\n{keys}"));
+    }
+
+    updated
+}
+
+/// Reads the executable and execution syntax of the default terminal emulator from the first configuration file
+/// setting one: the user configuration file, then the system-wide ones.
+///
+/// With `user_config` set to `false`, or if the terminal emulator is locked, only [`SYSTEM_CONFIG_PATH`] is read, as
+/// the other paths depend on the environment.
+pub(crate) fn configured_terminal(user_config: bool) -> Option<(String, Option<ExecutionSyntax>)> {
+    config_files(user_config).find_map(|contents| parse_terminal_config(&contents))
+}
+
+/// Returns `true` if the first configuration file setting `PreferAccessible` enables it, read like
+/// [`configured_terminal`].
+#[cfg(not(windows))]
+pub(crate) fn prefers_accessible(user_config: bool) -> bool {
+    config_files(user_config)
+        .find_map(|contents| {
+            Ini::parse(&contents)
+                .get(SECTION, "PreferAccessible")
+                .map(|value| value == "true")
+        })
+        .unwrap_or_default()
+}
+
+/// Reads the contents of the configuration files, the user configuration file first.
+fn config_files(user_config: bool) -> impl Iterator<Item = String> {
+    let xdg_paths = (user_config && !is_terminal_locked())
+        .then(crate::xdg::config_dirs)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|dir| dir.join("unidosx/terminal.conf"));
+
+    xdg_paths
+        .chain([PathBuf::from(SYSTEM_CONFIG_PATH)])
+        .filter_map(|path| filesystem::read_to_string(&path).ok())
+}
+
+/// Parses the executable and execution syntax of the default terminal emulator from the configuration file contents.
+pub(crate) fn parse_terminal_config(contents: &str) -> Option<(String, Option<ExecutionSyntax>)> {
+    let ini = Ini::parse(contents);
+    let exec = ini.get(SECTION, "Exec").filter(|exec| !exec.is_empty())?;
+    let syntax = ini
+        .get(SECTION, "ExecutionSyntax")
+        .and_then(ExecutionSyntax::from_id);
+
+    Some((exec.to_owned(), syntax))
+}
+
+/// Parses whether the configuration file contents lock the terminal emulator.
+pub(crate) fn parse_terminal_lock(contents: &str) -> bool {
+    Ini::parse(contents).get(SECTION, "Locked") == Some("true")
+}
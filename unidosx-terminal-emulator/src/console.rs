@@ -0,0 +1,109 @@
+//! Console attachment for GUI-subsystem Windows binaries.
+
+use std::{ffi::c_void, io, os::windows::ffi::OsStrExt, ptr};
+
+type Handle = *mut c_void;
+
+const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+const ERROR_ACCESS_DENIED: i32 = 5;
+const STD_INPUT_HANDLE: u32 = -10i32 as u32;
+const STD_OUTPUT_HANDLE: u32 = -11i32 as u32;
+const STD_ERROR_HANDLE: u32 = -12i32 as u32;
+const GENERIC_READ: u32 = 0x8000_0000;
+const GENERIC_WRITE: u32 = 0x4000_0000;
+const FILE_SHARE_READ: u32 = 0x0000_0001;
+const FILE_SHARE_WRITE: u32 = 0x0000_0002;
+const OPEN_EXISTING: u32 = 3;
+const INVALID_HANDLE_VALUE: Handle = -1isize as Handle;
+
+#[link(name = "kernel32")]
+unsafe extern "system" {
+    fn AttachConsole(process_id: u32) -> i32;
+    fn AllocConsole() -> i32;
+    fn GetStdHandle(std_handle: u32) -> Handle;
+    fn SetStdHandle(std_handle: u32, handle: Handle) -> i32;
+    fn CreateFileW(
+        file_name: *const u16,
+        desired_access: u32,
+        share_mode: u32,
+        security_attributes: *mut c_void,
+        creation_disposition: u32,
+        flags_and_attributes: u32,
+        template_file: Handle,
+    ) -> Handle;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// How the process got its console.
+pub enum ConsoleAttachment {
+    /// The process already had a console, nothing was changed.
+    Existing,
+    /// The process was attached to the console of its parent process.
+    Parent,
+    /// A new console was allocated for the process.
+    Allocated,
+}
+
+/// Gives a GUI-subsystem binary working standard input, output and error.
+///
+/// Attaches to the console of the parent process if launched from one, otherwise allocates a new console. Standard
+/// handles that were not redirected by the parent process are then wired to the console.
+pub fn attach_or_alloc_console() -> io::Result<ConsoleAttachment> {
+    // SAFETY: both functions take no pointers and only change the console of the current process.
+    let attachment = unsafe {
+        if AttachConsole(ATTACH_PARENT_PROCESS) != 0 {
+            ConsoleAttachment::Parent
+        } else if io::Error::last_os_error().raw_os_error() == Some(ERROR_ACCESS_DENIED) {
+            return Ok(ConsoleAttachment::Existing);
+        } else if AllocConsole() != 0 {
+            ConsoleAttachment::Allocated
+        } else {
+            return Err(io::Error::last_os_error());
+        }
+    };
+
+    wire_std_handle(STD_INPUT_HANDLE, "CONIN$")?;
+    wire_std_handle(STD_OUTPUT_HANDLE, "CONOUT$")?;
+    wire_std_handle(STD_ERROR_HANDLE, "CONOUT$")?;
+
+    Ok(attachment)
+}
+
+/// Points a standard handle to the console device if it is not already valid (e.g. redirected to a file or pipe).
+fn wire_std_handle(std_handle: u32, device: &str) -> io::Result<()> {
+    // SAFETY: `GetStdHandle` has no preconditions.
+    let current = unsafe { GetStdHandle(std_handle) };
+
+    if !current.is_null() && current != INVALID_HANDLE_VALUE {
+        return Ok(());
+    }
+
+    let device: Vec<u16> = std::ffi::OsStr::new(device)
+        .encode_wide()
+        .chain(Some(0))
+        .collect();
+
+    // SAFETY: `device` is a valid NUL-terminated wide string and the remaining pointers are allowed to be null.
+    let handle = unsafe {
+        CreateFileW(
+            device.as_ptr(),
+            GENERIC_READ | GENERIC_WRITE,
+            FILE_SHARE_READ | FILE_SHARE_WRITE,
+            ptr::null_mut(),
+            OPEN_EXISTING,
+            0,
+            ptr::null_mut(),
+        )
+    };
+
+    if handle == INVALID_HANDLE_VALUE {
+        return Err(io::Error::last_os_error());
+    }
+
+    // SAFETY: `handle` is a valid console handle owned by the process from now on.
+    if unsafe { SetStdHandle(std_handle, handle) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
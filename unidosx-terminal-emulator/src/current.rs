@@ -0,0 +1,454 @@
+//! Detection of the terminal emulator hosting the current process.
+
+use std::{
+    fmt::{self, Display, Formatter},
+    path::PathBuf,
+};
+#[cfg(all(target_os = "linux", feature = "controlling-tty"))]
+use std::{fs, path::Path};
+
+use crate::{
+    DetectionMethod, ExecutionSyntax, TerminalEmulator, environment,
+    lookup::{find_executable, same_name},
+    probes::lookup_known,
+};
+
+/// Environment variables set by terminal emulators in the processes they host, and the terminal emulator setting them.
+const HOST_VARIABLES: &[(&str, &str)] = &[
+    ("KITTY_WINDOW_ID", "kitty"),
+    ("WEZTERM_EXECUTABLE", "wezterm"),
+    ("ALACRITTY_SOCKET", "alacritty"),
+    ("ALACRITTY_WINDOW_ID", "alacritty"),
+    ("KONSOLE_VERSION", "konsole"),
+];
+
+#[cfg(all(
+    any(target_os = "linux", target_os = "macos"),
+    feature = "process-ancestry"
+))]
+/// The most ancestors inspected by [`probe_process_ancestry`], bounding the walk if the chain of parents loops.
+const MAX_ANCESTORS: usize = 64;
+
+/// Values of `TERM_PROGRAM` that differ from the name of the terminal emulator setting them.
+const TERM_PROGRAMS: &[(&str, &str)] = &[
+    ("WezTerm", "wezterm"),
+    ("Hyper", "hyper"),
+    ("WarpTerminal", "warp"),
+    ("iTerm.app", "iTerm2"),
+    ("Apple_Terminal", "Terminal"),
+    ("vscode", "code"),
+];
+
+/// Values of `TERM_PROGRAM` set by terminal multiplexers, which hide the terminal emulator behind them.
+const MULTIPLEXERS: &[&str] = &["tmux", "screen", "zellij"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// How likely the terminal emulator returned by [`detect_current`] is the one hosting the current process.
+pub enum Confidence {
+    /// Only the family of the terminal emulator is known (e.g. a VTE-based terminal emulator).
+    Low,
+    /// The terminal emulator is named by a variable that can be inherited or forwarded (e.g. `TERM_PROGRAM`), or the
+    /// variables disagree.
+    Medium,
+    /// The terminal emulator set a variable of its own (e.g. `KITTY_WINDOW_ID`).
+    High,
+}
+
+impl Confidence {
+    /// Returns the confidence one level lower.
+    fn lower(self) -> Self {
+        match self {
+            Self::High => Self::Medium,
+            Self::Medium | Self::Low => Self::Low,
+        }
+    }
+}
+
+impl Display for Confidence {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Low => write!(f, "low"),
+            Self::Medium => write!(f, "medium"),
+            Self::High => write!(f, "high"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// The terminal emulator hosting the current process, as returned by [`detect_current`].
+pub struct CurrentTerminal {
+    /// The terminal emulator, detected by [`DetectionMethod::HostEnvironment`] or [`DetectionMethod::ProcessAncestry`].
+    pub terminal: TerminalEmulator,
+    /// How likely the terminal emulator is the one hosting the current process.
+    pub confidence: Confidence,
+}
+
+/// Detects the terminal emulator hosting the current process from the variables it set in its environment (e.g.
+/// `KITTY_WINDOW_ID`, `TERM_PROGRAM`, `VTE_VERSION`) and, on Linux and macOS, from the ancestors of the current process
+/// (see [`probe_process_ancestry`]), unlike [`detect`](crate::detect) which returns the default one.
+///
+/// The variables are inherited by the terminal emulators started from another one, and `TERM_PROGRAM` can be forwarded
+/// over SSH, so the result comes with a [`Confidence`]: conflicting variables or a terminal multiplexer (`tmux`,
+/// `screen`) lower it. VTE-based terminal emulators only tell their family and are reported as `gnome-terminal`, with a
+/// low confidence. The terminal emulator is searched in PATH, its path is its name if it is not found (e.g. `iTerm2`).
+///
+/// The closest ancestor that is a known terminal emulator is returned with a high confidence when a variable names it
+/// too. Otherwise, a terminal emulator that set a variable of its own wins with a medium confidence, and the ancestor
+/// is returned with a medium confidence, so the result survives `su`, nested shells and scrubbed environments.
+///
+/// Returns `None` if neither the variables nor the ancestors name a terminal emulator.
+pub fn detect_current() -> Option<CurrentTerminal> {
+    let found: Vec<(String, Confidence)> = HOST_VARIABLES
+        .iter()
+        .filter(|(var, _)| environment::var_os(var).is_some_and(|value| !value.is_empty()))
+        .map(|&(_, name)| (name.to_owned(), Confidence::High))
+        .chain(term_program().map(|name| (name, Confidence::Medium)))
+        .chain(
+            environment::var_os("VTE_VERSION")
+                .filter(|value| !value.is_empty())
+                .map(|_| ("gnome-terminal".to_owned(), Confidence::Low)),
+        )
+        .collect();
+
+    #[cfg(all(
+        any(target_os = "linux", target_os = "macos"),
+        feature = "process-ancestry"
+    ))]
+    let ancestor = probe_process_ancestry();
+    #[cfg(not(all(
+        any(target_os = "linux", target_os = "macos"),
+        feature = "process-ancestry"
+    )))]
+    let ancestor: Option<TerminalEmulator> = None;
+
+    let (terminal, confidence) = match (found.first(), ancestor) {
+        (Some((name, Confidence::High)), Some(ancestor))
+            if !found
+                .iter()
+                .any(|(other, _)| same_name(other, ancestor.name())) =>
+        {
+            (from_variables(name), Confidence::Medium)
+        }
+        (_, Some(ancestor)) => {
+            let confidence = if found
+                .iter()
+                .any(|(other, _)| same_name(other, ancestor.name()))
+            {
+                Confidence::High
+            } else {
+                Confidence::Medium
+            };
+
+            (ancestor, confidence)
+        }
+        (Some((name, confidence)), None) => {
+            let conflicting = found.iter().any(|(other, _)| other != name);
+
+            (
+                from_variables(name),
+                if conflicting {
+                    confidence.lower()
+                } else {
+                    *confidence
+                },
+            )
+        }
+        (None, None) => return None,
+    };
+
+    let confidence = if ["TMUX", "STY"]
+        .iter()
+        .any(|var| environment::var_os(var).is_some())
+    {
+        confidence.lower()
+    } else {
+        confidence
+    };
+
+    Some(CurrentTerminal {
+        terminal,
+        confidence,
+    })
+}
+
+/// Returns the terminal emulator `name`, named by the variables of the environment.
+fn from_variables(name: &str) -> TerminalEmulator {
+    let execution_syntax =
+        lookup_known(name).map_or(ExecutionSyntax::default(), |(_, syntax)| syntax);
+    let path = find_executable(name).unwrap_or_else(|| PathBuf::from(name));
+    let mut terminal = TerminalEmulator::new(path, execution_syntax);
+    terminal.method = DetectionMethod::HostEnvironment;
+
+    terminal
+}
+
+#[cfg(all(
+    any(target_os = "linux", target_os = "macos"),
+    feature = "process-ancestry"
+))]
+/// Detects the terminal emulator hosting the current process by climbing the chain of its parent processes until one
+/// is a known terminal emulator.
+///
+/// Unlike environment variables, this keeps working under `su`, in nested shells and in scripts. Helper processes are
+/// resolved to their terminal emulator (e.g. `gnome-terminal-server`). The chain is broken by `ssh` and by terminal
+/// multiplexers whose server is detached (e.g. `tmux`), and an application embedding a terminal (e.g. an editor) started
+/// from a terminal emulator is reported as that terminal emulator. Processes whose executable cannot be read (e.g. owned
+/// by another user) are named after their command line on Linux, through `/proc`, and skipped on macOS.
+pub fn probe_process_ancestry() -> Option<TerminalEmulator> {
+    let mut pid = std::os::unix::process::parent_id();
+
+    for _ in 0..MAX_ANCESTORS {
+        if pid <= 1 {
+            return None;
+        }
+
+        let (parent, path) = ancestry::process(pid)?;
+
+        if let Some((command_line, execution_syntax)) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| lookup_known(terminal_for_process(name)))
+        {
+            return Some(TerminalEmulator {
+                command_line: command_line.into(),
+                execution_syntax,
+                path,
+                method: DetectionMethod::ProcessAncestry,
+                raw: None,
+            });
+        }
+
+        pid = parent;
+    }
+
+    None
+}
+
+#[cfg(all(target_os = "linux", feature = "process-ancestry"))]
+/// Reads the processes through `/proc`.
+mod ancestry {
+    use std::path::{Path, PathBuf};
+
+    use crate::filesystem;
+
+    /// Returns the parent process ID of the process `pid` and the path of its executable, empty if unknown.
+    pub(super) fn process(pid: u32) -> Option<(u32, PathBuf)> {
+        let proc = Path::new("/proc").join(pid.to_string());
+        let stat = filesystem::read_to_string(&proc.join("stat")).ok()?;
+        let parent = super::parse_stat_parent(&stat)?;
+        let path = filesystem::canonicalize(&proc.join("exe"))
+            .ok()
+            .or_else(|| {
+                let cmdline = filesystem::read_to_string(&proc.join("cmdline")).ok()?;
+
+                cmdline
+                    .split('\0')
+                    .next()
+                    .filter(|program| !program.is_empty())
+                    .map(PathBuf::from)
+            })
+            .unwrap_or_default();
+
+        Some((parent, path))
+    }
+}
+
+#[cfg(all(target_os = "macos", feature = "process-ancestry"))]
+/// Reads the processes through `libproc`.
+mod ancestry {
+    use std::{
+        ffi::{c_int, c_void},
+        os::unix::ffi::OsStrExt,
+        path::PathBuf,
+    };
+
+    /// `PROC_PIDTBSDINFO`, the flavor of `proc_pidinfo` returning a `proc_bsdinfo`.
+    const PROC_PIDTBSDINFO: c_int = 3;
+
+    /// The size of `proc_bsdinfo`.
+    const BSDINFO_SIZE: usize = 136;
+
+    /// The offset of `pbi_ppid` in `proc_bsdinfo`, after `pbi_flags`, `pbi_status`, `pbi_xstatus` and `pbi_pid`.
+    const PPID_OFFSET: usize = 16;
+
+    /// `PROC_PIDPATHINFO_MAXSIZE`, the size of the buffer of `proc_pidpath`.
+    const PATH_MAX_SIZE: usize = 4096;
+
+    unsafe extern "C" {
+        fn proc_pidinfo(
+            pid: c_int,
+            flavor: c_int,
+            arg: u64,
+            buffer: *mut c_void,
+            size: c_int,
+        ) -> c_int;
+        fn proc_pidpath(pid: c_int, buffer: *mut c_void, size: u32) -> c_int;
+    }
+
+    /// Returns the parent process ID of the process `pid` and the path of its executable, empty if unknown.
+    pub(super) fn process(pid: u32) -> Option<(u32, PathBuf)> {
+        let pid = c_int::try_from(pid).ok()?;
+        let mut info = [0u8; BSDINFO_SIZE];
+
+        // SAFETY: `info` is a writable buffer of `BSDINFO_SIZE` bytes, the size of `proc_bsdinfo`.
+        let written = unsafe {
+            proc_pidinfo(
+                pid,
+                PROC_PIDTBSDINFO,
+                0,
+                info.as_mut_ptr().cast(),
+                BSDINFO_SIZE as c_int,
+            )
+        };
+
+        if usize::try_from(written).ok()? < BSDINFO_SIZE {
+            return None;
+        }
+
+        let parent = u32::from_ne_bytes(info[PPID_OFFSET..PPID_OFFSET + 4].try_into().ok()?);
+        let mut path = vec![0u8; PATH_MAX_SIZE];
+
+        // SAFETY: `path` is a writable buffer of `PATH_MAX_SIZE` bytes.
+        let len = unsafe { proc_pidpath(pid, path.as_mut_ptr().cast(), PATH_MAX_SIZE as u32) };
+        path.truncate(usize::try_from(len).unwrap_or(0));
+
+        Some((parent, PathBuf::from(std::ffi::OsStr::from_bytes(&path))))
+    }
+}
+
+/// Parses the parent process ID from the contents of a `/proc/[pid]/stat` file.
+///
+/// The name of the process, between parentheses, can contain spaces and parentheses, so the fields are read after the
+/// last closing parenthesis.
+#[cfg(all(target_os = "linux", feature = "process-ancestry"))]
+pub(crate) fn parse_stat_parent(stat: &str) -> Option<u32> {
+    stat.rsplit_once(')')?
+        .1
+        .split_whitespace()
+        .nth(1)?
+        .parse()
+        .ok()
+}
+
+/// Returns the name of the terminal emulator set in `TERM_PROGRAM`, unless it is a terminal multiplexer.
+fn term_program() -> Option<String> {
+    let value = environment::var("TERM_PROGRAM")?;
+
+    if value.is_empty() || MULTIPLEXERS.contains(&value.as_str()) {
+        return None;
+    }
+
+    Some(
+        TERM_PROGRAMS
+            .iter()
+            .find(|(program, _)| *program == value)
+            .map_or(value.clone(), |(_, name)| (*name).to_owned()),
+    )
+}
+
+/// Helper processes that own the terminal of a terminal emulator, and the terminal emulator they belong to.
+#[cfg(any(
+    test,
+    all(target_os = "linux", feature = "controlling-tty"),
+    all(
+        any(target_os = "linux", target_os = "macos"),
+        feature = "process-ancestry"
+    )
+))]
+const HELPER_PROCESSES: &[(&str, &str)] = &[
+    ("gnome-terminal-server", "gnome-terminal"),
+    ("mate-terminal.wrapper", "mate-terminal"),
+    ("ptyxis-agent", "ptyxis"),
+    ("wezterm-gui", "wezterm"),
+    ("wezterm-mux-server", "wezterm"),
+];
+
+#[cfg(all(target_os = "linux", feature = "controlling-tty"))]
+/// Detects the terminal emulator owning the controlling terminal of the current process.
+///
+/// Maps the pseudo-terminal of the standard streams (`/dev/pts/N`) back to the process holding its master side, found
+/// through the `tty-index` of the `/dev/ptmx` file descriptors in `/proc`. Unlike environment variables, this keeps
+/// working under `sudo` or scrubbed environments, but only processes readable by the current user are inspected, and
+/// multiplexers such as `tmux` or `ssh` hide the terminal emulator behind them.
+pub fn probe_controlling_tty() -> Option<TerminalEmulator> {
+    let index = (0..=2).find_map(|fd| {
+        fs::read_link(format!("/proc/self/fd/{fd}"))
+            .ok()
+            .and_then(|link| pts_index(&link))
+    })?;
+
+    let pid = find_ptmx_owner(index)?;
+    let path = fs::read_link(format!("/proc/{pid}/exe")).ok()?;
+    let name = path.file_name()?.to_str()?;
+    let (command_line, execution_syntax) = lookup_known(terminal_for_process(name))?;
+
+    Some(TerminalEmulator {
+        command_line: command_line.into(),
+        execution_syntax,
+        path,
+        method: DetectionMethod::ControllingTty,
+        raw: None,
+    })
+}
+
+/// Finds the process holding the master side of the pseudo-terminal `/dev/pts/{index}`.
+#[cfg(all(target_os = "linux", feature = "controlling-tty"))]
+fn find_ptmx_owner(index: u32) -> Option<u32> {
+    let own_pid = std::process::id();
+
+    fs::read_dir("/proc").ok()?.flatten().find_map(|entry| {
+        let pid: u32 = entry.file_name().to_str()?.parse().ok()?;
+
+        if pid == own_pid {
+            return None;
+        }
+
+        let fds = fs::read_dir(entry.path().join("fd")).ok()?;
+
+        fds.flatten()
+            .any(|fd| {
+                let is_ptmx = fs::read_link(fd.path()).is_ok_and(|link| {
+                    link == Path::new("/dev/ptmx") || link == Path::new("/dev/pts/ptmx")
+                });
+
+                is_ptmx
+                    && fs::read_to_string(entry.path().join("fdinfo").join(fd.file_name()))
+                        .ok()
+                        .and_then(|fdinfo| parse_tty_index(&fdinfo))
+                        == Some(index)
+            })
+            .then_some(pid)
+    })
+}
+
+/// Returns the index of a pseudo-terminal device path (`/dev/pts/N`).
+#[cfg(any(test, all(target_os = "linux", feature = "controlling-tty")))]
+pub(crate) fn pts_index(path: &std::path::Path) -> Option<u32> {
+    path.to_str()?.strip_prefix("/dev/pts/")?.parse().ok()
+}
+
+/// Parses the `tty-index` field of a `/proc/[pid]/fdinfo/[fd]` file of a `/dev/ptmx` file descriptor.
+#[cfg(any(test, all(target_os = "linux", feature = "controlling-tty")))]
+pub(crate) fn parse_tty_index(fdinfo: &str) -> Option<u32> {
+    fdinfo.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+
+        (key.trim() == "tty-index").then(|| value.trim().parse().ok())?
+    })
+}
+
+/// Returns the terminal emulator a process belongs to, resolving helper processes (e.g. `gnome-terminal-server`).
+#[cfg(any(
+    test,
+    all(target_os = "linux", feature = "controlling-tty"),
+    all(
+        any(target_os = "linux", target_os = "macos"),
+        feature = "process-ancestry"
+    )
+))]
+pub(crate) fn terminal_for_process(name: &str) -> &str {
+    HELPER_PROCESSES
+        .iter()
+        .find(|(helper, _)| *helper == name)
+        .map_or(name, |(_, terminal)| terminal)
+}
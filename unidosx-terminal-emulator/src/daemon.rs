@@ -0,0 +1,467 @@
+//! Detection service shared by short-lived processes.
+//!
+//! Each process running [`detect`](crate::detect) pays for a cold detection, and even [`detect_cached`] reads and
+//! validates the cache file. [`serve_daemon`] answers the requests of many client processes over a UNIX socket from one
+//! long-running process, whose detection result stays in memory and is refreshed when the settings files change (see the
+//! `watch` feature).
+//!
+//! The protocol is one single-line JSON object per connection in each direction: `{"request": "detect"}` is answered
+//! with the detected terminal emulator, or `{}` if none is found, and `{"request": "launch", "program": ..., "args":
+//! [...], "cwd": ...}` with `{"pid": ...}`. Failures are answered with `{"error": ...}`.
+//!
+//! The requests served are counted, and [`serve_daemon_metrics`] exposes the counters to Prometheus.
+
+use std::{
+    collections::BTreeMap,
+    ffi::OsStr,
+    fs,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    os::unix::{
+        fs::{DirBuilderExt, PermissionsExt},
+        net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    CommandSpec, LaunchOptions, Launcher, TerminalEmulator, cache, detect_cached, environment, json,
+};
+
+/// The longest request or response accepted, in bytes.
+const MAX_MESSAGE_LEN: u64 = 64 * 1024;
+
+/// How long a client may take to send its request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The requests served by the daemon during the current process.
+static METRICS: Mutex<Metrics> = Mutex::new(Metrics::new());
+
+/// Counters of the requests served by the daemon.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Metrics {
+    /// The number of detection requests answered.
+    pub(crate) detections: u64,
+    /// The number of launches, and of failed launches, by terminal emulator name.
+    pub(crate) launches: BTreeMap<String, (u64, u64)>,
+}
+
+impl Metrics {
+    /// Creates counters at zero.
+    pub(crate) const fn new() -> Self {
+        Self {
+            detections: 0,
+            launches: BTreeMap::new(),
+        }
+    }
+
+    /// Formats the counters in the Prometheus text exposition format, along with the number of detections answered
+    /// from the cache.
+    pub(crate) fn render(&self, cache_hits: u64) -> String {
+        let mut text = String::new();
+        let mut counter = |name: &str, help: &str, samples: &[(Option<&str>, u64)]| {
+            text.push_str(&format!("# HELP {name} {help}\n# TYPE {name} counter\n"));
+
+            for (terminal, value) in samples {
+                match terminal {
+                    Some(terminal) => text.push_str(&format!(
+                        "{name}{{terminal=\"{}\"}} {value}\n",
+                        escape_label(terminal)
+                    )),
+                    None => text.push_str(&format!("{name} {value}\n")),
+                }
+            }
+        };
+
+        counter(
+            "unidosx_daemon_detections_total",
+            "Detection requests answered by the daemon.",
+            &[(None, self.detections)],
+        );
+        counter(
+            "unidosx_detection_cache_hits_total",
+            "Detections answered from the cache, in memory or on disk.",
+            &[(None, cache_hits)],
+        );
+
+        let samples = |select: fn(&(u64, u64)) -> u64| {
+            self.launches
+                .iter()
+                .map(|(terminal, counts)| (Some(terminal.as_str()), select(counts)))
+                .collect::<Vec<_>>()
+        };
+        counter(
+            "unidosx_daemon_launches_total",
+            "Launch requests served by the daemon, by terminal emulator.",
+            &samples(|(launches, _)| *launches),
+        );
+        counter(
+            "unidosx_daemon_launch_failures_total",
+            "Launch requests the daemon failed to spawn, by terminal emulator.",
+            &samples(|(_, failures)| *failures),
+        );
+
+        text
+    }
+}
+
+/// Escapes a Prometheus label value.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Locks the metrics, recovering them if a thread panicked while holding them.
+fn metrics() -> MutexGuard<'static, Metrics> {
+    METRICS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Returns the counters of the requests served by the daemon of the current process, in the Prometheus text
+/// exposition format.
+///
+/// Counts the detection requests, the detections answered from the cache, and the launches and failed launches by
+/// terminal emulator name.
+pub fn daemon_metrics() -> String {
+    metrics().render(cache::cache_hits())
+}
+
+/// Serves [`daemon_metrics`] over HTTP on `listener`, for Prometheus to scrape.
+///
+/// Blocks forever, so it is meant to run on a thread of the daemon process, e.g. with
+/// `TcpListener::bind("127.0.0.1:9464")`. Every request is answered with the metrics, whatever its path. Metrics are
+/// not served unless this is called.
+pub fn serve_daemon_metrics(listener: TcpListener) -> io::Result<()> {
+    for stream in listener.incoming() {
+        let Ok(mut stream) = stream else {
+            continue;
+        };
+
+        let _ = stream.set_read_timeout(Some(REQUEST_TIMEOUT)).and_then(|()| {
+            // The request is read up to the end of its headers, its content does not matter.
+            let mut reader = BufReader::new((&stream).take(MAX_MESSAGE_LEN));
+            let mut line = String::new();
+            while reader.read_line(&mut line)? > 0 && !line.trim().is_empty() {
+                line.clear();
+            }
+
+            let body = daemon_metrics();
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            )
+        });
+    }
+
+    Ok(())
+}
+
+/// Returns the path of the daemon socket (`$XDG_RUNTIME_DIR/unidosx/daemon.sock`), or `None` if `XDG_RUNTIME_DIR` is
+/// not set.
+///
+/// The runtime directory is only accessible by the current user, so are the daemon and its socket.
+pub fn daemon_socket_path() -> Option<PathBuf> {
+    crate::xdg::absolute(environment::var_os("XDG_RUNTIME_DIR"))
+        .map(|dir| dir.join("unidosx/daemon.sock"))
+}
+
+/// Serves detection and launch requests on the UNIX socket at `path`, usually [`daemon_socket_path`].
+///
+/// Blocks forever, answering each client on a thread of its own, so it is meant to run in a dedicated process or
+/// thread. Detection uses [`detect_cached`] with the environment of the daemon, and launches are spawned by the daemon,
+/// with its environment and the working directory of the client.
+///
+/// A socket left behind by a daemon that exited is replaced. Fails with [`io::ErrorKind::AddrInUse`] if another daemon
+/// is already listening on `path`.
+pub fn serve_daemon(path: &Path) -> io::Result<()> {
+    if UnixStream::connect(path).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            format!("a daemon is already listening on {}", path.display()),
+        ));
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(parent)?;
+    }
+
+    match fs::remove_file(path) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error),
+        _ => {}
+    }
+
+    let listener = UnixListener::bind(path)?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+
+    for stream in listener.incoming() {
+        // A client giving up before being accepted does not stop the daemon.
+        let Ok(stream) = stream else {
+            continue;
+        };
+
+        thread::spawn(move || {
+            let _ = serve(stream);
+        });
+    }
+
+    Ok(())
+}
+
+/// Answers the request of one client.
+fn serve(mut stream: UnixStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+
+    let mut request = String::new();
+    BufReader::new((&stream).take(MAX_MESSAGE_LEN)).read_line(&mut request)?;
+
+    let response = answer(&request)
+        .unwrap_or_else(|error| format!("{{\"error\": {}}}", json::string(&error.to_string())));
+
+    stream.write_all(response.as_bytes())?;
+    stream.write_all(b"\n")
+}
+
+/// Computes the response to `request`.
+pub(crate) fn answer(request: &str) -> io::Result<String> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidInput, "invalid request");
+    let fields = json::parse_flat_object(request).ok_or_else(invalid)?;
+    let field = |name: &str| {
+        fields
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value)
+    };
+
+    match field("request") {
+        Some(json::Value::String(request)) if request == "detect" => {
+            metrics().detections += 1;
+
+            Ok(detect_cached()
+                .and_then(|terminal| cache::to_json(&terminal, 0))
+                .unwrap_or_else(|| String::from("{}")))
+        }
+        Some(json::Value::String(request)) if request == "launch" => {
+            let Some(json::Value::String(program)) = field("program") else {
+                return Err(invalid());
+            };
+            let args = match field("args") {
+                Some(json::Value::Strings(args)) => args.as_slice(),
+                None => &[],
+                _ => return Err(invalid()),
+            };
+            let options = match field("cwd") {
+                Some(json::Value::String(cwd)) => LaunchOptions::new().current_dir(cwd),
+                Some(json::Value::Null) | None => LaunchOptions::new(),
+                _ => return Err(invalid()),
+            };
+            let terminal = detect_cached().ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, "no terminal emulator found")
+            })?;
+            let result = Launcher::new(&terminal).launch_with_options(program, args, &options);
+
+            let mut metrics = metrics();
+            let counts = metrics
+                .launches
+                .entry(terminal.name().to_owned())
+                .or_default();
+            counts.0 += 1;
+            counts.1 += u64::from(result.is_err());
+            drop(metrics);
+
+            Ok(format!("{{\"pid\": {}}}", result?.pid))
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// Sends `request` to the daemon listening on `path`, returning the fields of its response.
+fn request(path: &Path, request: &str) -> io::Result<Vec<(String, json::Value)>> {
+    let mut stream = UnixStream::connect(path)?;
+    stream.write_all(request.as_bytes())?;
+    stream.write_all(b"\n")?;
+
+    let mut response = String::new();
+    BufReader::new(stream.take(MAX_MESSAGE_LEN)).read_line(&mut response)?;
+
+    let fields = json::parse_flat_object(&response)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid response"))?;
+
+    match fields.iter().find(|(key, _)| key == "error") {
+        Some((_, json::Value::String(error))) => Err(io::Error::other(error.clone())),
+        _ => Ok(fields),
+    }
+}
+
+/// Asks the daemon listening on `path` for the default terminal emulator, like [`detect`](crate::detect).
+///
+/// Fails if no daemon is listening on `path`, callers usually fall back to [`detect_cached`] then.
+pub fn detect_via_daemon(path: &Path) -> io::Result<Option<TerminalEmulator>> {
+    let fields = self::request(path, r#"{"request": "detect"}"#)?;
+
+    if fields.is_empty() {
+        return Ok(None);
+    }
+
+    cache::from_fields(&fields, 0)
+        .map(Some)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid terminal emulator"))
+}
+
+/// Asks the daemon listening on `path` to launch `program` with `args` in the default terminal emulator, in the
+/// current working directory, returning the process ID of the terminal emulator.
+///
+/// The program, arguments and working directory must be valid Unicode.
+pub fn launch_via_daemon<S, I, A>(path: &Path, program: S, args: I) -> io::Result<u32>
+where
+    S: AsRef<OsStr>,
+    I: IntoIterator<Item = A>,
+    A: AsRef<OsStr>,
+{
+    launch_in(path, program, args, &std::env::current_dir()?)
+}
+
+/// Asks the daemon listening on `path` to launch `program` with `args` in `cwd`.
+fn launch_in<S, I, A>(path: &Path, program: S, args: I, cwd: &Path) -> io::Result<u32>
+where
+    S: AsRef<OsStr>,
+    I: IntoIterator<Item = A>,
+    A: AsRef<OsStr>,
+{
+    let unicode = |value: &OsStr| {
+        value
+            .to_str()
+            .map(str::to_owned)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not valid Unicode"))
+    };
+    let program = unicode(program.as_ref())?;
+    let args = args
+        .into_iter()
+        .map(|arg| unicode(arg.as_ref()))
+        .collect::<io::Result<Vec<_>>>()?;
+    let request = format!(
+        r#"{{"request": "launch", "program": {}, "args": {}, "cwd": {}}}"#,
+        json::string(&program),
+        json::string_array(args),
+        json::string(&unicode(cwd.as_os_str())?),
+    );
+
+    match self::request(path, &request)?
+        .into_iter()
+        .find(|(key, _)| key == "pid")
+    {
+        Some((_, json::Value::Number(pid))) => u32::try_from(pid)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid process ID")),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "invalid response",
+        )),
+    }
+}
+
+/// A client of the daemon, detecting and launching in-process when no daemon is running.
+///
+/// Short-lived programs create one with [`DaemonClient::connect`] and use it like [`detect_cached`] and
+/// [`Launcher::launch`], without having to check whether a daemon is running, e.g.
+/// `DaemonClient::connect()?.detect()?`. A daemon exiting between two requests is also handled by falling back.
+#[derive(Debug, Clone)]
+pub struct DaemonClient {
+    /// The socket of the running daemon, `None` when detecting and launching in-process.
+    socket: Option<PathBuf>,
+}
+
+impl DaemonClient {
+    /// Connects to the daemon listening on [`daemon_socket_path`], or falls back to in-process detection and launches
+    /// if none is running.
+    pub fn connect() -> io::Result<Self> {
+        match daemon_socket_path() {
+            Some(path) => Self::connect_to(path),
+            None => Ok(Self { socket: None }),
+        }
+    }
+
+    /// Connects to the daemon listening on `path`, or falls back to in-process detection and launches if none is
+    /// running.
+    ///
+    /// Fails if the socket exists but cannot be connected to for another reason than no daemon listening on it (e.g.
+    /// [`io::ErrorKind::PermissionDenied`]).
+    pub fn connect_to<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+
+        match UnixStream::connect(path) {
+            Ok(_) => Ok(Self {
+                socket: Some(path.to_owned()),
+            }),
+            Err(error) if not_running(&error) => Ok(Self { socket: None }),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Returns `true` if requests are sent to a daemon, `false` if they are handled in-process.
+    pub fn is_connected(&self) -> bool {
+        self.socket.is_some()
+    }
+
+    /// Returns the default terminal emulator, from the daemon if one is running, otherwise from [`detect_cached`].
+    pub fn detect(&self) -> io::Result<Option<TerminalEmulator>> {
+        if let Some(path) = &self.socket {
+            match detect_via_daemon(path) {
+                Err(error) if not_running(&error) => {}
+                result => return result,
+            }
+        }
+
+        Ok(detect_cached())
+    }
+
+    /// Launches the program of `spec` with its arguments, in its working directory or the current one, in the
+    /// default terminal emulator, returning the process ID of the terminal emulator.
+    ///
+    /// The launch is done by the daemon if one is running, otherwise in-process like [`Launcher::launch`]. The daemon
+    /// only receives the program, arguments and working directory: commands changing environment variables are always
+    /// launched in-process, so are commands that are not valid Unicode.
+    pub fn launch(&self, spec: &CommandSpec) -> io::Result<u32> {
+        let cwd = match &spec.cwd {
+            Some(cwd) => cwd.clone(),
+            None => std::env::current_dir()?,
+        };
+
+        if let Some(path) = &self.socket
+            && spec.env.is_empty()
+        {
+            match launch_in(path, &spec.program, &spec.args, &cwd) {
+                Err(error)
+                    if not_running(&error) || error.kind() == io::ErrorKind::InvalidInput => {}
+                result => return result,
+            }
+        }
+
+        let terminal = detect_cached()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no terminal emulator found"))?;
+        let options = LaunchOptions {
+            env: spec.env.clone(),
+            ..LaunchOptions::new().current_dir(cwd)
+        };
+
+        Launcher::new(&terminal)
+            .launch_with_options(&spec.program, &spec.args, &options)
+            .map(|result| result.pid)
+    }
+}
+
+/// Returns `true` if `error` means no daemon is listening on the socket.
+fn not_running(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused
+    )
+}
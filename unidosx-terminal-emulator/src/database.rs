@@ -0,0 +1,115 @@
+//! Export of the known terminal emulator database.
+
+use std::path::PathBuf;
+
+use crate::{
+    DetectionMethod, ExecutionSyntax, Launcher, TerminalEmulator, json,
+    probes::{
+        FRAMEBUFFER_TERMINALS, HARDCODED_DESKTOP_ENV, HARDCODED_EXTENDED, HARDCODED_MODERN,
+        HARDCODED_TRADITIONAL,
+    },
+};
+
+/// The version of the database schema.
+pub const DATABASE_SCHEMA_VERSION: u32 = 1;
+
+/// Exports the known terminal emulator database as a JSON document.
+///
+/// Allows other ecosystems (e.g. Python launchers, shell scripts) to use the database without linking this crate:
+///
+/// ```json
+/// {
+///   "schema_version": 1,
+///   "terminals": [
+///     {
+///       "name": "kmscon",
+///       "list": "traditional",
+///       "execution_syntax": "double-dash",
+///       "exec_args": ["--login", "--"],
+///       "single_argument": false,
+///       "framebuffer": true
+///     }
+///   ]
+/// }
+/// ```
+///
+/// - `name`: the executable name of the terminal emulator.
+/// - `list`: the hardcoded list it belongs to (`desktop-env`, `modern`, `traditional` or `extended`).
+/// - `execution_syntax`: `command` (`terminal [command]`), `double-dash` (`terminal -- command`), `e`
+///   (`terminal -e command`), `single-e` (`terminal -e 'command'`), `flag:<flag>` (`terminal <flag> command`) or
+///   `single-flag:<flag>` (`terminal <flag> 'command'`).
+/// - `exec_args`: the arguments placed between the executable and the command.
+/// - `single_argument`: whether the command must be given as a single `sh -c` command line.
+/// - `framebuffer`: whether it takes over the current virtual console instead of opening a window.
+///
+/// The schema version is incremented whenever a field is removed or changes meaning, new fields may be added without
+/// changing it.
+pub fn export_database() -> String {
+    let lists = [
+        (
+            "desktop-env",
+            HARDCODED_DESKTOP_ENV,
+            DetectionMethod::HardcodedDesktopEnv,
+        ),
+        ("modern", HARDCODED_MODERN, DetectionMethod::HardcodedModern),
+        (
+            "traditional",
+            HARDCODED_TRADITIONAL,
+            DetectionMethod::HardcodedTraditional,
+        ),
+        (
+            "extended",
+            HARDCODED_EXTENDED,
+            DetectionMethod::HardcodedExtended,
+        ),
+    ];
+
+    let terminals: Vec<String> = lists
+        .into_iter()
+        .flat_map(|(list, terminals, method)| {
+            terminals
+                .iter()
+                .map(move |&(name, syntax)| export_terminal(list, name, syntax, method))
+        })
+        .collect();
+
+    format!(
+        "{{\n  \"schema_version\": {DATABASE_SCHEMA_VERSION},\n  \"terminals\": [\n{}\n  ]\n}}\n",
+        terminals.join(",\n")
+    )
+}
+
+/// Exports a terminal emulator as a JSON object.
+fn export_terminal(
+    list: &str,
+    name: &'static str,
+    execution_syntax: ExecutionSyntax,
+    method: DetectionMethod,
+) -> String {
+    let terminal = TerminalEmulator {
+        command_line: name.into(),
+        execution_syntax,
+        path: PathBuf::from(name),
+        method,
+        raw: None,
+    };
+
+    let exec_args = match Launcher::new(&terminal) {
+        Launcher::Terminal { args, .. } => args
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    format!(
+        "    {{\"name\": {}, \"list\": {}, \"execution_syntax\": {}, \"exec_args\": {}, \"single_argument\": {}, \
+         \"framebuffer\": {}}}",
+        json::string(name),
+        json::string(list),
+        json::string(&execution_syntax.id()),
+        json::string_array(exec_args),
+        execution_syntax.is_single_argument(),
+        FRAMEBUFFER_TERMINALS.contains(&name),
+    )
+}
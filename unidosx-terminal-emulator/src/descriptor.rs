@@ -0,0 +1,160 @@
+//! Descriptors of launches, to re-open them after a restart.
+//!
+//! A [`LaunchDescriptor`] keeps the command of a launch (the terminal emulator, the command it runs, its working
+//! directory and options) as a JSON object, so applications can save the tool windows they opened and re-open them
+//! with [`relaunch`] once restarted.
+
+use std::{ffi::OsStr, io, time::SystemTime};
+
+use crate::{CommandSpec, Compositor, LaunchBackend, LaunchResult, json, launcher, tagging};
+
+/// The version of the descriptor format, increased when its fields change.
+const DESCRIPTOR_VERSION: u64 = 1;
+
+/// Every compositor, to parse them back from a descriptor.
+const COMPOSITORS: &[Compositor] = &[
+    Compositor::Sway,
+    Compositor::Hyprland,
+    Compositor::Niri,
+    Compositor::River,
+    Compositor::KWin,
+    Compositor::Mutter,
+    Compositor::Wayfire,
+    Compositor::Labwc,
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Enough information to re-open a launch, see [`LaunchResult::to_descriptor`] and [`relaunch`].
+pub struct LaunchDescriptor {
+    /// The command that was spawned, without the variables of the session and the launch ID.
+    pub spec: CommandSpec,
+    /// The backend that handled the launch.
+    pub backend: LaunchBackend,
+}
+
+impl LaunchDescriptor {
+    /// Serializes the descriptor as a single-line JSON object.
+    ///
+    /// Paths, arguments and environment variables that are not valid Unicode are converted lossily.
+    pub fn to_json(&self) -> String {
+        let lossy = |value: &OsStr| value.to_string_lossy().into_owned();
+        let env =
+            self.spec.env.iter().filter_map(|(var, value)| {
+                Some(format!("{}={}", lossy(var), lossy(value.as_ref()?)))
+            });
+        let env_remove = self
+            .spec
+            .env
+            .iter()
+            .filter(|(_, value)| value.is_none())
+            .map(|(var, _)| lossy(var));
+
+        format!(
+            "{{\"version\": {DESCRIPTOR_VERSION}, \"backend\": {}, \"program\": {}, \"args\": {}, \"cwd\": {}, \
+             \"env\": {}, \"env_remove\": {}, \"new_console\": {}}}",
+            json::string(&backend_id(self.backend)),
+            json::string(&lossy(&self.spec.program)),
+            json::string_array(self.spec.args.iter().map(|arg| lossy(arg))),
+            self.spec.cwd.as_ref().map_or_else(
+                || String::from("null"),
+                |cwd| json::string(&lossy(cwd.as_os_str()))
+            ),
+            json::string_array(env),
+            json::string_array(env_remove),
+            self.spec.new_console,
+        )
+    }
+
+    /// Parses a descriptor serialized by [`LaunchDescriptor::to_json`].
+    ///
+    /// Returns `None` if `json` is not a descriptor, or was written by an incompatible version of this crate.
+    pub fn from_json(json: &str) -> Option<Self> {
+        let fields = json::parse_flat_object(json)?;
+        let field = |name: &str| {
+            fields
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value)
+        };
+        let string = |name: &str| match field(name)? {
+            json::Value::String(value) => Some(value.as_str()),
+            _ => None,
+        };
+        let strings = |name: &str| match field(name)? {
+            json::Value::Strings(values) => Some(values.as_slice()),
+            _ => None,
+        };
+
+        if field("version")? != &json::Value::Number(DESCRIPTOR_VERSION) {
+            return None;
+        }
+
+        let mut spec = CommandSpec::new(string("program")?).args(strings("args")?);
+        spec.cwd = string("cwd").map(Into::into);
+        spec.new_console = field("new_console")? == &json::Value::Bool(true);
+
+        for var in strings("env")? {
+            let (var, value) = var.split_once('=')?;
+            spec = spec.env(var, value);
+        }
+
+        for var in strings("env_remove")? {
+            spec = spec.env_remove(var);
+        }
+
+        Some(Self {
+            spec,
+            backend: parse_backend(string("backend")?)?,
+        })
+    }
+}
+
+/// Re-opens the launch described by `descriptor`, as [`Launcher::launch`](crate::Launcher::launch) did.
+///
+/// The launch is tagged with a new launch ID if tagging is enabled (see [`tag_launches`](crate::tag_launches)), gets
+/// the variables of the current session, and is recorded in the launch history.
+pub fn relaunch(descriptor: &LaunchDescriptor) -> io::Result<LaunchResult> {
+    let requested_at = SystemTime::now();
+    let graphical = launcher::needs_graphical_session(descriptor.backend, &descriptor.spec.program);
+
+    launcher::spawn(
+        tagging::tag(descriptor.spec.clone()),
+        descriptor.backend,
+        graphical,
+        requested_at,
+    )
+}
+
+/// Returns the identifier of `backend` used in descriptors (e.g. `compositor-spawn:sway`).
+fn backend_id(backend: LaunchBackend) -> String {
+    match backend {
+        LaunchBackend::Exec => String::from("exec"),
+        LaunchBackend::NewConsole => String::from("new-console"),
+        LaunchBackend::CmdStart => String::from("cmd-start"),
+        LaunchBackend::WindowsTerminalTab => String::from("windows-terminal-tab"),
+        LaunchBackend::VirtualTerminal => String::from("virtual-terminal"),
+        LaunchBackend::CompositorSpawn(compositor) => {
+            format!("compositor-spawn:{}", compositor.to_string().to_lowercase())
+        }
+    }
+}
+
+/// Parses a backend identifier written by [`backend_id`].
+fn parse_backend(id: &str) -> Option<LaunchBackend> {
+    match id {
+        "exec" => Some(LaunchBackend::Exec),
+        "new-console" => Some(LaunchBackend::NewConsole),
+        "cmd-start" => Some(LaunchBackend::CmdStart),
+        "windows-terminal-tab" => Some(LaunchBackend::WindowsTerminalTab),
+        "virtual-terminal" => Some(LaunchBackend::VirtualTerminal),
+        _ => {
+            let name = id.strip_prefix("compositor-spawn:")?;
+
+            COMPOSITORS
+                .iter()
+                .find(|compositor| compositor.to_string().to_lowercase() == name)
+                .copied()
+                .map(LaunchBackend::CompositorSpawn)
+        }
+    }
+}
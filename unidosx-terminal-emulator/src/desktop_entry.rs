@@ -0,0 +1,82 @@
+//! Desktop entry lookup, used to show terminal emulators with their user-facing names.
+
+use std::path::Path;
+
+use crate::{environment, filesystem, ini::Ini, lookup::same_name, parse_exec_line, xdg};
+
+/// The group holding the keys of a desktop entry.
+const DESKTOP_ENTRY: &str = "Desktop Entry";
+
+/// Finds the desktop entry of the application whose executable is named `executable`.
+///
+/// The `applications` directories of the XDG data directories are searched in order of preference, matching the
+/// `TryExec` key or the program of the `Exec` key. Hidden entries are skipped.
+pub(crate) fn find_desktop_entry(executable: &str) -> Option<Ini> {
+    xdg::data_dirs()
+        .into_iter()
+        .filter_map(|dir| filesystem::read_dir(&dir.join("applications")).ok())
+        .flat_map(|mut paths| {
+            paths.sort();
+            paths
+        })
+        .filter(|path| path.extension().is_some_and(|ext| ext == "desktop"))
+        .filter_map(|path| filesystem::read_to_string(&path).ok())
+        .map(|contents| Ini::parse(&contents))
+        .find(|entry| entry_runs(entry, executable))
+}
+
+/// Finds a desktop entry by its desktop file ID (e.g. `org.gnome.Console.desktop`) in the XDG data directories.
+#[cfg(feature = "xdg-terminal-exec")]
+pub(crate) fn find_desktop_entry_by_id(id: &str) -> Option<Ini> {
+    if id.contains('/') {
+        return None;
+    }
+
+    xdg::data_dirs()
+        .into_iter()
+        .find_map(|dir| filesystem::read_to_string(&dir.join("applications").join(id)).ok())
+        .map(|contents| Ini::parse(&contents))
+        .filter(|entry| entry.get(DESKTOP_ENTRY, "Hidden") != Some("true"))
+}
+
+/// Returns `true` if the desktop entry runs the executable named `executable`.
+pub(crate) fn entry_runs(entry: &Ini, executable: &str) -> bool {
+    if entry.get(DESKTOP_ENTRY, "Hidden") == Some("true") {
+        return false;
+    }
+
+    entry_program(entry).is_some_and(|program| {
+        Path::new(&program)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| same_name(name, executable))
+    })
+}
+
+/// Returns the program run by a desktop entry, from its `TryExec` key or the program of its `Exec` key.
+pub(crate) fn entry_program(entry: &Ini) -> Option<String> {
+    match entry.get(DESKTOP_ENTRY, "TryExec") {
+        Some(program) => Some(program.to_owned()),
+        None => parse_exec_line(entry.get(DESKTOP_ENTRY, "Exec")?)
+            .program
+            .into_string()
+            .ok(),
+    }
+    .filter(|program| !program.is_empty())
+}
+
+/// Returns the `Name` of a desktop entry in the language of the user.
+pub(crate) fn localized_name(entry: &Ini) -> Option<&str> {
+    entry
+        .get_localized(DESKTOP_ENTRY, "Name", messages_locale().as_deref())
+        .filter(|name| !name.is_empty())
+}
+
+/// Returns the locale used for messages (`LC_ALL`, `LC_MESSAGES`, then `LANG`), `None` for the C locale.
+pub(crate) fn messages_locale() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .into_iter()
+        .filter_map(environment::var)
+        .find(|locale| !locale.is_empty())
+        .filter(|locale| locale != "C" && locale != "POSIX" && !locale.starts_with("C."))
+}
@@ -0,0 +1,223 @@
+//! Configurable detection runs.
+//!
+//! [`Detector`] runs the detection methods of [`detect`] against the current system, or against another system
+//! mounted under a directory (e.g. a container or VM image) to answer "what terminal emulator would this system pick?"
+//! without booting it. The detection methods and their order can also be chosen at runtime with [`Detector::methods`]
+//! and [`Detector::disable`], and detection logic of other crates added with [`Detector::with_backend`].
+
+use std::{cell::RefCell, path::PathBuf, rc::Rc, sync::Arc};
+
+use crate::{
+    DetectionBackend, DetectionMethod, DetectionReport, Environment, PROBES, RootFilesystem,
+    TerminalEmulator,
+    backend::{self, Backend},
+    detect, detect_report, filesystem,
+    probes::ProbeResult,
+    report::Probe,
+};
+
+thread_local! {
+    /// The detection methods run on the current thread, if not the default ones.
+    static METHODS: RefCell<Option<Rc<Methods>>> = const { RefCell::new(None) };
+}
+
+/// The environment of a system inspected by [`Detector::for_root`], as set by a typical login without a user session.
+const ROOT_ENVIRONMENT: &[(&str, &str)] = &[
+    (
+        "PATH",
+        "/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin",
+    ),
+    ("XDG_CONFIG_DIRS", "/etc/xdg"),
+    ("XDG_DATA_DIRS", "/usr/local/share:/usr/share"),
+];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+/// Runs the detection methods of [`detect`] against the current system or another one.
+pub struct Detector {
+    /// The directory the inspected system is mounted on, or `None` for the current system.
+    root: Option<PathBuf>,
+    /// The detection methods to run.
+    methods: Methods,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+/// The detection methods run by a [`Detector`].
+struct Methods {
+    /// The detection methods to run in order, or `None` for the default ones.
+    order: Option<Vec<DetectionMethod>>,
+    /// The detection methods skipped at runtime.
+    disabled: Vec<DetectionMethod>,
+    /// The backends registered by other crates, in order of registration.
+    backends: Vec<Backend>,
+}
+
+/// A detection method run by [`detect`], built in or provided by a backend.
+pub(crate) enum Step {
+    /// A built-in detection method.
+    Probe(Probe),
+    /// A backend registered with [`Detector::with_backend`].
+    Backend(Backend),
+}
+
+impl Step {
+    /// Runs the detection method.
+    pub(crate) fn run(&self) -> ProbeResult {
+        match self {
+            Self::Probe((_, probe)) => probe(),
+            Self::Backend(backend) => backend.run(),
+        }
+    }
+}
+
+impl Detector {
+    /// Creates a detector for the current system, equivalent to [`detect`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a detector for the system mounted on `root` (e.g. an extracted container image or a mounted VM disk).
+    ///
+    /// The system-wide configuration is read from the inspected system (e.g. `/etc/xdg/kdeglobals`,
+    /// `/etc/unidosx/terminal.conf`, the `x-terminal-emulator` alternative) and executables are searched in its
+    /// standard directories, with the environment of a login without a user session. The detection methods querying
+    /// the running session (e.g. the GNOME settings through `gsettings`) are skipped, and the state of the current
+    /// session (display server, launch failures) is ignored.
+    ///
+    /// The paths of the detected terminal emulators are the ones of the inspected system (e.g. `/usr/bin/kitty`).
+    pub fn for_root<P: Into<PathBuf>>(root: P) -> Self {
+        Self {
+            root: Some(root.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Runs only `methods`, in the given order, instead of every enabled detection method in the default order (e.g.
+    /// to prefer the KDE settings over `TERMINAL_EMULATOR`).
+    ///
+    /// Methods listed more than once are run at their first position. Methods whose feature is disabled are skipped,
+    /// and reported with `SkipReason::FeatureDisabled` by [`Detector::detect_report`]. A terminal emulator locked by the
+    /// system-wide configuration file (see [`is_terminal_locked`](crate::is_terminal_locked)) is still returned, even if
+    /// [`DetectionMethod::ConfigFile`] is not listed. Backends are placed according to their priority, see
+    /// [`Detector::with_backend`].
+    pub fn methods<I: IntoIterator<Item = DetectionMethod>>(mut self, methods: I) -> Self {
+        let mut ordered: Vec<DetectionMethod> = Vec::new();
+
+        for method in methods {
+            if !ordered.contains(&method) {
+                ordered.push(method);
+            }
+        }
+
+        self.methods.order = Some(ordered);
+        self
+    }
+
+    /// Skips `method` at runtime (e.g. `x-terminal-emulator` for some users), without recompiling without its feature.
+    ///
+    /// Disabled methods are reported with `SkipReason::Disabled` by [`Detector::detect_report`]. Disabling
+    /// [`DetectionMethod::ConfigFile`] does not bypass a terminal emulator locked by the system-wide configuration file
+    /// (see [`is_terminal_locked`](crate::is_terminal_locked)).
+    pub fn disable(mut self, method: DetectionMethod) -> Self {
+        if !self.methods.disabled.contains(&method) {
+            self.methods.disabled.push(method);
+        }
+
+        self
+    }
+
+    /// Runs `backend` alongside the built-in detection methods, before them if its priority is positive and after them
+    /// otherwise, see [`DetectionBackend::priority`].
+    ///
+    /// The terminal emulators found by the backend are reported as found by `DetectionMethod::Backend` with its name,
+    /// which can be passed to [`Detector::disable`]. A terminal emulator locked by the system-wide configuration file
+    /// (see [`is_terminal_locked`](crate::is_terminal_locked)) is still returned instead.
+    pub fn with_backend<B: DetectionBackend + 'static>(mut self, backend: B) -> Self {
+        self.methods.backends.push(Backend(Arc::new(backend)));
+        self
+    }
+
+    /// Detects the default terminal emulator, see [`detect`].
+    pub fn detect(&self) -> Option<TerminalEmulator> {
+        self.run(detect)
+    }
+
+    /// Detects the default terminal emulator, reporting what every detection method found, see [`detect_report`].
+    pub fn detect_report(&self) -> DetectionReport {
+        self.run(detect_report)
+    }
+
+    /// Runs `f` against the system inspected by this detector.
+    fn run<R, F: FnOnce() -> R>(&self, f: F) -> R {
+        /// Restores the previous detection methods, even if `f` panics.
+        struct Restore(Option<Rc<Methods>>);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                METHODS.with(|methods| *methods.borrow_mut() = self.0.take());
+            }
+        }
+
+        let previous = METHODS.with(|current| current.replace(Some(Rc::new(self.methods.clone()))));
+        let _restore = Restore(previous);
+
+        match &self.root {
+            Some(root) => filesystem::offline(RootFilesystem::new(root), || {
+                ROOT_ENVIRONMENT
+                    .iter()
+                    .copied()
+                    .collect::<Environment>()
+                    .scope(f)
+            }),
+            None => f(),
+        }
+    }
+}
+
+/// Returns the detection methods run on the current thread in order, including the disabled ones, if not the default
+/// ones.
+pub(crate) fn order() -> Option<Vec<DetectionMethod>> {
+    METHODS.with(|methods| methods.borrow().as_ref()?.order.clone())
+}
+
+/// Returns `true` if `method` is disabled at runtime on the current thread, see [`Detector::disable`].
+pub(crate) fn is_disabled(method: DetectionMethod) -> bool {
+    METHODS.with(|methods| {
+        methods
+            .borrow()
+            .as_ref()
+            .is_some_and(|methods| methods.disabled.contains(&method))
+    })
+}
+
+/// Returns the backends registered on the current thread, see [`Detector::with_backend`].
+pub(crate) fn backends() -> Vec<Backend> {
+    METHODS.with(|methods| {
+        methods
+            .borrow()
+            .as_ref()
+            .map(|methods| methods.backends.clone())
+            .unwrap_or_default()
+    })
+}
+
+/// Returns the enabled detection methods run on the current thread, in order.
+pub(crate) fn steps() -> Vec<Step> {
+    let probes = match order() {
+        Some(order) => order
+            .iter()
+            .filter_map(|&method| PROBES.iter().find(|(probe, _)| *probe == method).copied())
+            .collect(),
+        None => PROBES.to_vec(),
+    };
+    let probes = probes
+        .into_iter()
+        .filter(|&(method, _)| !is_disabled(method))
+        .map(Step::Probe)
+        .collect();
+    let backends: Vec<Backend> = backends()
+        .into_iter()
+        .filter(|backend| !is_disabled(backend.method()))
+        .collect();
+
+    backend::around(probes, &backends, |backend| Step::Backend(backend.clone()))
+}
@@ -0,0 +1,109 @@
+//! Immutable snapshots of the process environment.
+//!
+//! Detection reads many environment variables across its methods. Reading them from a single snapshot keeps every
+//! method consistent even if the environment changes while detecting, and lets callers reproduce a detection run with
+//! a given environment.
+
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    env,
+    ffi::{OsStr, OsString},
+    rc::Rc,
+};
+
+thread_local! {
+    /// The snapshot used by the detection methods running on the current thread, if any.
+    static ACTIVE: RefCell<Option<Rc<Environment>>> = const { RefCell::new(None) };
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// An immutable snapshot of environment variables.
+pub struct Environment {
+    /// The environment variables, by name.
+    vars: BTreeMap<OsString, OsString>,
+}
+
+impl Environment {
+    /// Captures the environment of the current process.
+    pub fn capture() -> Self {
+        env::vars_os().collect()
+    }
+
+    /// Adds or replaces a variable.
+    pub fn var<K: AsRef<OsStr>, V: AsRef<OsStr>>(mut self, key: K, value: V) -> Self {
+        self.vars
+            .insert(key.as_ref().to_owned(), value.as_ref().to_owned());
+        self
+    }
+
+    /// Removes a variable.
+    pub fn remove_var<K: AsRef<OsStr>>(mut self, key: K) -> Self {
+        self.vars.remove(key.as_ref());
+        self
+    }
+
+    /// Returns the value of a variable.
+    pub fn get<K: AsRef<OsStr>>(&self, key: K) -> Option<&OsStr> {
+        self.vars.get(key.as_ref()).map(OsString::as_os_str)
+    }
+
+    /// Runs `f` with the detection methods on the current thread reading the environment from this snapshot.
+    ///
+    /// Scopes can be nested, the innermost snapshot is used.
+    pub fn scope<R, F: FnOnce() -> R>(&self, f: F) -> R {
+        /// Restores the previous snapshot, even if `f` panics.
+        struct Restore(Option<Rc<Environment>>);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                ACTIVE.with(|active| *active.borrow_mut() = self.0.take());
+            }
+        }
+
+        let previous = ACTIVE.with(|active| active.replace(Some(Rc::new(self.clone()))));
+        let _restore = Restore(previous);
+
+        f()
+    }
+}
+
+impl<K: AsRef<OsStr>, V: AsRef<OsStr>> FromIterator<(K, V)> for Environment {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        iter.into_iter()
+            .fold(Self::default(), |env, (key, value)| env.var(key, value))
+    }
+}
+
+/// Runs `f` with a single snapshot of the environment, captured now unless a scope is already active.
+#[cfg(not(windows))]
+pub(crate) fn snapshot<R, F: FnOnce() -> R>(f: F) -> R {
+    if ACTIVE.with(|active| active.borrow().is_some()) {
+        f()
+    } else {
+        Environment::capture().scope(f)
+    }
+}
+
+/// Returns the active snapshot, or the environment of the current process outside of a scope.
+#[cfg(not(windows))]
+pub(crate) fn current() -> Rc<Environment> {
+    ACTIVE
+        .with(|active| active.borrow().clone())
+        .unwrap_or_else(|| Rc::new(Environment::capture()))
+}
+
+/// Reads an environment variable from the active snapshot, or from the process environment outside of a scope.
+pub(crate) fn var_os<K: AsRef<OsStr>>(key: K) -> Option<OsString> {
+    let key = key.as_ref();
+
+    ACTIVE.with(|active| match &*active.borrow() {
+        Some(snapshot) => snapshot.get(key).map(ToOwned::to_owned),
+        None => env::var_os(key),
+    })
+}
+
+/// Reads an environment variable holding a Unicode value, see [`var_os`].
+pub(crate) fn var<K: AsRef<OsStr>>(key: K) -> Option<String> {
+    var_os(key).and_then(|value| value.into_string().ok())
+}
@@ -0,0 +1,115 @@
+//! Parsing of command lines in the `Exec` format of desktop entries.
+//!
+//! Desktop entries and the desktop settings naming a terminal emulator (e.g. `TerminalApplication` in `kdeglobals`)
+//! store command lines in the format of the `Exec` key of the
+//! [Desktop Entry Specification](https://specifications.freedesktop.org/desktop-entry-spec/latest/exec-variables.html).
+
+use crate::CommandSpec;
+
+/// Parses a command line in the format of the `Exec` key of desktop entries, as written in the file.
+///
+/// The value is unescaped (`\s`, `\n`, `\t`, `\r` and `\\`), then split into arguments at unquoted whitespace.
+/// Arguments can be quoted with double quotes, in which `\"`, `` \` ``, `\$` and `\\` stand for the escaped
+/// character. Field codes (e.g. `%U`, `%f`) are removed, dropping the arguments made only of them, and `%%` stands for
+/// `%`.
+///
+/// An empty or blank command line gives an empty program, and an unterminated quote extends to the end of the line.
+pub fn parse_exec_line(exec: &str) -> CommandSpec {
+    let mut words = split_quoted(&unescape(exec))
+        .into_iter()
+        .filter_map(|word| expand_field_codes(&word));
+
+    CommandSpec::new(words.next().unwrap_or_default()).args(words)
+}
+
+/// Applies the escape sequences of string values of desktop entries.
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('s') => unescaped.push(' '),
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some('r') => unescaped.push('\r'),
+            Some('\\') => unescaped.push('\\'),
+            Some(other) => {
+                unescaped.push('\\');
+                unescaped.push(other);
+            }
+            None => unescaped.push('\\'),
+        }
+    }
+
+    unescaped
+}
+
+/// Splits a command line into arguments at unquoted whitespace, removing the quotes.
+fn split_quoted(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quoted = false;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => quoted = !quoted,
+            '\\' if quoted => match chars.next() {
+                Some(escaped @ ('"' | '`' | '$' | '\\')) => word.push(escaped),
+                Some(other) => {
+                    word.push('\\');
+                    word.push(other);
+                }
+                None => word.push('\\'),
+            },
+            c if c.is_whitespace() && !quoted => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+                continue;
+            }
+            c => word.push(c),
+        }
+
+        in_word = true;
+    }
+
+    if in_word {
+        words.push(word);
+    }
+
+    words
+}
+
+/// Expands the field codes of an argument, returning `None` if it is made only of field codes.
+fn expand_field_codes(word: &str) -> Option<String> {
+    if !word.contains('%') {
+        return Some(word.to_owned());
+    }
+
+    let mut expanded = String::with_capacity(word.len());
+    let mut chars = word.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            expanded.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('%') => expanded.push('%'),
+            Some(_) => {}
+            None => expanded.push('%'),
+        }
+    }
+
+    (!expanded.is_empty()).then_some(expanded)
+}
@@ -0,0 +1,66 @@
+//! Session-scoped memory of terminal emulators that failed to launch.
+//!
+//! Terminal emulators that failed to launch during the current process are deprioritized by the detection methods,
+//! so a broken installation or wrapper script does not break every launch of the session.
+
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+};
+
+/// The terminal emulators that failed to launch, by path.
+static FAILED: Mutex<BTreeSet<PathBuf>> = Mutex::new(BTreeSet::new());
+
+/// Locks the failure memory, recovering it if a thread panicked while holding it.
+fn failed() -> MutexGuard<'static, BTreeSet<PathBuf>> {
+    FAILED
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Records that the terminal emulator at `path` failed to launch.
+pub fn record_launch_failure<P: AsRef<Path>>(path: P) {
+    failed().insert(path.as_ref().to_owned());
+}
+
+/// Returns `true` if the terminal emulator at `path` failed to launch during the current process.
+pub fn has_launch_failed<P: AsRef<Path>>(path: P) -> bool {
+    failed().contains(path.as_ref())
+}
+
+/// Forgets every recorded launch failure.
+pub fn clear_launch_failures() {
+    failed().clear();
+}
+
+/// Returns the first item whose path did not fail to launch, can open a window in the current session and is not a
+/// release with a known launch bug to avoid, or the first item if none of them can.
+///
+/// The current session is ignored when inspecting another system, the first item is returned.
+pub(crate) fn prefer_working<T, I, F>(items: I, path: F) -> Option<T>
+where
+    I: IntoIterator<Item = T>,
+    F: Fn(&T) -> &Path,
+{
+    let mut first_unusable = None;
+
+    if crate::filesystem::is_offline() {
+        return items.into_iter().next();
+    }
+
+    for item in items {
+        #[cfg(feature = "version-detection")]
+        let avoided = crate::versions::is_avoided(path(&item));
+        #[cfg(not(feature = "version-detection"))]
+        let avoided = false;
+
+        if !has_launch_failed(path(&item)) && crate::session::can_display(path(&item)) && !avoided {
+            return Some(item);
+        }
+
+        first_unusable.get_or_insert(item);
+    }
+
+    first_unusable
+}
@@ -0,0 +1,55 @@
+//! Launching with fallback to the next candidate terminal emulator.
+
+use std::{
+    ffi::{OsStr, OsString},
+    io,
+    time::Duration,
+};
+
+use crate::{LaunchResult, Launcher, TerminalEmulator, has_launch_failed, record_launch_failure};
+
+/// Launches `program` with `args` in the first candidate terminal emulator that starts successfully.
+///
+/// A candidate fails if it can not be spawned (e.g. the executable is missing) or if it exits unsuccessfully within
+/// `crash_window` (see [`LaunchResult::check_liveness`]), in which case the next candidate is tried.
+///
+/// Failing candidates are recorded with [`record_launch_failure`], and candidates that already failed during the
+/// current process are tried last.
+///
+/// Returns the candidate that worked with its [`LaunchResult`], or the error of the last candidate if all failed.
+pub fn launch_with_fallback<S, I, A>(
+    candidates: &[TerminalEmulator],
+    program: S,
+    args: I,
+    crash_window: Duration,
+) -> io::Result<(&TerminalEmulator, LaunchResult)>
+where
+    S: AsRef<OsStr>,
+    I: IntoIterator<Item = A>,
+    A: AsRef<OsStr>,
+{
+    let args: Vec<OsString> = args
+        .into_iter()
+        .map(|arg| arg.as_ref().to_owned())
+        .collect();
+    let mut last_error = io::Error::new(io::ErrorKind::NotFound, "no candidate terminal emulator");
+
+    // Candidates that already failed during this process are tried last.
+    let (working, failed): (Vec<_>, Vec<_>) = candidates
+        .iter()
+        .partition(|terminal| !has_launch_failed(&terminal.path));
+
+    for terminal in working.into_iter().chain(failed) {
+        match Launcher::new(terminal).launch_checked(program.as_ref(), &args, crash_window) {
+            Ok(result) => return Ok((terminal, result)),
+            // Every candidate would fail the same way without a graphical session.
+            Err(error) if error.kind() == io::ErrorKind::NotConnected => return Err(error),
+            Err(error) => {
+                record_launch_failure(&terminal.path);
+                last_error = error;
+            }
+        }
+    }
+
+    Err(last_error)
+}
@@ -0,0 +1,130 @@
+//! Compile-time check of the enabled detection methods.
+//!
+//! Building without any detection method for the target (e.g. with `default-features = false`, or only `terminal-app`
+//! on Linux) would make [`detect`](crate::detect) silently find nothing, so the build fails instead, naming the
+//! enabled features. The features of [`detect_current`](crate::detect_current) (`controlling-tty` on Linux,
+//! `process-ancestry` on Linux and macOS) are enough on their own, for crates only identifying the current terminal
+//! emulator.
+
+use crate::{DetectionMethod, PROBES};
+
+/// Every feature of this crate that is not a group of other features, and whether it is enabled.
+const FEATURES: &[(&str, bool)] = &[
+    ("terminal-app", cfg!(feature = "terminal-app")),
+    ("env-var", cfg!(feature = "env-var")),
+    ("config-file", cfg!(feature = "config-file")),
+    ("xdg-terminal-exec", cfg!(feature = "xdg-terminal-exec")),
+    ("x-terminal-emulator", cfg!(feature = "x-terminal-emulator")),
+    ("gnome-settings", cfg!(feature = "gnome-settings")),
+    ("kde-settings", cfg!(feature = "kde-settings")),
+    ("xfce-settings", cfg!(feature = "xfce-settings")),
+    ("lxqt-settings", cfg!(feature = "lxqt-settings")),
+    (
+        "hardcoded-traditional",
+        cfg!(feature = "hardcoded-traditional"),
+    ),
+    (
+        "hardcoded-desktop-env",
+        cfg!(feature = "hardcoded-desktop-env"),
+    ),
+    ("hardcoded-modern", cfg!(feature = "hardcoded-modern")),
+    ("hardcoded-extended", cfg!(feature = "hardcoded-extended")),
+    ("controlling-tty", cfg!(feature = "controlling-tty")),
+    ("process-ancestry", cfg!(feature = "process-ancestry")),
+    ("version-detection", cfg!(feature = "version-detection")),
+    ("benchmark", cfg!(feature = "benchmark")),
+    ("openvt", cfg!(feature = "openvt")),
+    ("focus-sway", cfg!(feature = "focus-sway")),
+    ("focus-hyprland", cfg!(feature = "focus-hyprland")),
+    ("focus-x11", cfg!(feature = "focus-x11")),
+    ("focus-applescript", cfg!(feature = "focus-applescript")),
+    ("watch", cfg!(feature = "watch")),
+    ("daemon", cfg!(feature = "daemon")),
+    ("terminal-query", cfg!(feature = "terminal-query")),
+    ("test-util", cfg!(feature = "test-util")),
+    (
+        "xdg-terminal-exec-bin",
+        cfg!(feature = "xdg-terminal-exec-bin"),
+    ),
+];
+
+/// The capacity of the buffer the error message is written to.
+const MESSAGE_CAPACITY: usize = 1024;
+
+/// The beginning of the error message, followed by the enabled features.
+const MESSAGE_PREFIX: &str = "no terminal emulator detection method is enabled for this target, so detect() would \
+                              never find one: enable the default features, or at least one detection feature (e.g. \
+                              `env-var`, `config-file`, `hardcoded`; `terminal-app` only detects on macOS), or `controlling-tty` or \
+                              `process-ancestry` to only use detect_current(). Enabled features: ";
+
+/// The error message and its length.
+const MESSAGE: ([u8; MESSAGE_CAPACITY], usize) = message();
+
+const _: () = {
+    let mut index = 0;
+    // The current terminal emulator is still detected by `detect_current`, e.g. for crates not launching any.
+    let mut enabled = cfg!(any(
+        all(target_os = "linux", feature = "controlling-tty"),
+        all(
+            any(target_os = "linux", target_os = "macos"),
+            feature = "process-ancestry"
+        )
+    ));
+
+    // The accessibility preference only searches a fixed list when asked to, it does not detect on its own.
+    while index < PROBES.len() {
+        enabled |= !matches!(PROBES[index].0, DetectionMethod::Accessibility);
+        index += 1;
+    }
+
+    if !enabled {
+        match std::str::from_utf8(MESSAGE.0.split_at(MESSAGE.1).0) {
+            Ok(message) => panic!("{}", message),
+            Err(_) => panic!("no terminal emulator detection method is enabled for this target"),
+        }
+    }
+};
+
+/// Writes the error message, listing the enabled features.
+const fn message() -> ([u8; MESSAGE_CAPACITY], usize) {
+    let mut buffer = [0; MESSAGE_CAPACITY];
+    let mut len = push(&mut buffer, 0, MESSAGE_PREFIX);
+    let mut index = 0;
+    let mut first = true;
+
+    while index < FEATURES.len() {
+        let (name, enabled) = FEATURES[index];
+
+        if enabled {
+            if !first {
+                len = push(&mut buffer, len, ", ");
+            }
+
+            len = push(&mut buffer, len, name);
+            first = false;
+        }
+
+        index += 1;
+    }
+
+    if first {
+        len = push(&mut buffer, len, "none");
+    }
+
+    len = push(&mut buffer, len, ".");
+
+    (buffer, len)
+}
+
+/// Appends `text` to `buffer` holding `len` bytes, returning the new length.
+const fn push(buffer: &mut [u8; MESSAGE_CAPACITY], len: usize, text: &str) -> usize {
+    let bytes = text.as_bytes();
+    let mut index = 0;
+
+    while index < bytes.len() {
+        buffer[len + index] = bytes[index];
+        index += 1;
+    }
+
+    len + bytes.len()
+}
@@ -0,0 +1,312 @@
+//! Pluggable filesystem used by the detection methods.
+//!
+//! Detection only needs to list directories, read file metadata and read settings files. Routing these through the
+//! [`Filesystem`] trait lets embedders run detection against a virtual filesystem or another root (e.g. a mounted
+//! container image, see [`RootFilesystem`]), and tests describe the installed executables without creating real
+//! files.
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::VecDeque,
+    ffi::OsString,
+    fs, io,
+    path::{Component, Path, PathBuf},
+    rc::Rc,
+};
+
+thread_local! {
+    /// The filesystem used by the detection methods running on the current thread, if not the real one.
+    static ACTIVE: RefCell<Option<Rc<dyn Filesystem>>> = const { RefCell::new(None) };
+
+    /// Whether the active filesystem is another system, inspected without its running session.
+    static OFFLINE: Cell<bool> = const { Cell::new(false) };
+}
+
+/// The maximum number of symbolic links followed when resolving a path in a [`RootFilesystem`], as done by Linux.
+const MAX_SYMLINKS: usize = 40;
+
+/// The error number reported when resolving a loop of symbolic links (`ELOOP`).
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const ELOOP: i32 = 40;
+
+/// The error number reported when resolving a loop of symbolic links (`ELOOP`).
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+const ELOOP: i32 = 62;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+/// The metadata of a file needed to search executables.
+pub struct FileInfo {
+    /// Whether the path is a directory.
+    pub is_dir: bool,
+    /// Whether the path is a regular file that can be executed.
+    pub is_executable: bool,
+}
+
+/// The filesystem operations used by the detection methods.
+pub trait Filesystem {
+    /// Returns the metadata of `path`, following symbolic links.
+    fn file_info(&self, path: &Path) -> io::Result<FileInfo>;
+
+    /// Returns the paths of the entries of the directory at `path`.
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>>;
+
+    /// Reads the file at `path` as a string.
+    fn read_to_string(&self, path: &Path) -> io::Result<String>;
+
+    /// Returns the absolute path of `path` with every symbolic link resolved.
+    ///
+    /// Filesystems without symbolic links can keep the default implementation, returning `path` unchanged.
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        Ok(path.to_path_buf())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+/// The filesystem of the current process, used unless another one is installed with [`with_filesystem`].
+pub struct RealFilesystem;
+
+impl Filesystem for RealFilesystem {
+    fn file_info(&self, path: &Path) -> io::Result<FileInfo> {
+        let metadata = path.metadata()?;
+
+        #[cfg(unix)]
+        let executable = {
+            use std::os::unix::fs::PermissionsExt;
+
+            metadata.permissions().mode() & 0o111 != 0
+        };
+        #[cfg(not(unix))]
+        let executable = true;
+
+        Ok(FileInfo {
+            is_dir: metadata.is_dir(),
+            is_executable: metadata.is_file() && executable,
+        })
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect()
+    }
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        fs::canonicalize(path)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// The filesystem of another system mounted under a directory of the current one (e.g. a container or VM image).
+///
+/// Absolute paths are resolved under the root directory, including the targets of symbolic links (e.g. the
+/// `/etc/alternatives` links of Debian), so the paths seen by the detection methods are the ones of the inspected
+/// system.
+pub struct RootFilesystem {
+    /// The directory the inspected system is mounted on.
+    root: PathBuf,
+}
+
+impl RootFilesystem {
+    /// Creates a filesystem rooted at `root`.
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Returns the path on the current system of `path` in the inspected system, following symbolic links.
+    fn resolve(&self, path: &Path) -> io::Result<PathBuf> {
+        let mut pending: VecDeque<OsString> = components(path).collect();
+        let mut resolved = PathBuf::from("/");
+        let mut followed = 0;
+
+        while let Some(component) = pending.pop_front() {
+            if component == "/" {
+                resolved = PathBuf::from("/");
+                continue;
+            }
+
+            if component == ".." {
+                resolved.pop();
+                continue;
+            }
+
+            let candidate = resolved.join(&component);
+            let host = self.host_path(&candidate);
+
+            match fs::symlink_metadata(&host) {
+                Ok(metadata) if metadata.file_type().is_symlink() => {
+                    followed += 1;
+
+                    if followed > MAX_SYMLINKS {
+                        return Err(symlink_loop());
+                    }
+
+                    let target = fs::read_link(&host)?;
+
+                    for component in components(&target).collect::<Vec<_>>().into_iter().rev() {
+                        pending.push_front(component);
+                    }
+                }
+                _ => resolved = candidate,
+            }
+        }
+
+        Ok(self.host_path(&resolved))
+    }
+
+    /// Returns the path on the current system of an absolute path without symbolic links in the inspected system.
+    fn host_path(&self, path: &Path) -> PathBuf {
+        self.root.join(path.strip_prefix("/").unwrap_or(path))
+    }
+}
+
+impl Filesystem for RootFilesystem {
+    fn file_info(&self, path: &Path) -> io::Result<FileInfo> {
+        RealFilesystem.file_info(&self.resolve(path)?)
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        Ok(fs::read_dir(self.resolve(path)?)?
+            .flatten()
+            .map(|entry| path.join(entry.file_name()))
+            .collect())
+    }
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        fs::read_to_string(self.resolve(path)?)
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        let resolved = self.resolve(path)?;
+        let inner = resolved.strip_prefix(&self.root).unwrap_or(&resolved);
+
+        Ok(Path::new("/").join(inner))
+    }
+}
+
+/// Returns the error of a loop of symbolic links, as reported by the operating system.
+fn symlink_loop() -> io::Error {
+    #[cfg(unix)]
+    {
+        io::Error::from_raw_os_error(ELOOP)
+    }
+
+    #[cfg(not(unix))]
+    {
+        io::Error::other("too many levels of symbolic links")
+    }
+}
+
+/// Returns `true` if `error` reports a loop of symbolic links.
+pub(crate) fn is_symlink_loop(error: &io::Error) -> bool {
+    #[cfg(unix)]
+    {
+        error.raw_os_error() == Some(ELOOP)
+    }
+
+    #[cfg(not(unix))]
+    {
+        error.to_string() == symlink_loop().to_string()
+    }
+}
+
+/// Splits `path` into the components resolved by [`RootFilesystem`], `/` restarting from the root.
+fn components(path: &Path) -> impl Iterator<Item = OsString> {
+    path.components().filter_map(|component| match component {
+        Component::RootDir => Some(OsString::from("/")),
+        Component::ParentDir => Some(OsString::from("..")),
+        Component::Normal(name) => Some(name.to_owned()),
+        Component::CurDir | Component::Prefix(_) => None,
+    })
+}
+
+/// Runs `f` with the executable lookups on the current thread going through `filesystem`.
+///
+/// Scopes can be nested, the innermost filesystem is used.
+pub fn with_filesystem<R, F: FnOnce() -> R>(filesystem: impl Filesystem + 'static, f: F) -> R {
+    /// Restores the previous filesystem, even if `f` panics.
+    struct Restore(Option<Rc<dyn Filesystem>>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            ACTIVE.with(|active| *active.borrow_mut() = self.0.take());
+        }
+    }
+
+    let previous = ACTIVE.with(|active| active.replace(Some(Rc::new(filesystem))));
+    let _restore = Restore(previous);
+
+    f()
+}
+
+/// Runs `f` with the detection methods on the current thread inspecting `filesystem` as another system.
+///
+/// The state of the current session (e.g. the display server, launch failures, the privileges of the current process)
+/// is ignored, and the detection methods querying it are skipped.
+#[cfg(not(windows))]
+pub(crate) fn offline<R, F: FnOnce() -> R>(filesystem: impl Filesystem + 'static, f: F) -> R {
+    /// Restores the previous state, even if `f` panics.
+    struct Restore(bool);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            OFFLINE.with(|offline| offline.set(self.0));
+        }
+    }
+
+    let _restore = Restore(OFFLINE.with(|offline| offline.replace(true)));
+
+    with_filesystem(filesystem, f)
+}
+
+/// Returns `true` if the detection methods on the current thread are inspecting another system, see [`offline`].
+pub(crate) fn is_offline() -> bool {
+    OFFLINE.with(Cell::get)
+}
+
+/// Returns `true` if the executable lookups on the current thread go through the real filesystem, see
+/// [`with_filesystem`].
+pub(crate) fn is_real() -> bool {
+    active().is_none()
+}
+
+/// Returns the metadata of `path` from the active filesystem.
+pub(crate) fn file_info(path: &Path) -> io::Result<FileInfo> {
+    match active() {
+        Some(filesystem) => filesystem.file_info(path),
+        None => RealFilesystem.file_info(path),
+    }
+}
+
+/// Lists the directory at `path` in the active filesystem.
+pub(crate) fn read_dir(path: &Path) -> io::Result<Vec<PathBuf>> {
+    match active() {
+        Some(filesystem) => filesystem.read_dir(path),
+        None => RealFilesystem.read_dir(path),
+    }
+}
+
+/// Reads the file at `path` from the active filesystem.
+pub(crate) fn read_to_string(path: &Path) -> io::Result<String> {
+    match active() {
+        Some(filesystem) => filesystem.read_to_string(path),
+        None => RealFilesystem.read_to_string(path),
+    }
+}
+
+/// Returns the absolute path of `path` in the active filesystem with every symbolic link resolved.
+#[cfg(not(windows))]
+pub(crate) fn canonicalize(path: &Path) -> io::Result<PathBuf> {
+    match active() {
+        Some(filesystem) => filesystem.canonicalize(path),
+        None => RealFilesystem.canonicalize(path),
+    }
+}
+
+/// Returns the filesystem installed on the current thread, if any.
+fn active() -> Option<Rc<dyn Filesystem>> {
+    ACTIVE.with(|active| active.borrow().clone())
+}
@@ -0,0 +1,105 @@
+//! Launches from inside a Flatpak sandbox.
+//!
+//! A Flatpak application only sees the executables of its runtime and its own bundle, so the terminal emulators
+//! installed on the host are neither found nor spawnable from inside the sandbox. When the current process is
+//! sandboxed, executables are looked up on the host and launches are wrapped in `flatpak-spawn --host`, which asks the
+//! Flatpak portal to run them outside of the sandbox. The application needs the `--talk-name=org.freedesktop.Flatpak`
+//! permission for it.
+
+use std::{
+    collections::BTreeMap,
+    ffi::{OsStr, OsString},
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+    time::Duration,
+};
+
+use crate::{CommandSpec, environment, filesystem, options::push_flag};
+
+/// The file describing the sandbox, present at the root of every Flatpak sandbox.
+const FLATPAK_INFO: &str = "/.flatpak-info";
+
+/// How long a lookup on the host may take.
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The executables already looked up on the host, by name.
+static HOST_EXECUTABLES: Mutex<BTreeMap<OsString, Option<PathBuf>>> = Mutex::new(BTreeMap::new());
+
+/// Locks the executables looked up on the host, recovering them if a thread panicked while holding them.
+fn host_executables() -> MutexGuard<'static, BTreeMap<OsString, Option<PathBuf>>> {
+    HOST_EXECUTABLES
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Returns `true` if the current process runs inside a Flatpak sandbox, so launches go through `flatpak-spawn --host`.
+pub fn is_flatpak_sandboxed() -> bool {
+    cfg!(target_os = "linux") && Path::new(FLATPAK_INFO).is_file()
+}
+
+/// Returns `true` if executables are looked up and launched on the host, rather than in the active filesystem.
+pub(crate) fn uses_host() -> bool {
+    filesystem::is_real() && !filesystem::is_offline() && is_flatpak_sandboxed()
+}
+
+/// Searches for an executable named `name` in the PATH of the host, through `flatpak-spawn --host`.
+///
+/// The results are cached, since every lookup spawns a process on the host.
+pub(crate) fn find_on_host(name: &OsStr) -> Option<PathBuf> {
+    if let Some(found) = host_executables().get(name) {
+        return found.clone();
+    }
+
+    let found = CommandSpec::new("flatpak-spawn")
+        .args(["--host", "sh", "-c", r#"command -v -- "$1""#, "sh"])
+        .arg(name)
+        .output_within(LOOKUP_TIMEOUT)
+        .filter(|output| output.status.success())
+        .and_then(|output| parse_lookup(&output.stdout));
+
+    host_executables().insert(name.to_owned(), found.clone());
+    found
+}
+
+/// Parses the output of `command -v`, which only names an executable if it prints an absolute path.
+pub(crate) fn parse_lookup(output: &[u8]) -> Option<PathBuf> {
+    let path = PathBuf::from(String::from_utf8_lossy(output.trim_ascii()).as_ref());
+
+    path.is_absolute().then_some(path)
+}
+
+/// Wraps `spec` in `flatpak-spawn --host`, passing its working directory and environment changes on to the host.
+pub(crate) fn host_command(spec: CommandSpec) -> CommandSpec {
+    let mut flags = vec![OsString::from("--host")];
+
+    if let Some(cwd) = &spec.cwd {
+        push_flag(&mut flags, "--directory=", cwd.as_os_str());
+    }
+
+    for (key, value) in &spec.env {
+        match value {
+            Some(value) => {
+                let mut assignment = key.clone();
+                assignment.push("=");
+                assignment.push(value);
+                push_flag(&mut flags, "--env=", &assignment);
+            }
+            None => push_flag(&mut flags, "--unset-env=", key),
+        }
+    }
+
+    CommandSpec::new("flatpak-spawn")
+        .args(flags)
+        .arg(&spec.program)
+        .args(&spec.args)
+}
+
+/// Returns the directory shared with the host for the files of launches, `$XDG_RUNTIME_DIR/app/$FLATPAK_ID`.
+///
+/// The temporary directory and `XDG_RUNTIME_DIR` of the sandbox are not visible on the host.
+pub(crate) fn shared_runtime_dir() -> Option<PathBuf> {
+    let runtime = crate::xdg::absolute(environment::var_os("XDG_RUNTIME_DIR"))?;
+    let id = environment::var_os("FLATPAK_ID").filter(|id| !id.is_empty())?;
+
+    Some(runtime.join("app").join(id))
+}
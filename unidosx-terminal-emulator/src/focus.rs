@@ -0,0 +1,200 @@
+//! Focusing the windows of the launched terminal emulators.
+//!
+//! Terminal emulators spawned from background processes often open unfocused, behind the active window. The window is
+//! found from the process ID of the launch and focused through the tools of the running session: `swaymsg` on sway,
+//! `hyprctl` on Hyprland, `xdotool` or `wmctrl` on X11 and AppleScript on macOS, each enabled by its own feature.
+
+use std::{
+    io,
+    process::Output,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{CommandSpec, LaunchResult, lookup::find_executable};
+
+/// How long [`focus_launched`] waits for the window of the launched terminal emulator to appear.
+const FOCUS_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How often the window of the launched terminal emulator is looked for.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The tools focusing windows in the running session.
+enum Route {
+    /// `swaymsg [pid=N] focus`.
+    #[cfg(feature = "focus-sway")]
+    Sway,
+    /// `hyprctl dispatch focuswindow pid:N`.
+    #[cfg(feature = "focus-hyprland")]
+    Hyprland,
+    /// `xdotool windowactivate`, or `wmctrl -i -a` if `xdotool` is not installed.
+    #[cfg(feature = "focus-x11")]
+    X11,
+    /// `osascript`, asking System Events to bring the process to the front.
+    #[cfg(feature = "focus-applescript")]
+    AppleScript,
+}
+
+/// Brings the window of the terminal emulator launched by `result` to the foreground.
+///
+/// The window is searched by the process ID of the launch and, if launches are tagged (see
+/// [`tag_launches`](crate::tag_launches)), the process IDs carrying its launch ID, so terminal emulators handing the
+/// window over to another process are found too. Blocks until the window is focused or, as it can take a moment to
+/// appear, up to two seconds.
+///
+/// Returns an error of kind [`io::ErrorKind::Unsupported`] if no focusing tool is enabled for the running session (see
+/// the `focus-*` features), [`io::ErrorKind::NotFound`] if no window of the launch appeared in time, or the error of
+/// the focusing tool.
+pub fn focus_launched(result: &LaunchResult) -> io::Result<()> {
+    let route = route().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            "no window focusing tool is enabled for the running session",
+        )
+    })?;
+    let deadline = Instant::now() + FOCUS_TIMEOUT;
+
+    loop {
+        let mut pids = vec![result.pid];
+
+        for pid in result
+            .launch_id()
+            .map(crate::find_launched)
+            .unwrap_or_default()
+        {
+            if !pids.contains(&pid) {
+                pids.push(pid);
+            }
+        }
+
+        for &pid in &pids {
+            if focus(route, pid)? {
+                return Ok(());
+            }
+        }
+
+        if Instant::now() >= deadline {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no window of process {} appeared", result.pid),
+            ));
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Returns the tool focusing windows in the running session, if its feature is enabled.
+fn route() -> Option<Route> {
+    #[cfg(feature = "focus-applescript")]
+    if cfg!(target_os = "macos") {
+        return Some(Route::AppleScript);
+    }
+
+    #[cfg(any(feature = "focus-sway", feature = "focus-hyprland"))]
+    match crate::detect_compositor() {
+        #[cfg(feature = "focus-sway")]
+        Some(crate::Compositor::Sway) => return Some(Route::Sway),
+        #[cfg(feature = "focus-hyprland")]
+        Some(crate::Compositor::Hyprland) => return Some(Route::Hyprland),
+        _ => {}
+    }
+
+    #[cfg(feature = "focus-x11")]
+    if crate::environment::var_os("DISPLAY").is_some_and(|display| !display.is_empty()) {
+        return Some(Route::X11);
+    }
+
+    None
+}
+
+/// Focuses the window of the process `pid` through `route`, returning `false` if the process has no window yet.
+fn focus(route: Route, pid: u32) -> io::Result<bool> {
+    match route {
+        #[cfg(feature = "focus-sway")]
+        Route::Sway => Ok(run(&focus_command(route, pid)?)?.status.success()),
+        #[cfg(feature = "focus-hyprland")]
+        Route::Hyprland => {
+            // hyprctl exits successfully even if no window matched, only answering `ok` if one did.
+            let output = run(&focus_command(route, pid)?)?;
+
+            Ok(output.status.success() && String::from_utf8_lossy(&output.stdout).trim() == "ok")
+        }
+        #[cfg(feature = "focus-x11")]
+        Route::X11 => {
+            if find_executable("xdotool").is_some() {
+                return Ok(run(&focus_command(route, pid)?)?.status.success());
+            }
+
+            let listing = run(&CommandSpec::new(tool("wmctrl")?).arg("-lp"))?;
+            let listing = String::from_utf8_lossy(&listing.stdout);
+            let windows = parse_wmctrl_windows(&listing, pid);
+
+            match windows.first() {
+                Some(window) => {
+                    let spec = CommandSpec::new(tool("wmctrl")?).args(["-i", "-a", window]);
+
+                    Ok(run(&spec)?.status.success())
+                }
+                None => Ok(false),
+            }
+        }
+        #[cfg(feature = "focus-applescript")]
+        Route::AppleScript => Ok(run(&focus_command(route, pid)?)?.status.success()),
+    }
+}
+
+/// Builds the command focusing the window of the process `pid` through `route`.
+///
+/// On X11, the command uses `xdotool`, the fallback to `wmctrl` needs to list the windows first.
+fn focus_command(route: Route, pid: u32) -> io::Result<CommandSpec> {
+    match route {
+        #[cfg(feature = "focus-sway")]
+        Route::Sway => Ok(CommandSpec::new(tool("swaymsg")?).arg(format!("[pid={pid}] focus"))),
+        #[cfg(feature = "focus-hyprland")]
+        Route::Hyprland => Ok(CommandSpec::new(tool("hyprctl")?)
+            .args(["dispatch", "focuswindow"])
+            .arg(format!("pid:{pid}"))),
+        // `search` selects the windows of the process, `windowactivate` then focuses the first one.
+        #[cfg(feature = "focus-x11")]
+        Route::X11 => Ok(CommandSpec::new(tool("xdotool")?)
+            .args(["search", "--limit", "1", "--pid"])
+            .arg(pid.to_string())
+            .arg("windowactivate")),
+        #[cfg(feature = "focus-applescript")]
+        Route::AppleScript => Ok(CommandSpec::new(tool("osascript")?).args([
+            "-e".to_owned(),
+            format!(
+                "tell application \"System Events\" to set frontmost of (first process whose unix id is {pid}) \
+                 to true"
+            ),
+        ])),
+    }
+}
+
+/// Returns the identifiers of the windows of the process `pid`, listed by `wmctrl -lp`.
+#[cfg(feature = "focus-x11")]
+pub(crate) fn parse_wmctrl_windows(listing: &str, pid: u32) -> Vec<&str> {
+    listing
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let window = fields.next()?;
+            let owner: u32 = fields.nth(1)?.parse().ok()?;
+
+            (owner == pid).then_some(window)
+        })
+        .collect()
+}
+
+/// Returns the path of the focusing tool `name`.
+fn tool(name: &str) -> io::Result<std::path::PathBuf> {
+    find_executable(name)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{name} is not installed")))
+}
+
+/// Runs `spec`, capturing its output.
+fn run(spec: &CommandSpec) -> io::Result<Output> {
+    spec.to_command().output()
+}
@@ -0,0 +1,183 @@
+//! Opt-in history of the launches performed through the crate.
+//!
+//! Each launch is appended as one JSON object per line to `unidosx/launch-history.jsonl` in the XDG state directory,
+//! helping to debug reports of launches that opened nothing.
+
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{CommandSpec, LaunchBackend, json};
+
+/// Whether launches are recorded in the history.
+static RECORD: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// The outcome of a recorded launch.
+pub enum LaunchOutcome {
+    /// The process was spawned.
+    Spawned {
+        /// The process ID of the spawned process.
+        pid: u32,
+    },
+    /// The process could not be spawned, or exited unsuccessfully right after being spawned.
+    Failed {
+        /// The error describing the failure.
+        error: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// A launch recorded in the history.
+pub struct LaunchRecord {
+    /// When the launch was requested, with millisecond precision.
+    pub timestamp: SystemTime,
+    /// The program that was executed, usually the terminal emulator.
+    pub program: String,
+    /// The whole command line that was executed.
+    pub command: String,
+    /// The backend that handled the launch.
+    pub backend: String,
+    /// The outcome of the launch.
+    pub outcome: LaunchOutcome,
+}
+
+/// Starts (or stops) recording launches in the history.
+///
+/// Disabled by default. When enabled, every launch made through [`Launcher::launch`](crate::Launcher::launch) and
+/// [`launch_with_fallback`](crate::launch_with_fallback) is appended to [`launch_history_path`], failures to write the
+/// history are ignored.
+pub fn record_launch_history(enabled: bool) {
+    RECORD.store(enabled, Ordering::Relaxed);
+}
+
+/// Returns the path of the history file (`$XDG_STATE_HOME/unidosx/launch-history.jsonl`), or `None` if neither
+/// `XDG_STATE_HOME` nor `HOME` is set.
+pub fn launch_history_path() -> Option<PathBuf> {
+    crate::xdg::state_home().map(|state| state.join("unidosx/launch-history.jsonl"))
+}
+
+/// Returns the recorded launches, oldest first.
+///
+/// Returns an empty history if the history file does not exist, lines that can not be parsed are skipped.
+pub fn launch_history() -> io::Result<Vec<LaunchRecord>> {
+    match launch_history_path() {
+        Some(path) => read(&path),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Removes every recorded launch.
+pub fn clear_launch_history() -> io::Result<()> {
+    match launch_history_path().map(fs::remove_file) {
+        Some(Err(error)) if error.kind() != io::ErrorKind::NotFound => Err(error),
+        _ => Ok(()),
+    }
+}
+
+/// Records a launch of `spec` if recording is enabled.
+pub(crate) fn record(
+    spec: &CommandSpec,
+    backend: LaunchBackend,
+    requested_at: SystemTime,
+    outcome: LaunchOutcome,
+) {
+    if !RECORD.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let record = LaunchRecord {
+        timestamp: requested_at,
+        program: spec.program.to_string_lossy().into_owned(),
+        command: spec.to_string(),
+        backend: backend.to_string(),
+        outcome,
+    };
+
+    if let Some(path) = launch_history_path() {
+        let _ = append(&path, &record);
+    }
+}
+
+/// Appends a record to the history file at `path`, creating it if needed.
+pub(crate) fn append(path: &Path, record: &LaunchRecord) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let millis = record
+        .timestamp
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let (outcome, pid, error) = match &record.outcome {
+        LaunchOutcome::Spawned { pid } => ("spawned", pid.to_string(), String::from("null")),
+        LaunchOutcome::Failed { error } => ("failed", String::from("null"), json::string(error)),
+    };
+
+    // A single write per line keeps concurrent appends from interleaving.
+    let line = format!(
+        "{{\"timestamp_ms\": {millis}, \"program\": {}, \"command\": {}, \"backend\": {}, \"outcome\": {}, \
+         \"pid\": {pid}, \"error\": {error}}}\n",
+        json::string(&record.program),
+        json::string(&record.command),
+        json::string(&record.backend),
+        json::string(outcome),
+    );
+
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(line.as_bytes())
+}
+
+/// Reads the history file at `path`, skipping lines that can not be parsed.
+pub(crate) fn read(path: &Path) -> io::Result<Vec<LaunchRecord>> {
+    match fs::read_to_string(path) {
+        Ok(history) => Ok(history.lines().filter_map(parse_line).collect()),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(error) => Err(error),
+    }
+}
+
+/// Parses a line of the history file.
+fn parse_line(line: &str) -> Option<LaunchRecord> {
+    let fields = json::parse_flat_object(line)?;
+    let field = |name: &str| {
+        fields
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value)
+    };
+    let string = |name: &str| match field(name)? {
+        json::Value::String(value) => Some(value.clone()),
+        _ => None,
+    };
+    let number = |name: &str| match field(name)? {
+        json::Value::Number(value) => Some(*value),
+        _ => None,
+    };
+
+    let outcome = match string("outcome")?.as_str() {
+        "spawned" => LaunchOutcome::Spawned {
+            pid: number("pid")?.try_into().ok()?,
+        },
+        "failed" => LaunchOutcome::Failed {
+            error: string("error").unwrap_or_default(),
+        },
+        _ => return None,
+    };
+
+    Some(LaunchRecord {
+        timestamp: UNIX_EPOCH + Duration::from_millis(number("timestamp_ms")?),
+        program: string("program")?,
+        command: string("command")?,
+        backend: string("backend")?,
+        outcome,
+    })
+}
@@ -0,0 +1,118 @@
+//! Execution syntax of terminal emulators missing from the hardcoded lists.
+//!
+//! Unknown terminal emulators are assumed to take the command after `-e`, which many do, but not all. When enabled with
+//! [`probe_unknown_terminals`], their `--help` output is read to find the syntax they document instead: a command
+//! after `--`, after `-e`, after `-x` or `--execute`, or directly following the terminal emulator. Nothing is ever
+//! launched in a window to find it out.
+//!
+//! The answer is remembered for each executable during the current process.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::{
+        Mutex, MutexGuard,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use crate::{CommandSpec, ExecutionSyntax};
+
+/// How long `--help` may take before the execution syntax is considered unknown.
+const HELP_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Whether the execution syntax of unknown terminal emulators is inferred during detection.
+static PROBE: AtomicBool = AtomicBool::new(false);
+
+/// The execution syntaxes inferred during the current process, by executable path.
+static INFERRED: Mutex<BTreeMap<PathBuf, Option<ExecutionSyntax>>> = Mutex::new(BTreeMap::new());
+
+/// Infers the execution syntax of terminal emulators missing from the hardcoded lists during detection, from their
+/// `--help` output.
+///
+/// Disabled by default, unknown terminal emulators then use the default execution syntax, `-e`. When enabled, the
+/// execution syntax is read with [`infer_execution_syntax`], falling back to `-e` if it documents none. An execution
+/// syntax set along with the terminal emulator (e.g. `TERMINAL_EMULATOR_SYNTAX`) still takes precedence.
+pub fn probe_unknown_terminals(enabled: bool) {
+    PROBE.store(enabled, Ordering::Relaxed);
+}
+
+/// Returns `true` if the execution syntax of unknown terminal emulators is inferred, see [`probe_unknown_terminals`].
+pub fn unknown_terminals_probed() -> bool {
+    PROBE.load(Ordering::Relaxed)
+}
+
+/// Locks the inferred execution syntaxes, recovering them if a thread panicked while holding them.
+fn inferred() -> MutexGuard<'static, BTreeMap<PathBuf, Option<ExecutionSyntax>>> {
+    INFERRED
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Infers the execution syntax of the terminal emulator at `path` from its `--help` output.
+///
+/// Returns `None` if the terminal emulator fails, does not exit within a second or documents no way to run a command.
+/// When inspecting another system, its executables are never run and `None` is returned.
+pub fn infer_execution_syntax<P: AsRef<Path>>(path: P) -> Option<ExecutionSyntax> {
+    let path = path.as_ref();
+
+    if crate::filesystem::is_offline() {
+        return None;
+    }
+
+    if let Some(syntax) = inferred().get(path) {
+        return *syntax;
+    }
+
+    let syntax = CommandSpec::new(path)
+        .arg("--help")
+        .output_within(HELP_TIMEOUT)
+        .and_then(|output| {
+            let mut help = String::from_utf8_lossy(&output.stdout).into_owned();
+            help.push_str(&String::from_utf8_lossy(&output.stderr));
+
+            parse_help(&help)
+        });
+
+    inferred().insert(path.to_owned(), syntax);
+    syntax
+}
+
+/// Finds the execution syntax documented by a `--help` output.
+///
+/// `--` is preferred as it keeps the arguments of the command apart (e.g. `Usage: gnome-terminal [OPTION…] [--
+/// COMMAND…]`), then `-e`, then `-x` and `--execute`, then a command directly following the terminal emulator in the
+/// usage line (e.g. `Usage: kitty [options] [program-to-run ...]`).
+pub(crate) fn parse_help(help: &str) -> Option<ExecutionSyntax> {
+    let words = || {
+        help.split_whitespace()
+            .map(|word| word.trim_matches(|c| matches!(c, '[' | ']' | '<' | '>' | ',' | '=')))
+    };
+    let documents = |option: &str| words().any(|word| word == option);
+
+    if documents("--") {
+        return Some(ExecutionSyntax::DoubleDash);
+    }
+
+    if documents("-e") {
+        return Some(ExecutionSyntax::E);
+    }
+
+    if let Some(flag) = ["-x", "--execute"].into_iter().find(|flag| documents(flag)) {
+        return Some(ExecutionSyntax::CustomFlag {
+            flag,
+            single_argument: false,
+        });
+    }
+
+    help.lines()
+        .map(|line| line.trim().to_lowercase())
+        .filter(|line| line.starts_with("usage:"))
+        .any(|line| {
+            ["[command", "<command", "[program", "<program"]
+                .iter()
+                .any(|positional| line.contains(positional))
+        })
+        .then_some(ExecutionSyntax::Command)
+}
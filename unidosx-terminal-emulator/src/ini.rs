@@ -0,0 +1,112 @@
+//! A small, lenient parser for INI-style settings files (KDE, XFCE and LXQt configuration, desktop entries).
+//!
+//! Settings files are written by many tools and edited by hand, so the parser never fails: a leading BOM, CRLF line
+//! endings, comments, blank lines and lines without `=` are skipped, duplicate sections are merged and later values
+//! override earlier ones.
+
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// A parsed settings file.
+pub(crate) struct Ini {
+    /// The keys of each section, by section name. Keys before the first section header belong to the `""` section.
+    sections: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl Ini {
+    /// Parses the contents of a settings file.
+    pub(crate) fn parse(contents: &str) -> Self {
+        let contents = contents.strip_prefix('\u{feff}').unwrap_or(contents);
+        let mut ini = Self::default();
+        let mut section = String::new();
+
+        for line in contents.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+
+            if let Some(header) = line.strip_prefix('[') {
+                if let Some((name, _)) = header.split_once(']') {
+                    section = name.trim().to_owned();
+                }
+
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let key = strip_kde_flags(key.trim());
+
+            if key.is_empty() {
+                continue;
+            }
+
+            ini.sections
+                .entry(section.clone())
+                .or_default()
+                .insert(key.to_owned(), value.trim().to_owned());
+        }
+
+        ini
+    }
+
+    /// Returns the value of `key` in `section`.
+    ///
+    /// Locale-suffixed keys (e.g. `Name[de]`) are distinct keys, `get("Desktop Entry", "Name")` only returns the
+    /// unlocalized value.
+    pub(crate) fn get(&self, section: &str, key: &str) -> Option<&str> {
+        self.sections.get(section)?.get(key).map(String::as_str)
+    }
+
+    /// Returns the value of `key` in `section` for the given POSIX locale (e.g. `de_DE.UTF-8@euro`), falling back to
+    /// the unlocalized value.
+    ///
+    /// Localized keys are matched as defined by the Desktop Entry specification: `lang_COUNTRY@MODIFIER`,
+    /// `lang_COUNTRY`, `lang@MODIFIER`, then `lang`. The encoding of the locale is ignored.
+    pub(crate) fn get_localized(
+        &self,
+        section: &str,
+        key: &str,
+        locale: Option<&str>,
+    ) -> Option<&str> {
+        let localized = locale.and_then(|locale| {
+            let (locale, modifier) = match locale.split_once('@') {
+                Some((locale, modifier)) => (locale, Some(modifier)),
+                None => (locale, None),
+            };
+            let locale = locale.split_once('.').map_or(locale, |(locale, _)| locale);
+            let (lang, country) = match locale.split_once('_') {
+                Some((lang, country)) => (lang, Some(country)),
+                None => (locale, None),
+            };
+
+            let candidates = [
+                country
+                    .zip(modifier)
+                    .map(|(country, modifier)| format!("{lang}_{country}@{modifier}")),
+                country.map(|country| format!("{lang}_{country}")),
+                modifier.map(|modifier| format!("{lang}@{modifier}")),
+                Some(lang.to_owned()),
+            ];
+
+            candidates
+                .into_iter()
+                .flatten()
+                .find_map(|suffix| self.get(section, &format!("{key}[{suffix}]")))
+        });
+
+        localized.or_else(|| self.get(section, key))
+    }
+}
+
+/// Strips the KDE flag suffixes from a key (e.g. `TerminalApplication[$e]`), keeping locale suffixes.
+fn strip_kde_flags(mut key: &str) -> &str {
+    while let Some(start) = key.rfind("[$")
+        && key.ends_with(']')
+    {
+        key = key[..start].trim_end();
+    }
+
+    key
+}
@@ -0,0 +1,177 @@
+//! Minimal JSON writing helpers.
+
+use std::fmt::Write;
+
+/// Quotes and escapes a string as a JSON string literal.
+pub(crate) fn string(value: &str) -> String {
+    let mut json = String::with_capacity(value.len() + 2);
+    json.push('"');
+
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+
+    json.push('"');
+    json
+}
+
+/// Formats strings as a JSON array of strings.
+pub(crate) fn string_array<I, S>(values: I) -> String
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let values: Vec<String> = values
+        .into_iter()
+        .map(|value| string(value.as_ref()))
+        .collect();
+
+    format!("[{}]", values.join(", "))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A value of a flat JSON object.
+pub(crate) enum Value {
+    /// A string.
+    String(String),
+    /// A non-negative integer.
+    Number(u64),
+    /// `true` or `false`.
+    Bool(bool),
+    /// An array of strings.
+    Strings(Vec<String>),
+    /// `null`.
+    Null,
+}
+
+/// Parses a flat JSON object whose values are strings, non-negative integers, booleans, arrays of strings or `null`.
+///
+/// Returns `None` if the input is not such an object, nested values are not supported.
+pub(crate) fn parse_flat_object(input: &str) -> Option<Vec<(String, Value)>> {
+    let mut chars = input.trim().chars().peekable();
+    let mut fields = Vec::new();
+
+    expect(&mut chars, '{')?;
+    skip_whitespace(&mut chars);
+
+    if chars.peek() == Some(&'}') {
+        chars.next();
+    } else {
+        loop {
+            skip_whitespace(&mut chars);
+            let key = parse_string(&mut chars)?;
+            skip_whitespace(&mut chars);
+            expect(&mut chars, ':')?;
+            skip_whitespace(&mut chars);
+            fields.push((key, parse_value(&mut chars)?));
+            skip_whitespace(&mut chars);
+
+            match chars.next()? {
+                ',' => continue,
+                '}' => break,
+                _ => return None,
+            }
+        }
+    }
+
+    chars.next().is_none().then_some(fields)
+}
+
+type Chars<'a> = std::iter::Peekable<std::str::Chars<'a>>;
+
+/// Skips whitespace between tokens.
+fn skip_whitespace(chars: &mut Chars<'_>) {
+    while chars.next_if(|c| c.is_ascii_whitespace()).is_some() {}
+}
+
+/// Consumes `expected`, returning `None` if the next character is another one.
+fn expect(chars: &mut Chars<'_>, expected: char) -> Option<()> {
+    chars.next_if_eq(&expected).map(|_| ())
+}
+
+/// Parses a string, a non-negative integer, a boolean, an array of strings or `null`.
+fn parse_value(chars: &mut Chars<'_>) -> Option<Value> {
+    let keyword =
+        |chars: &mut Chars<'_>, keyword: &str| keyword.chars().try_for_each(|c| expect(chars, c));
+
+    match chars.peek()? {
+        '"' => parse_string(chars).map(Value::String),
+        '[' => parse_strings(chars).map(Value::Strings),
+        'n' => keyword(chars, "null").map(|()| Value::Null),
+        't' => keyword(chars, "true").map(|()| Value::Bool(true)),
+        'f' => keyword(chars, "false").map(|()| Value::Bool(false)),
+        c if c.is_ascii_digit() => {
+            let mut number = String::new();
+
+            while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                number.push(digit);
+            }
+
+            number.parse().ok().map(Value::Number)
+        }
+        _ => None,
+    }
+}
+
+/// Parses an array of strings.
+fn parse_strings(chars: &mut Chars<'_>) -> Option<Vec<String>> {
+    let mut strings = Vec::new();
+
+    expect(chars, '[')?;
+    skip_whitespace(chars);
+
+    if expect(chars, ']').is_some() {
+        return Some(strings);
+    }
+
+    loop {
+        skip_whitespace(chars);
+        strings.push(parse_string(chars)?);
+        skip_whitespace(chars);
+
+        match chars.next()? {
+            ',' => continue,
+            ']' => return Some(strings),
+            _ => return None,
+        }
+    }
+}
+
+/// Parses a string literal, unescaping it.
+fn parse_string(chars: &mut Chars<'_>) -> Option<String> {
+    expect(chars, '"')?;
+    let mut value = String::new();
+
+    loop {
+        match chars.next()? {
+            '"' => return Some(value),
+            '\\' => value.push(match chars.next()? {
+                '"' => '"',
+                '\\' => '\\',
+                '/' => '/',
+                'b' => '\u{8}',
+                'f' => '\u{c}',
+                'n' => '\n',
+                'r' => '\r',
+                't' => '\t',
+                'u' => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    char::from_u32(u32::from_str_radix(&hex, 16).ok()?)
+                        .unwrap_or(char::REPLACEMENT_CHARACTER)
+                }
+                _ => return None,
+            }),
+            c => value.push(c),
+        }
+    }
+}
@@ -0,0 +1,547 @@
+//! Launch results.
+
+#[cfg(unix)]
+use std::{
+    ffi::c_int,
+    sync::{
+        OnceLock,
+        mpsc::{self, Receiver, Sender},
+    },
+};
+use std::{
+    fmt::{self, Display, Formatter},
+    fs, io,
+    path::PathBuf,
+    process::{Child, ExitStatus},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{
+    CommandSpec, Compositor, LAUNCH_ID_VAR, LaunchDescriptor, LaunchOutcome, Launcher,
+    find_launched, history, propagated_environment,
+};
+
+/// How often a freshly spawned process is checked for an early exit.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How often the processes of dropped results are checked for an exit.
+#[cfg(unix)]
+const REAP_INTERVAL: Duration = Duration::from_millis(100);
+
+/// `SIGTERM`, asking a process to exit.
+#[cfg(unix)]
+const SIGTERM: c_int = 15;
+
+/// `SIGKILL`, killing a process.
+#[cfg(unix)]
+const SIGKILL: c_int = 9;
+
+#[cfg(unix)]
+unsafe extern "C" {
+    fn kill(pid: c_int, sig: c_int) -> c_int;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// The backend that handled a launch.
+pub enum LaunchBackend {
+    /// Executed the terminal emulator directly.
+    Exec,
+    /// Spawned the command with a console of its own.
+    NewConsole,
+    /// Spawned the command through `cmd /c start`.
+    CmdStart,
+    /// Opened the command as a new Windows Terminal tab.
+    WindowsTerminalTab,
+    /// Ran the command on a free Linux virtual terminal through `openvt`.
+    VirtualTerminal,
+    /// Asked the Wayland compositor to spawn the command.
+    CompositorSpawn(Compositor),
+}
+
+impl From<&Launcher> for LaunchBackend {
+    fn from(launcher: &Launcher) -> Self {
+        match launcher {
+            Launcher::Terminal { .. } => Self::Exec,
+            Launcher::AllocConsole => Self::NewConsole,
+            Launcher::CmdStart => Self::CmdStart,
+            Launcher::WindowsTerminalTab { .. } => Self::WindowsTerminalTab,
+            Launcher::VirtualTerminal { .. } => Self::VirtualTerminal,
+            Launcher::CompositorSpawn { compositor, .. } => Self::CompositorSpawn(*compositor),
+        }
+    }
+}
+
+impl Display for LaunchBackend {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Exec => write!(f, "Exec"),
+            Self::NewConsole => write!(f, "New Console"),
+            Self::CmdStart => write!(f, "cmd /c start"),
+            Self::WindowsTerminalTab => write!(f, "Windows Terminal Tab"),
+            Self::VirtualTerminal => write!(f, "openvt"),
+            Self::CompositorSpawn(compositor) => write!(f, "{compositor} Spawn"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// The exit status and output of a command that ran inside a terminal emulator, see
+/// [`LaunchResult::wait_for_output`].
+pub struct CapturedOutput {
+    /// The exit status of the command.
+    pub status: i32,
+    /// The standard output of the command.
+    pub stdout: Vec<u8>,
+    /// The standard error of the command.
+    pub stderr: Vec<u8>,
+}
+
+#[derive(Debug)]
+/// The outcome of a launch, describing what was executed and how.
+///
+/// On UNIX, dropping the result without taking the spawned process with [`LaunchResult::into_child`] reaps it in the
+/// background once it exits, so fire-and-forget launches never leave zombie processes behind.
+pub struct LaunchResult {
+    /// The exact command that was spawned.
+    pub spec: CommandSpec,
+    /// The process ID of the spawned process.
+    pub pid: u32,
+    /// The backend that handled the launch.
+    pub backend: LaunchBackend,
+    /// When the launch was requested.
+    pub requested_at: SystemTime,
+    /// When the process was spawned.
+    pub spawned_at: SystemTime,
+    /// The spawned process, `None` once taken by [`LaunchResult::into_child`].
+    child: Option<Child>,
+    /// The file the exit status of the command is written to, if tracked.
+    pub(crate) exit_status_file: Option<PathBuf>,
+    /// The files the standard output and standard error of the command are copied to, if captured.
+    pub(crate) output_files: Option<(PathBuf, PathBuf)>,
+    /// The named pipe the standard input of the command is read from, if piped.
+    pub(crate) stdin_pipe: Option<PathBuf>,
+}
+
+impl LaunchResult {
+    /// Creates a launch result for a spawned process.
+    pub(crate) fn new(
+        spec: CommandSpec,
+        backend: LaunchBackend,
+        requested_at: SystemTime,
+        child: Child,
+    ) -> Self {
+        Self {
+            spec,
+            pid: child.id(),
+            backend,
+            requested_at,
+            spawned_at: SystemTime::now(),
+            child: Some(child),
+            exit_status_file: None,
+            output_files: None,
+            stdin_pipe: None,
+        }
+    }
+
+    /// Returns the launch ID set in the environment of the spawned process, if launches are tagged (see
+    /// [`tag_launches`](crate::tag_launches)).
+    pub fn launch_id(&self) -> Option<&str> {
+        self.spec
+            .env
+            .iter()
+            .rev()
+            .find(|(var, _)| var == LAUNCH_ID_VAR)
+            .and_then(|(_, value)| value.as_deref()?.to_str())
+    }
+
+    /// Returns a descriptor of the launch, to re-open it later with [`relaunch`](crate::relaunch) (e.g. after the application restarted).
+    ///
+    /// The variables of the session (see [`set_propagated_environment`](crate::set_propagated_environment)) and the
+    /// launch ID are left out, as they are set again from the session of the relaunch.
+    pub fn to_descriptor(&self) -> LaunchDescriptor {
+        let session = propagated_environment();
+        let mut spec = self.spec.clone();
+        spec.env.retain(|(var, _)| {
+            var != LAUNCH_ID_VAR
+                && !session
+                    .iter()
+                    .any(|session_var| var == session_var.as_str())
+        });
+
+        LaunchDescriptor {
+            spec,
+            backend: self.backend,
+        }
+    }
+
+    /// Returns the spawned process.
+    pub fn child(&mut self) -> &mut Child {
+        self.child
+            .as_mut()
+            .expect("the spawned process is only taken when consuming the result")
+    }
+
+    /// Verifies that the spawned process does not exit unsuccessfully within `window` after being spawned.
+    ///
+    /// Blocks until the process exits or `window` elapses. Processes exiting successfully are considered working, as
+    /// some terminal emulators hand the command over to an existing instance and exit right away, and compositor
+    /// control programs exit once the compositor accepted the request.
+    ///
+    /// Returns an error describing the exit status if the process exited unsuccessfully, the failure is recorded in the
+    /// launch history if enabled.
+    pub fn check_liveness(&mut self, window: Duration) -> io::Result<()> {
+        let elapsed = self.spawned_at.elapsed().unwrap_or_default();
+        let deadline = Instant::now() + window.saturating_sub(elapsed);
+
+        loop {
+            if let Some(status) = self.child().try_wait()? {
+                if status.success() {
+                    return Ok(());
+                }
+
+                let error = io::Error::other(format!(
+                    "{} exited with {status} within {} ms",
+                    self.spec.program.to_string_lossy(),
+                    window.as_millis()
+                ));
+
+                history::record(
+                    &self.spec,
+                    self.backend,
+                    self.requested_at,
+                    LaunchOutcome::Failed {
+                        error: error.to_string(),
+                    },
+                );
+
+                return Err(error);
+            }
+
+            let now = Instant::now();
+
+            if now >= deadline {
+                return Ok(());
+            }
+
+            thread::sleep(POLL_INTERVAL.min(deadline - now));
+        }
+    }
+
+    /// Waits for the command that ran inside the terminal emulator to exit, returning its exit status.
+    ///
+    /// The exit status of the terminal emulator says nothing about the command, which must be launched with
+    /// [`LaunchOptions::track_exit_status`](crate::LaunchOptions::track_exit_status). Blocks until the command exits
+    /// or `timeout` elapses, forever if `None`.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`] if the exit status is not tracked, and with
+    /// [`io::ErrorKind::TimedOut`] if the command is still running after `timeout`, or never started (e.g. the
+    /// terminal emulator failed). Commands killed by a signal (e.g. when their window is closed) may never report an exit
+    /// status.
+    pub fn wait_for_inner_exit(&self, timeout: Option<Duration>) -> io::Result<i32> {
+        let Some(file) = &self.exit_status_file else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the exit status of the command is not tracked",
+            ));
+        };
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+        loop {
+            match fs::read_to_string(file) {
+                Ok(contents) => {
+                    let _ = fs::remove_file(file);
+
+                    return contents.trim().parse().map_err(|_| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("invalid exit status: {contents:?}"),
+                        )
+                    });
+                }
+                Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error),
+                Err(_) => {}
+            }
+
+            let now = Instant::now();
+
+            if deadline.is_some_and(|deadline| now >= deadline) {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "the command did not exit in time",
+                ));
+            }
+
+            thread::sleep(
+                deadline.map_or(POLL_INTERVAL, |deadline| POLL_INTERVAL.min(deadline - now)),
+            );
+        }
+    }
+
+    /// Opens the standard input of the command launched with
+    /// [`LaunchOptions::pipe_stdin`](crate::LaunchOptions::pipe_stdin), returning its writing end.
+    ///
+    /// The command starts once it is opened and reads what is written until it is closed, e.g. with
+    /// `io::copy(&mut data, &mut result.open_stdin(None)?)`. Blocks until the terminal emulator has started the command,
+    /// or `timeout` elapses, forever if `None`. Can only be called once.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`] if the standard input is not piped, and with
+    /// [`io::ErrorKind::TimedOut`] if the command did not start in time (e.g. the terminal emulator failed).
+    #[cfg(unix)]
+    pub fn open_stdin(&self, timeout: Option<Duration>) -> io::Result<fs::File> {
+        use std::os::unix::fs::FileTypeExt;
+
+        let Some(pipe) = &self.stdin_pipe else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the standard input of the command is not piped",
+            ));
+        };
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+        loop {
+            match fs::symlink_metadata(pipe) {
+                // Created by the wrapper, which then opens it for reading.
+                Ok(metadata) if metadata.file_type().is_fifo() => {
+                    return fs::OpenOptions::new().write(true).open(pipe);
+                }
+                Ok(_) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("{} is not a named pipe", pipe.display()),
+                    ));
+                }
+                Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error),
+                Err(_) => {}
+            }
+
+            let now = Instant::now();
+
+            if deadline.is_some_and(|deadline| now >= deadline) {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "the command did not start in time",
+                ));
+            }
+
+            thread::sleep(
+                deadline.map_or(POLL_INTERVAL, |deadline| POLL_INTERVAL.min(deadline - now)),
+            );
+        }
+    }
+
+    /// Waits for the command launched with [`LaunchOptions::wait`](crate::LaunchOptions::wait) to exit, returning its
+    /// exit status if tracked.
+    ///
+    /// Waits for the spawned process, which only exits once the command exits with the wait flag of the terminal
+    /// emulator, or reads the exit status written by the `sh` wrapper for terminal emulators without one (see
+    /// [`LaunchResult::wait_for_inner_exit`]). Returns `None` when waiting for the spawned process, whose exit status
+    /// is the one of the terminal emulator.
+    pub fn wait_for_command(&mut self) -> io::Result<Option<i32>> {
+        if self.exit_status_file.is_some() {
+            return self.wait_for_inner_exit(None).map(Some);
+        }
+
+        self.child().wait().map(|_| None)
+    }
+
+    /// Waits for the command that ran inside the terminal emulator to exit, returning its exit status and output.
+    ///
+    /// The command must be launched with [`LaunchOptions::capture_output`](crate::LaunchOptions::capture_output), see
+    /// [`LaunchResult::wait_for_inner_exit`] for how the command is waited for. The files holding the output are removed
+    /// once read.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`] if the output is not captured.
+    pub fn wait_for_output(&self, timeout: Option<Duration>) -> io::Result<CapturedOutput> {
+        let Some((stdout_file, stderr_file)) = &self.output_files else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the output of the command is not captured",
+            ));
+        };
+        let status = self.wait_for_inner_exit(timeout)?;
+        // The output files are missing if the command never ran (e.g. its working directory does not exist).
+        let read = |file: &PathBuf| match fs::read(file) {
+            Ok(output) => {
+                let _ = fs::remove_file(file);
+                Ok(output)
+            }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(error) => Err(error),
+        };
+
+        Ok(CapturedOutput {
+            status,
+            stdout: read(stdout_file)?,
+            stderr: read(stderr_file)?,
+        })
+    }
+
+    /// Closes the launched terminal emulator, gracefully if it exits within `grace` and forcefully otherwise.
+    ///
+    /// The spawned process is first asked to exit, with `SIGTERM` on UNIX and through `taskkill` (which asks its windows
+    /// to close) on Windows, letting the terminal emulator close its window and clean up. If launches are tagged (see
+    /// [`tag_launches`](crate::tag_launches)), the processes carrying the launch ID are asked too, so terminal emulators
+    /// handing the window over to another process are closed. The processes still running after `grace` are killed.
+    ///
+    /// Windows opened by a Wayland compositor or as a Windows Terminal tab do not belong to the spawned process, which
+    /// only asked for them, so they are left open.
+    ///
+    /// Returns the exit status of the spawned process.
+    pub fn close(&mut self, grace: Duration) -> io::Result<ExitStatus> {
+        let pid = self.pid;
+        let mut pids = vec![pid];
+
+        for tagged in self.launch_id().map(find_launched).unwrap_or_default() {
+            if !pids.contains(&tagged) {
+                pids.push(tagged);
+            }
+        }
+
+        let exited = self.child().try_wait()?;
+
+        for &target in &pids {
+            if target != pid || exited.is_none() {
+                request_exit(target);
+            }
+        }
+
+        let deadline = Instant::now() + grace;
+
+        loop {
+            let status = self.child().try_wait()?;
+            let others_running = pids[1..].iter().any(|&other| is_running(other));
+
+            match status {
+                Some(status) if !others_running => return Ok(status),
+                _ if Instant::now() >= deadline => break,
+                _ => thread::sleep(POLL_INTERVAL),
+            }
+        }
+
+        for &other in &pids[1..] {
+            force_exit(other);
+        }
+
+        if self.child().try_wait()?.is_none() {
+            self.child().kill()?;
+        }
+
+        self.child().wait()
+    }
+
+    /// Consumes the result, returning the spawned process.
+    ///
+    /// The caller becomes responsible for waiting for the process, or it remains a zombie after exiting on UNIX.
+    pub fn into_child(mut self) -> Child {
+        self.child
+            .take()
+            .expect("the spawned process is only taken when consuming the result")
+    }
+}
+
+impl Drop for LaunchResult {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(child) = self.child.take() {
+            reap(child);
+        }
+    }
+}
+
+/// Asks the process `pid` to exit.
+fn request_exit(pid: u32) {
+    #[cfg(unix)]
+    if let Ok(pid) = c_int::try_from(pid) {
+        // SAFETY: `kill` has no memory safety preconditions.
+        unsafe { kill(pid, SIGTERM) };
+    }
+
+    #[cfg(windows)]
+    {
+        let _ = std::process::Command::new("taskkill")
+            .args(["/PID", &pid.to_string()])
+            .output();
+    }
+}
+
+/// Kills the process `pid`, which is not a child of the current process.
+fn force_exit(pid: u32) {
+    #[cfg(unix)]
+    if let Ok(pid) = c_int::try_from(pid) {
+        // SAFETY: `kill` has no memory safety preconditions.
+        unsafe { kill(pid, SIGKILL) };
+    }
+
+    #[cfg(windows)]
+    {
+        let _ = std::process::Command::new("taskkill")
+            .args(["/F", "/PID", &pid.to_string()])
+            .output();
+    }
+}
+
+/// Returns `true` if the process `pid`, which is not a child of the current process, is still running.
+fn is_running(pid: u32) -> bool {
+    #[cfg(unix)]
+    {
+        // SAFETY: `kill` has no memory safety preconditions, signal 0 only checks that the process exists.
+        c_int::try_from(pid).is_ok_and(|pid| unsafe { kill(pid, 0) } == 0)
+    }
+
+    // Launch IDs are only found on Linux.
+    #[cfg(not(unix))]
+    {
+        let _ = pid;
+        false
+    }
+}
+
+/// Hands `child` to the reaper thread, so it does not remain a zombie after exiting.
+///
+/// A single thread, started with the first dropped result, reaps the processes of all dropped results. It does not keep
+/// the current process alive when it exits.
+#[cfg(unix)]
+fn reap(mut child: Child) {
+    static REAPER: OnceLock<Option<Sender<Child>>> = OnceLock::new();
+
+    if matches!(child.try_wait(), Ok(Some(_))) {
+        return;
+    }
+
+    // If no thread can be spawned, the processes are only reaped when the current process exits.
+    let reaper = REAPER.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel();
+
+        thread::Builder::new()
+            .name(String::from("unidosx-reaper"))
+            .spawn(move || run_reaper(&receiver))
+            .ok()
+            .map(|_| sender)
+    });
+
+    if let Some(sender) = reaper {
+        let _ = sender.send(child);
+    }
+}
+
+/// Reaps the processes received from `receiver` once they exit, blocking while there are none left.
+///
+/// Exited processes are checked for every [`REAP_INTERVAL`], as waiting for one of them would leave the others as
+/// zombies until it exits.
+#[cfg(unix)]
+fn run_reaper(receiver: &Receiver<Child>) {
+    let mut children = Vec::new();
+
+    loop {
+        let received = if children.is_empty() {
+            receiver.recv().ok()
+        } else {
+            receiver.recv_timeout(REAP_INTERVAL).ok()
+        };
+
+        children.extend(received);
+        children.extend(receiver.try_iter());
+        children.retain_mut(|child: &mut Child| matches!(child.try_wait(), Ok(None)));
+    }
+}
@@ -0,0 +1,541 @@
+//! Launch strategies for running commands in a terminal emulator.
+
+use std::{
+    borrow::Cow,
+    ffi::{OsStr, OsString},
+    io,
+    path::PathBuf,
+    process::Command,
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    CommandSpec, Compositor, DetectionMethod, ExecutionSyntax, LaunchBackend, LaunchOptions,
+    LaunchOutcome, LaunchResult, TerminalEmulator, detect_compositor, flatpak, history,
+    lookup::{executable_name, find_executable},
+    options,
+    probes::FRAMEBUFFER_TERMINALS,
+    session,
+    shell::{self, Shell},
+    spawn_log, tagging,
+};
+
+/// The AppleScript opening a Terminal.app window running the command line given as the first argument of `osascript`.
+const TERMINAL_APP_SCRIPT: &[&str] = &[
+    "on run argv",
+    "tell application \"Terminal\"",
+    "activate",
+    "do script (item 1 of argv)",
+    "end tell",
+    "end run",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Describes how a command is launched in a terminal emulator.
+pub enum Launcher {
+    /// Runs the command through a terminal emulator executable, placing the command after `args`.
+    Terminal {
+        /// The program that starts the terminal emulator.
+        program: PathBuf,
+        /// The arguments placed between the program and the command.
+        args: Vec<OsString>,
+        /// Whether the terminal emulator expects the command as a single argument.
+        ///
+        /// When set, the command is composed into a single `sh -c` command line.
+        single_argument: bool,
+    },
+    /// Spawns the command with a console of its own, the same console `AllocConsole` would create.
+    ///
+    /// Only available on Windows, other platforms spawn the command as is.
+    AllocConsole,
+    /// Spawns the command through `cmd /c start`, letting Windows open it in the default console host (e.g. Windows
+    /// Terminal when it is set as the default terminal application).
+    CmdStart,
+    /// Opens the command as a new tab in the focused Windows Terminal window (`wt -w 0 new-tab [command]`).
+    ///
+    /// Windows Terminal splits its command line on `;`, so arguments must not contain it.
+    WindowsTerminalTab {
+        /// The path to the Windows Terminal executable (`wt.exe`).
+        program: PathBuf,
+    },
+    /// Runs the command on a free Linux virtual terminal and switches to it (`openvt -s -w -- [command]`).
+    ///
+    /// Requires access to the console, usually only granted to root or the user logged in on it.
+    VirtualTerminal {
+        /// The path to the `openvt` executable.
+        program: PathBuf,
+    },
+    /// Asks the Wayland compositor to spawn the command built by another launcher, letting the compositor manage the
+    /// new window.
+    ///
+    /// The spawned process is the compositor control program, which exits once the request is sent. Compositors that
+    /// can not spawn commands run the command directly.
+    CompositorSpawn {
+        /// The compositor that spawns the command.
+        compositor: Compositor,
+        /// The path to the compositor control program.
+        program: PathBuf,
+        /// The launcher building the command spawned by the compositor.
+        launcher: Box<Launcher>,
+    },
+}
+
+impl Launcher {
+    /// Creates the default launcher for the given terminal emulator.
+    ///
+    /// Terminal emulators that use the native API are launched with [`Launcher::WindowsTerminalTab`] when running
+    /// inside Windows Terminal, or [`Launcher::AllocConsole`] otherwise. Terminal.app is launched through `osascript`,
+    /// which makes it run the command as a single shell command line in a new window. That window starts from the
+    /// environment of the user session, so the environment of the launch is not passed to the command.
+    pub fn new(terminal: &TerminalEmulator) -> Self {
+        if terminal.execution_syntax == ExecutionSyntax::NativeApi {
+            return Self::windows_terminal_tab().unwrap_or(Self::AllocConsole);
+        }
+
+        // Terminal.app takes no command line arguments: AppleScript makes it run the command line given to `osascript`.
+        if terminal.method == DetectionMethod::TerminalApp {
+            return Self::Terminal {
+                program: PathBuf::from("osascript"),
+                args: TERMINAL_APP_SCRIPT
+                    .iter()
+                    .flat_map(|line| ["-e", line])
+                    .map(OsString::from)
+                    .collect(),
+                single_argument: true,
+            };
+        }
+
+        let (program, mut args) = (terminal.path.clone(), Vec::new());
+
+        // kmscon starts the login program unless told to run the command instead.
+        if terminal.command_line == "kmscon" {
+            args.push("--login".into());
+        }
+
+        #[cfg(feature = "version-detection")]
+        let execution_syntax = crate::versions::execution_syntax(terminal);
+        #[cfg(not(feature = "version-detection"))]
+        let execution_syntax = terminal.execution_syntax;
+        args.extend(execution_syntax.as_args().iter().map(OsString::from));
+
+        Self::Terminal {
+            program,
+            args,
+            single_argument: execution_syntax.is_single_argument(),
+        }
+    }
+
+    /// Creates a launcher that opens commands as a new tab in the current Windows Terminal window.
+    ///
+    /// Returns `None` if the process is not running inside Windows Terminal (`WT_SESSION` is not set) or `wt.exe`
+    /// could not be found.
+    pub fn windows_terminal_tab() -> Option<Self> {
+        crate::environment::var_os("WT_SESSION")?;
+
+        find_executable("wt.exe").map(|program| Self::WindowsTerminalTab { program })
+    }
+
+    #[cfg(all(target_os = "linux", feature = "openvt"))]
+    /// Creates a launcher that runs commands on a free Linux virtual terminal.
+    ///
+    /// Returns `None` if a graphical session exists (`DISPLAY` or `WAYLAND_DISPLAY` is set) or `openvt` could not be
+    /// found.
+    pub fn virtual_terminal() -> Option<Self> {
+        if crate::session::has_graphical_session() {
+            return None;
+        }
+
+        find_executable("openvt").map(|program| Self::VirtualTerminal { program })
+    }
+
+    /// Makes the running Wayland compositor spawn the commands of this launcher.
+    ///
+    /// Supports sway, Hyprland, niri and river. Returns the launcher unchanged, falling back to executing the commands
+    /// directly, if none of them is running or its control program could not be found.
+    pub fn with_compositor(self) -> Self {
+        let control = detect_compositor().and_then(|compositor| {
+            let program = find_executable(compositor.control_program()?)?;
+
+            Some((compositor, program))
+        });
+
+        match control {
+            Some((compositor, program)) => Self::CompositorSpawn {
+                compositor,
+                program,
+                launcher: Box::new(self),
+            },
+            None => self,
+        }
+    }
+
+    /// Builds a command that launches `program` through this launcher.
+    ///
+    /// Arguments added to the returned command are passed to `program`, except for terminal emulators that expect the
+    /// command as a single argument, use [`Launcher::command_with_args`] for those.
+    pub fn command<S: AsRef<OsStr>>(&self, program: S) -> Command {
+        self.command_with_args(program, std::iter::empty::<&OsStr>())
+    }
+
+    /// Launches `program` with `args` through this launcher.
+    ///
+    /// The returned [`LaunchResult`] records the exact command spawned, its process ID and timestamps, and the backend
+    /// that handled the launch. The launch is recorded in the history if enabled with
+    /// [`record_launch_history`](crate::record_launch_history).
+    ///
+    /// Launches opening a window on Linux and BSD fail with [`io::ErrorKind::NotConnected`] if no graphical session is
+    /// available (see [`check_graphical_session`](crate::check_graphical_session)), and receive the session variables
+    /// (see [`set_propagated_environment`](crate::set_propagated_environment)).
+    pub fn launch<S, I, A>(&self, program: S, args: I) -> io::Result<LaunchResult>
+    where
+        S: AsRef<OsStr>,
+        I: IntoIterator<Item = A>,
+        A: AsRef<OsStr>,
+    {
+        self.launch_with_options(program, args, &LaunchOptions::default())
+    }
+
+    /// Launches `program` with `args` through this launcher with `options`, like [`Launcher::launch`].
+    ///
+    /// See [`Launcher::build_invocation_with_options`] for how the options are applied.
+    pub fn launch_with_options<S, I, A>(
+        &self,
+        program: S,
+        args: I,
+        options: &LaunchOptions,
+    ) -> io::Result<LaunchResult>
+    where
+        S: AsRef<OsStr>,
+        I: IntoIterator<Item = A>,
+        A: AsRef<OsStr>,
+    {
+        let requested_at = SystemTime::now();
+        let options = &self.resolve_wait(options);
+        let spec = tagging::tag(self.build_invocation_with_options(program, args, options));
+        let mut result = spawn(
+            spec,
+            LaunchBackend::from(self),
+            self.needs_graphical_session(),
+            requested_at,
+        )?;
+        result
+            .exit_status_file
+            .clone_from(&options.exit_status_file);
+        result.output_files.clone_from(&options.output_files);
+        result.stdin_pipe.clone_from(&options.stdin_pipe);
+
+        Ok(result)
+    }
+
+    /// Describes what launching `program` with `args` through this launcher would spawn, without spawning anything.
+    ///
+    /// Returns the event the launch hands to the logger set with [`set_spawn_logger`](crate::set_spawn_logger), byte
+    /// for byte: the backend, working directory, environment changes and arguments. Only the launch ID of tagged
+    /// launches (see [`tag_launches`](crate::tag_launches)) is missing, as it is generated at launch time.
+    pub fn dry_run<S, I, A>(&self, program: S, args: I) -> String
+    where
+        S: AsRef<OsStr>,
+        I: IntoIterator<Item = A>,
+        A: AsRef<OsStr>,
+    {
+        self.dry_run_with_options(program, args, &LaunchOptions::default())
+    }
+
+    /// Describes what launching `program` with `args` through this launcher with `options` would spawn, like
+    /// [`Launcher::dry_run`].
+    pub fn dry_run_with_options<S, I, A>(
+        &self,
+        program: S,
+        args: I,
+        options: &LaunchOptions,
+    ) -> String
+    where
+        S: AsRef<OsStr>,
+        I: IntoIterator<Item = A>,
+        A: AsRef<OsStr>,
+    {
+        let mut spec =
+            self.build_invocation_with_options(program, args, &self.resolve_wait(options));
+
+        if self.needs_graphical_session() {
+            spec = session::propagate(spec);
+        }
+
+        if flatpak::uses_host() {
+            spec = flatpak::host_command(spec);
+        }
+
+        spawn_log::event(&spec, LaunchBackend::from(self))
+    }
+
+    /// Returns `options`, tracking the exit status of the command if it must be waited for and the spawned process does
+    /// not wait for it, see [`LaunchOptions::wait`].
+    fn resolve_wait<'a>(&self, options: &'a LaunchOptions) -> Cow<'a, LaunchOptions> {
+        let wrapped = match self {
+            Self::Terminal { program, .. } => options::wait_flag(program).is_none(),
+            Self::CompositorSpawn { .. } => true,
+            _ => false,
+        };
+
+        if options.wait && wrapped && options.exit_status_file.is_none() {
+            Cow::Owned(options.clone().track_exit_status(true))
+        } else {
+            Cow::Borrowed(options)
+        }
+    }
+
+    /// Launches `program` with `args` through this launcher, failing if the spawned process exits unsuccessfully within
+    /// `window`.
+    ///
+    /// Turns silent early exits (e.g. a terminal emulator rejecting its arguments) into errors, see
+    /// [`LaunchResult::check_liveness`].
+    pub fn launch_checked<S, I, A>(
+        &self,
+        program: S,
+        args: I,
+        window: Duration,
+    ) -> io::Result<LaunchResult>
+    where
+        S: AsRef<OsStr>,
+        I: IntoIterator<Item = A>,
+        A: AsRef<OsStr>,
+    {
+        let mut result = self.launch(program, args)?;
+        result.check_liveness(window)?;
+
+        Ok(result)
+    }
+
+    /// Builds a command that launches `program` with `args` through this launcher.
+    ///
+    /// For terminal emulators that expect the command as a single argument, the command is composed into a
+    /// `sh -c 'exec "$@"' sh [program] [args...]` command line, keeping arguments with spaces or quotes intact.
+    pub fn command_with_args<S, I, A>(&self, program: S, args: I) -> Command
+    where
+        S: AsRef<OsStr>,
+        I: IntoIterator<Item = A>,
+        A: AsRef<OsStr>,
+    {
+        self.build_invocation(program, args).to_command()
+    }
+
+    /// Returns `true` if launches through this launcher open a window in the graphical session.
+    ///
+    /// Only launches of terminal emulators on Linux and BSD need one, framebuffer terminal emulators run on the
+    /// virtual console instead.
+    fn needs_graphical_session(&self) -> bool {
+        match self {
+            Self::Terminal { program, .. } => needs_graphical_session(LaunchBackend::Exec, program),
+            Self::CompositorSpawn { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Builds the command specification that launching `program` with `args` through this launcher would execute,
+    /// without spawning anything.
+    pub fn build_invocation<S, I, A>(&self, program: S, args: I) -> CommandSpec
+    where
+        S: AsRef<OsStr>,
+        I: IntoIterator<Item = A>,
+        A: AsRef<OsStr>,
+    {
+        self.build_invocation_with_options(program, args, &LaunchOptions::default())
+    }
+
+    /// Builds the command specification that launching `program` with `args` through this launcher with `options`
+    /// would execute, without spawning anything.
+    ///
+    /// Each option is translated into the flag of the terminal emulator (e.g. `--working-directory=`, `--title` or
+    /// `--hold`), or applied by a `sh -c` wrapper around the command if it has none. On Windows, the title is given to
+    /// `start` or Windows Terminal, and the command interpreter of the user (see [`Shell::detect`]) holds the window
+    /// open with `cmd /k` or `pwsh -NoExit`. The working directory and environment variables are also set on the
+    /// spawned process.
+    pub fn build_invocation_with_options<S, I, A>(
+        &self,
+        program: S,
+        args: I,
+        options: &LaunchOptions,
+    ) -> CommandSpec
+    where
+        S: AsRef<OsStr>,
+        I: IntoIterator<Item = A>,
+        A: AsRef<OsStr>,
+    {
+        let program = program.as_ref().to_owned();
+        let args: Vec<OsString> = args
+            .into_iter()
+            .map(|arg| arg.as_ref().to_owned())
+            .collect();
+        // The command interpreter of the user (e.g. `cmd /k`, `pwsh -NoExit`) runs the command and keeps the console open.
+        let hold_with_shell = |program: OsString, args: Vec<OsString>| {
+            if options.hold {
+                let held = Shell::detect().hold(&program, &args);
+                (held.program, held.args)
+            } else {
+                (program, args)
+            }
+        };
+
+        let spec = match self {
+            Self::Terminal {
+                program: terminal,
+                args: terminal_args,
+                single_argument,
+            } => {
+                let (flags, program, args) =
+                    options::terminal_flags(terminal, options, program, args);
+                let spec = CommandSpec::new(terminal).args(flags);
+
+                if !*single_argument {
+                    spec.args(terminal_args).arg(program).args(args)
+                } else {
+                    let command = shell::quote_command_line(&program, args);
+
+                    // Flags ending with `=` (e.g. `--command=`) take the command in the same argument.
+                    match terminal_args.split_last() {
+                        Some((flag, flags)) if flag.as_encoded_bytes().ends_with(b"=") => {
+                            let mut joined = flag.clone();
+                            joined.push(command);
+
+                            spec.args(flags).arg(joined)
+                        }
+                        _ => spec.args(terminal_args).arg(command),
+                    }
+                }
+            }
+            Self::AllocConsole => {
+                let (program, args) = hold_with_shell(program, args);
+
+                CommandSpec {
+                    new_console: true,
+                    ..CommandSpec::new(program).args(args)
+                }
+            }
+            // `start` takes its first quoted argument as the window title.
+            Self::CmdStart => {
+                let (program, args) = hold_with_shell(program, args);
+
+                CommandSpec::new("cmd")
+                    .args(["/c", "start"])
+                    .arg(options.title.as_deref().unwrap_or_default())
+                    .args(options.wait.then_some("/wait"))
+                    .arg(program)
+                    .args(args)
+            }
+            Self::WindowsTerminalTab { program: terminal } => {
+                let (program, args) = hold_with_shell(program, args);
+                let mut spec = CommandSpec::new(terminal).args(["-w", "0", "new-tab"]);
+
+                if let Some(cwd) = &options.cwd {
+                    spec = spec.arg("-d").arg(cwd);
+                }
+
+                if let Some(title) = &options.title {
+                    spec = spec.args(["--title", title]);
+                }
+
+                spec.arg(program).args(args)
+            }
+            Self::VirtualTerminal { program: openvt } => {
+                let wrapper = options::Wrapper {
+                    hold: options.hold,
+                    status_file: options
+                        .exit_status_file
+                        .clone()
+                        .map(PathBuf::into_os_string),
+                    output_files: options
+                        .output_files
+                        .clone()
+                        .map(|(stdout, stderr)| (stdout.into_os_string(), stderr.into_os_string())),
+                    stdin_pipe: options.stdin_pipe.clone().map(PathBuf::into_os_string),
+                    ..Default::default()
+                };
+                let (program, args) = wrapper.wrap(program, args);
+
+                CommandSpec::new(openvt)
+                    .args(["-s", "-w", "--"])
+                    .arg(program)
+                    .args(args)
+            }
+            Self::CompositorSpawn {
+                compositor,
+                program: control,
+                launcher,
+            } => {
+                // The compositor runs the command line of the launch, with its working directory and environment.
+                let spec = launcher.build_invocation_with_options(program, args, options);
+
+                return compositor.spawn(control, &spec).unwrap_or(spec);
+            }
+        };
+
+        options.apply(spec)
+    }
+}
+
+/// Returns `true` if a launch of `program` through `backend` opens a window, needing a graphical session.
+pub(crate) fn needs_graphical_session<P: AsRef<std::path::Path>>(
+    backend: LaunchBackend,
+    program: P,
+) -> bool {
+    match backend {
+        LaunchBackend::Exec => {
+            cfg!(all(unix, not(target_os = "macos")))
+                && !executable_name(program.as_ref())
+                    .is_some_and(|name| FRAMEBUFFER_TERMINALS.contains(&name))
+        }
+        LaunchBackend::CompositorSpawn(_) => true,
+        _ => false,
+    }
+}
+
+/// Spawns `spec` on behalf of `backend`, recording the launch in the history.
+///
+/// If `graphical`, the launch fails early without a graphical session, and the session variables are passed to it.
+pub(crate) fn spawn(
+    mut spec: CommandSpec,
+    backend: LaunchBackend,
+    graphical: bool,
+    requested_at: SystemTime,
+) -> io::Result<LaunchResult> {
+    if graphical {
+        spec = session::propagate(spec);
+    }
+
+    if flatpak::uses_host() {
+        spec = flatpak::host_command(spec);
+    }
+
+    spawn_log::log(&spec, backend);
+
+    let spawned = if graphical {
+        session::check_graphical_session().and_then(|()| spec.to_command().spawn())
+    } else {
+        spec.to_command().spawn()
+    };
+
+    match spawned {
+        Ok(child) => {
+            history::record(
+                &spec,
+                backend,
+                requested_at,
+                LaunchOutcome::Spawned { pid: child.id() },
+            );
+
+            Ok(LaunchResult::new(spec, backend, requested_at, child))
+        }
+        Err(error) => {
+            history::record(
+                &spec,
+                backend,
+                requested_at,
+                LaunchOutcome::Failed {
+                    error: error.to_string(),
+                },
+            );
+
+            Err(error)
+        }
+    }
+}
@@ -10,34 +10,142 @@
 //! - `linux`: Enables Linux-specific detection methods. (enabled by default)
 //! - `macos`: Enables macOS-specific detection methods. (enabled by default)
 //! - `env-var`: Enables detection using the `TERMINAL_EMULATOR` environment variable. (enabled by linux, macos, and unix)
+//! - `config-file`: Enables detection using the configuration file of this crate and the `first_run()` selection helper. (enabled by linux, macos, and unix)
 //! - `terminal-app`: Enables detection for Terminal.app on macOS. (enabled by macos)
 //! - `xdg-terminal-exec`: Enables detection using `xdg-terminal-exec`. (enabled by linux and unix)
 //! - `x-terminal-emulator`: Enables detection using `x-terminal-emulator`. (enabled by linux)
 //! - `gnome-settings`: Enables detection using GNOME settings. (enabled by linux and unix)
 //! - `kde-settings`: Enables detection using KDE settings. (enabled by linux and unix)
+//! - `xfce-settings`: Enables detection using XFCE settings. (enabled by linux and unix)
+//! - `lxqt-settings`: Enables detection using LXQt settings. (enabled by linux and unix)
 //! - `hardcoded`: Enables detection using a hardcoded list of known terminal emulators. (enabled by linux and unix)
 //! - `hardcoded-traditional`: Enables detection using a traditional hardcoded list of known terminal emulators. (enabled by hardcoded)
 //! - `hardcoded-modern`: Enables detection using a modern hardcoded list of known terminal emulators. (enabled by hardcoded)
 //! - `hardcoded-desktop-env`: Enables detection using desktop environment-specific hardcoded lists of known terminal emulators. (enabled by hardcoded)
 //! - `hardcoded-extended`: Enables detection using an extended hardcoded list of known terminal emulators. (enabled by hardcoded)
+//! - `controlling-tty`: Enables detection of the terminal emulator hosting the current process through its controlling terminal. (enabled by linux)
+//! - `process-ancestry`: Enables detection of the terminal emulator hosting the current process through its parent processes, on Linux and macOS. (enabled by linux and macos)
+//! - `version-detection`: Reads the version of the terminal emulators with known launch bugs (e.g. gnome-terminal before 3.22, which does not understand `--`) to work around or avoid them. (enabled by unix and linux)
+//! - `watch`: Keeps the result of `detect_cached()` in memory, watching the settings files (inotify on Linux) to pick up their changes immediately in long-running applications.
+//! - `daemon`: Serves detection and launch requests over a UNIX socket from a long-running process, keeping the detection result warm for short-lived clients (enables `watch`).
+//! - `terminal-query`: Identifies the terminal emulator hosting the current process by sending it XTVERSION and Device Attributes queries (UNIX only).
+//! - `benchmark`: Enables `benchmark_candidates()` measuring the startup time of the installed terminal emulators and the `SelectionStrategy::Fastest` selection strategy.
+//! - `openvt`: Enables launching commands on a free Linux virtual terminal through `openvt` when no graphical session exists, usually requiring root privileges.
+//! - `focus`: Enables `focus_launched()` with every window focusing tool. (enables focus-sway, focus-hyprland, focus-x11, and focus-applescript)
+//! - `focus-sway`: Focuses the launched windows on sway through `swaymsg`.
+//! - `focus-hyprland`: Focuses the launched windows on Hyprland through `hyprctl`.
+//! - `focus-x11`: Focuses the launched windows on X11 through `xdotool`, or `wmctrl` if `xdotool` is not installed.
+//! - `focus-applescript`: Focuses the launched windows on macOS through AppleScript (`osascript`).
+//! - `test-util`: Exposes `FakeTerminal` and `override_detect()` to force the result of `detect()` in the tests of dependent crates.
+//! - `xdg-terminal-exec-bin`: Builds an `xdg-terminal-exec` binary (`xdg-terminal-exec [--dir=PATH] [-e | --] [command...]`) backed by the detection methods, installable as the default terminal launcher of the system. `--completions=bash|zsh|fish` prints its shell completion script and `--man-page` its manual page, for distribution packages. It exits with 2 if no terminal emulator is found, 3 if it fails to start and 4 on unsupported platforms, and `--quiet` silences its error messages.
+//!
+//! Outside Windows, at least one detection method must be enabled for the target: building without one (e.g. with `default-features = false`, or only `terminal-app` on Linux) fails with an error naming the enabled features, instead of `detect()` silently finding nothing. The `controlling-tty` and `process-ancestry` features count as detection methods, since `detect_current()` finds the terminal emulator hosting the process with them alone: a build with only these features is for crates that do not launch terminal emulators, and `detect()` returns `None` in it.
 //!
 //! ## Detection Methods
 //!
 //! - **Environment Variable**: Checks for the `TERMINAL_EMULATOR` environment variable.
+//! - **Configuration File**: Reads the default terminal emulator saved in `$XDG_CONFIG_HOME/unidosx/terminal.conf` by `set_default_terminal()`, falling back to the system-wide default in `$XDG_CONFIG_DIRS/unidosx/terminal.conf` or `/etc/unidosx/terminal.conf`. `Locked=true` in `/etc/unidosx/terminal.conf` locks the terminal emulator, ignoring every other detection method and user override.
+//! - **Accessibility Preference**: When enabled with `prefer_accessible_terminals(true)` or `PreferAccessible=true` in the configuration file, prefers the terminal emulators known to work well with Orca and AT-SPI (gnome-terminal, Ptyxis, MATE Terminal, GNOME Console) over GPU-accelerated terminal emulators with poor accessibility.
 //! - **Terminal.app**: If running on macOS, it checks for Terminal.app as the default terminal.
-//! - **xdg-terminal-exec**: Utilizes the `xdg-terminal-exec` command to find the default terminal emulator.
+//! - **xdg-terminal-exec**: Utilizes the `xdg-terminal-exec` command to find the default terminal emulator. If it is not installed, the first available terminal emulator from its `xdg-terminals.list` configuration is used.
 //! - **x-terminal-emulator**: Uses the `x-terminal-emulator` command from Debian-based systems.
 //! - **GNOME Settings**: Queries GNOME settings to determine the preferred terminal emulator.
 //! - **KDE Settings**: Checks KDE configuration for the default terminal emulator.
+//! - **XFCE Settings**: Checks the XFCE preferred applications (`xfce4/helpers.rc`) for the default terminal emulator.
+//! - **LXQt Settings**: Checks the LXQt session settings (`lxqt/session.conf`) for the default terminal emulator.
 //! - **"GIO's Way"**: Uses a hardcoded list of known terminal emulators to find a match.
+//! - **Controlling TTY**: Maps the controlling terminal of the current process (`/dev/pts/N`) back to the terminal emulator owning it, identifying the terminal the process is *currently* running in (Linux only, not used by `detect()`).
+//! - **Host Environment**: Reads the variables set by the terminal emulator hosting the current process (e.g. `KITTY_WINDOW_ID`, `WEZTERM_EXECUTABLE`, `KONSOLE_VERSION`, `TERM_PROGRAM`, `VTE_VERSION`). `detect_current()` returns the terminal emulator with a `Confidence`, lowered when the variables conflict or a terminal multiplexer runs in between, so TUIs can gate features such as the kitty graphics protocol (not used by `detect()`).
+//! - **Process Ancestry**: Climbs the parent processes of the current process (through `/proc` on Linux and `libproc` on macOS) until one is a known terminal emulator, so `detect_current()` keeps working under `su`, in nested shells and in scripts (not used by `detect()`).
+//!
+//! Each detection method is also available as its own `probe_*` function (e.g. `probe_xdg_terminal_exec()`, `probe_gnome_settings()`), allowing custom detection pipelines to be composed. `detect_report()` runs every detection method and reports the terminal emulator each one found, or why it found nothing as a typed `SkipReason` (e.g. `EnvMissing`, `BinaryNotFound`, `FeatureDisabled`), so tooling can aggregate failure modes. `try_detect()` returns a `DetectionError` instead of `None` when no terminal emulator is found, telling the most significant failure (`NoTerminalFound`, `CommandFailed`, `Io`, `Parse` or `Unsupported`) and why each detection method found nothing. Without a graphical session (e.g. over SSH without X11 forwarding), unless on a bare Linux virtual console where framebuffer terminal emulators can run, it fails up front with `HeadlessEnvironment`, so the caller can run the command in the current terminal instead of launching a terminal emulator that cannot open a window.
+//!
+//! `detect_all()` returns every installed terminal emulator instead of the first one, for example to let the user choose one in a settings dialog. It runs every enabled detection method and searches PATH for the hardcoded terminal emulators. Terminal emulators resolving to the same executable are only listed once, and each one is tagged with the detection method that found it.
+//!
+//! Detection methods running commands (e.g. `gsettings`) can be bounded by a `CancellationToken`: `CancellationToken::new().with_timeout(budget).scope(detect)` kills and reaps the commands still running when the deadline passes or when `cancel()` is called from another thread, skipping their detection methods with `SkipReason::Timeout` or `SkipReason::Cancelled` instead of blocking a GUI or leaving zombie processes behind.
+//!
+//! `detect()` reads every environment variable from a single snapshot captured when detection starts, so all detection methods see the same environment. Use `detect_with()` to detect with a pre-built `Environment`, or `Environment::scope()` to run custom detection pipelines against one.
+//!
+//! Settings files and desktop entries are searched following the XDG Base Directory specification: `XDG_CONFIG_HOME` and `XDG_CONFIG_DIRS` for configuration files (e.g. `kdeglobals`, `xdg-terminals.list`), `XDG_DATA_HOME` and `XDG_DATA_DIRS` for desktop entries.
+//!
+//! Paths and arguments that are not valid Unicode (e.g. a `TERMINAL_EMULATOR` path or file name in a legacy encoding) are carried unchanged through detection and launches: executables are searched and spawned with their exact bytes, and `Shell::Sh.compose()` quotes them byte for byte. Only PowerShell and `cmd`, which read Unicode, replace invalid sequences.
+//!
+//! Command lines read from desktop entries and settings (e.g. `Exec="/opt/My Terminal/term" %U`) are parsed following the `Exec` key rules of the Desktop Entry Specification, handling quotes, escapes and field codes. The parser is available as `parse_exec_line()`, returning a `CommandSpec`.
+//!
+//! The execution syntax of each terminal emulator comes from a built-in quirks table instead of a global default: gnome-terminal takes `--`, foot takes the command positionally and xfce4-terminal takes it as a single `-e` argument. The table also maps wrappers, clients and desktop entry IDs to their terminal emulator (e.g. `gnome-terminal.wrapper`, which follows the `x-terminal-emulator` syntax, `footclient` or `org.gnome.Terminal.desktop`), so the settings-based detection methods and `xdg-terminals.list` entries get the right syntax too. Terminal emulators using another flag (e.g. `-x`, `+e` or `--command=`) are described with `ExecutionSyntax::CustomFlag`. With the `version-detection` feature, terminal emulator releases with known launch bugs are worked around or avoided, based on the version they print with `--version`: gnome-terminal releases older than 3.22, which do not understand `--`, get a single `-e` argument instead. `DetectionReport::notes` tells which releases were found. `quote_command_line(program, args)` composes the single shell-quoted string the launcher passes to terminal emulators taking the whole command after `-e` (e.g. xfce4-terminal), for callers building such commands manually. Spaces, quotes, `$`, newlines and non-ASCII characters are kept unchanged.
+//!
+//! Terminal emulators missing from the quirks table are assumed to take `-e`. With `probe_unknown_terminals(true)`, their execution syntax is instead inferred from their `--help` output (`--`, `-e`, `-x`/`--execute` or a positional command), remembered for each executable during the process; `infer_execution_syntax(path)` runs the same inference directly.
+//!
+//! Executables and settings files are searched through the `Filesystem` trait, which only lists directories, reads file metadata and reads files. `with_filesystem()` installs another implementation on the current thread, so embedders can search a virtual filesystem and tests can describe the installed executables without creating real files.
+//!
+//! `Detector::for_root(path)` runs detection against another system mounted under `path` (e.g. an extracted container image or a mounted VM disk), reading its system-wide configuration and searching its standard directories, so image-building tooling can tell which terminal emulator the system would pick without booting it. The detection methods querying the running session (e.g. `gsettings`) are skipped with `SkipReason::SessionRequired`. `Detector::new().methods([...])` runs only the listed detection methods in the given order, chosen at runtime (e.g. preferring `DetectionMethod::KdeSettings` over `DetectionMethod::EnvironmentVariable`). `Detector::disable()` skips a detection method at runtime (e.g. `DetectionMethod::XTerminalEmulator` for some users), reporting it with `SkipReason::Disabled`, so a single binary can adapt to each system without recompiling.
+//!
+//! Other crates can add their own detection logic (e.g. for company-internal terminal emulators) by implementing the `DetectionBackend` trait and registering it with `Detector::with_backend()`. Backends have a name, reported as `DetectionMethod::Backend(name)`, and a priority placing them before or after the built-in detection methods. They build the terminal emulators they find with `TerminalEmulator::new()`.
+//!
+//! `TerminalEmulator::name()`, `path()`, `execution_syntax()` and `detection_method()` describe the detected terminal emulator. `TerminalEmulator::display_name()` returns the name shown to users, read from the desktop entry of the terminal emulator in the XDG data directories and localized according to `LC_ALL`, `LC_MESSAGES` or `LANG`.
+//!
+//! `detect_cached()` stores the detected terminal emulator in `$XDG_CACHE_HOME/unidosx/terminal.json` and reuses it across runs until one of the environment variables or settings files (e.g. `kdeglobals`, `xdg-terminals.list`, the dconf database) detection depends on changes, avoiding the cost of detection in short-lived command line tools. With the `watch` feature, the result is also kept in memory and the settings directories (including `dconf`, `kdeglobals` and `xdg-terminals.list` locations) are watched with inotify on Linux, so long-running applications pick up settings changes immediately.
+//!
+//! With the `daemon` feature, `serve_daemon(path)` answers detection and launch requests on a UNIX socket (usually `daemon_socket_path()`, in `XDG_RUNTIME_DIR`) from one long-running process, whose detection result stays in memory. Short-lived scripts call `detect_via_daemon(path)` and `launch_via_daemon(path, program, args)` instead of paying for a cold detection each time, falling back to `detect_cached()` when no daemon is running. `DaemonClient::connect()?` does the fallback itself: its `detect()` and `launch(&spec)` go through the daemon when one is running, and detect and launch in-process otherwise. `daemon_metrics()` returns the number of detections served, cache hits, and launches and launch failures by terminal emulator in the Prometheus text format, and `serve_daemon_metrics(listener)` serves them over HTTP for fleet monitoring.
+//!
+//! With the `terminal-query` feature, `query_terminal(timeout)` asks the terminal emulator hosting the current process for its identity through its controlling terminal: the XTVERSION answer (`TerminalIdentity::name()` and `version()`, e.g. `kitty` and `0.35.2`) and the Secondary and Primary Device Attributes, switching the terminal to raw mode with `stty` meanwhile. Unlike `detect_current()`, the answer comes from the terminal emulator itself, so it is not fooled by inherited or forwarded environment variables.
+//!
+//! Terminal emulators that failed to launch during the current process (see `launch_with_fallback()` and `record_launch_failure()`) are deprioritized by the detection methods, so a broken wrapper script does not break every launch of the session.
+//!
+//! On UNIX, launched processes are reaped in the background once they exit if the result of the launch is dropped, so fire-and-forget launches never leave zombie processes in the calling process. Take the process with `LaunchResult::into_child()` to wait for it yourself.
+//!
+//! Launches can be recorded in a history file (`$XDG_STATE_HOME/unidosx/launch-history.jsonl`, one JSON object per line) with the program, command line, timestamp and outcome of each launch, helping to debug launches that opened nothing. Recording is disabled by default, enable it with `record_launch_history(true)` and query it with `launch_history()`.
+//!
+//! Launched processes can be tagged with a unique launch ID, enabled with `tag_launches(true)`: every launch sets `UNIDOSX_LAUNCH_ID` in the environment of the spawned process, returned by `LaunchResult::launch_id()`, and `find_launched(id)` finds the processes carrying it on Linux, so applications can later focus or close the window they opened.
+//!
+//! With the `focus-*` features, `focus_launched()` brings the window of a launched terminal emulator to the foreground, as many terminal emulators open unfocused when spawned from background processes. The window is found by the process ID of the launch, and by its launch ID if tagged, then focused through `swaymsg` on sway, `hyprctl` on Hyprland, `xdotool` or `wmctrl` on X11, or AppleScript on macOS.
+//!
+//! `LaunchResult::close(grace)` closes a launched terminal emulator, so helper windows opened by an application can be cleaned up when it exits: the spawned process, and the processes carrying its launch ID if tagged, are asked to exit with `SIGTERM` (`taskkill` on Windows), and killed if still running after `grace`.
+//!
+//! `LaunchResult::to_descriptor()` returns a `LaunchDescriptor` of the launch (the terminal emulator, the command it runs, its working directory and options) serializable with `to_json()`, so applications can save the tool windows they opened and re-open them with `relaunch(&descriptor)` after a restart. The variables of the session and the launch ID are left out, `relaunch` sets them again.
+//!
+//! `launch_batch(launcher, specs, max_concurrent)` launches many commands through a launcher, staggering them so at most `max_concurrent` windows start opening every half second, as spawning dozens of terminal emulators at once overwhelms some compositors.
+//!
+//! `Launcher::launch_with_options()` and `Launcher::build_invocation_with_options()` take `LaunchOptions` (working directory, window title, holding the window open after the command exits, environment variables), translated into the flags of the terminal emulator (e.g. `--working-directory=`, `--title`, `--hold`) or applied by a `sh -c` wrapper around the command when it has no such flag. With `LaunchOptions::track_exit_status(true)`, the `sh -c` wrapper writes the exit status of the command to a file once it exits, and `LaunchResult::wait_for_inner_exit()` returns it, as the exit status of the terminal emulator says nothing about the command it ran. `LaunchOptions::locale()` sets `LANG` and `LC_ALL` for the command, and `LaunchOptions::encoding()` passes the encoding flags of legacy terminal emulators (xterm `-u8` or `-en`, mlterm `--km=`), so programs expecting UTF-8 render correctly in terminal emulators defaulting to a legacy locale. With `LaunchOptions::capture_output(true)`, the standard output and standard error of the command are also copied to files through `tee`, still showing in the window, and `LaunchResult::wait_for_output()` returns them with the exit status once the command exits, so installers can show a terminal while logging its output. `LaunchOptions::color_scheme()` passes a light or dark preference to the terminal emulators taking it as a flag (gnome-terminal and Konsole `Light`/`Dark` profiles, foot `initial-color-theme`, xterm reverse video), so helper windows match the theme of the application. `LaunchOptions::font_size()` sets the font size of the window in the terminal emulators taking it as a flag (kitty and Alacritty `-o`, Ghostty `--font-size=`, xterm `-fs`), for launchers needing large text. With `LaunchOptions::wait(true)`, `LaunchResult::wait_for_command()` blocks until the command exits: the wait flag of terminal emulators handing their windows over to a server (gnome-terminal `--wait`, Konsole `--nofork`, xfce4-terminal `--disable-server`, mate-terminal `--disable-factory`) or `start /wait` keeps the spawned process running, and the exit status written by the `sh -c` wrapper is waited for otherwise. With `LaunchOptions::pipe_stdin(true)`, the `sh -c` wrapper reads the standard input of the command from a named pipe, written through `LaunchResult::open_stdin()`, so generated data can be paged in a new window (e.g. `generate_report | xdg-terminal-exec --pipe-stdin less`) although the command runs on the terminal of that window.
+//!
+//! `run_in_new_terminal(program, args, &options)` detects the default terminal emulator, builds the command with the quoting its execution syntax needs and spawns it in one call on every platform, returning the spawned `Child`. `spawn_in_terminal(&terminal, program, args)` does the same with a given terminal emulator, returning the spawned `Child` so it can be waited on or killed. `relaunch_in_terminal_if_needed()`, called first in `main`, re-executes a terminal application with the same arguments in a new terminal window and exits when it has no terminal (e.g. when started from a desktop file), setting `UNIDOSX_RELAUNCHED` so it never loops.
+//!
+//! `Launcher::dry_run()` describes what a launch would spawn without spawning anything: the backend, working directory, environment changes and arguments on one line (e.g. `Exec: env DISPLAY=:0 kitty -- vim notes.txt`). Every launch hands the same line, byte for byte, to the logger set with `set_spawn_logger()` right before spawning, so the dry run can be diffed against what was done. The crate has no dependencies, the logger can forward the events to `tracing` or `log`.
+//!
+//! On Linux and BSD, launches opening a window fail early with a clear error when no graphical session is available (neither `DISPLAY` nor `WAYLAND_DISPLAY` is set), a common failure when launching from cron or systemd services, and the session variables (`DISPLAY`, `WAYLAND_DISPLAY`, `XAUTHORITY` and `DBUS_SESSION_BUS_ADDRESS` by default, see `set_propagated_environment()`) are passed explicitly to the terminal emulator. `has_graphical_session()` tells whether a terminal emulator can be opened at all (`DISPLAY` or `WAYLAND_DISPLAY` on Linux and BSD, the Aqua session on macOS, an interactive session on Windows), and `is_attached_to_tty()` whether the standard input and output are already a terminal.
+//!
+//! Inside a Flatpak sandbox (`/.flatpak-info` exists, see `is_flatpak_sandboxed()`), the terminal emulators installed on the host are invisible and cannot be spawned directly, so executables are looked up in the `PATH` of the host and launches are wrapped in `flatpak-spawn --host`, with their working directory and environment changes passed through `--directory=`, `--env=` and `--unset-env=`. The files of launches (e.g. exit statuses) go to `$XDG_RUNTIME_DIR/app/$FLATPAK_ID`, which the sandbox shares with the host. The application needs the `--talk-name=org.freedesktop.Flatpak` permission.
+//!
+//! `select_terminal()` rotates through the installed terminal emulators (`SelectionStrategy::RoundRobin`) or picks one at random, optionally weighted by name (`SelectionStrategy::Random` and `SelectionStrategy::Weighted`), for testing matrices and demo setups.
+//!
+//! `first_run()` asks the user to choose the default terminal emulator among the installed ones when none is configured, saving the choice in the configuration file. Choices are presented through the `TerminalPrompt` trait, `StdinPrompt` asks on the terminal and GUI applications can implement their own picker.
+//!
+//! Wayland-only terminal emulators (foot) are deprioritized in favor of X11-capable ones unless `WAYLAND_DISPLAY` names a live socket (relative to `XDG_RUNTIME_DIR`), preventing launches guaranteed to fail in X11 sessions. Likewise, X11-only terminal emulators (e.g. xterm, urxvt, st) are deprioritized unless `DISPLAY` names a display accepting connections, as in Wayland sessions without XWayland. `TerminalEmulator::required_env_vars()` lists the variables a terminal emulator needs, and `TerminalEmulator::check_environment()` reports the missing one (e.g. "foot needs WAYLAND_DISPLAY, which is not set"), so `select_terminal()` skips such terminal emulators instead of failing at launch. `TerminalEmulator::capabilities()` returns a `TerminalCapabilities` from the built-in database: whether the window can be held open, titled, given a working directory and waited for by a flag of the terminal emulator (the `sh -c` wrapper is used otherwise), whether it has tabs, talks to Wayland directly and displays 24-bit colors, so callers can adapt their launch strategy. `copy_to_clipboard(text)` copies text from terminal applications through the OSC 52 escape sequence when the current terminal emulator supports it or over SSH, and through `wl-copy`, `xclip`, `xsel`, `pbcopy` or `clip` otherwise.
+//!
+//! ### Privileged Context
+//!
+//! When the process runs setuid/setgid, or as root on behalf of another user (`sudo`, `pkexec`, `doas`), the detection
+//! methods relying on the environment or the user configuration are skipped and PATH is replaced by a secure default,
+//! preventing a less privileged user from redirecting launches to arbitrary binaries. Use
+//! `trust_privileged_environment(true)` to opt out.
 //!
 //! ### Windows Support
 //!
 //! On Windows, the terminal emulator is part of the Win32 API making the default terminal emulator invocation works through the Win32 call `AllocConsole`, so no detection is necessary.
 //!
+//! The `Launcher` enum launches commands on every platform, on Windows it either spawns the command with its own console (`AllocConsole`) or through `cmd /c start`. When running inside Windows Terminal, commands are opened as a new tab of the current window (`wt -w 0 new-tab`). Windows held open with `LaunchOptions::hold(true)` stay open in the command interpreter of the user, `Shell::detect()` (`pwsh -NoExit`, `powershell -NoExit` or `cmd /k`).
+//!
+//! GUI-subsystem binaries can call `attach_or_alloc_console()` to attach to the console of their parent process, or allocate a new one, getting working standard input and output.
+//!
+//! On tiling Wayland compositors (sway, Hyprland, niri and river), `Launcher::with_compositor()` asks the compositor to spawn the terminal emulator, falling back to executing it directly. The running compositor (sway, Hyprland, niri, river, KWin, Mutter, Wayfire or labwc) is identified by `detect_compositor()`.
+//!
 //! ## Hardcoded Terminal Emulators
 //!
-//! When searching for terminal emulators using hardcoded lists it will use the PATH environment variable to search for the terminal emulators.
+//! When searching for terminal emulators using hardcoded lists it will use the PATH environment variable to search for the terminal emulators. If PATH is missing or empty, the standard directories (`/usr/local/bin`, `/usr/bin` and `/bin`, plus `/opt/homebrew/bin` on macOS) are searched instead. On Windows, the extensions listed in PATHEXT are also tried, so wrapper scripts like `wezterm.cmd` are found. On Windows and macOS, names are matched ignoring case, and on macOS the app bundles in `/Applications` and `~/Applications` are also searched (e.g. `Alacritty.app`). Empty and relative PATH entries are never searched. When an executable is not found, the report lists the candidates skipped on the way with `SkipReason::BinaryRejected` (e.g. a relative PATH entry, a loop of symbolic links, a directory that can not be searched or a file without execute permission, see `LookupProblem`).
+//!
+//! The framebuffer terminal emulators (fbterm and kmscon) take over the current virtual console instead of opening a window, so they are only offered when running on a bare Linux virtual console.
+//!
+//! The known terminal emulators, with their execution syntax and launch arguments, can be exported as a versioned JSON document with `export_database()` for use outside of Rust.
 //!
 //! ### Traditional
 //!
@@ -99,53 +207,484 @@
 //! This project is licensed under the [MIT License](LICENSE).
 
 use std::{
+    borrow::{Borrow, Cow},
+    cmp::Ordering,
     fmt::{self, Display, Formatter},
-    path::PathBuf,
+    hash::{Hash, Hasher},
+    io,
+    path::{Path, PathBuf},
     process::Command,
 };
 
+#[cfg(not(windows))]
+mod accessibility;
+#[cfg(not(windows))]
+mod backend;
+mod batch;
+#[cfg(all(not(windows), feature = "benchmark"))]
+mod benchmark;
+#[cfg(not(windows))]
+mod cache;
+mod cancel;
+mod capabilities;
+mod clipboard;
+mod command_spec;
+mod compositor;
+#[cfg(feature = "config-file")]
+mod config;
+#[cfg(windows)]
+mod console;
+mod current;
+#[cfg(all(unix, feature = "daemon"))]
+mod daemon;
+mod database;
+mod descriptor;
+mod desktop_entry;
+#[cfg(not(windows))]
+mod detector;
+mod environment;
+mod exec_line;
+mod failures;
+mod fallback;
+#[cfg(not(windows))]
+mod features;
+mod filesystem;
+mod flatpak;
+#[cfg(any(
+    feature = "focus-sway",
+    feature = "focus-hyprland",
+    feature = "focus-x11",
+    feature = "focus-applescript"
+))]
+mod focus;
+mod history;
+mod inference;
+mod ini;
+mod json;
+mod launch;
+mod launcher;
+mod lookup;
+mod options;
+mod privilege;
+mod probes;
+#[cfg(all(unix, feature = "terminal-query"))]
+mod query;
+#[cfg(any(
+    test,
+    not(windows),
+    feature = "env-var",
+    feature = "config-file",
+    feature = "xdg-terminal-exec",
+    feature = "x-terminal-emulator",
+    feature = "gnome-settings",
+    feature = "kde-settings",
+    feature = "xfce-settings",
+    feature = "lxqt-settings",
+    feature = "test-util",
+    all(target_os = "linux", feature = "controlling-tty")
+))]
+mod quirks;
+#[cfg(not(windows))]
+mod report;
+mod run;
+#[cfg(not(windows))]
+mod selection;
+mod session;
+mod shell;
+mod spawn_log;
+mod tagging;
+#[cfg(any(test, feature = "test-util"))]
+mod test_util;
 #[cfg(test)]
 mod tests;
+#[cfg(feature = "version-detection")]
+mod versions;
+#[cfg(all(not(windows), feature = "watch"))]
+mod watch;
+#[cfg(all(not(windows), feature = "config-file"))]
+mod wizard;
+mod xdg;
+
+#[cfg(not(windows))]
+pub use accessibility::{
+    accessible_terminals_preferred, prefer_accessible_terminals, probe_accessible_terminals,
+};
+#[cfg(not(windows))]
+pub use backend::DetectionBackend;
+pub use batch::launch_batch;
+#[cfg(all(not(windows), feature = "benchmark"))]
+pub use benchmark::{CandidateBenchmark, benchmark_candidates};
+#[cfg(not(windows))]
+pub use cache::{clear_detection_cache, detect_cached, detection_cache_path};
+pub use cancel::CancellationToken;
+pub use capabilities::TerminalCapabilities;
+pub use clipboard::{ClipboardMethod, copy_to_clipboard};
+pub use command_spec::CommandSpec;
+pub use compositor::{Compositor, detect_compositor};
+#[cfg(feature = "config-file")]
+pub use config::{default_terminal_config_path, is_terminal_locked, set_default_terminal};
+#[cfg(windows)]
+pub use console::{ConsoleAttachment, attach_or_alloc_console};
+#[cfg(all(target_os = "linux", feature = "controlling-tty"))]
+pub use current::probe_controlling_tty;
+#[cfg(all(
+    any(target_os = "linux", target_os = "macos"),
+    feature = "process-ancestry"
+))]
+pub use current::probe_process_ancestry;
+pub use current::{Confidence, CurrentTerminal, detect_current};
+#[cfg(all(unix, feature = "daemon"))]
+pub use daemon::{
+    DaemonClient, daemon_metrics, daemon_socket_path, detect_via_daemon, launch_via_daemon,
+    serve_daemon, serve_daemon_metrics,
+};
+pub use database::{DATABASE_SCHEMA_VERSION, export_database};
+pub use descriptor::{LaunchDescriptor, relaunch};
+#[cfg(not(windows))]
+pub use detector::Detector;
+pub use environment::Environment;
+pub use exec_line::parse_exec_line;
+pub use failures::{clear_launch_failures, has_launch_failed, record_launch_failure};
+pub use fallback::launch_with_fallback;
+pub use filesystem::{FileInfo, Filesystem, RealFilesystem, RootFilesystem, with_filesystem};
+pub use flatpak::is_flatpak_sandboxed;
+#[cfg(any(
+    feature = "focus-sway",
+    feature = "focus-hyprland",
+    feature = "focus-x11",
+    feature = "focus-applescript"
+))]
+pub use focus::focus_launched;
+pub use history::{
+    LaunchOutcome, LaunchRecord, clear_launch_history, launch_history, launch_history_path,
+    record_launch_history,
+};
+pub use inference::{infer_execution_syntax, probe_unknown_terminals, unknown_terminals_probed};
+pub use launch::{CapturedOutput, LaunchBackend, LaunchResult};
+pub use launcher::Launcher;
+pub use lookup::LookupProblem;
+pub use options::{ColorScheme, LaunchOptions};
+pub use privilege::{is_privileged_context, trust_privileged_environment};
+pub use probes::SkipReason;
+#[cfg(any(
+    all(target_os = "macos", feature = "terminal-app"),
+    feature = "env-var",
+    feature = "config-file",
+    feature = "xdg-terminal-exec",
+    feature = "x-terminal-emulator",
+    feature = "gnome-settings",
+    feature = "kde-settings",
+    feature = "xfce-settings",
+    feature = "lxqt-settings",
+    feature = "hardcoded-desktop-env",
+    feature = "hardcoded-modern",
+    feature = "hardcoded-traditional",
+    feature = "hardcoded-extended"
+))]
+pub use probes::*;
+#[cfg(all(unix, feature = "terminal-query"))]
+pub use query::{TerminalIdentity, query_terminal};
+#[cfg(not(windows))]
+pub use report::{DetectionError, DetectionReport, ReportEntry, detect_report, try_detect};
+pub use run::{
+    RELAUNCHED_VAR, relaunch_in_terminal_if_needed, run_in_new_terminal, spawn_in_terminal,
+};
+#[cfg(not(windows))]
+pub use selection::{SelectionStrategy, detect_all, select_terminal};
+pub use session::{
+    DEFAULT_PROPAGATED_VARS, check_graphical_session, has_graphical_session, is_attached_to_tty,
+    propagated_environment, set_propagated_environment,
+};
+pub use shell::{Shell, quote_command_line};
+pub use spawn_log::set_spawn_logger;
+pub use tagging::{LAUNCH_ID_VAR, find_launched, tag_launches};
+#[cfg(feature = "test-util")]
+pub use test_util::{FakeTerminal, override_detect};
+#[cfg(all(not(windows), feature = "config-file"))]
+pub use wizard::{StdinPrompt, TerminalPrompt, first_run};
 
 #[cfg(windows)]
 /// Detects the default terminal emulator.
-pub fn detect<'a>() -> TerminalEmulator<'a> {
+pub fn detect() -> TerminalEmulator {
+    #[cfg(any(test, feature = "test-util"))]
+    if let Some(Some(terminal)) = test_util::overridden() {
+        return terminal;
+    }
+
+    detect_with(&Environment::capture())
+}
+
+#[cfg(windows)]
+/// Detects the default terminal emulator, reading the environment from `env`.
+pub fn detect_with(_env: &Environment) -> TerminalEmulator {
     TerminalEmulator {
-        command_line: "",
+        command_line: "".into(),
         execution_syntax: ExecutionSyntax::NativeApi,
         path: PathBuf::new(),
         method: DetectionMethod::Windows,
+        raw: None,
     }
 }
 
-/// Builds a command to run a given command in the specified terminal emulator.
+#[cfg(not(windows))]
+/// The detection methods run by [`detect`], in order.
+const PROBES: &[report::Probe] = &[
+    #[cfg(feature = "env-var")]
+    (DetectionMethod::EnvironmentVariable, probes::check_env_var),
+    #[cfg(feature = "config-file")]
+    (DetectionMethod::ConfigFile, probes::check_config_file),
+    (
+        DetectionMethod::Accessibility,
+        accessibility::check_if_preferred,
+    ),
+    #[cfg(all(target_os = "macos", feature = "terminal-app"))]
+    (DetectionMethod::TerminalApp, probes::check_terminal_app),
+    #[cfg(feature = "xdg-terminal-exec")]
+    (
+        DetectionMethod::XdgTerminalExec,
+        probes::check_xdg_terminal_exec,
+    ),
+    #[cfg(feature = "x-terminal-emulator")]
+    (
+        DetectionMethod::XTerminalEmulator,
+        probes::check_x_terminal_emulator,
+    ),
+    #[cfg(feature = "gnome-settings")]
+    (DetectionMethod::GnomeSettings, probes::check_gnome_settings),
+    #[cfg(feature = "kde-settings")]
+    (DetectionMethod::KdeSettings, probes::check_kde_settings),
+    #[cfg(feature = "xfce-settings")]
+    (DetectionMethod::XfceSettings, probes::check_xfce_settings),
+    #[cfg(feature = "lxqt-settings")]
+    (DetectionMethod::LxqtSettings, probes::check_lxqt_settings),
+    #[cfg(feature = "hardcoded-desktop-env")]
+    (
+        DetectionMethod::HardcodedDesktopEnv,
+        probes::check_hardcoded_desktop_env,
+    ),
+    #[cfg(feature = "hardcoded-modern")]
+    (
+        DetectionMethod::HardcodedModern,
+        probes::check_hardcoded_modern,
+    ),
+    #[cfg(feature = "hardcoded-traditional")]
+    (
+        DetectionMethod::HardcodedTraditional,
+        probes::check_hardcoded_traditional,
+    ),
+    #[cfg(feature = "hardcoded-extended")]
+    (
+        DetectionMethod::HardcodedExtended,
+        probes::check_hardcoded_extended,
+    ),
+];
+
+#[cfg(not(windows))]
+/// Detects the default terminal emulator.
+///
+/// Runs every enabled detection method in order, returning the first terminal emulator found, or `None` if no
+/// detection method found one. Use the `probe_*` functions to compose a different detection pipeline.
+///
+/// Terminal emulators that failed to launch during the current process, Wayland-only terminal emulators (foot) without
+/// a live Wayland socket and X11-only terminal emulators (e.g. xterm) without a live X11 display, are only returned if
+/// no other one is found.
 ///
-/// Returns `None` if the terminal emulator uses a native API for command execution.
-pub fn build_command_in_terminal<'a>(terminal: &TerminalEmulator<'a>) -> Option<Command> {
-    if terminal.execution_syntax == ExecutionSyntax::NativeApi {
-        return None;
+/// If the system-wide configuration file locks the terminal emulator (see [`is_terminal_locked`]), only the terminal
+/// emulator it sets is returned.
+///
+/// Every detection method reads the same snapshot of the environment, captured once when detection starts.
+pub fn detect() -> Option<TerminalEmulator> {
+    #[cfg(any(test, feature = "test-util"))]
+    if let Some(result) = test_util::overridden() {
+        return result;
     }
 
-    let mut cmd = Command::new(&terminal.path);
+    environment::snapshot(|| {
+        #[cfg(feature = "config-file")]
+        if is_terminal_locked() {
+            return probe_config_file();
+        }
 
-    if let Some(arg) = terminal.execution_syntax.as_arg() {
-        cmd.arg(arg);
-    }
+        failures::prefer_working(
+            detector::steps()
+                .into_iter()
+                .filter_map(|step| step.run().ok()),
+            |terminal| &terminal.path,
+        )
+    })
+}
+
+#[cfg(not(windows))]
+/// Detects the default terminal emulator, reading the environment from `env` instead of the process environment.
+///
+/// Allows reproducing a detection run, see [`detect`].
+pub fn detect_with(env: &Environment) -> Option<TerminalEmulator> {
+    env.scope(detect)
+}
 
-    Some(cmd)
+/// Builds a command to run a given command in the specified terminal emulator.
+///
+/// Returns `None` if the terminal emulator uses a native API for command execution, use [`Launcher`] to launch commands
+/// on every platform.
+///
+/// Terminal emulators using [`ExecutionSyntax::SingleE`] expect the whole command as one argument, use
+/// [`Launcher::command_with_args`] to compose it.
+pub fn build_command_in_terminal(terminal: &TerminalEmulator) -> Option<Command> {
+    match Launcher::new(terminal) {
+        Launcher::Terminal { program, args, .. } => {
+            Some(CommandSpec::new(program).args(args).into())
+        }
+        _ => None,
+    }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone)]
 /// Represents a terminal emulator.
-pub struct TerminalEmulator<'a> {
+///
+/// Terminal emulators are identified by their name (e.g. `kitty`), or their executable name if unknown: equality,
+/// hashing and ordering only use it, and `Borrow<str>` allows looking them up by name in sets and maps.
+///
+/// It owns its data, so detection results can be kept in long-lived structures (e.g. a `OnceLock`) and sent across
+/// threads.
+///
+/// Terminal emulators are returned by the detection methods, detection backends build one with
+/// [`TerminalEmulator::new`], and tests with `FakeTerminal` (`test-util` feature).
+pub struct TerminalEmulator {
     /// The command line and name of the terminal emulator.
-    command_line: &'a str,
+    command_line: Cow<'static, str>,
     /// The execution syntax used by the terminal emulator.
     execution_syntax: ExecutionSyntax,
     /// The path to the terminal emulator executable.
     path: PathBuf,
     /// The detection method used to find the terminal emulator.
     method: DetectionMethod,
+    /// The unparsed value read by the detection method, if any.
+    raw: Option<String>,
+}
+
+impl TerminalEmulator {
+    /// Creates a terminal emulator for the executable at `path`, for detection backends (see `DetectionBackend`).
+    ///
+    /// Known terminal emulators are named as usual (e.g. `kitty`), other ones by their executable name. The path is not
+    /// checked. The detection method is set to the backend returning the terminal emulator.
+    pub fn new<P: Into<PathBuf>>(path: P, execution_syntax: ExecutionSyntax) -> Self {
+        let path = path.into();
+        let command_line = lookup::executable_name(&path)
+            .and_then(probes::lookup_known)
+            .map_or("", |(name, _)| name);
+
+        Self {
+            command_line: command_line.into(),
+            execution_syntax,
+            path,
+            method: DetectionMethod::Backend(""),
+            raw: None,
+        }
+    }
+
+    /// Returns the name identifying the terminal emulator (e.g. `kitty`), or its executable name if unknown.
+    ///
+    /// See [`TerminalEmulator::display_name`] for the name shown to users.
+    pub fn name(&self) -> &str {
+        if self.command_line.is_empty() {
+            lookup::executable_name(&self.path).unwrap_or_default()
+        } else {
+            &self.command_line
+        }
+    }
+
+    /// Returns the path to the terminal emulator executable (the application bundle for Terminal.app).
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the execution syntax used by the terminal emulator.
+    pub fn execution_syntax(&self) -> ExecutionSyntax {
+        self.execution_syntax
+    }
+
+    /// Returns the detection method that found the terminal emulator.
+    pub fn detection_method(&self) -> DetectionMethod {
+        self.method
+    }
+
+    /// Returns the user-facing name of the terminal emulator.
+    ///
+    /// The name is read from the desktop entry of the terminal emulator, localized according to `LC_ALL`,
+    /// `LC_MESSAGES` or `LANG` (e.g. "Terminal" or "Терминал"), falling back to the executable name.
+    pub fn display_name(&self) -> String {
+        if self.method == DetectionMethod::Windows {
+            return String::from("Windows Console");
+        }
+
+        let executable = lookup::executable_name(&self.path).unwrap_or(&self.command_line);
+
+        desktop_entry::find_desktop_entry(executable)
+            .and_then(|entry| desktop_entry::localized_name(&entry).map(str::to_owned))
+            .unwrap_or_else(|| executable.to_owned())
+    }
+
+    /// Returns the unparsed value read by the detection method, exactly as the system reports it (e.g. `'kgx'` for
+    /// the GNOME settings, or `konsole --separate` for the KDE settings).
+    ///
+    /// Returns `None` for the detection methods that do not read a value, like the hardcoded lists.
+    pub fn raw_value(&self) -> Option<&str> {
+        self.raw.as_deref()
+    }
+
+    /// Returns what the terminal emulator supports (e.g. setting the window title by a flag, tabs, 24-bit colors), from
+    /// the built-in database.
+    pub fn capabilities(&self) -> TerminalCapabilities {
+        capabilities::of(&self.path)
+    }
+
+    /// Returns the environment variables the terminal emulator needs to open a window (e.g. `WAYLAND_DISPLAY` for
+    /// foot, `DISPLAY` for xterm).
+    pub fn required_env_vars(&self) -> &'static [&'static str] {
+        session::required_vars(&self.path)
+    }
+
+    /// Verifies that the environment variables the terminal emulator needs are set and name a display accepting
+    /// connections, failing with an error naming the missing one otherwise (e.g. "foot needs WAYLAND_DISPLAY, which is
+    /// not set").
+    ///
+    /// The error has the [`io::ErrorKind::NotConnected`] kind. Detection and [`select_terminal`] only return terminal
+    /// emulators failing this check if no other one is installed.
+    pub fn check_environment(&self) -> io::Result<()> {
+        session::check_required_vars(&self.path)
+    }
+}
+
+impl PartialEq for TerminalEmulator {
+    fn eq(&self, other: &Self) -> bool {
+        self.name() == other.name()
+    }
+}
+
+impl Eq for TerminalEmulator {}
+
+impl Hash for TerminalEmulator {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name().hash(state);
+    }
+}
+
+impl PartialOrd for TerminalEmulator {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TerminalEmulator {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.name().cmp(other.name())
+    }
+}
+
+impl Borrow<str> for TerminalEmulator {
+    fn borrow(&self) -> &str {
+        self.name()
+    }
 }
 
 /// Command execution syntax used by terminal emulators.
@@ -158,19 +697,124 @@ pub enum ExecutionSyntax {
     #[default]
     /// `terminal -e command`
     E,
+    /// `terminal -e 'command'`, the command is given as a single argument.
+    SingleE,
     /// Uses the native API to launch commands in the terminal.
     NativeApi,
+    /// `terminal <flag> command`, for terminal emulators using another flag (e.g. `-x`, `+e` or `--command=`).
+    ///
+    /// A flag ending with `=` (e.g. `--command=`) takes the command in the same argument, as a single argument.
+    CustomFlag {
+        /// The flag placed between the terminal emulator and the command.
+        flag: &'static str,
+        /// Whether the command must be given as a single argument.
+        single_argument: bool,
+    },
 }
 
 impl ExecutionSyntax {
     /// Returns the execution syntax as the argument.
+    ///
+    /// Only returns the first argument of syntaxes made of several ones, see [`ExecutionSyntax::as_args`].
     pub fn as_arg(&self) -> Option<&str> {
+        self.as_args().first().copied()
+    }
+
+    /// Returns the arguments placed between the terminal emulator and the command (e.g. `["--"]`), empty if the
+    /// command directly follows the terminal emulator or uses the native API.
+    pub fn as_args(&self) -> &[&'static str] {
+        match self {
+            Self::DoubleDash => &["--"],
+            Self::E | Self::SingleE => &["-e"],
+            Self::Command | Self::NativeApi => &[],
+            Self::CustomFlag { flag, .. } => std::slice::from_ref(flag),
+        }
+    }
+
+    /// Returns `true` if the command must be given as a single argument.
+    pub fn is_single_argument(&self) -> bool {
         match self {
-            Self::DoubleDash => Some("--"),
-            Self::E => Some("-e"),
-            _ => None,
+            Self::SingleE => true,
+            Self::CustomFlag {
+                flag,
+                single_argument,
+            } => *single_argument || flag.ends_with('='),
+            _ => false,
         }
     }
+
+    /// Returns the identifier of the execution syntax used in files written by this crate (e.g. `double-dash`, or
+    /// `flag:-x` and `single-flag:--command=` for custom flags).
+    pub(crate) fn id(&self) -> Cow<'static, str> {
+        match self {
+            Self::Command => Cow::Borrowed("command"),
+            Self::DoubleDash => Cow::Borrowed("double-dash"),
+            Self::E => Cow::Borrowed("e"),
+            Self::SingleE => Cow::Borrowed("single-e"),
+            Self::NativeApi => Cow::Borrowed("native-api"),
+            Self::CustomFlag {
+                flag,
+                single_argument: false,
+            } => Cow::Owned(format!("flag:{flag}")),
+            Self::CustomFlag {
+                flag,
+                single_argument: true,
+            } => Cow::Owned(format!("single-flag:{flag}")),
+        }
+    }
+
+    /// Parses an identifier returned by [`ExecutionSyntax::id`].
+    #[cfg(any(not(windows), feature = "config-file"))]
+    pub(crate) fn from_id(id: &str) -> Option<Self> {
+        let custom = |flag: &str, single_argument| {
+            (!flag.is_empty()).then(|| Self::CustomFlag {
+                flag: intern_flag(flag),
+                single_argument,
+            })
+        };
+
+        if let Some(flag) = id.strip_prefix("flag:") {
+            return custom(flag, false);
+        }
+
+        if let Some(flag) = id.strip_prefix("single-flag:") {
+            return custom(flag, true);
+        }
+
+        [
+            Self::Command,
+            Self::DoubleDash,
+            Self::E,
+            Self::SingleE,
+            Self::NativeApi,
+        ]
+        .into_iter()
+        .find(|syntax| syntax.id() == id)
+    }
+}
+
+/// Returns a `'static` copy of the custom flag `flag` read from a file, allocating it once per distinct flag.
+#[cfg(any(
+    test,
+    not(windows),
+    feature = "config-file",
+    feature = "gnome-settings"
+))]
+fn intern_flag(flag: &str) -> &'static str {
+    static FLAGS: std::sync::Mutex<std::collections::BTreeSet<&'static str>> =
+        std::sync::Mutex::new(std::collections::BTreeSet::new());
+
+    let mut flags = FLAGS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    if let Some(interned) = flags.get(flag) {
+        return interned;
+    }
+
+    let interned: &'static str = Box::leak(flag.into());
+    flags.insert(interned);
+    interned
 }
 
 impl Display for ExecutionSyntax {
@@ -179,7 +823,14 @@ impl Display for ExecutionSyntax {
             Self::Command => write!(f, "[command]"),
             Self::DoubleDash => write!(f, "-- [command]"),
             Self::E => write!(f, "-e [command]"),
+            Self::SingleE => write!(f, "-e '[command]'"),
             Self::NativeApi => write!(f, "Native API"),
+            Self::CustomFlag { flag, .. } if flag.ends_with('=') => write!(f, "{flag}'[command]'"),
+            Self::CustomFlag {
+                flag,
+                single_argument: true,
+            } => write!(f, "{flag} '[command]'"),
+            Self::CustomFlag { flag, .. } => write!(f, "{flag} [command]"),
         }
     }
 }
@@ -191,6 +842,10 @@ pub enum DetectionMethod {
     Windows,
     /// Uses the `TERMINAL_EMULATOR` environment variable.
     EnvironmentVariable,
+    /// Uses the configuration file of this crate.
+    ConfigFile,
+    /// Uses the terminal emulators known to work well with screen readers, when preferred.
+    Accessibility,
     /// Uses Terminal.app on macOS.
     TerminalApp,
     /// Uses `xdg-terminal-exec`.
@@ -201,6 +856,10 @@ pub enum DetectionMethod {
     GnomeSettings,
     /// Uses KDE settings.
     KdeSettings,
+    /// Uses XFCE settings.
+    XfceSettings,
+    /// Uses LXQt settings.
+    LxqtSettings,
     /// Uses desktop environment-specific hardcoded lists of known terminal emulators.
     HardcodedDesktopEnv,
     /// Uses a modern hardcoded list of known terminal emulators.
@@ -209,6 +868,14 @@ pub enum DetectionMethod {
     HardcodedTraditional,
     /// Uses an extended hardcoded list of known terminal emulators.
     HardcodedExtended,
+    /// Maps the controlling terminal of the current process back to the terminal emulator owning it.
+    ControllingTty,
+    /// Uses the variables set by the terminal emulator hosting the current process (e.g. `KITTY_WINDOW_ID`).
+    HostEnvironment,
+    /// Climbs the parent processes of the current process until one is a known terminal emulator.
+    ProcessAncestry,
+    /// Uses a detection backend provided by another crate, with its name, see `Detector::with_backend`.
+    Backend(&'static str),
 }
 
 impl DetectionMethod {
@@ -229,11 +896,15 @@ impl Display for DetectionMethod {
         match self {
             Self::Windows => write!(f, "Windows"),
             Self::EnvironmentVariable => write!(f, "Environment Variable"),
+            Self::ConfigFile => write!(f, "Configuration File"),
+            Self::Accessibility => write!(f, "Accessibility Preference"),
             Self::TerminalApp => write!(f, "Terminal.app"),
             Self::XdgTerminalExec => write!(f, "xdg-terminal-exec"),
             Self::XTerminalEmulator => write!(f, "x-terminal-emulator"),
             Self::GnomeSettings => write!(f, "GNOME Settings"),
             Self::KdeSettings => write!(f, "KDE Settings"),
+            Self::XfceSettings => write!(f, "XFCE Settings"),
+            Self::LxqtSettings => write!(f, "LXQt Settings"),
             Self::HardcodedDesktopEnv => {
                 write!(f, "Hardcoded Desktop Environment List")
             }
@@ -242,6 +913,10 @@ impl Display for DetectionMethod {
                 write!(f, "Hardcoded Traditional List")
             }
             Self::HardcodedExtended => write!(f, "Hardcoded Extended List"),
+            Self::ControllingTty => write!(f, "Controlling TTY"),
+            Self::HostEnvironment => write!(f, "Host Environment"),
+            Self::ProcessAncestry => write!(f, "Process Ancestry"),
+            Self::Backend(name) => write!(f, "{name} Backend"),
         }
     }
 }
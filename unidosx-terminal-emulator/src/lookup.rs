@@ -0,0 +1,289 @@
+//! Executable lookup helpers used by the detection methods.
+
+use std::{
+    env,
+    ffi::OsStr,
+    fmt::{self, Display, Formatter},
+    io,
+    path::{Path, PathBuf},
+};
+
+#[cfg(unix)]
+use crate::privilege::{SECURE_PATH, environment_trusted};
+use crate::{environment, filesystem};
+
+/// The PATHEXT value used when it is not set.
+#[cfg(any(test, windows))]
+const DEFAULT_PATHEXT: &str = ".COM;.EXE;.BAT;.CMD";
+
+/// The directories searched when PATH is missing or empty (e.g. in systemd units or some GUI launch contexts).
+#[cfg(all(unix, not(target_os = "macos")))]
+const STANDARD_PATH: &str = "/usr/local/bin:/usr/bin:/bin";
+
+/// The directories searched when PATH is missing or empty, including the Homebrew prefixes.
+#[cfg(target_os = "macos")]
+const STANDARD_PATH: &str = "/opt/homebrew/bin:/usr/local/bin:/usr/bin:/bin";
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Why a candidate was skipped while searching for an executable.
+pub enum LookupProblem {
+    /// A PATH entry is empty or relative, so it would resolve against the current working directory, and was not
+    /// searched.
+    RelativeEntry {
+        /// The PATH entry.
+        entry: PathBuf,
+    },
+    /// The candidate is a loop of symbolic links.
+    SymlinkLoop {
+        /// The path of the candidate.
+        path: PathBuf,
+    },
+    /// The candidate could not be inspected (e.g. its directory can not be searched).
+    Unreadable {
+        /// The path of the candidate.
+        path: PathBuf,
+        /// The error.
+        detail: String,
+    },
+    /// The candidate exists but is not an executable file (e.g. a directory or a file without execute permission).
+    NotExecutable {
+        /// The path of the candidate.
+        path: PathBuf,
+    },
+}
+
+impl Display for LookupProblem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RelativeEntry { entry } => {
+                write!(
+                    f,
+                    "relative PATH entry {:?} not searched",
+                    entry.display().to_string()
+                )
+            }
+            Self::SymlinkLoop { path } => write!(f, "{} is a symbolic link loop", path.display()),
+            Self::Unreadable { path, detail } => {
+                write!(f, "{} is unreadable: {detail}", path.display())
+            }
+            Self::NotExecutable { path } => write!(f, "{} is not executable", path.display()),
+        }
+    }
+}
+
+/// Searches for an executable named `name` in the directories listed in the PATH environment variable.
+///
+/// If `name` contains a path separator, it is checked directly instead. On UNIX, the standard directories are searched
+/// when PATH is missing or empty. Names and paths that are not valid Unicode are searched unchanged.
+pub(crate) fn find_executable<S: AsRef<OsStr>>(name: S) -> Option<PathBuf> {
+    lookup_executable(name).ok()
+}
+
+/// Searches for an executable like [`find_executable`], returning why the candidates were skipped if none is found.
+///
+/// Missing candidates are not problems, so an empty list means that no candidate exists.
+pub(crate) fn lookup_executable<S: AsRef<OsStr>>(name: S) -> Result<PathBuf, Vec<LookupProblem>> {
+    let name = name.as_ref();
+    let mut problems = Vec::new();
+
+    if name.is_empty() {
+        return Err(problems);
+    }
+
+    let bytes = name.as_encoded_bytes();
+
+    if bytes.contains(&(std::path::MAIN_SEPARATOR as u8)) || bytes.contains(&b'/') {
+        let path = PathBuf::from(name);
+
+        return if check_candidate(&path, &mut problems) {
+            Ok(path)
+        } else {
+            Err(problems)
+        };
+    }
+
+    // Inside a Flatpak sandbox, executables are launched on the host, see `flatpak`.
+    if crate::flatpak::uses_host() {
+        return crate::flatpak::find_on_host(name).ok_or(problems);
+    }
+
+    // In a privileged context PATH is controlled by a less privileged user.
+    #[cfg(unix)]
+    let paths = if environment_trusted() {
+        environment::var_os("PATH")
+            .filter(|paths| !paths.is_empty())
+            .unwrap_or_else(|| STANDARD_PATH.into())
+    } else {
+        SECURE_PATH.into()
+    };
+    #[cfg(not(unix))]
+    let paths = environment::var_os("PATH").ok_or_else(Vec::new)?;
+
+    let found = search(name, &paths, &mut problems);
+
+    // GUI applications on macOS are usually installed as app bundles outside of PATH.
+    #[cfg(target_os = "macos")]
+    let found = found.or_else(|| {
+        let home = environment::var_os("HOME").map(|home| PathBuf::from(home).join("Applications"));
+
+        [Some(PathBuf::from("/Applications")), home]
+            .into_iter()
+            .flatten()
+            .find_map(|apps| find_in_app_bundles(&apps, name.to_str()?))
+    });
+
+    found.ok_or(problems)
+}
+
+/// Searches for an executable named `name` in a PATH-like list of directories.
+///
+/// Empty and relative entries are skipped, as they would resolve against the current working directory.
+///
+/// On Windows, names without an extension are searched with each extension listed in PATHEXT (e.g. `wezterm.cmd`).
+#[cfg(all(test, unix))]
+pub(crate) fn search_paths<S: AsRef<OsStr>>(name: S, paths: &OsStr) -> Option<PathBuf> {
+    search(name.as_ref(), paths, &mut Vec::new())
+}
+
+/// Searches for an executable like [`search_paths`], recording why the candidates were skipped in `problems`.
+fn search(name: &OsStr, paths: &OsStr, problems: &mut Vec<LookupProblem>) -> Option<PathBuf> {
+    #[cfg(windows)]
+    let names: Vec<std::ffi::OsString> = match name.to_str() {
+        Some(name) => with_extensions(name, environment::var_os("PATHEXT").as_deref())
+            .into_iter()
+            .map(Into::into)
+            .collect(),
+        None => vec![name.to_owned()],
+    };
+    #[cfg(not(windows))]
+    let names = [name];
+
+    for dir in env::split_paths(paths) {
+        if !dir.is_absolute() {
+            push(problems, LookupProblem::RelativeEntry { entry: dir });
+            continue;
+        }
+
+        for name in &names {
+            let path = dir.join(name);
+
+            if check_candidate(&path, problems) {
+                return Some(path);
+            }
+        }
+    }
+
+    None
+}
+
+/// Returns `true` if `path` is an executable file in the active filesystem, recording why not in `problems`.
+fn check_candidate(path: &Path, problems: &mut Vec<LookupProblem>) -> bool {
+    let path = path.to_path_buf();
+
+    let problem = match filesystem::file_info(&path) {
+        Ok(info) if info.is_executable => return true,
+        Ok(_) => LookupProblem::NotExecutable { path },
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return false,
+        Err(error) if filesystem::is_symlink_loop(&error) => LookupProblem::SymlinkLoop { path },
+        Err(error) => LookupProblem::Unreadable {
+            path,
+            detail: error.to_string(),
+        },
+    };
+
+    push(problems, problem);
+    false
+}
+
+/// Records `problem` once.
+fn push(problems: &mut Vec<LookupProblem>, problem: LookupProblem) {
+    if !problems.contains(&problem) {
+        problems.push(problem);
+    }
+}
+
+/// Returns the file names an executable named `name` can have according to PATHEXT.
+///
+/// Names already having an extension are kept as is. If PATHEXT is not set, the default Windows value is used.
+#[cfg(any(test, windows))]
+pub(crate) fn with_extensions(name: &str, pathext: Option<&OsStr>) -> Vec<String> {
+    if Path::new(name).extension().is_some() {
+        return vec![name.to_owned()];
+    }
+
+    pathext
+        .and_then(OsStr::to_str)
+        .filter(|pathext| !pathext.is_empty())
+        .unwrap_or(DEFAULT_PATHEXT)
+        .split(';')
+        .filter(|ext| ext.starts_with('.') && ext.len() > 1)
+        .map(|ext| format!("{name}{}", ext.to_ascii_lowercase()))
+        .collect()
+}
+
+/// Returns `true` if the path points to an executable file in the active filesystem.
+#[cfg(any(target_os = "macos", all(test, unix)))]
+pub(crate) fn is_executable(path: &Path) -> bool {
+    filesystem::file_info(path).is_ok_and(|info| info.is_executable)
+}
+
+/// Returns `true` if two executable names refer to the same executable.
+///
+/// Case is ignored on Windows and macOS, as their filesystems are case-insensitive by default (e.g. `Alacritty` and
+/// `alacritty`).
+pub(crate) fn same_name(a: &str, b: &str) -> bool {
+    if cfg!(any(windows, target_os = "macos")) {
+        a.eq_ignore_ascii_case(b)
+    } else {
+        a == b
+    }
+}
+
+/// Returns the name of an executable from its path, without the PATHEXT extension on Windows.
+pub(crate) fn executable_name(path: &Path) -> Option<&str> {
+    let name = path.file_name()?.to_str()?;
+
+    #[cfg(windows)]
+    if let Some((stem, ext)) = name.rsplit_once('.')
+        && DEFAULT_PATHEXT
+            .split(';')
+            .any(|known| known[1..].eq_ignore_ascii_case(ext))
+    {
+        return Some(stem);
+    }
+
+    Some(name)
+}
+
+/// Searches the app bundles in `apps` for the executable of the application named `name`.
+///
+/// Both the bundle and the executable are matched ignoring case (e.g. `Alacritty.app/Contents/MacOS/alacritty`). If
+/// no executable in the bundle matches the name, the only executable of the bundle is used.
+#[cfg(any(target_os = "macos", all(test, unix)))]
+pub(crate) fn find_in_app_bundles(apps: &Path, name: &str) -> Option<PathBuf> {
+    let bundle = filesystem::read_dir(apps).ok()?.into_iter().find(|path| {
+        path.extension().is_some_and(|ext| ext == "app")
+            && path
+                .file_stem()
+                .and_then(OsStr::to_str)
+                .is_some_and(|stem| stem.eq_ignore_ascii_case(name))
+    })?;
+
+    let executables: Vec<PathBuf> = filesystem::read_dir(&bundle.join("Contents/MacOS"))
+        .ok()?
+        .into_iter()
+        .filter(|path| is_executable(path))
+        .collect();
+
+    let matching = executables.iter().find(|path| {
+        path.file_name()
+            .and_then(OsStr::to_str)
+            .is_some_and(|file| file.eq_ignore_ascii_case(name))
+    });
+
+    match (matching, executables.as_slice()) {
+        (Some(path), _) => Some(path.clone()),
+        (None, [only]) => Some(only.clone()),
+        _ => None,
+    }
+}
@@ -0,0 +1,717 @@
+//! Options of a launch: working directory, window title, holding the window open, environment, locale, color scheme,
+//! font size and exit status.
+//!
+//! Terminal emulators take these options through different flags (e.g. `--working-directory=`, `--directory` or
+//! `-cd`), and some have no flag at all. [`LaunchOptions`] are translated into the flags of the terminal emulator
+//! launched, listed in [`TERMINAL_FLAGS`], and the command is wrapped in `sh -c` for the options it has no flag for.
+//!
+//! Legacy terminal emulators (e.g. xterm) may default to the encoding of a legacy locale, mangling the output of
+//! programs expecting UTF-8. The locale is set through `LANG` and `LC_ALL`, and the encoding through the flags listed in
+//! [`ENCODING_FLAGS`]. A light or dark color scheme is selected through the flags listed in [`COLOR_SCHEME_FLAGS`], so
+//! helper windows can match the theme of the application opening them, and the font size through the flags listed in
+//! [`FONT_SIZE_FLAGS`].
+//!
+//! The exit status and output of the command are lost to the terminal emulator, whose own exit status says nothing
+//! about them. When tracked, the `sh -c` wrapper writes the exit status to a file once the command exits, read by
+//! [`LaunchResult::wait_for_inner_exit`](crate::LaunchResult::wait_for_inner_exit), and copies the output to files
+//! through `tee`, read by [`LaunchResult::wait_for_output`](crate::LaunchResult::wait_for_output).
+
+use std::{
+    ffi::{OsStr, OsString},
+    fs,
+    hash::{BuildHasher, RandomState},
+    io,
+    path::{Path, PathBuf},
+    process,
+    sync::{
+        Mutex, PoisonError,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::SystemTime,
+};
+
+use crate::{CommandSpec, environment, lookup::executable_name};
+
+/// The number of exit status and output files named during the current process, keeping their names unique.
+static TEMPORARY_FILES: AtomicU64 = AtomicU64::new(0);
+
+/// The private directories holding the exit status, output and standard input files, by parent directory.
+static PRIVATE_DIRS: Mutex<Vec<(PathBuf, PathBuf)>> = Mutex::new(Vec::new());
+
+/// The name of a terminal emulator and its flags setting the working directory, setting the window title and holding
+/// the window open.
+type TerminalFlags = (
+    &'static str,
+    Option<&'static str>,
+    Option<&'static str>,
+    Option<&'static str>,
+);
+
+/// The flags of known terminal emulators setting the working directory and the window title, and holding the window
+/// open after the command exits.
+///
+/// Flags ending with `=` take their value in the same argument.
+const TERMINAL_FLAGS: &[TerminalFlags] = &[
+    (
+        "gnome-terminal",
+        Some("--working-directory="),
+        Some("--title="),
+        None,
+    ),
+    ("kgx", Some("--working-directory="), Some("--title="), None),
+    ("ptyxis", Some("--working-directory="), None, None),
+    ("konsole", Some("--workdir"), None, Some("--hold")),
+    (
+        "xfce4-terminal",
+        Some("--working-directory="),
+        Some("--title="),
+        Some("--hold"),
+    ),
+    (
+        "mate-terminal",
+        Some("--working-directory="),
+        Some("--title="),
+        None,
+    ),
+    (
+        "lxterminal",
+        Some("--working-directory="),
+        Some("--title="),
+        None,
+    ),
+    ("qterminal", Some("--workdir"), None, None),
+    (
+        "kitty",
+        Some("--directory"),
+        Some("--title"),
+        Some("--hold"),
+    ),
+    (
+        "alacritty",
+        Some("--working-directory"),
+        Some("--title"),
+        Some("--hold"),
+    ),
+    (
+        "foot",
+        Some("--working-directory="),
+        Some("--title="),
+        Some("--hold"),
+    ),
+    (
+        "ghostty",
+        Some("--working-directory="),
+        Some("--title="),
+        Some("--wait-after-command"),
+    ),
+    (
+        "terminator",
+        Some("--working-directory="),
+        Some("--title="),
+        Some("--hold"),
+    ),
+    (
+        "tilix",
+        Some("--working-directory="),
+        Some("--title="),
+        None,
+    ),
+    (
+        "terminology",
+        Some("--current-directory="),
+        Some("--title="),
+        Some("--hold"),
+    ),
+    (
+        "sakura",
+        Some("--working-directory="),
+        Some("--title="),
+        Some("--hold"),
+    ),
+    ("roxterm", Some("--directory="), Some("--title="), None),
+    ("tilda", Some("--working-dir"), None, None),
+    ("cool-retro-term", Some("--workdir"), None, None),
+    ("deepin-terminal", Some("--work-directory"), None, None),
+    (
+        "io.elementary.terminal",
+        Some("--working-directory="),
+        None,
+        None,
+    ),
+    ("rio", Some("--working-dir"), None, None),
+    ("xterm", None, Some("-T"), Some("-hold")),
+    ("urxvt", Some("-cd"), Some("-T"), Some("-hold")),
+    ("st", None, Some("-t"), None),
+    ("rxvt", None, Some("-T"), None),
+    ("mrxvt", None, Some("-T"), None),
+    ("aterm", None, Some("-T"), None),
+    ("mlterm", None, Some("-T"), None),
+    ("pterm", None, Some("-T"), None),
+];
+
+/// The flags keeping terminal emulators running until the command exits, instead of handing the window over to a
+/// server process and exiting right away.
+const WAIT_FLAGS: &[(&str, &str)] = &[
+    ("gnome-terminal", "--wait"),
+    ("konsole", "--nofork"),
+    ("xfce4-terminal", "--disable-server"),
+    ("mate-terminal", "--disable-factory"),
+];
+
+/// The flags of legacy terminal emulators selecting UTF-8, and selecting another encoding given as value.
+///
+/// Flags ending with `=` take their value in the same argument.
+const ENCODING_FLAGS: &[(&str, Option<&str>, Option<&str>)] = &[
+    ("xterm", Some("-u8"), Some("-en")),
+    ("mlterm", None, Some("--km=")),
+];
+
+/// The flags of terminal emulators selecting a light and a dark color scheme.
+///
+/// gnome-terminal and Konsole select a profile, which must exist. foot selects the `[colors-light]` or `[colors-dark]`
+/// section of its configuration, and xterm reverses its default black on white colors.
+const COLOR_SCHEME_FLAGS: &[(&str, &[&str], &[&str])] = &[
+    ("gnome-terminal", &["--profile=Light"], &["--profile=Dark"]),
+    ("konsole", &["--profile", "Light"], &["--profile", "Dark"]),
+    (
+        "foot",
+        &["-o", "initial-color-theme=light"],
+        &["-o", "initial-color-theme=dark"],
+    ),
+    ("xterm", &[], &["-rv"]),
+];
+
+/// The flags of terminal emulators setting the font size, followed by the prefix of their value.
+///
+/// Flags ending with `=` take their value in the same argument. xterm only applies it to FreeType fonts.
+const FONT_SIZE_FLAGS: &[(&str, &str, &str)] = &[
+    ("kitty", "-o", "font_size="),
+    ("alacritty", "-o", "font.size="),
+    ("ghostty", "--font-size=", ""),
+    ("xterm", "-fs", ""),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// The color scheme of a terminal window, see [`LaunchOptions::color_scheme`].
+pub enum ColorScheme {
+    /// Dark text on a light background.
+    Light,
+    /// Light text on a dark background.
+    Dark,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+/// Options of a launch, see [`Launcher::launch_with_options`](crate::Launcher::launch_with_options).
+pub struct LaunchOptions {
+    /// The working directory of the command.
+    pub cwd: Option<PathBuf>,
+    /// The title of the terminal window.
+    pub title: Option<String>,
+    /// Whether the terminal window stays open after the command exits.
+    pub hold: bool,
+    /// The environment variables to set (`Some`) or remove (`None`) for the command, in order.
+    pub env: Vec<(OsString, Option<OsString>)>,
+    /// The locale of the command (e.g. `en_US.UTF-8`), set as `LANG` and `LC_ALL`.
+    pub locale: Option<String>,
+    /// The character encoding of the terminal emulator (e.g. `UTF-8`), for legacy terminal emulators taking a flag.
+    pub encoding: Option<String>,
+    /// The color scheme of the terminal window, for terminal emulators taking a flag.
+    pub color_scheme: Option<ColorScheme>,
+    /// The font size of the terminal window in points, for terminal emulators taking a flag.
+    pub font_size: Option<u32>,
+    /// The file the exit status of the command is written to once it exits, see [`LaunchOptions::track_exit_status`].
+    pub exit_status_file: Option<PathBuf>,
+    /// The files the standard output and standard error of the command are copied to, see
+    /// [`LaunchOptions::capture_output`].
+    pub output_files: Option<(PathBuf, PathBuf)>,
+    /// Whether the spawned process only exits once the command exits, see [`LaunchOptions::wait`].
+    pub wait: bool,
+    /// The named pipe the standard input of the command is read from, see [`LaunchOptions::pipe_stdin`].
+    pub stdin_pipe: Option<PathBuf>,
+}
+
+impl LaunchOptions {
+    /// Creates options changing nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the working directory of the command.
+    ///
+    /// Passed with the flag of the terminal emulator when it has one (e.g. `--working-directory=` for gnome-terminal,
+    /// `--directory` for kitty, `--workdir` for konsole). Otherwise, the command is run by a shell changing to the
+    /// directory first, as for xterm, or wezterm whose `--cwd` only exists for `wezterm start`.
+    pub fn current_dir<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.cwd = Some(dir.as_ref().to_owned());
+        self
+    }
+
+    /// Sets the title of the terminal window, e.g. "Backup in progress" for a long-running job.
+    ///
+    /// Passed with the flag of the terminal emulator when it has one (e.g. `--title` for kitty, `-T` for xterm, `-t`
+    /// for st). Otherwise, the title is set by an OSC 0 escape sequence printed before the command runs, which the
+    /// command may change again.
+    pub fn title<S: Into<String>>(mut self, title: S) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Keeps the terminal window open after the command exits, so the output of short commands can be read.
+    ///
+    /// Passed with the flag of the terminal emulator when it has one (e.g. `--hold` for kitty, `-hold` for xterm).
+    /// Otherwise, the command is run by a shell printing its exit status and waiting for Enter before closing.
+    pub fn hold(mut self, hold: bool) -> Self {
+        self.hold = hold;
+        self
+    }
+
+    /// Sets an environment variable.
+    pub fn env<K: AsRef<OsStr>, V: AsRef<OsStr>>(mut self, key: K, value: V) -> Self {
+        self.env
+            .push((key.as_ref().to_owned(), Some(value.as_ref().to_owned())));
+        self
+    }
+
+    /// Removes an environment variable.
+    pub fn env_remove<K: AsRef<OsStr>>(mut self, key: K) -> Self {
+        self.env.push((key.as_ref().to_owned(), None));
+        self
+    }
+
+    /// Sets the locale of the command (e.g. `en_US.UTF-8`), through `LANG` and `LC_ALL`.
+    ///
+    /// Environment variables set with [`LaunchOptions::env`] take precedence.
+    pub fn locale<S: Into<String>>(mut self, locale: S) -> Self {
+        self.locale = Some(locale.into());
+        self
+    }
+
+    /// Sets the character encoding of the terminal emulator (e.g. `UTF-8`).
+    ///
+    /// Only legacy terminal emulators taking it as a flag use it: xterm with `-u8` for UTF-8 and `-en` otherwise, and
+    /// mlterm with `--km=`. The other ones follow the locale, see [`LaunchOptions::locale`].
+    pub fn encoding<S: Into<String>>(mut self, encoding: S) -> Self {
+        self.encoding = Some(encoding.into());
+        self
+    }
+
+    /// Sets the color scheme of the terminal window, so it matches the theme of the application.
+    ///
+    /// Only a hint: it is used by the terminal emulators taking it as a flag, gnome-terminal and Konsole through the
+    /// `Light` and `Dark` profiles, which must be created by the user, foot and xterm. The other ones keep their
+    /// configured colors.
+    pub fn color_scheme(mut self, color_scheme: ColorScheme) -> Self {
+        self.color_scheme = Some(color_scheme);
+        self
+    }
+
+    /// Sets the font size of the terminal window in points, e.g. for large text.
+    ///
+    /// Only used by the terminal emulators taking it as a flag: kitty, Alacritty, Ghostty and xterm. The other ones keep
+    /// their configured font size.
+    pub fn font_size(mut self, points: u32) -> Self {
+        self.font_size = Some(points);
+        self
+    }
+
+    /// Tracks the exit status of the command, read with
+    /// [`LaunchResult::wait_for_inner_exit`](crate::LaunchResult::wait_for_inner_exit).
+    ///
+    /// The exit status is written to a new file in a private directory of `XDG_RUNTIME_DIR` (or the temporary
+    /// directory), removed once read. It is not tracked if that directory cannot be created. Only launchers running the command through `sh` track it: terminal emulators on Linux, BSD and macOS, and
+    /// `openvt`.
+    pub fn track_exit_status(mut self, track: bool) -> Self {
+        self.exit_status_file = track.then(|| temporary_file("exit")).flatten();
+        self
+    }
+
+    /// Copies the standard output and standard error of the command to files, read with
+    /// [`LaunchResult::wait_for_output`](crate::LaunchResult::wait_for_output) once it exits.
+    ///
+    /// The output is still shown in the terminal window, but through `tee`: the command writes to pipes instead of the
+    /// terminal, so programs checking for a terminal (e.g. to use colors or a full-screen interface) behave as if their
+    /// output was redirected. Also tracks the exit status, see [`LaunchOptions::track_exit_status`], which tells where
+    /// the files are created and when the output is not captured.
+    pub fn capture_output(mut self, capture: bool) -> Self {
+        self.output_files = capture
+            .then(|| Some((temporary_file("stdout")?, temporary_file("stderr")?)))
+            .flatten();
+
+        if self.output_files.is_some() && self.exit_status_file.is_none() {
+            self = self.track_exit_status(true);
+        }
+
+        self
+    }
+
+    /// Feeds the standard input of the command from the caller, through a named pipe written with
+    /// [`LaunchResult::open_stdin`](crate::LaunchResult::open_stdin), e.g. to page generated output with `less`.
+    ///
+    /// The command runs on the terminal of its window, so it cannot inherit the standard input of the caller. Instead,
+    /// the `sh -c` wrapper creates a named pipe in the private directory described in
+    /// [`LaunchOptions::track_exit_status`], and the command starts once the caller opens it, reading until the caller
+    /// closes it. The pipe is removed as soon as both ends are open. The standard input is not piped if that directory
+    /// cannot be created. Only launchers running the command through `sh` support it, see [`LaunchOptions::track_exit_status`].
+    pub fn pipe_stdin(mut self, pipe: bool) -> Self {
+        self.stdin_pipe = pipe.then(|| temporary_file("stdin")).flatten();
+        self
+    }
+
+    /// Keeps the spawned process running until the command exits, so
+    /// [`LaunchResult::wait_for_command`](crate::LaunchResult::wait_for_command) blocks until then.
+    ///
+    /// Terminal emulators handing their windows over to a server process take a flag for it: `--wait` for
+    /// gnome-terminal, `--nofork` for Konsole, `--disable-server` for xfce4-terminal and `--disable-factory` for
+    /// mate-terminal. `start /wait` is used on Windows. For the other terminal emulators on Linux, BSD and macOS, the
+    /// exit of the command is tracked by the `sh` wrapper instead, see [`LaunchOptions::track_exit_status`]. Windows
+    /// Terminal has no such flag, its tabs are not waited for.
+    pub fn wait(mut self, wait: bool) -> Self {
+        self.wait = wait;
+        self
+    }
+
+    /// Sets the working directory and environment variables of `spec`.
+    pub(crate) fn apply(&self, mut spec: CommandSpec) -> CommandSpec {
+        if let Some(cwd) = &self.cwd {
+            spec.cwd = Some(cwd.clone());
+        }
+
+        if let Some(locale) = &self.locale {
+            for var in ["LANG", "LC_ALL"] {
+                spec.env.push((var.into(), Some(locale.into())));
+            }
+        }
+
+        spec.env.extend(self.env.iter().cloned());
+        spec
+    }
+}
+
+/// Returns the path of a new file holding `kind` of a launch, in a private directory of `XDG_RUNTIME_DIR` (or the
+/// temporary directory), or of the directory shared with the host inside a Flatpak sandbox.
+///
+/// Returns `None` if no private directory could be created: the temporary directory is writable by every user, who
+/// could replace a predictable file with a symbolic link or a named pipe before the launch writes to it.
+fn temporary_file(kind: &str) -> Option<PathBuf> {
+    // The launch runs on the host when sandboxed, which only sees the directory the sandbox shares with it.
+    let shared = crate::flatpak::uses_host()
+        .then(crate::flatpak::shared_runtime_dir)
+        .flatten();
+    let parent = shared
+        .or_else(|| crate::xdg::absolute(environment::var_os("XDG_RUNTIME_DIR")))
+        .unwrap_or_else(std::env::temp_dir);
+    let id = TEMPORARY_FILES.fetch_add(1, Ordering::Relaxed);
+
+    Some(private_dir(&parent)?.join(format!("{kind}-{id}")))
+}
+
+/// Returns a directory of `parent` that only the current user can access, created with a random name on first use.
+fn private_dir(parent: &Path) -> Option<PathBuf> {
+    let mut dirs = PRIVATE_DIRS.lock().unwrap_or_else(PoisonError::into_inner);
+
+    if let Some((_, dir)) = dirs.iter().find(|(known, _)| known == parent)
+        && is_private(dir).unwrap_or_default()
+    {
+        return Some(dir.clone());
+    }
+
+    let random = RandomState::new().hash_one(SystemTime::now());
+    let dir = parent.join(format!("unidosx-{}-{random:016x}", process::id()));
+    // Fails if anything already exists at the path, including a symbolic link planted there.
+    create_private_dir(&dir).ok()?;
+    is_private(&dir).ok()?.then_some(())?;
+
+    dirs.retain(|(known, _)| known != parent);
+    dirs.push((parent.to_path_buf(), dir.clone()));
+    Some(dir)
+}
+
+/// Creates `dir`, accessible only by the current user.
+fn create_private_dir(dir: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+
+        fs::DirBuilder::new().mode(0o700).create(dir)
+    }
+
+    #[cfg(not(unix))]
+    {
+        fs::create_dir(dir)
+    }
+}
+
+/// Returns `true` if `dir` is a directory, not a symbolic link, that is owned and only accessible by the current user.
+fn is_private(dir: &Path) -> io::Result<bool> {
+    let metadata = fs::symlink_metadata(dir)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        Ok(metadata.is_dir()
+            && metadata.mode() & 0o777 == 0o700
+            && metadata.uid() == crate::privilege::effective_uid())
+    }
+
+    #[cfg(not(unix))]
+    {
+        Ok(metadata.is_dir())
+    }
+}
+
+/// Translates `options` into the flags of the terminal emulator at `terminal`, returning them with the command to run,
+/// wrapped in `sh -c` for the options the terminal emulator has no flag for.
+///
+/// Without options, the flags are empty and the command is returned unchanged.
+pub(crate) fn terminal_flags(
+    terminal: &Path,
+    options: &LaunchOptions,
+    program: OsString,
+    args: Vec<OsString>,
+) -> (Vec<OsString>, OsString, Vec<OsString>) {
+    let (_, cwd_flag, title_flag, hold_flag) = known_flags(terminal);
+    let mut flags = Vec::new();
+    let mut wrapper = Wrapper::default();
+
+    if let Some(cwd) = &options.cwd {
+        match cwd_flag {
+            Some(flag) => push_flag(&mut flags, flag, cwd.as_os_str()),
+            None => wrapper.cwd = Some(cwd.clone().into_os_string()),
+        }
+    }
+
+    if let Some(title) = &options.title {
+        match title_flag {
+            Some(flag) => push_flag(&mut flags, flag, title.as_ref()),
+            None => wrapper.title = Some(title.into()),
+        }
+    }
+
+    if options.hold {
+        match hold_flag {
+            Some(flag) => flags.push(flag.into()),
+            None => wrapper.hold = true,
+        }
+    }
+
+    if options.wait
+        && let Some(flag) = wait_flag(terminal)
+    {
+        flags.push(flag.into());
+    }
+
+    if let Some(encoding) = &options.encoding {
+        flags.extend(encoding_flags(terminal, encoding));
+    }
+
+    if let Some(color_scheme) = options.color_scheme {
+        flags.extend(color_scheme_flags(terminal, color_scheme));
+    }
+
+    if let Some(points) = options.font_size {
+        flags.extend(font_size_flags(terminal, points));
+    }
+
+    wrapper.status_file = options
+        .exit_status_file
+        .clone()
+        .map(PathBuf::into_os_string);
+    wrapper.stdin_pipe = options.stdin_pipe.clone().map(PathBuf::into_os_string);
+    wrapper.output_files = options
+        .output_files
+        .clone()
+        .map(|(stdout, stderr)| (stdout.into_os_string(), stderr.into_os_string()));
+    let (program, args) = wrapper.wrap(program, args);
+
+    (flags, program, args)
+}
+
+/// Returns the flags of the terminal emulator at `terminal` setting the working directory, setting the window title and
+/// holding the window open, `None` for the ones it has no flag for.
+pub(crate) fn known_flags(terminal: &Path) -> TerminalFlags {
+    executable_name(terminal)
+        .and_then(|name| {
+            TERMINAL_FLAGS
+                .iter()
+                .find(|(known, ..)| crate::lookup::same_name(known, name))
+        })
+        .copied()
+        .unwrap_or_default()
+}
+
+/// Returns the flag keeping the terminal emulator at `terminal` running until the command exits, if it needs one.
+pub(crate) fn wait_flag(terminal: &Path) -> Option<&'static str> {
+    executable_name(terminal).and_then(|name| {
+        WAIT_FLAGS
+            .iter()
+            .find(|(known, _)| crate::lookup::same_name(known, name))
+            .map(|&(_, flag)| flag)
+    })
+}
+
+/// Returns the flags selecting `encoding` in the terminal emulator at `terminal`, empty if it takes none.
+fn encoding_flags(terminal: &Path, encoding: &str) -> Vec<OsString> {
+    let Some(&(_, utf8_flag, encoding_flag)) = executable_name(terminal).and_then(|name| {
+        ENCODING_FLAGS
+            .iter()
+            .find(|(known, ..)| crate::lookup::same_name(known, name))
+    }) else {
+        return Vec::new();
+    };
+    let is_utf8 = ["utf-8", "utf8"].contains(&encoding.to_ascii_lowercase().as_str());
+    let mut flags = Vec::new();
+
+    match (utf8_flag, encoding_flag) {
+        (Some(flag), _) if is_utf8 => flags.push(flag.into()),
+        (_, Some(flag)) => push_flag(&mut flags, flag, encoding.as_ref()),
+        _ => {}
+    }
+
+    flags
+}
+
+/// Returns the flags selecting `color_scheme` in the terminal emulator at `terminal`, empty if it takes none.
+fn color_scheme_flags(terminal: &Path, color_scheme: ColorScheme) -> Vec<OsString> {
+    let Some((_, light, dark)) = executable_name(terminal).and_then(|name| {
+        COLOR_SCHEME_FLAGS
+            .iter()
+            .find(|(known, ..)| crate::lookup::same_name(known, name))
+    }) else {
+        return Vec::new();
+    };
+    let flags = match color_scheme {
+        ColorScheme::Light => light,
+        ColorScheme::Dark => dark,
+    };
+
+    flags.iter().map(OsString::from).collect()
+}
+
+/// Returns the flags setting the font size to `points` in the terminal emulator at `terminal`, empty if it takes none.
+fn font_size_flags(terminal: &Path, points: u32) -> Vec<OsString> {
+    let mut flags = Vec::new();
+
+    if let Some((_, flag, prefix)) = executable_name(terminal).and_then(|name| {
+        FONT_SIZE_FLAGS
+            .iter()
+            .find(|(known, ..)| crate::lookup::same_name(known, name))
+    }) {
+        push_flag(&mut flags, flag, format!("{prefix}{points}").as_ref());
+    }
+
+    flags
+}
+
+/// Adds `flag` with its `value`, in the same argument if the flag ends with `=`.
+pub(crate) fn push_flag(flags: &mut Vec<OsString>, flag: &str, value: &OsStr) {
+    if flag.ends_with('=') {
+        let mut joined = OsString::from(flag);
+        joined.push(value);
+        flags.push(joined);
+    } else {
+        flags.push(flag.into());
+        flags.push(value.to_owned());
+    }
+}
+
+#[derive(Debug, Default)]
+/// The options applied by a `sh -c` wrapper around the command.
+pub(crate) struct Wrapper {
+    /// The directory to change to before running the command.
+    pub(crate) cwd: Option<OsString>,
+    /// The window title to set through an escape sequence.
+    pub(crate) title: Option<OsString>,
+    /// Whether to wait for Enter after the command exits.
+    pub(crate) hold: bool,
+    /// The file to write the exit status of the command to.
+    pub(crate) status_file: Option<OsString>,
+    /// The files to copy the standard output and standard error of the command to.
+    pub(crate) output_files: Option<(OsString, OsString)>,
+    /// The named pipe to read the standard input of the command from.
+    pub(crate) stdin_pipe: Option<OsString>,
+}
+
+impl Wrapper {
+    /// Wraps `program` with `args` in `sh -c`, passing the values of the options as positional parameters so they are
+    /// never reinterpreted by the shell.
+    ///
+    /// Returns the command unchanged if no option needs the wrapper.
+    pub(crate) fn wrap(self, program: OsString, args: Vec<OsString>) -> (OsString, Vec<OsString>) {
+        if self.cwd.is_none()
+            && self.title.is_none()
+            && !self.hold
+            && self.status_file.is_none()
+            && self.output_files.is_none()
+            && self.stdin_pipe.is_none()
+        {
+            return (program, args);
+        }
+
+        let mut script = Vec::new();
+        let mut values = Vec::new();
+
+        // Written on exit, also when changing the working directory fails. Moved in place so it is never read partially.
+        if let Some(status_file) = &self.status_file {
+            script.push(
+                r#"status_file=$1; shift; trap 'printf "%s\n" "$?" > "$status_file.tmp" && mv -f -- "$status_file.tmp" "$status_file"' EXIT"#,
+            );
+            values.push(status_file.clone());
+        }
+
+        if let Some((stdout, stderr)) = &self.output_files {
+            script.push(r#"out_file=$1; err_file=$2; shift 2"#);
+            values.push(stdout.clone());
+            values.push(stderr.clone());
+        }
+
+        if let Some(cwd) = self.cwd {
+            script.push(r#"cd -- "$1" || exit; shift"#);
+            values.push(cwd);
+        }
+
+        if let Some(title) = self.title {
+            script.push(r#"printf '\033]0;%s\007' "$1"; shift"#);
+            values.push(title);
+        }
+
+        // Opening the reading end blocks until the caller opens the writing end, no one else can open it afterwards.
+        if let Some(stdin_pipe) = &self.stdin_pipe {
+            script.push(r#"mkfifo -m 600 -- "$1" || exit; exec < "$1"; rm -f -- "$1"; shift"#);
+            values.push(stdin_pipe.clone());
+        }
+
+        if !self.hold && self.status_file.is_none() && self.output_files.is_none() {
+            script.push(r#"exec "$@""#);
+        } else {
+            script.push(if self.output_files.is_some() {
+                // Named pipes keep the exit status of the command, lost at the end of a `|` pipeline in POSIX shells.
+                r#"mkfifo -m 600 -- "$out_file.fifo" "$err_file.fifo" || exit; tee -- "$out_file" < "$out_file.fifo" & tee -- "$err_file" < "$err_file.fifo" >&2 & "$@" > "$out_file.fifo" 2> "$err_file.fifo"; status=$?; wait; rm -f -- "$out_file.fifo" "$err_file.fifo""#
+            } else {
+                r#""$@"; status=$?"#
+            });
+
+            if self.hold {
+                script.push(if self.stdin_pipe.is_none() {
+                    r#"printf '\n[exited with status %s, press Enter to close] ' "$status"; read -r _"#
+                } else {
+                    // The standard input is the pipe, at its end once the command exits.
+                    r#"printf '\n[exited with status %s, press Enter to close] ' "$status"; read -r _ < /dev/tty"#
+                });
+            }
+
+            script.push(r#"exit "$status""#);
+        }
+
+        let mut wrapped = vec![
+            OsString::from("-c"),
+            script.join("; ").into(),
+            OsString::from("sh"),
+        ];
+        wrapped.extend(values);
+        wrapped.push(program);
+        wrapped.extend(args);
+
+        (OsString::from("sh"), wrapped)
+    }
+}
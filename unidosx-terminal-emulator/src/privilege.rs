@@ -0,0 +1,74 @@
+//! Privileged context detection.
+//!
+//! When the process runs setuid/setgid, or as root on behalf of another user (`sudo`, `pkexec`, `doas`), the
+//! environment and the user configuration are controlled by a less privileged user. Honoring them would let that user
+//! run arbitrary binaries with elevated privileges (e.g. `TERMINAL_EMULATOR=/tmp/evil`), so the detection methods
+//! relying on them are skipped unless explicitly trusted.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether the environment is trusted even in a privileged context.
+static TRUST_ENVIRONMENT: AtomicBool = AtomicBool::new(false);
+
+/// Environment variables set by tools that run commands as root on behalf of another user.
+#[cfg(unix)]
+const ELEVATION_VARS: &[&str] = &["SUDO_UID", "PKEXEC_UID", "DOAS_USER"];
+
+/// The PATH used to search executables in a privileged context.
+#[cfg(unix)]
+pub(crate) const SECURE_PATH: &str = "/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin";
+
+#[cfg(unix)]
+unsafe extern "C" {
+    fn getuid() -> u32;
+    fn geteuid() -> u32;
+    fn getgid() -> u32;
+    fn getegid() -> u32;
+}
+
+/// Trusts (or stops trusting) the environment and the user configuration when running in a privileged context.
+///
+/// Only opt in if the environment of the process is controlled by the same user it runs as.
+pub fn trust_privileged_environment(trust: bool) {
+    TRUST_ENVIRONMENT.store(trust, Ordering::Relaxed);
+}
+
+/// Returns `true` if the process runs setuid/setgid, or as root on behalf of another user.
+pub fn is_privileged_context() -> bool {
+    #[cfg(unix)]
+    {
+        // SAFETY: these functions are always successful and have no side effects.
+        let ids = unsafe { (getuid(), geteuid(), getgid(), getegid()) };
+        let elevated = ELEVATION_VARS
+            .iter()
+            .any(|var| crate::environment::var_os(var).is_some());
+
+        is_privileged(ids, elevated)
+    }
+
+    #[cfg(not(unix))]
+    {
+        false
+    }
+}
+
+/// Returns the effective user ID of the process.
+#[cfg(unix)]
+pub(crate) fn effective_uid() -> u32 {
+    // SAFETY: this function is always successful and has no side effects.
+    unsafe { geteuid() }
+}
+
+/// Returns `true` if the environment and the user configuration can be used for detection.
+pub(crate) fn environment_trusted() -> bool {
+    // When inspecting another system, the environment is built by this crate instead of inherited.
+    TRUST_ENVIRONMENT.load(Ordering::Relaxed)
+        || crate::filesystem::is_offline()
+        || !is_privileged_context()
+}
+
+/// Decides if the given real/effective user and group IDs describe a privileged context.
+#[cfg(unix)]
+pub(crate) fn is_privileged((uid, euid, gid, egid): (u32, u32, u32, u32), elevated: bool) -> bool {
+    uid != euid || gid != egid || (euid == 0 && elevated)
+}
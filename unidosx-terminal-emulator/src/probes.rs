@@ -0,0 +1,866 @@
+//! Individual detection methods.
+//!
+//! Each detection method is exposed as its own `probe_*` function, allowing custom detection pipelines to be composed
+//! instead of relying on the order used by [`detect`](crate::detect).
+
+use std::fmt::{self, Display, Formatter};
+#[cfg(all(target_os = "macos", feature = "terminal-app"))]
+use std::path::PathBuf;
+#[cfg(feature = "gnome-settings")]
+use std::process::Command;
+
+#[cfg(any(
+    feature = "env-var",
+    feature = "config-file",
+    feature = "xdg-terminal-exec",
+    feature = "gnome-settings",
+    feature = "kde-settings",
+    feature = "xfce-settings",
+    feature = "lxqt-settings"
+))]
+use crate::lookup::executable_name;
+#[cfg(any(
+    feature = "env-var",
+    feature = "config-file",
+    feature = "xdg-terminal-exec",
+    feature = "gnome-settings",
+    feature = "kde-settings",
+    feature = "xfce-settings",
+    feature = "lxqt-settings"
+))]
+use crate::privilege::environment_trusted;
+use crate::{
+    DetectionMethod, ExecutionSyntax, LookupProblem, TerminalEmulator, lookup::find_executable,
+};
+
+/// The result of a detection method, the terminal emulator it found or why it found nothing.
+pub(crate) type ProbeResult = Result<TerminalEmulator, SkipReason>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Why a detection method found no terminal emulator.
+pub enum SkipReason {
+    /// The detection method is not compiled in, because its feature is disabled or it is specific to another platform.
+    FeatureDisabled,
+    /// The detection method is disabled at runtime, see [`Detector::disable`](crate::Detector::disable).
+    Disabled,
+    /// The detection method reads the user environment or configuration and is skipped in a privileged context, see
+    /// [`is_privileged_context`](crate::is_privileged_context).
+    PrivilegedContext,
+    /// The terminal emulator is locked by the system-wide configuration file, see
+    /// [`is_terminal_locked`](crate::is_terminal_locked).
+    Locked,
+    /// A required environment variable is not set or blank.
+    EnvMissing {
+        /// The name of the environment variable.
+        name: String,
+    },
+    /// The setting read by the detection method does not set a terminal emulator.
+    NotConfigured,
+    /// The executable named by the detection method is not installed.
+    BinaryNotFound {
+        /// The name or path of the executable.
+        name: String,
+    },
+    /// The executable named by the detection method was not found, and candidates were skipped on the way (e.g. a
+    /// loop of symbolic links, or a directory that can not be searched).
+    BinaryRejected {
+        /// The name or path of the executable.
+        name: String,
+        /// Why each candidate was skipped.
+        problems: Vec<LookupProblem>,
+    },
+    /// No terminal emulator of the list searched by the detection method is installed.
+    NoneInstalled,
+    /// The detection method queries the running session, which is not available when inspecting another system.
+    SessionRequired,
+    /// A command run by the detection method failed.
+    CommandFailed {
+        /// The error or exit status of the command.
+        detail: String,
+    },
+    /// A command run by the detection method did not answer in time.
+    Timeout,
+    /// A command run by the detection method was cancelled, see [`CancellationToken`](crate::CancellationToken).
+    Cancelled,
+    /// A value read by the detection method could not be parsed.
+    ParseError {
+        /// What could not be parsed.
+        detail: String,
+    },
+    /// A file read by the detection method exists but could not be read.
+    Io {
+        /// The path of the file and the error.
+        detail: String,
+    },
+}
+
+impl Display for SkipReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FeatureDisabled => write!(f, "disabled at compile time"),
+            Self::Disabled => write!(f, "disabled at runtime"),
+            Self::PrivilegedContext => write!(f, "skipped in a privileged context"),
+            Self::Locked => write!(f, "locked by the system-wide configuration"),
+            Self::EnvMissing { name } => write!(f, "{name} is not set"),
+            Self::NotConfigured => write!(f, "no terminal emulator configured"),
+            Self::SessionRequired => write!(f, "requires the running session"),
+            Self::BinaryNotFound { name } => write!(f, "{name} not found"),
+            Self::BinaryRejected { name, problems } => {
+                write!(f, "{name} not found")?;
+
+                for (index, problem) in problems.iter().enumerate() {
+                    write!(f, "{}{problem}", if index == 0 { ": " } else { "; " })?;
+                }
+
+                Ok(())
+            }
+            Self::NoneInstalled => write!(f, "none installed"),
+            Self::CommandFailed { detail } => write!(f, "command failed: {detail}"),
+            Self::Timeout => write!(f, "timed out"),
+            Self::Cancelled => write!(f, "cancelled"),
+            Self::ParseError { detail } => write!(f, "parse error: {detail}"),
+            Self::Io { detail } => write!(f, "I/O error: {detail}"),
+        }
+    }
+}
+
+/// Traditional terminal emulators and their execution syntax.
+pub(crate) const HARDCODED_TRADITIONAL: &[(&str, ExecutionSyntax)] = &[
+    ("xterm", ExecutionSyntax::E),
+    ("rxvt", ExecutionSyntax::E),
+    ("urxvt", ExecutionSyntax::E),
+    ("aterm", ExecutionSyntax::E),
+    ("eterm", ExecutionSyntax::E),
+    ("pterm", ExecutionSyntax::E),
+    ("mrxvt", ExecutionSyntax::E),
+    ("st", ExecutionSyntax::E),
+    ("mlterm", ExecutionSyntax::E),
+    ("fbterm", ExecutionSyntax::DoubleDash),
+    ("kmscon", ExecutionSyntax::DoubleDash),
+];
+
+/// Desktop environment-specific terminal emulators and their execution syntax.
+pub(crate) const HARDCODED_DESKTOP_ENV: &[(&str, ExecutionSyntax)] = &[
+    ("kgx", ExecutionSyntax::DoubleDash),
+    ("gnome-terminal", ExecutionSyntax::DoubleDash),
+    ("konsole", ExecutionSyntax::E),
+    ("xfce4-terminal", ExecutionSyntax::SingleE),
+    ("mate-terminal", ExecutionSyntax::SingleE),
+    ("lxterminal", ExecutionSyntax::SingleE),
+    ("qterminal", ExecutionSyntax::E),
+    ("ptyxis", ExecutionSyntax::DoubleDash),
+    ("deepin-terminal", ExecutionSyntax::E),
+    ("io.elementary.terminal", ExecutionSyntax::E),
+];
+
+/// Modern terminal emulators and their execution syntax.
+pub(crate) const HARDCODED_MODERN: &[(&str, ExecutionSyntax)] = &[
+    ("kitty", ExecutionSyntax::Command),
+    ("alacritty", ExecutionSyntax::E),
+    ("wezterm", ExecutionSyntax::E),
+    ("ghostty", ExecutionSyntax::E),
+    ("foot", ExecutionSyntax::Command),
+    ("rio", ExecutionSyntax::E),
+    ("contour", ExecutionSyntax::Command),
+    ("hyper", ExecutionSyntax::E),
+    ("tabby", ExecutionSyntax::E),
+    ("blackbox", ExecutionSyntax::E),
+    ("warp", ExecutionSyntax::E),
+    ("extraterm", ExecutionSyntax::E),
+];
+
+/// Extended terminal emulators and their execution syntax.
+pub(crate) const HARDCODED_EXTENDED: &[(&str, ExecutionSyntax)] = &[
+    ("terminator", ExecutionSyntax::SingleE),
+    ("tilix", ExecutionSyntax::SingleE),
+    ("guake", ExecutionSyntax::SingleE),
+    ("yakuake", ExecutionSyntax::E),
+    ("tilda", ExecutionSyntax::E),
+    ("terminology", ExecutionSyntax::E),
+    ("cool-retro-term", ExecutionSyntax::E),
+    ("sakura", ExecutionSyntax::SingleE),
+    ("roxterm", ExecutionSyntax::E),
+    ("edex-ui", ExecutionSyntax::E),
+];
+
+/// Looks up a terminal emulator by its executable name in the hardcoded lists, then among the other executables of the
+/// known terminal emulators (e.g. `gnome-terminal.wrapper`), see the quirks table.
+#[cfg(any(
+    test,
+    not(windows),
+    feature = "env-var",
+    feature = "config-file",
+    feature = "xdg-terminal-exec",
+    feature = "x-terminal-emulator",
+    feature = "gnome-settings",
+    feature = "kde-settings",
+    feature = "xfce-settings",
+    feature = "lxqt-settings",
+    feature = "test-util",
+    all(target_os = "linux", feature = "controlling-tty")
+))]
+pub(crate) fn lookup_known(name: &str) -> Option<(&'static str, ExecutionSyntax)> {
+    [
+        HARDCODED_DESKTOP_ENV,
+        HARDCODED_MODERN,
+        HARDCODED_TRADITIONAL,
+        HARDCODED_EXTENDED,
+    ]
+    .into_iter()
+    .flatten()
+    .find(|(known, _)| crate::lookup::same_name(known, name))
+    .copied()
+    .or_else(|| crate::quirks::lookup_executable(name))
+}
+
+/// Resolves a terminal emulator from a name or path, using the hardcoded lists to find its execution syntax.
+///
+/// Unknown terminal emulators use `fallback` if given, or the execution syntax inferred from their `--help` output if
+/// enabled with [`probe_unknown_terminals`](crate::probe_unknown_terminals), or the default execution syntax. `raw` is the unparsed value
+/// read by the detection method.
+#[cfg(any(
+    feature = "env-var",
+    feature = "config-file",
+    feature = "xdg-terminal-exec",
+    feature = "gnome-settings",
+    feature = "kde-settings",
+    feature = "xfce-settings",
+    feature = "lxqt-settings"
+))]
+fn resolve<S: AsRef<std::ffi::OsStr>>(
+    name: S,
+    fallback: Option<ExecutionSyntax>,
+    method: DetectionMethod,
+    raw: Option<String>,
+) -> ProbeResult {
+    let name = name.as_ref();
+    let path = crate::lookup::lookup_executable(name).map_err(|problems| {
+        let name = name.to_string_lossy().into_owned();
+
+        if problems.is_empty() {
+            SkipReason::BinaryNotFound { name }
+        } else {
+            SkipReason::BinaryRejected { name, problems }
+        }
+    })?;
+
+    // Executables whose name is not valid Unicode are never known ones, but are still usable.
+    let (command_line, execution_syntax) = executable_name(&path)
+        .and_then(lookup_known)
+        .unwrap_or_else(|| {
+            let inferred = fallback.or_else(|| {
+                crate::unknown_terminals_probed()
+                    .then(|| crate::infer_execution_syntax(&path))
+                    .flatten()
+            });
+
+            ("", inferred.unwrap_or_default())
+        });
+
+    Ok(TerminalEmulator {
+        command_line: command_line.into(),
+        execution_syntax,
+        path,
+        method,
+        raw,
+    })
+}
+
+/// Framebuffer terminal emulators, which take over the current virtual console instead of opening a window.
+pub(crate) const FRAMEBUFFER_TERMINALS: &[&str] = &["fbterm", "kmscon"];
+
+/// Finds the first terminal emulator from a hardcoded list available in PATH.
+///
+/// Framebuffer terminal emulators are only offered on a bare virtual console, and terminal emulators that failed to
+/// launch during the current process or can not open a window in the current session are only returned if no other
+/// one is found.
+#[cfg(any(
+    feature = "hardcoded-traditional",
+    feature = "hardcoded-desktop-env",
+    feature = "hardcoded-modern",
+    feature = "hardcoded-extended"
+))]
+fn probe_list(
+    list: &'static [(&'static str, ExecutionSyntax)],
+    method: DetectionMethod,
+) -> ProbeResult {
+    crate::failures::prefer_working(find_list(list, method), |terminal| &terminal.path)
+        .ok_or(SkipReason::NoneInstalled)
+}
+
+/// Finds every terminal emulator from a hardcoded list available in PATH.
+///
+/// Framebuffer terminal emulators are only offered on a bare virtual console.
+pub(crate) fn find_list(
+    list: &'static [(&'static str, ExecutionSyntax)],
+    method: DetectionMethod,
+) -> impl Iterator<Item = TerminalEmulator> {
+    list.iter()
+        .filter_map(move |&(command_line, execution_syntax)| {
+            if FRAMEBUFFER_TERMINALS.contains(&command_line) && !crate::session::on_bare_console() {
+                return None;
+            }
+
+            find_executable(command_line).map(|path| TerminalEmulator {
+                command_line: command_line.into(),
+                execution_syntax,
+                path,
+                method,
+                raw: None,
+            })
+        })
+}
+
+/// Finds every terminal emulator of the enabled hardcoded lists available in PATH.
+#[cfg(not(windows))]
+pub(crate) fn find_hardcoded() -> Vec<TerminalEmulator> {
+    #[allow(unused_mut)]
+    let mut found = Vec::new();
+
+    #[cfg(feature = "hardcoded-desktop-env")]
+    found.extend(find_list(
+        HARDCODED_DESKTOP_ENV,
+        DetectionMethod::HardcodedDesktopEnv,
+    ));
+    #[cfg(feature = "hardcoded-modern")]
+    found.extend(find_list(
+        HARDCODED_MODERN,
+        DetectionMethod::HardcodedModern,
+    ));
+    #[cfg(feature = "hardcoded-traditional")]
+    found.extend(find_list(
+        HARDCODED_TRADITIONAL,
+        DetectionMethod::HardcodedTraditional,
+    ));
+    #[cfg(feature = "hardcoded-extended")]
+    found.extend(find_list(
+        HARDCODED_EXTENDED,
+        DetectionMethod::HardcodedExtended,
+    ));
+
+    found
+}
+
+#[cfg(feature = "env-var")]
+/// Detects the terminal emulator set in the `TERMINAL_EMULATOR` environment variable.
+///
+/// Skipped in a privileged context (see [`is_privileged_context`](crate::is_privileged_context)) or if the terminal
+/// emulator is locked by the system-wide configuration file.
+pub fn probe_env_var() -> Option<TerminalEmulator> {
+    check_env_var().ok()
+}
+
+/// Runs [`probe_env_var`], returning why it found nothing.
+#[cfg(feature = "env-var")]
+pub(crate) fn check_env_var() -> ProbeResult {
+    if !environment_trusted() {
+        return Err(SkipReason::PrivilegedContext);
+    }
+
+    if locked() {
+        return Err(SkipReason::Locked);
+    }
+
+    // Values that are not valid Unicode (e.g. a path in a legacy encoding) are used unchanged, without trimming.
+    let value = crate::environment::var_os("TERMINAL_EMULATOR")
+        .filter(|value| value.to_str().is_none_or(|value| !value.trim().is_empty()))
+        .ok_or_else(|| SkipReason::EnvMissing {
+            name: String::from("TERMINAL_EMULATOR"),
+        })?;
+    let name = value
+        .to_str()
+        .map_or(value.as_os_str(), |value| value.trim().as_ref());
+
+    resolve(
+        name,
+        None,
+        DetectionMethod::EnvironmentVariable,
+        Some(value.to_string_lossy().into_owned()),
+    )
+}
+
+/// Returns `true` if the system-wide configuration file locks the terminal emulator.
+#[cfg(feature = "env-var")]
+fn locked() -> bool {
+    #[cfg(feature = "config-file")]
+    return crate::config::is_terminal_locked();
+    #[cfg(not(feature = "config-file"))]
+    false
+}
+
+#[cfg(feature = "config-file")]
+/// Detects the default terminal emulator saved in the configuration file of this crate (see
+/// [`set_default_terminal`](crate::set_default_terminal)), falling back to the system-wide configuration files.
+///
+/// Only `/etc/unidosx/terminal.conf` is read in a privileged context (see
+/// [`is_privileged_context`](crate::is_privileged_context)) or if it locks the terminal emulator (see
+/// [`is_terminal_locked`](crate::is_terminal_locked)).
+pub fn probe_config_file() -> Option<TerminalEmulator> {
+    check_config_file().ok()
+}
+
+/// Runs [`probe_config_file`], returning why it found nothing.
+#[cfg(feature = "config-file")]
+pub(crate) fn check_config_file() -> ProbeResult {
+    let (exec, syntax) = crate::config::configured_terminal(environment_trusted())
+        .ok_or(SkipReason::NotConfigured)?;
+
+    resolve(
+        &exec,
+        syntax,
+        DetectionMethod::ConfigFile,
+        Some(exec.clone()),
+    )
+}
+
+#[cfg(all(target_os = "macos", feature = "terminal-app"))]
+/// Detects Terminal.app, the terminal emulator bundled with macOS.
+pub fn probe_terminal_app() -> Option<TerminalEmulator> {
+    check_terminal_app().ok()
+}
+
+/// Runs [`probe_terminal_app`], returning why it found nothing.
+#[cfg(all(target_os = "macos", feature = "terminal-app"))]
+pub(crate) fn check_terminal_app() -> ProbeResult {
+    [
+        "/System/Applications/Utilities/Terminal.app",
+        "/Applications/Utilities/Terminal.app",
+    ]
+    .into_iter()
+    .map(PathBuf::from)
+    .find(|path| crate::filesystem::file_info(path).is_ok_and(|info| info.is_dir))
+    .map(|path| TerminalEmulator {
+        command_line: "Terminal".into(),
+        execution_syntax: ExecutionSyntax::Command,
+        path,
+        method: DetectionMethod::TerminalApp,
+        raw: None,
+    })
+    .ok_or_else(|| SkipReason::BinaryNotFound {
+        name: String::from("Terminal.app"),
+    })
+}
+
+#[cfg(feature = "xdg-terminal-exec")]
+/// Detects the `xdg-terminal-exec` launcher.
+///
+/// If `xdg-terminal-exec` is not installed, the first available terminal emulator listed in its configuration
+/// (`xdg-terminals.list`) is used instead.
+///
+/// Skipped in a privileged context as `xdg-terminal-exec` reads the user configuration, see
+/// [`is_privileged_context`](crate::is_privileged_context).
+pub fn probe_xdg_terminal_exec() -> Option<TerminalEmulator> {
+    check_xdg_terminal_exec().ok()
+}
+
+/// Runs [`probe_xdg_terminal_exec`], returning why it found nothing.
+///
+/// Without `xdg-terminal-exec`, the reason of the last entry of `xdg-terminals.list` is returned.
+#[cfg(feature = "xdg-terminal-exec")]
+pub(crate) fn check_xdg_terminal_exec() -> ProbeResult {
+    if !environment_trusted() {
+        return Err(SkipReason::PrivilegedContext);
+    }
+
+    // The `xdg-terminal-exec` binary of this crate would launch itself.
+    let launcher = find_executable("xdg-terminal-exec")
+        .filter(|path| !is_current_exe(path))
+        .map(|path| TerminalEmulator {
+            command_line: "xdg-terminal-exec".into(),
+            execution_syntax: ExecutionSyntax::Command,
+            path,
+            method: DetectionMethod::XdgTerminalExec,
+            raw: None,
+        });
+
+    if let Some(launcher) = launcher {
+        return Ok(launcher);
+    }
+
+    let mut reason = SkipReason::BinaryNotFound {
+        name: String::from("xdg-terminal-exec"),
+    };
+
+    for id in xdg_terminals_list() {
+        let Some(entry) = crate::desktop_entry::find_desktop_entry_by_id(&id) else {
+            reason = SkipReason::BinaryNotFound { name: id };
+            continue;
+        };
+        let Some(program) = crate::desktop_entry::entry_program(&entry) else {
+            reason = SkipReason::ParseError {
+                detail: format!("{id} has no valid Exec key"),
+            };
+            continue;
+        };
+
+        // The desktop entry ID tells the terminal emulator even if it is launched through a wrapper (e.g. `flatpak`).
+        let fallback =
+            crate::quirks::lookup_desktop_id(&id).map(|(_, execution_syntax)| execution_syntax);
+
+        match resolve(
+            &program,
+            fallback,
+            DetectionMethod::XdgTerminalExec,
+            Some(program.clone()),
+        ) {
+            Ok(terminal) => return Ok(terminal),
+            Err(error) => reason = error,
+        }
+    }
+
+    Err(reason)
+}
+
+/// Returns `true` if `path` is the executable of the current process.
+#[cfg(feature = "xdg-terminal-exec")]
+fn is_current_exe(path: &std::path::Path) -> bool {
+    if crate::filesystem::is_offline() {
+        return false;
+    }
+
+    let canonical = |path: &std::path::Path| std::fs::canonicalize(path).ok();
+
+    std::env::current_exe()
+        .ok()
+        .and_then(|exe| canonical(&exe))
+        .is_some_and(|exe| canonical(path) == Some(exe))
+}
+
+/// Reads the desktop file IDs of the preferred terminal emulators from the `xdg-terminals.list` files.
+///
+/// The desktop-specific lists (e.g. `gnome-xdg-terminals.list`) and the generic one are searched in the XDG
+/// configuration directories, then in the `xdg-terminal-exec` directory of the XDG data directories. The entries of
+/// the first list found are returned.
+#[cfg(feature = "xdg-terminal-exec")]
+fn xdg_terminals_list() -> Vec<String> {
+    let desktops = crate::environment::var("XDG_CURRENT_DESKTOP").unwrap_or_default();
+    let names: Vec<String> = desktops
+        .split(':')
+        .filter(|desktop| !desktop.is_empty())
+        .map(|desktop| format!("{}-xdg-terminals.list", desktop.to_ascii_lowercase()))
+        .chain(Some(String::from("xdg-terminals.list")))
+        .collect();
+
+    let dirs = crate::xdg::config_dirs().into_iter().chain(
+        crate::xdg::data_dirs()
+            .into_iter()
+            .map(|dir| dir.join("xdg-terminal-exec")),
+    );
+
+    dirs.flat_map(|dir| names.iter().map(move |name| dir.join(name)))
+        .find_map(|path| crate::filesystem::read_to_string(&path).ok())
+        .map(|contents| parse_xdg_terminals_list(&contents))
+        .unwrap_or_default()
+}
+
+/// Parses an `xdg-terminals.list` file into desktop file IDs, skipping comments, excluded entries and actions.
+#[cfg(any(test, feature = "xdg-terminal-exec"))]
+pub(crate) fn parse_xdg_terminals_list(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with('-'))
+        .map(|line| line.trim_start_matches('+'))
+        .map(|line| line.split_once(':').map_or(line, |(id, _)| id).to_owned())
+        .collect()
+}
+
+#[cfg(feature = "x-terminal-emulator")]
+/// Detects the `x-terminal-emulator` alternative from Debian-based systems.
+///
+/// The Debian policy requires `x-terminal-emulator` to support the `-e` execution syntax.
+pub fn probe_x_terminal_emulator() -> Option<TerminalEmulator> {
+    check_x_terminal_emulator().ok()
+}
+
+/// Runs [`probe_x_terminal_emulator`], returning why it found nothing.
+#[cfg(feature = "x-terminal-emulator")]
+pub(crate) fn check_x_terminal_emulator() -> ProbeResult {
+    find_executable("x-terminal-emulator")
+        .map(|path| TerminalEmulator {
+            command_line: "x-terminal-emulator".into(),
+            execution_syntax: ExecutionSyntax::E,
+            path,
+            method: DetectionMethod::XTerminalEmulator,
+            raw: None,
+        })
+        .ok_or_else(|| SkipReason::BinaryNotFound {
+            name: String::from("x-terminal-emulator"),
+        })
+}
+
+#[cfg(feature = "gnome-settings")]
+/// Detects the terminal emulator configured in the GNOME settings.
+///
+/// Skipped in a privileged context, see [`is_privileged_context`](crate::is_privileged_context).
+pub fn probe_gnome_settings() -> Option<TerminalEmulator> {
+    check_gnome_settings().ok()
+}
+
+/// Runs [`probe_gnome_settings`], returning why it found nothing.
+#[cfg(feature = "gnome-settings")]
+pub(crate) fn check_gnome_settings() -> ProbeResult {
+    if !environment_trusted() {
+        return Err(SkipReason::PrivilegedContext);
+    }
+
+    if crate::filesystem::is_offline() {
+        return Err(SkipReason::SessionRequired);
+    }
+
+    let raw = gsettings_get("exec")?;
+    let exec = parse_gsettings_string(&raw).ok_or(SkipReason::NotConfigured)?;
+    let exec_arg = gsettings_get("exec-arg")
+        .ok()
+        .as_deref()
+        .and_then(parse_gsettings_string);
+    let fallback = Some(exec_arg_syntax(exec_arg.as_deref()));
+
+    resolve(&exec, fallback, DetectionMethod::GnomeSettings, Some(raw))
+}
+
+/// Reads a key from the `org.gnome.desktop.default-applications.terminal` schema, as printed by `gsettings get`.
+#[cfg(feature = "gnome-settings")]
+fn gsettings_get(key: &str) -> Result<String, SkipReason> {
+    let output = crate::cancel::output(
+        Command::new("gsettings")
+            .args([
+                "get",
+                "org.gnome.desktop.default-applications.terminal",
+                key,
+            ])
+            .env("LC_ALL", "C"),
+    )?;
+
+    if !output.status.success() {
+        return Err(SkipReason::CommandFailed {
+            detail: format!(
+                "gsettings exited with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        });
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+/// Parses a GVariant string as printed by `gsettings get`, returning `None` if it is empty.
+#[cfg(any(test, feature = "gnome-settings"))]
+pub(crate) fn parse_gsettings_string(output: &str) -> Option<String> {
+    let output = output.trim();
+    let value = output
+        .strip_prefix('\'')
+        .and_then(|value| value.strip_suffix('\''))
+        .unwrap_or(output);
+
+    (!value.is_empty()).then(|| value.to_owned())
+}
+
+/// Returns the execution syntax of the GNOME `exec-arg` setting, the flag preceding the command.
+///
+/// Flags other than `-e` and `--` (e.g. GNOME's default `-x`) are kept as they are.
+#[cfg(any(test, feature = "gnome-settings"))]
+pub(crate) fn exec_arg_syntax(exec_arg: Option<&str>) -> ExecutionSyntax {
+    match exec_arg {
+        None => ExecutionSyntax::Command,
+        Some("--") => ExecutionSyntax::DoubleDash,
+        Some("-e") => ExecutionSyntax::E,
+        Some(flag) => ExecutionSyntax::CustomFlag {
+            flag: crate::intern_flag(flag),
+            single_argument: false,
+        },
+    }
+}
+
+#[cfg(feature = "kde-settings")]
+/// Detects the terminal emulator configured in the KDE settings (`kdeglobals`).
+///
+/// Skipped in a privileged context, see [`is_privileged_context`](crate::is_privileged_context).
+pub fn probe_kde_settings() -> Option<TerminalEmulator> {
+    check_kde_settings().ok()
+}
+
+/// Runs [`probe_kde_settings`], returning why it found nothing.
+#[cfg(feature = "kde-settings")]
+pub(crate) fn check_kde_settings() -> ProbeResult {
+    probe_settings_file(
+        "kdeglobals",
+        "General",
+        "TerminalApplication",
+        DetectionMethod::KdeSettings,
+    )
+}
+
+#[cfg(feature = "xfce-settings")]
+/// Detects the terminal emulator configured in the XFCE preferred applications (`xfce4/helpers.rc`).
+///
+/// Skipped in a privileged context, see [`is_privileged_context`](crate::is_privileged_context).
+pub fn probe_xfce_settings() -> Option<TerminalEmulator> {
+    check_xfce_settings().ok()
+}
+
+/// Runs [`probe_xfce_settings`], returning why it found nothing.
+#[cfg(feature = "xfce-settings")]
+pub(crate) fn check_xfce_settings() -> ProbeResult {
+    probe_settings_file(
+        "xfce4/helpers.rc",
+        "",
+        "TerminalEmulator",
+        DetectionMethod::XfceSettings,
+    )
+}
+
+#[cfg(feature = "lxqt-settings")]
+/// Detects the terminal emulator configured in the LXQt session settings (`lxqt/session.conf`).
+///
+/// Skipped in a privileged context, see [`is_privileged_context`](crate::is_privileged_context).
+pub fn probe_lxqt_settings() -> Option<TerminalEmulator> {
+    check_lxqt_settings().ok()
+}
+
+/// Runs [`probe_lxqt_settings`], returning why it found nothing.
+#[cfg(feature = "lxqt-settings")]
+pub(crate) fn check_lxqt_settings() -> ProbeResult {
+    probe_settings_file(
+        "lxqt/session.conf",
+        "Environment",
+        "TERM",
+        DetectionMethod::LxqtSettings,
+    )
+}
+
+/// Resolves the terminal emulator set by `key` in `section` of a settings file in the XDG configuration directories.
+///
+/// The user configuration is preferred, system-wide files (e.g. `/etc/xdg/kdeglobals`) are only used if it does not
+/// set a terminal emulator.
+#[cfg(any(
+    feature = "kde-settings",
+    feature = "xfce-settings",
+    feature = "lxqt-settings"
+))]
+fn probe_settings_file(
+    file: &str,
+    section: &str,
+    key: &str,
+    method: DetectionMethod,
+) -> ProbeResult {
+    if !environment_trusted() {
+        return Err(SkipReason::PrivilegedContext);
+    }
+
+    let mut reason = SkipReason::NotConfigured;
+    let raw = crate::xdg::config_dirs()
+        .into_iter()
+        .filter_map(|dir| {
+            let path = dir.join(file);
+
+            crate::filesystem::read_to_string(&path)
+                .map_err(|error| {
+                    if error.kind() != std::io::ErrorKind::NotFound {
+                        reason = SkipReason::Io {
+                            detail: format!("{}: {error}", path.display()),
+                        };
+                    }
+                })
+                .ok()
+        })
+        .find_map(|contents| settings_value(&contents, section, key))
+        .ok_or(reason)?;
+    let exec = crate::parse_exec_line(&raw).program;
+
+    if exec.is_empty() {
+        return Err(SkipReason::NotConfigured);
+    }
+
+    resolve(&exec, None, method, Some(raw))
+}
+
+/// Extracts the executable from the `TerminalApplication` key of the `[General]` section of `kdeglobals`.
+#[cfg(test)]
+pub(crate) fn parse_kdeglobals_terminal(contents: &str) -> Option<String> {
+    settings_executable(contents, "General", "TerminalApplication")
+}
+
+/// Extracts the executable from the `TerminalEmulator` key of the XFCE `helpers.rc`.
+#[cfg(test)]
+pub(crate) fn parse_xfce_helpers_terminal(contents: &str) -> Option<String> {
+    settings_executable(contents, "", "TerminalEmulator")
+}
+
+/// Extracts the executable from the `TERM` key of the `[Environment]` section of the LXQt `session.conf`.
+#[cfg(test)]
+pub(crate) fn parse_lxqt_session_terminal(contents: &str) -> Option<String> {
+    settings_executable(contents, "Environment", "TERM")
+}
+
+/// Returns the program of a settings value, dropping the arguments of a command line.
+#[cfg(test)]
+fn settings_executable(contents: &str, section: &str, key: &str) -> Option<String> {
+    crate::parse_exec_line(&settings_value(contents, section, key)?)
+        .program
+        .into_string()
+        .ok()
+}
+
+/// Returns the value of `key` in `section` of a settings file, or `None` if it is not set or blank.
+#[cfg(any(
+    test,
+    feature = "kde-settings",
+    feature = "xfce-settings",
+    feature = "lxqt-settings"
+))]
+fn settings_value(contents: &str, section: &str, key: &str) -> Option<String> {
+    crate::ini::Ini::parse(contents)
+        .get(section, key)
+        .filter(|value| !value.trim().is_empty())
+        .map(str::to_owned)
+}
+
+#[cfg(feature = "hardcoded-desktop-env")]
+/// Detects the first available terminal emulator from the desktop environment-specific hardcoded list.
+pub fn probe_hardcoded_desktop_env() -> Option<TerminalEmulator> {
+    check_hardcoded_desktop_env().ok()
+}
+
+/// Runs [`probe_hardcoded_desktop_env`], returning why it found nothing.
+#[cfg(feature = "hardcoded-desktop-env")]
+pub(crate) fn check_hardcoded_desktop_env() -> ProbeResult {
+    probe_list(HARDCODED_DESKTOP_ENV, DetectionMethod::HardcodedDesktopEnv)
+}
+
+#[cfg(feature = "hardcoded-modern")]
+/// Detects the first available terminal emulator from the modern hardcoded list.
+pub fn probe_hardcoded_modern() -> Option<TerminalEmulator> {
+    check_hardcoded_modern().ok()
+}
+
+/// Runs [`probe_hardcoded_modern`], returning why it found nothing.
+#[cfg(feature = "hardcoded-modern")]
+pub(crate) fn check_hardcoded_modern() -> ProbeResult {
+    probe_list(HARDCODED_MODERN, DetectionMethod::HardcodedModern)
+}
+
+#[cfg(feature = "hardcoded-traditional")]
+/// Detects the first available terminal emulator from the traditional hardcoded list.
+pub fn probe_hardcoded_traditional() -> Option<TerminalEmulator> {
+    check_hardcoded_traditional().ok()
+}
+
+/// Runs [`probe_hardcoded_traditional`], returning why it found nothing.
+#[cfg(feature = "hardcoded-traditional")]
+pub(crate) fn check_hardcoded_traditional() -> ProbeResult {
+    probe_list(HARDCODED_TRADITIONAL, DetectionMethod::HardcodedTraditional)
+}
+
+#[cfg(feature = "hardcoded-extended")]
+/// Detects the first available terminal emulator from the extended hardcoded list.
+pub fn probe_hardcoded_extended() -> Option<TerminalEmulator> {
+    check_hardcoded_extended().ok()
+}
+
+/// Runs [`probe_hardcoded_extended`], returning why it found nothing.
+#[cfg(feature = "hardcoded-extended")]
+pub(crate) fn check_hardcoded_extended() -> ProbeResult {
+    probe_list(HARDCODED_EXTENDED, DetectionMethod::HardcodedExtended)
+}
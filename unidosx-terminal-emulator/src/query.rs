@@ -0,0 +1,200 @@
+//! Identification of the terminal emulator hosting the current process by querying it.
+//!
+//! [`detect_current`](crate::detect_current) relies on environment variables, which are inherited by nested terminal
+//! emulators, forwarded over SSH or scrubbed. The terminal emulator itself answers escape sequence queries on the
+//! terminal: XTVERSION (`CSI > 0 q`) with its name and version (e.g. `kitty(0.35.2)`), Secondary Device Attributes
+//! (`CSI > c`) with a type and firmware version, and Primary Device Attributes (`CSI c`) with its features. Every
+//! terminal emulator answers the primary attributes, so they are sent last and mark the end of the answers.
+//!
+//! The terminal is switched to raw mode with `stty` while waiting for the answers, so they are neither echoed nor held
+//! until a newline, and its mode is restored afterwards.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Read, Write},
+    process::Stdio,
+    time::{Duration, Instant},
+};
+
+use crate::CommandSpec;
+
+/// The queries, in order: XTVERSION, Secondary Device Attributes, then Primary Device Attributes.
+const QUERIES: &[u8] = b"\x1b[>0q\x1b[>c\x1b[c";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+/// The answers of the terminal emulator hosting the current process, see [`query_terminal`].
+pub struct TerminalIdentity {
+    /// The XTVERSION answer, the name and version of the terminal emulator (e.g. `kitty(0.35.2)`, `WezTerm 20240203`),
+    /// `None` if it does not support the query.
+    pub xtversion: Option<String>,
+    /// The Secondary Device Attributes: the terminal type, the firmware version and the ROM cartridge number (e.g.
+    /// `[1, 7600, 1]` for VTE 0.76), empty if not answered.
+    pub secondary_attributes: Vec<u32>,
+    /// The Primary Device Attributes: the conformance level followed by the supported features (e.g. `[62, 22]` for a
+    /// VT220 with ANSI colors).
+    pub primary_attributes: Vec<u32>,
+}
+
+impl TerminalIdentity {
+    /// Returns the name of the terminal emulator from its XTVERSION answer (e.g. `kitty`).
+    pub fn name(&self) -> Option<&str> {
+        self.xtversion
+            .as_deref()
+            .map(|xtversion| split_xtversion(xtversion).0)
+    }
+
+    /// Returns the version of the terminal emulator from its XTVERSION answer (e.g. `0.35.2`).
+    pub fn version(&self) -> Option<&str> {
+        self.xtversion
+            .as_deref()
+            .and_then(|xtversion| split_xtversion(xtversion).1)
+    }
+}
+
+/// Splits an XTVERSION answer into the name and version, written `name(version)` or `name version`.
+fn split_xtversion(xtversion: &str) -> (&str, Option<&str>) {
+    match xtversion.split_once(['(', ' ']) {
+        Some((name, version)) => {
+            let version = version.trim_end_matches(')').trim();
+            (name, (!version.is_empty()).then_some(version))
+        }
+        None => (xtversion, None),
+    }
+}
+
+/// Queries the terminal emulator hosting the current process through its controlling terminal (`/dev/tty`), waiting
+/// up to `timeout` for its answers.
+///
+/// Must not be called while another thread reads the terminal, which could consume the answers. Answers arriving after
+/// `timeout` (e.g. over a slow SSH connection) are left for the application to read.
+///
+/// Fails if the process has no controlling terminal, with [`io::ErrorKind::TimedOut`] if the terminal emulator did not
+/// answer the primary attributes within `timeout`, or if the terminal mode cannot be changed.
+pub fn query_terminal(timeout: Duration) -> io::Result<TerminalIdentity> {
+    let mut tty = OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+    let _raw = RawMode::enable(&tty)?;
+
+    tty.write_all(QUERIES)?;
+    tty.flush()?;
+
+    let deadline = Instant::now() + timeout;
+    let mut answers = Vec::new();
+    let mut buffer = [0; 256];
+
+    loop {
+        // Returns nothing after a tenth of a second without input, see `RawMode`.
+        let read = tty.read(&mut buffer)?;
+        answers.extend_from_slice(&buffer[..read]);
+
+        if let Some(identity) = parse_answers(&answers) {
+            return Ok(identity);
+        }
+
+        if Instant::now() >= deadline {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "the terminal emulator did not answer in time",
+            ));
+        }
+    }
+}
+
+/// Parses the answers to the queries, returning `None` until the Primary Device Attributes are answered.
+pub(crate) fn parse_answers(answers: &[u8]) -> Option<TerminalIdentity> {
+    let mut identity = TerminalIdentity::default();
+    let mut rest = answers;
+
+    loop {
+        let start = rest.iter().position(|&byte| byte == 0x1b)?;
+        rest = &rest[start..];
+
+        // XTVERSION: DCS > | text ST
+        if let Some(text) = rest.strip_prefix(b"\x1bP>|") {
+            let end = text.windows(2).position(|window| window == b"\x1b\\")?;
+            identity.xtversion = Some(String::from_utf8_lossy(&text[..end]).into_owned());
+            rest = &text[end + 2..];
+            continue;
+        }
+
+        // Device Attributes: CSI > params c (secondary) or CSI ? params c (primary).
+        if let Some(csi) = rest.strip_prefix(b"\x1b[") {
+            let end = csi
+                .iter()
+                .position(|&byte| !(byte.is_ascii_digit() || b";>?".contains(&byte)))?;
+            let (params, terminator) = (&csi[..end], csi[end]);
+            rest = &csi[end + 1..];
+
+            if terminator != b'c' {
+                continue;
+            }
+
+            match params.split_first() {
+                Some((b'>', params)) => identity.secondary_attributes = parse_params(params),
+                Some((b'?', params)) => {
+                    identity.primary_attributes = parse_params(params);
+                    return Some(identity);
+                }
+                _ => {}
+            }
+
+            continue;
+        }
+
+        rest = &rest[1..];
+    }
+}
+
+/// Parses numeric parameters separated by `;`.
+fn parse_params(params: &[u8]) -> Vec<u32> {
+    String::from_utf8_lossy(params)
+        .split(';')
+        .filter_map(|param| param.parse().ok())
+        .collect()
+}
+
+/// Raw mode of the terminal, restoring the previous mode when dropped.
+struct RawMode {
+    /// The terminal.
+    tty: File,
+    /// The previous mode, as printed by `stty -g`.
+    saved: String,
+}
+
+impl RawMode {
+    /// Switches `tty` to raw mode without echo, reads returning after a tenth of a second without input.
+    fn enable(tty: &File) -> io::Result<Self> {
+        let tty = tty.try_clone()?;
+        let saved = stty(&tty, &["-g"])?;
+        stty(&tty, &["raw", "-echo", "min", "0", "time", "1"])?;
+
+        Ok(Self {
+            tty,
+            saved: saved.trim().to_owned(),
+        })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = stty(&self.tty, &[self.saved.as_str()]);
+    }
+}
+
+/// Runs `stty` with `args` on `tty`, returning its output.
+fn stty(tty: &File, args: &[&str]) -> io::Result<String> {
+    let output = CommandSpec::new("stty")
+        .args(args)
+        .to_command()
+        .stdin(Stdio::from(tty.try_clone()?))
+        .stderr(Stdio::null())
+        .output()?;
+
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "stty failed with {}",
+            output.status
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
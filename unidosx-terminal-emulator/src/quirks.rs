@@ -0,0 +1,94 @@
+//! Per-terminal quirks.
+//!
+//! The hardcoded lists name each terminal emulator by its main executable, but the settings and desktop entries often
+//! refer to them differently: Debian wrappers (`gnome-terminal.wrapper`), clients (`footclient`), legacy names
+//! (`rxvt-unicode`) or desktop entry IDs (`org.gnome.Terminal.desktop`). The tables below map them back to the
+//! terminal emulator, with the execution syntax they expect, so detection does not fall back to the default syntax.
+
+use crate::ExecutionSyntax;
+
+/// Other executables of known terminal emulators, the terminal emulator they belong to, and the execution syntax they
+/// expect.
+///
+/// The Debian wrappers follow the `x-terminal-emulator` policy, taking the command after `-e` as separate arguments.
+const EXECUTABLES: &[(&str, &str, ExecutionSyntax)] = &[
+    (
+        "gnome-terminal.wrapper",
+        "gnome-terminal",
+        ExecutionSyntax::E,
+    ),
+    (
+        "gnome-terminal.real",
+        "gnome-terminal",
+        ExecutionSyntax::DoubleDash,
+    ),
+    (
+        "xfce4-terminal.wrapper",
+        "xfce4-terminal",
+        ExecutionSyntax::E,
+    ),
+    ("mate-terminal.wrapper", "mate-terminal", ExecutionSyntax::E),
+    ("gnome-console", "kgx", ExecutionSyntax::DoubleDash),
+    ("footclient", "foot", ExecutionSyntax::Command),
+    ("uxterm", "xterm", ExecutionSyntax::E),
+    ("lxterm", "xterm", ExecutionSyntax::E),
+    ("koi8rxterm", "xterm", ExecutionSyntax::E),
+    ("rxvt-unicode", "urxvt", ExecutionSyntax::E),
+    ("urxvtc", "urxvt", ExecutionSyntax::E),
+    ("urxvt256c", "urxvt", ExecutionSyntax::E),
+];
+
+/// Desktop entry IDs of known terminal emulators, and the terminal emulator they launch.
+#[cfg(any(test, feature = "xdg-terminal-exec"))]
+const DESKTOP_IDS: &[(&str, &str)] = &[
+    ("org.gnome.Terminal.desktop", "gnome-terminal"),
+    ("org.gnome.Console.desktop", "kgx"),
+    ("org.gnome.Ptyxis.desktop", "ptyxis"),
+    ("org.kde.konsole.desktop", "konsole"),
+    ("org.kde.yakuake.desktop", "yakuake"),
+    ("xfce4-terminal.desktop", "xfce4-terminal"),
+    ("mate-terminal.desktop", "mate-terminal"),
+    ("lxterminal.desktop", "lxterminal"),
+    ("org.lxqt.QTerminal.desktop", "qterminal"),
+    ("qterminal.desktop", "qterminal"),
+    ("deepin-terminal.desktop", "deepin-terminal"),
+    ("io.elementary.terminal.desktop", "io.elementary.terminal"),
+    ("kitty.desktop", "kitty"),
+    ("Alacritty.desktop", "alacritty"),
+    ("org.wezfurlong.wezterm.desktop", "wezterm"),
+    ("com.mitchellh.ghostty.desktop", "ghostty"),
+    ("foot.desktop", "foot"),
+    ("footclient.desktop", "foot"),
+    ("org.codeberg.dnkl.foot.desktop", "foot"),
+    ("org.contourterminal.Contour.desktop", "contour"),
+    ("com.raggesilver.BlackBox.desktop", "blackbox"),
+    ("com.gexperts.Tilix.desktop", "tilix"),
+    ("terminator.desktop", "terminator"),
+    ("guake.desktop", "guake"),
+    ("debian-xterm.desktop", "xterm"),
+    ("debian-uxterm.desktop", "xterm"),
+    ("xterm.desktop", "xterm"),
+    ("rxvt-unicode.desktop", "urxvt"),
+    ("terminology.desktop", "terminology"),
+    ("cool-retro-term.desktop", "cool-retro-term"),
+    ("sakura.desktop", "sakura"),
+    ("roxterm.desktop", "roxterm"),
+];
+
+/// Looks up another executable of a known terminal emulator (e.g. `gnome-terminal.wrapper`), returning the terminal
+/// emulator it belongs to and the execution syntax it expects.
+pub(crate) fn lookup_executable(name: &str) -> Option<(&'static str, ExecutionSyntax)> {
+    EXECUTABLES
+        .iter()
+        .find(|(executable, _, _)| crate::lookup::same_name(executable, name))
+        .map(|&(_, terminal, syntax)| (terminal, syntax))
+}
+
+/// Looks up the desktop entry ID of a known terminal emulator (e.g. `org.gnome.Terminal.desktop`), returning the
+/// terminal emulator it launches and its execution syntax.
+#[cfg(any(test, feature = "xdg-terminal-exec"))]
+pub(crate) fn lookup_desktop_id(id: &str) -> Option<(&'static str, ExecutionSyntax)> {
+    let (_, terminal) = DESKTOP_IDS.iter().find(|(known, _)| *known == id)?;
+
+    crate::probes::lookup_known(terminal)
+}
@@ -0,0 +1,341 @@
+//! Detailed report of a detection run.
+//!
+//! [`detect`](crate::detect) only returns the terminal emulator it found. [`detect_report`] also tells what every
+//! detection method found, or why it found nothing as a [`SkipReason`], so tooling can diagnose and aggregate
+//! detection failures.
+
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+};
+
+use crate::{
+    DetectionMethod, PROBES, SkipReason, TerminalEmulator, backend, environment, failures,
+    probes::ProbeResult, session,
+};
+
+/// A detection method and the function running it.
+pub(crate) type Probe = (DetectionMethod, fn() -> ProbeResult);
+
+/// Every detection method run by [`detect`](crate::detect), in order, whether it is enabled or not.
+const DETECTION_ORDER: &[DetectionMethod] = &[
+    DetectionMethod::EnvironmentVariable,
+    DetectionMethod::ConfigFile,
+    DetectionMethod::Accessibility,
+    DetectionMethod::TerminalApp,
+    DetectionMethod::XdgTerminalExec,
+    DetectionMethod::XTerminalEmulator,
+    DetectionMethod::GnomeSettings,
+    DetectionMethod::KdeSettings,
+    DetectionMethod::XfceSettings,
+    DetectionMethod::LxqtSettings,
+    DetectionMethod::HardcodedDesktopEnv,
+    DetectionMethod::HardcodedModern,
+    DetectionMethod::HardcodedTraditional,
+    DetectionMethod::HardcodedExtended,
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// The result of a detection method in a [`DetectionReport`].
+pub struct ReportEntry {
+    /// The detection method.
+    pub method: DetectionMethod,
+    /// The terminal emulator found by the detection method, or why it found nothing.
+    pub result: Result<TerminalEmulator, SkipReason>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// The report of a detection run, see [`detect_report`].
+pub struct DetectionReport {
+    /// The result of every detection method run by [`detect`](crate::detect), in order, including the disabled ones.
+    pub entries: Vec<ReportEntry>,
+    /// The terminal emulator returned by [`detect`](crate::detect).
+    pub selected: Option<TerminalEmulator>,
+    /// Notes about the terminal emulators found, e.g. a release with a known launch bug that is worked around or
+    /// avoided (see the `version-detection` feature).
+    pub notes: Vec<String>,
+}
+
+impl DetectionReport {
+    /// Returns the detection methods that found nothing and why.
+    pub fn skipped(&self) -> impl Iterator<Item = (DetectionMethod, &SkipReason)> {
+        self.entries
+            .iter()
+            .filter_map(|entry| Some((entry.method, entry.result.as_ref().err()?)))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Why [`try_detect`] found no terminal emulator.
+///
+/// The variant tells the most significant failure: the first detection method, in order, whose command failed, whose
+/// file could not be read or whose value could not be parsed, or else whether the detection methods did not apply or
+/// found nothing installed. Every variant but [`HeadlessEnvironment`](Self::HeadlessEnvironment) lists why each
+/// detection method found nothing, see [`DetectionError::skipped`].
+pub enum DetectionError {
+    /// No graphical session is available to open a terminal emulator in (e.g. over SSH without X11 forwarding), see
+    /// [`check_graphical_session`](crate::check_graphical_session), and the process does not run on a bare virtual
+    /// console served by framebuffer terminal emulators. Checked before running any detection method, since whatever
+    /// they would find could not open a window: the command should run in the current terminal instead.
+    HeadlessEnvironment {
+        /// Why no graphical session is available and how to provide one.
+        detail: String,
+    },
+    /// No terminal emulator named by the settings or the hardcoded lists is installed.
+    NoTerminalFound {
+        /// Why each detection method found nothing, in order.
+        skipped: Vec<(DetectionMethod, SkipReason)>,
+    },
+    /// A command run by a detection method failed or timed out (e.g. `gsettings` is not installed).
+    CommandFailed {
+        /// The detection method running the command.
+        method: DetectionMethod,
+        /// The error or exit status of the command.
+        detail: String,
+        /// Why each detection method found nothing, in order.
+        skipped: Vec<(DetectionMethod, SkipReason)>,
+    },
+    /// A file read by a detection method exists but could not be read.
+    Io {
+        /// The detection method reading the file.
+        method: DetectionMethod,
+        /// The path of the file and the error.
+        detail: String,
+        /// Why each detection method found nothing, in order.
+        skipped: Vec<(DetectionMethod, SkipReason)>,
+    },
+    /// A value read by a detection method could not be parsed.
+    Parse {
+        /// The detection method reading the value.
+        method: DetectionMethod,
+        /// What could not be parsed.
+        detail: String,
+        /// Why each detection method found nothing, in order.
+        skipped: Vec<(DetectionMethod, SkipReason)>,
+    },
+    /// Every detection method is disabled or does not apply in the current context (e.g. a privileged context
+    /// ignoring the user settings, or another system inspected without its session).
+    Unsupported {
+        /// Why each detection method found nothing, in order.
+        skipped: Vec<(DetectionMethod, SkipReason)>,
+    },
+}
+
+impl DetectionError {
+    /// Returns why each detection method found nothing, in order, empty for
+    /// [`HeadlessEnvironment`](Self::HeadlessEnvironment).
+    pub fn skipped(&self) -> &[(DetectionMethod, SkipReason)] {
+        match self {
+            Self::HeadlessEnvironment { .. } => &[],
+            Self::NoTerminalFound { skipped }
+            | Self::CommandFailed { skipped, .. }
+            | Self::Io { skipped, .. }
+            | Self::Parse { skipped, .. }
+            | Self::Unsupported { skipped } => skipped,
+        }
+    }
+
+    /// Classifies why the detection methods found nothing.
+    pub(crate) fn from_skipped(skipped: Vec<(DetectionMethod, SkipReason)>) -> Self {
+        let failure = skipped
+            .iter()
+            .find(|(_, reason)| reason.is_failure())
+            .cloned();
+
+        match failure {
+            Some((method, SkipReason::Io { detail })) => Self::Io {
+                method,
+                detail,
+                skipped,
+            },
+            Some((method, SkipReason::ParseError { detail })) => Self::Parse {
+                method,
+                detail,
+                skipped,
+            },
+            Some((method, SkipReason::CommandFailed { detail })) => Self::CommandFailed {
+                method,
+                detail,
+                skipped,
+            },
+            Some((method, reason)) => Self::CommandFailed {
+                method,
+                detail: reason.to_string(),
+                skipped,
+            },
+            None if skipped.iter().all(|(_, reason)| reason.is_not_applicable()) => {
+                Self::Unsupported { skipped }
+            }
+            None => Self::NoTerminalFound { skipped },
+        }
+    }
+}
+
+impl Display for DetectionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::HeadlessEnvironment { detail } => write!(f, "{detail}")?,
+            Self::NoTerminalFound { .. } => write!(f, "no terminal emulator found")?,
+            Self::CommandFailed { method, detail, .. } => {
+                write!(f, "{method} command failed: {detail}")?
+            }
+            Self::Io { method, detail, .. } => write!(f, "{method} I/O error: {detail}")?,
+            Self::Parse { method, detail, .. } => write!(f, "{method} parse error: {detail}")?,
+            Self::Unsupported { .. } => {
+                write!(f, "no detection method applies in the current context")?
+            }
+        }
+
+        for (method, reason) in self.skipped() {
+            write!(f, "; {method}: {reason}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for DetectionError {}
+
+impl SkipReason {
+    /// Returns `true` if the detection method failed, rather than finding no terminal emulator set or installed.
+    fn is_failure(&self) -> bool {
+        matches!(
+            self,
+            Self::CommandFailed { .. } | Self::Timeout | Self::Io { .. } | Self::ParseError { .. }
+        )
+    }
+
+    /// Returns `true` if the detection method did not run because it does not apply, rather than finding nothing.
+    fn is_not_applicable(&self) -> bool {
+        matches!(
+            self,
+            Self::FeatureDisabled
+                | Self::Disabled
+                | Self::PrivilegedContext
+                | Self::Locked
+                | Self::SessionRequired
+        )
+    }
+}
+
+/// Fails with [`DetectionError::HeadlessEnvironment`] if no terminal emulator could open, neither in a graphical session
+/// nor on the virtual console (`bare_console`).
+pub(crate) fn check_headless(bare_console: bool) -> Result<(), DetectionError> {
+    match crate::check_graphical_session() {
+        Err(error) if !bare_console => Err(DetectionError::HeadlessEnvironment {
+            detail: error.to_string(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Detects the default terminal emulator like [`detect`](crate::detect), explaining why none was found.
+///
+/// Runs every detection method like [`detect_report`], so it is slower than [`detect`](crate::detect). Unlike
+/// [`detect`](crate::detect), fails with [`DetectionError::HeadlessEnvironment`] without running them if no graphical
+/// session is available, since launching whatever terminal emulator they find in `PATH` would fail. On a bare Linux
+/// virtual console, framebuffer terminal emulators (e.g. fbterm) and `openvt` need no graphical session, so detection
+/// runs as usual.
+pub fn try_detect() -> Result<TerminalEmulator, DetectionError> {
+    check_headless(session::on_bare_console())?;
+
+    let report = detect_report();
+
+    match report.selected {
+        Some(terminal) => Ok(terminal),
+        None => Err(DetectionError::from_skipped(
+            report
+                .skipped()
+                .map(|(method, reason)| (method, reason.clone()))
+                .collect(),
+        )),
+    }
+}
+
+/// Detects the default terminal emulator like [`detect`](crate::detect), reporting what every detection method found.
+///
+/// Unlike [`detect`](crate::detect), every detection method is run even after one found a terminal emulator.
+pub fn detect_report() -> DetectionReport {
+    environment::snapshot(|| {
+        #[cfg(feature = "config-file")]
+        let locked = crate::is_terminal_locked();
+        #[cfg(not(feature = "config-file"))]
+        let locked = false;
+
+        let order = crate::detector::order();
+        let entries: Vec<ReportEntry> = order
+            .as_deref()
+            .unwrap_or(DETECTION_ORDER)
+            .iter()
+            .map(|&method| {
+                let probe = PROBES
+                    .iter()
+                    .find(|(probe_method, _)| *probe_method == method);
+                let result = match probe {
+                    None => Err(SkipReason::FeatureDisabled),
+                    Some(_) if crate::detector::is_disabled(method) => Err(SkipReason::Disabled),
+                    Some(_) if locked && method != DetectionMethod::ConfigFile => {
+                        Err(SkipReason::Locked)
+                    }
+                    Some((_, probe)) => probe(),
+                };
+
+                ReportEntry { method, result }
+            })
+            .collect();
+        let entries = backend::around(entries, &crate::detector::backends(), |backend| {
+            let method = backend.method();
+            let result = if crate::detector::is_disabled(method) {
+                Err(SkipReason::Disabled)
+            } else if locked {
+                Err(SkipReason::Locked)
+            } else {
+                backend.run()
+            };
+
+            ReportEntry { method, result }
+        });
+
+        let mut found = entries
+            .iter()
+            .filter_map(|entry| entry.result.as_ref().ok().cloned());
+        // The locked terminal emulator is selected even if the configuration file is not among the detection methods run.
+        let selected = if locked {
+            found.next().or_else(|| {
+                PROBES
+                    .iter()
+                    .find(|(method, _)| *method == DetectionMethod::ConfigFile)
+                    .and_then(|(_, probe)| probe().ok())
+            })
+        } else {
+            failures::prefer_working(found, |terminal| &terminal.path)
+        };
+
+        // Every terminal emulator found is noted, telling why the avoided ones were not selected.
+        #[cfg(feature = "version-detection")]
+        let notes = {
+            let mut notes: Vec<String> = Vec::new();
+
+            for terminal in entries
+                .iter()
+                .filter_map(|entry| entry.result.as_ref().ok())
+            {
+                if let Some(note) = crate::versions::note(terminal)
+                    && !notes.contains(&note)
+                {
+                    notes.push(note);
+                }
+            }
+
+            notes
+        };
+        #[cfg(not(feature = "version-detection"))]
+        let notes = Vec::new();
+
+        DetectionReport {
+            entries,
+            selected,
+            notes,
+        }
+    })
+}
@@ -0,0 +1,103 @@
+//! Running a command in a new terminal window in a single call.
+
+use std::{
+    env,
+    ffi::OsStr,
+    io,
+    process::{self, Child},
+};
+
+use crate::{
+    LaunchOptions, LaunchResult, Launcher, TerminalEmulator, environment, is_attached_to_tty,
+};
+
+/// The environment variable set in the environment of a program re-executed in a terminal emulator by
+/// [`relaunch_in_terminal_if_needed`], so it is never re-executed again.
+pub const RELAUNCHED_VAR: &str = "UNIDOSX_RELAUNCHED";
+
+/// Runs `program` with `args` in a new window of the default terminal emulator, with `options`.
+///
+/// Detects the terminal emulator with [`detect`](crate::detect), builds the command with the quoting its execution
+/// syntax needs and spawns it, like [`Launcher::launch_with_options`]. Works the same on Linux, BSD, macOS and
+/// Windows, so a graphical application can open its terminal companion with one line.
+///
+/// Fails with [`io::ErrorKind::NotFound`] if no terminal emulator is found, or with the error of the launch. The caller
+/// becomes responsible for waiting for the returned process, see [`LaunchResult::into_child`].
+pub fn run_in_new_terminal<S, I, A>(
+    program: S,
+    args: I,
+    options: &LaunchOptions,
+) -> io::Result<Child>
+where
+    S: AsRef<OsStr>,
+    I: IntoIterator<Item = A>,
+    A: AsRef<OsStr>,
+{
+    let terminal = detect_terminal()?;
+
+    Launcher::new(&terminal)
+        .launch_with_options(program, args, options)
+        .map(LaunchResult::into_child)
+}
+
+/// Runs `program` with `args` in a new window of `terminal`, returning the spawned process.
+///
+/// Builds the command and spawns it like [`Launcher::launch`], so the caller only has to wait on or kill the returned
+/// process, which is usually the terminal emulator itself. The caller becomes responsible for waiting for it, see
+/// [`LaunchResult::into_child`].
+pub fn spawn_in_terminal<S, I, A>(
+    terminal: &TerminalEmulator,
+    program: S,
+    args: I,
+) -> io::Result<Child>
+where
+    S: AsRef<OsStr>,
+    I: IntoIterator<Item = A>,
+    A: AsRef<OsStr>,
+{
+    Launcher::new(terminal)
+        .launch(program, args)
+        .map(LaunchResult::into_child)
+}
+
+/// Re-executes the current program in a new window of the default terminal emulator if it does not run in a terminal,
+/// exiting the current process once the terminal emulator is started.
+///
+/// Meant to be called first in the `main` function of terminal applications, which have no terminal when started from
+/// a desktop file or a file manager. Returns right away if the standard input and output are both terminals, or if
+/// the program was already re-executed ([`RELAUNCHED_VAR`] is set), so it never loops. The program is re-executed with
+/// the same arguments, in the same working directory, with [`RELAUNCHED_VAR`] set.
+///
+/// Returns an error if the program cannot be re-executed (e.g. no terminal emulator is found), letting the caller
+/// continue without a terminal or report it.
+pub fn relaunch_in_terminal_if_needed() -> io::Result<()> {
+    if !needs_relaunch() {
+        return Ok(());
+    }
+
+    let program = env::current_exe()?;
+    let options = LaunchOptions::new()
+        .current_dir(env::current_dir()?)
+        .env(RELAUNCHED_VAR, "1");
+
+    run_in_new_terminal(program, env::args_os().skip(1), &options)?;
+    process::exit(0)
+}
+
+/// Returns `true` if the current program has no terminal and was not re-executed yet.
+pub(crate) fn needs_relaunch() -> bool {
+    !is_attached_to_tty() && environment::var_os(RELAUNCHED_VAR).is_none()
+}
+
+/// Detects the default terminal emulator, which is always found on Windows.
+#[cfg(windows)]
+fn detect_terminal() -> io::Result<TerminalEmulator> {
+    Ok(crate::detect())
+}
+
+/// Detects the default terminal emulator, failing if none is found.
+#[cfg(not(windows))]
+fn detect_terminal() -> io::Result<TerminalEmulator> {
+    crate::detect()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no terminal emulator found"))
+}
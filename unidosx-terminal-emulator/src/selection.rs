@@ -0,0 +1,202 @@
+//! Selection among every installed terminal emulator.
+//!
+//! Testing matrices and demo setups may want to exercise every installed terminal emulator instead of the default
+//! one, [`select_terminal`] rotates through them or picks one at random. [`detect_all`] lists them, e.g. for a settings
+//! dialog.
+
+use std::{
+    hash::{BuildHasher, RandomState},
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{PROBES, TerminalEmulator, detect, environment, filesystem};
+
+/// How long a terminal emulator benchmarked by [`SelectionStrategy::Fastest`] may take to start.
+#[cfg(feature = "benchmark")]
+const BENCHMARK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// The number of selections made with [`SelectionStrategy::RoundRobin`] during the current process.
+static ROUND_ROBIN: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+/// How [`select_terminal`] chooses among the installed terminal emulators.
+pub enum SelectionStrategy {
+    /// The default terminal emulator, as returned by [`detect`].
+    #[default]
+    Default,
+    /// Each installed terminal emulator in turn, in order of preference, rotating on every selection made during the
+    /// current process.
+    RoundRobin,
+    /// An installed terminal emulator at random, each one with the same probability.
+    Random,
+    /// An installed terminal emulator at random, with a probability proportional to its weight.
+    Weighted {
+        /// The weight of each terminal emulator, by name (e.g. `kitty`).
+        weights: Vec<(String, u32)>,
+        /// The weight of the terminal emulators not listed in `weights`, `0` to never select them.
+        default_weight: u32,
+    },
+    /// The installed terminal emulator with the shortest startup time, measured with
+    /// [`benchmark_candidates`](crate::benchmark_candidates). The installed terminal emulators are benchmarked on the
+    /// first selection if none was benchmarked yet, and the ones that were not benchmarked are selected last.
+    #[cfg(feature = "benchmark")]
+    Fastest,
+}
+
+/// Selects an installed terminal emulator using `strategy`.
+///
+/// Terminal emulators that can not open a window in the current session (see
+/// [`TerminalEmulator::check_environment`]) are only selected if no other one is installed.
+///
+/// Returns `None` if no terminal emulator is installed, or if every installed terminal emulator has a weight of `0`.
+pub fn select_terminal(strategy: &SelectionStrategy) -> Option<TerminalEmulator> {
+    if *strategy == SelectionStrategy::Default {
+        return detect();
+    }
+
+    #[cfg(feature = "benchmark")]
+    if *strategy == SelectionStrategy::Fastest && crate::benchmark::is_empty() {
+        crate::benchmark_candidates(BENCHMARK_TIMEOUT);
+    }
+
+    let mut candidates = installed_terminals();
+
+    // Terminal emulators missing an environment variable they need are only selected if no other one is installed.
+    if candidates
+        .iter()
+        .any(|terminal| terminal.check_environment().is_ok())
+    {
+        candidates.retain(|terminal| terminal.check_environment().is_ok());
+    }
+
+    let index = pick(strategy, &candidates, next_round_robin(), random())?;
+
+    Some(candidates.swap_remove(index))
+}
+
+/// Returns every installed terminal emulator, in order of preference, each one tagged with the detection method that
+/// found it first.
+///
+/// Runs every enabled detection method and searches PATH for every terminal emulator of the enabled hardcoded lists.
+/// Terminal emulators resolving to the same executable (e.g. `x-terminal-emulator` linking to `konsole`) are only
+/// returned once. Meant for settings dialogs letting the user choose among the installed terminal emulators.
+///
+/// Only the locked terminal emulator is returned if the system-wide configuration file locks it.
+pub fn detect_all() -> Vec<TerminalEmulator> {
+    let mut resolved: Vec<PathBuf> = Vec::new();
+
+    environment::snapshot(|| {
+        found_terminals()
+            .into_iter()
+            .filter(|terminal| {
+                let path = filesystem::canonicalize(&terminal.path)
+                    .unwrap_or_else(|_| terminal.path.clone());
+
+                if resolved.contains(&path) {
+                    return false;
+                }
+
+                resolved.push(path);
+                true
+            })
+            .collect()
+    })
+}
+
+/// Returns every terminal emulator found by the detection methods, in order of preference and without duplicates.
+///
+/// Only the locked terminal emulator is returned if the system-wide configuration file locks it.
+pub(crate) fn installed_terminals() -> Vec<TerminalEmulator> {
+    environment::snapshot(|| {
+        let mut installed: Vec<TerminalEmulator> = Vec::new();
+
+        for terminal in found_terminals() {
+            if !installed.contains(&terminal) {
+                installed.push(terminal);
+            }
+        }
+
+        installed
+    })
+}
+
+/// Returns every terminal emulator found by the detection methods, in order of preference and with duplicates.
+fn found_terminals() -> Vec<TerminalEmulator> {
+    #[cfg(feature = "config-file")]
+    if crate::is_terminal_locked() {
+        return crate::probe_config_file().into_iter().collect();
+    }
+
+    PROBES
+        .iter()
+        .filter_map(|(_, probe)| probe().ok())
+        .chain(crate::probes::find_hardcoded())
+        .collect()
+}
+
+/// Returns the index of the candidate chosen by `strategy`, given the round-robin counter and a random number.
+pub(crate) fn pick(
+    strategy: &SelectionStrategy,
+    candidates: &[TerminalEmulator],
+    round_robin: usize,
+    random: u64,
+) -> Option<usize> {
+    if candidates.is_empty() {
+        return None;
+    }
+
+    match strategy {
+        SelectionStrategy::Default => Some(0),
+        SelectionStrategy::RoundRobin => Some(round_robin % candidates.len()),
+        SelectionStrategy::Random => Some((random % candidates.len() as u64) as usize),
+        SelectionStrategy::Weighted {
+            weights,
+            default_weight,
+        } => {
+            let weight = |terminal: &TerminalEmulator| {
+                weights
+                    .iter()
+                    .find(|(name, _)| name.as_str() == terminal.name())
+                    .map_or(*default_weight, |&(_, weight)| weight)
+            };
+            let total: u64 = candidates
+                .iter()
+                .map(|terminal| u64::from(weight(terminal)))
+                .sum();
+
+            if total == 0 {
+                return None;
+            }
+
+            let mut target = random % total;
+
+            candidates.iter().position(|terminal| {
+                let weight = u64::from(weight(terminal));
+
+                if target < weight {
+                    return true;
+                }
+
+                target -= weight;
+                false
+            })
+        }
+        #[cfg(feature = "benchmark")]
+        SelectionStrategy::Fastest => (0..candidates.len()).min_by_key(|&index| {
+            let startup = crate::benchmark::startup_time(&candidates[index].path);
+
+            (startup.is_none(), startup)
+        }),
+    }
+}
+
+/// Advances the round-robin counter, returning its previous value.
+fn next_round_robin() -> usize {
+    ROUND_ROBIN.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Returns a random number, seeded by the randomly keyed hasher of the standard library.
+fn random() -> u64 {
+    RandomState::new().hash_one(std::time::SystemTime::now())
+}
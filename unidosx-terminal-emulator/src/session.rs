@@ -0,0 +1,359 @@
+//! Session environment checks and propagation.
+//!
+//! Processes started from cron, systemd services or SSH sessions usually lack the variables connecting them to the
+//! graphical session, so terminal emulators launched from them fail to open a window, often silently. Launches needing
+//! a graphical session are checked for one beforehand, and the session variables are passed explicitly to the launched
+//! terminal emulator.
+//!
+//! [`has_graphical_session`] and [`is_attached_to_tty`] let callers decide beforehand whether a terminal emulator can
+//! be opened at all, or whether the current process already has a terminal.
+
+use std::{
+    io::{self, IsTerminal},
+    sync::{Mutex, MutexGuard},
+};
+
+use crate::{CommandSpec, environment};
+
+/// The environment variables propagated to graphical launches by default.
+pub const DEFAULT_PROPAGATED_VARS: &[&str] = &[
+    "DISPLAY",
+    "WAYLAND_DISPLAY",
+    "XAUTHORITY",
+    "DBUS_SESSION_BUS_ADDRESS",
+];
+
+/// The environment variables propagated to graphical launches, `None` for [`DEFAULT_PROPAGATED_VARS`].
+static PROPAGATED: Mutex<Option<Vec<String>>> = Mutex::new(None);
+
+/// Locks the propagated variables, recovering them if a thread panicked while holding them.
+fn propagated() -> MutexGuard<'static, Option<Vec<String>>> {
+    PROPAGATED
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Replaces the environment variables propagated to the terminal emulators of graphical launches.
+///
+/// The variables set in the environment read by the detection methods (see [`Environment`](crate::Environment)) are
+/// passed explicitly to the launched terminal emulator, defaults to [`DEFAULT_PROPAGATED_VARS`].
+pub fn set_propagated_environment<I, S>(vars: I)
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    *propagated() = Some(vars.into_iter().map(Into::into).collect());
+}
+
+/// Returns the environment variables propagated to the terminal emulators of graphical launches.
+pub fn propagated_environment() -> Vec<String> {
+    propagated().clone().unwrap_or_else(|| {
+        DEFAULT_PROPAGATED_VARS
+            .iter()
+            .map(|&var| var.to_owned())
+            .collect()
+    })
+}
+
+#[cfg(windows)]
+#[link(name = "kernel32")]
+unsafe extern "system" {
+    fn ProcessIdToSessionId(process_id: u32, session_id: *mut u32) -> i32;
+}
+
+/// Verifies that a graphical session is available, failing with an error explaining how to provide one otherwise.
+///
+/// The error has the [`io::ErrorKind::NotConnected`] kind.
+pub fn check_graphical_session() -> io::Result<()> {
+    if has_graphical_session() {
+        return Ok(());
+    }
+
+    #[cfg(windows)]
+    let message =
+        String::from("no graphical session: the process runs in session 0, as services do");
+
+    #[cfg(target_os = "macos")]
+    let message = String::from(
+        "no graphical session: the process does not run in the Aqua session of a logged-in user (e.g. over SSH)",
+    );
+
+    #[cfg(not(any(windows, target_os = "macos")))]
+    let message = {
+        let mut message = String::from(
+            "no graphical session: neither DISPLAY nor WAYLAND_DISPLAY is set, processes started from cron or \
+             systemd services must import them (e.g. `systemctl --user import-environment DISPLAY WAYLAND_DISPLAY`)",
+        );
+
+        // Set by the login manager, so a graphical session exists but its variables were not passed down.
+        if let Some(session_type) = environment::var_os("XDG_SESSION_TYPE")
+            .filter(|session_type| session_type == "x11" || session_type == "wayland")
+        {
+            message.push_str(&format!(
+                ", although XDG_SESSION_TYPE is {}",
+                session_type.to_string_lossy()
+            ));
+        }
+
+        message
+    };
+
+    Err(io::Error::new(io::ErrorKind::NotConnected, message))
+}
+
+/// Sets the propagated environment variables that are set in the active environment on `spec`.
+pub(crate) fn propagate(spec: CommandSpec) -> CommandSpec {
+    propagated_environment()
+        .into_iter()
+        .filter_map(|var| environment::var_os(&var).map(|value| (var, value)))
+        .fold(spec, |spec, (var, value)| spec.env(var, value))
+}
+
+/// Returns `true` if the current process can open windows, so spawning a terminal emulator is possible.
+///
+/// On Linux and BSD, `DISPLAY` or `WAYLAND_DISPLAY` must be set (see [`check_graphical_session`] for why they may not
+/// be). On macOS, the process must run in the Aqua session of a logged-in user (`launchctl managername`), which SSH
+/// sessions and daemons do not. On Windows, it must run in an interactive session, services run in session 0.
+pub fn has_graphical_session() -> bool {
+    #[cfg(windows)]
+    {
+        let mut session_id = 0;
+
+        // SAFETY: `session_id` is a valid pointer to a `u32` for the duration of the call.
+        let found = unsafe { ProcessIdToSessionId(std::process::id(), &mut session_id) } != 0;
+
+        !found || session_id != 0
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        match CommandSpec::new("launchctl")
+            .arg("managername")
+            .output_within(std::time::Duration::from_secs(1))
+        {
+            Some(output) if output.status.success() => {
+                String::from_utf8_lossy(&output.stdout).trim() == "Aqua"
+            }
+            _ => ["SSH_CONNECTION", "SSH_TTY"]
+                .into_iter()
+                .all(|var| environment::var_os(var).is_none()),
+        }
+    }
+
+    #[cfg(not(any(windows, target_os = "macos")))]
+    {
+        ["DISPLAY", "WAYLAND_DISPLAY"]
+            .into_iter()
+            .any(|var| environment::var_os(var).is_some_and(|value| !value.is_empty()))
+    }
+}
+
+/// Returns `true` if the standard input and output of the current process are both terminals, so a terminal
+/// application can run without opening a terminal emulator.
+///
+/// Processes started from a desktop file, a file manager or a service have none, see
+/// [`relaunch_in_terminal_if_needed`](crate::relaunch_in_terminal_if_needed).
+pub fn is_attached_to_tty() -> bool {
+    io::stdin().is_terminal() && io::stdout().is_terminal()
+}
+
+/// The environment variables terminal emulators need to open a window, by executable name.
+///
+/// Wayland-only terminal emulators need `WAYLAND_DISPLAY`, and X11-only ones `DISPLAY`.
+#[cfg(all(unix, not(target_os = "macos")))]
+const REQUIRED_VARS: &[(&str, &[&str])] = &[
+    ("foot", &["WAYLAND_DISPLAY"]),
+    ("xterm", &["DISPLAY"]),
+    ("rxvt", &["DISPLAY"]),
+    ("urxvt", &["DISPLAY"]),
+    ("aterm", &["DISPLAY"]),
+    ("eterm", &["DISPLAY"]),
+    ("mrxvt", &["DISPLAY"]),
+    ("st", &["DISPLAY"]),
+];
+
+/// How long connecting to a remote X11 display may take.
+#[cfg(all(unix, not(target_os = "macos")))]
+const X11_TCP_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(200);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The address of an X11 display.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub(crate) enum X11Address {
+    /// A local display, listening on a UNIX socket.
+    Unix(std::path::PathBuf),
+    /// A remote display (e.g. forwarded through SSH), listening on a TCP port.
+    Tcp(String, u16),
+}
+
+/// Returns the environment variables the terminal emulator at `path` needs to open a window.
+pub(crate) fn required_vars(path: &std::path::Path) -> &'static [&'static str] {
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        crate::lookup::executable_name(path)
+            .and_then(|name| REQUIRED_VARS.iter().find(|&&(known, _)| known == name))
+            .map_or(&[], |&(_, vars)| vars)
+    }
+
+    #[cfg(not(all(unix, not(target_os = "macos"))))]
+    {
+        let _ = path;
+        &[]
+    }
+}
+
+/// Verifies that the environment variables needed by the terminal emulator at `path` are set and name a live
+/// display, failing with an error naming the first missing one otherwise.
+///
+/// `WAYLAND_DISPLAY` must name a live Wayland socket, see [`wayland_socket_alive`], and `DISPLAY` a live X11 display,
+/// see [`x11_display_alive`]. The error has the [`io::ErrorKind::NotConnected`] kind.
+pub(crate) fn check_required_vars(path: &std::path::Path) -> io::Result<()> {
+    let name = crate::lookup::executable_name(path).unwrap_or_default();
+
+    for &var in required_vars(path) {
+        let Some(value) = environment::var_os(var).filter(|value| !value.is_empty()) else {
+            return Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                format!("{name} needs {var}, which is not set"),
+            ));
+        };
+
+        if !display_alive(var) {
+            return Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                format!(
+                    "{name} needs {var}, but {} accepts no connections",
+                    value.to_string_lossy()
+                ),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns `true` if the display named by the environment variable `var` accepts connections, or if `var` does not
+/// name a display.
+fn display_alive(var: &str) -> bool {
+    #[cfg(all(unix, not(target_os = "macos")))]
+    match var {
+        "WAYLAND_DISPLAY" => return wayland_socket_alive(),
+        "DISPLAY" => return x11_display_alive(),
+        _ => {}
+    }
+
+    let _ = var;
+    true
+}
+
+/// Returns `true` if the terminal emulator at `path` can open a window in the current session, see
+/// [`check_required_vars`].
+pub(crate) fn can_display(path: &std::path::Path) -> bool {
+    check_required_vars(path).is_ok()
+}
+
+/// Returns `true` if `WAYLAND_DISPLAY` names a Wayland socket accepting connections.
+///
+/// Relative names are resolved against `XDG_RUNTIME_DIR`, as done by `libwayland-client`.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub(crate) fn wayland_socket_alive() -> bool {
+    let Some(display) =
+        environment::var_os("WAYLAND_DISPLAY").filter(|display| !display.is_empty())
+    else {
+        return false;
+    };
+    let display = std::path::PathBuf::from(display);
+
+    let socket = if display.is_absolute() {
+        display
+    } else {
+        match crate::xdg::absolute(environment::var_os("XDG_RUNTIME_DIR")) {
+            Some(runtime_dir) => runtime_dir.join(display),
+            None => return false,
+        }
+    };
+
+    std::os::unix::net::UnixStream::connect(socket).is_ok()
+}
+
+/// Returns `true` if `DISPLAY` names an X11 display accepting connections.
+///
+/// Only the socket is checked, no X11 connection is set up, so displays refusing the client for lack of authorization
+/// are still considered alive.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub(crate) fn x11_display_alive() -> bool {
+    let Some(address) = environment::var("DISPLAY").and_then(|display| parse_display(&display))
+    else {
+        return false;
+    };
+
+    match address {
+        X11Address::Unix(socket) => std::os::unix::net::UnixStream::connect(socket).is_ok(),
+        X11Address::Tcp(host, port) => {
+            std::net::ToSocketAddrs::to_socket_addrs(&(host.as_str(), port))
+                .into_iter()
+                .flatten()
+                .any(|address| {
+                    std::net::TcpStream::connect_timeout(&address, X11_TCP_TIMEOUT).is_ok()
+                })
+        }
+    }
+}
+
+/// Parses a `DISPLAY` value (`[host]:display[.screen]`, or the path of a socket named `<name>:display`).
+///
+/// Local displays (no host, or `unix`) listen on `/tmp/.X11-unix/X<display>`, remote ones on TCP port
+/// `6000 + display`.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub(crate) fn parse_display(display: &str) -> Option<X11Address> {
+    let (host, number) = display.rsplit_once(':')?;
+    let number: u16 = number
+        .split_once('.')
+        .map_or(number, |(number, _)| number)
+        .parse()
+        .ok()?;
+
+    // The socket of launchd-started X servers is named after the whole value.
+    if host.starts_with('/') {
+        return Some(X11Address::Unix(display.into()));
+    }
+
+    match host {
+        "" | "unix" => Some(X11Address::Unix(format!("/tmp/.X11-unix/X{number}").into())),
+        host => Some(X11Address::Tcp(
+            host.trim_start_matches('[')
+                .trim_end_matches(']')
+                .to_owned(),
+            6000u16.checked_add(number)?,
+        )),
+    }
+}
+
+/// Returns `true` if the process runs on a bare Linux virtual console, outside of any graphical session.
+pub(crate) fn on_bare_console() -> bool {
+    if has_graphical_session() || crate::filesystem::is_offline() {
+        return false;
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        std::fs::read_link("/proc/self/fd/0")
+            .ok()
+            .and_then(|tty| tty.to_str().map(is_virtual_console))
+            .unwrap_or(false)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        false
+    }
+}
+
+/// Returns `true` if the TTY device is a Linux virtual console (`/dev/ttyN` or `/dev/console`).
+#[cfg(any(test, target_os = "linux"))]
+pub(crate) fn is_virtual_console(tty: &str) -> bool {
+    tty == "/dev/console"
+        || tty
+            .strip_prefix("/dev/tty")
+            .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+}
@@ -0,0 +1,193 @@
+//! Shell detection and command composition for shell-wrapped launches.
+
+use std::ffi::{OsStr, OsString};
+
+use crate::CommandSpec;
+#[cfg(windows)]
+use crate::lookup::find_executable;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// Command interpreters used to wrap launched commands.
+pub enum Shell {
+    /// The POSIX shell (`sh`).
+    Sh,
+    /// PowerShell 7 or later (`pwsh`).
+    PowerShell,
+    /// Windows PowerShell 5.1 (`powershell`).
+    WindowsPowerShell,
+    /// The Windows command processor (`cmd`).
+    Cmd,
+}
+
+impl Shell {
+    /// Detects the preferred command interpreter of the user.
+    ///
+    /// On Windows, PowerShell 7 is preferred over Windows PowerShell, falling back to `cmd`. Other platforms always
+    /// use `sh`.
+    pub fn detect() -> Self {
+        #[cfg(windows)]
+        {
+            if find_executable("pwsh.exe").is_some() {
+                Self::PowerShell
+            } else if find_executable("powershell.exe").is_some() {
+                Self::WindowsPowerShell
+            } else {
+                Self::Cmd
+            }
+        }
+
+        #[cfg(not(windows))]
+        {
+            Self::Sh
+        }
+    }
+
+    /// Returns the program name of the command interpreter.
+    pub fn program(&self) -> &'static str {
+        match self {
+            Self::Sh => "sh",
+            Self::PowerShell => "pwsh",
+            Self::WindowsPowerShell => "powershell",
+            Self::Cmd => "cmd",
+        }
+    }
+
+    /// Quotes an argument so the command interpreter reads it back unchanged.
+    ///
+    /// Arguments that are not valid Unicode are kept unchanged by `sh`, the other command interpreters only read
+    /// Unicode.
+    pub fn quote(&self, arg: &OsStr) -> OsString {
+        let arg_str = arg.to_string_lossy();
+
+        match self {
+            Self::Sh => quote(arg),
+            Self::PowerShell | Self::WindowsPowerShell => {
+                format!("'{}'", arg_str.replace('\'', "''")).into()
+            }
+            Self::Cmd => format!("\"{}\"", arg_str.replace('"', "\"\"")).into(),
+        }
+    }
+
+    /// Composes a script that runs `program` with `args`.
+    pub fn compose<I, S>(&self, program: &OsStr, args: I) -> OsString
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let mut script = OsString::from(match self {
+            Self::Sh => "exec ",
+            Self::PowerShell | Self::WindowsPowerShell => "& ",
+            Self::Cmd => "",
+        });
+        script.push(self.quote(program));
+
+        for arg in args {
+            script.push(" ");
+            script.push(self.quote(arg.as_ref()));
+        }
+
+        script
+    }
+
+    /// Builds a command that runs `program` with `args` through the command interpreter, which then stays open instead
+    /// of exiting, keeping the console window open (`cmd /k`, `pwsh -NoExit`).
+    pub fn hold<I, S>(&self, program: &OsStr, args: I) -> CommandSpec
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        match self {
+            Self::Sh => CommandSpec::new("sh")
+                .args(["-c", r#""$@"; exec "${SHELL:-sh}""#, "sh"])
+                .arg(program)
+                .args(args),
+            Self::PowerShell | Self::WindowsPowerShell => CommandSpec::new(self.program())
+                .args(["-NoLogo", "-NoExit", "-Command"])
+                .arg(self.compose(program, args)),
+            Self::Cmd => CommandSpec::new("cmd").arg("/k").arg(program).args(args),
+        }
+    }
+
+    /// Builds a command that runs `script` through the command interpreter.
+    pub fn wrap<S: AsRef<OsStr>>(&self, script: S) -> CommandSpec {
+        let script = script.as_ref();
+        let spec = CommandSpec::new(self.program());
+
+        match self {
+            Self::Sh => spec.args([OsStr::new("-c"), script]),
+            Self::PowerShell | Self::WindowsPowerShell => {
+                spec.args([OsStr::new("-NoLogo"), OsStr::new("-Command"), script])
+            }
+            Self::Cmd => spec.args([OsStr::new("/C"), script]),
+        }
+    }
+}
+
+/// Quotes an argument for a POSIX shell using single quotes.
+///
+/// On UNIX, arguments that are not valid Unicode are quoted byte for byte.
+pub(crate) fn quote(arg: &OsStr) -> OsString {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::{OsStrExt, OsStringExt};
+
+        let mut quoted = vec![b'\''];
+
+        for &byte in arg.as_bytes() {
+            match byte {
+                b'\'' => quoted.extend_from_slice(br"'\''"),
+                byte => quoted.push(byte),
+            }
+        }
+
+        quoted.push(b'\'');
+        OsString::from_vec(quoted)
+    }
+
+    #[cfg(not(unix))]
+    {
+        format!("'{}'", arg.to_string_lossy().replace('\'', r"'\''")).into()
+    }
+}
+
+/// Quotes an argument for a POSIX shell only if it contains characters with a special meaning.
+pub(crate) fn quote_if_needed(arg: &OsStr) -> String {
+    let safe = arg.to_str().is_some_and(|arg| {
+        !arg.is_empty()
+            && arg
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-_./:=+,@%".contains(c))
+    });
+
+    if safe {
+        arg.to_string_lossy().into_owned()
+    } else {
+        quote(arg).to_string_lossy().into_owned()
+    }
+}
+
+/// Composes a single `sh -c` command line that runs `program` with `args`, preserving the argument boundaries.
+///
+/// Terminal emulators taking the whole command as one string after `-e` (e.g. xfce4-terminal) split it like a shell,
+/// which the launcher handles automatically for [`ExecutionSyntax::SingleE`](crate::ExecutionSyntax::SingleE). Use it
+/// when building such a command manually, e.g. `xfce4-terminal -e "$(quote_command_line(...))"`.
+///
+/// Every argument is single-quoted, so spaces, quotes, `$`, backslashes, newlines and non-ASCII characters are kept
+/// unchanged, byte for byte on UNIX. The arguments are then passed to `sh` as positional parameters and expanded
+/// through `"$@"`, so they are never reinterpreted by the shell.
+pub fn quote_command_line<P, I, S>(program: P, args: I) -> OsString
+where
+    P: AsRef<OsStr>,
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let mut line = OsString::from(r#"sh -c 'exec "$@"' sh "#);
+    line.push(quote(program.as_ref()));
+
+    for arg in args {
+        line.push(" ");
+        line.push(quote(arg.as_ref()));
+    }
+
+    line
+}
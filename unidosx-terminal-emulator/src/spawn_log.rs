@@ -0,0 +1,46 @@
+//! Logging of the final command of each launch.
+//!
+//! Every launch emits one canonical event at spawn time, the backend followed by the command line with its working
+//! directory, environment changes and arguments (e.g. `Exec: env DISPLAY=:0 kitty -- vim notes.txt`). It is the same
+//! text [`Launcher::dry_run`](crate::Launcher::dry_run) returns without spawning anything, so what a launch said it
+//! would do can be diffed against what it did.
+//!
+//! This crate has no dependencies: events are handed to the logger set with [`set_spawn_logger`], which can forward
+//! them to `tracing`, `log` or a file.
+
+use std::sync::{Mutex, MutexGuard};
+
+use crate::{CommandSpec, LaunchBackend};
+
+/// The logger receiving the spawn events, if any.
+static LOGGER: Mutex<Option<fn(&str)>> = Mutex::new(None);
+
+/// Locks the logger, recovering it if a thread panicked while holding it.
+fn logger() -> MutexGuard<'static, Option<fn(&str)>> {
+    LOGGER
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Sets the logger receiving the event of every launch right before it is spawned, or removes it with `None`.
+///
+/// No logger is set by default. The event is the line [`Launcher::dry_run`](crate::Launcher::dry_run) returns for the
+/// same launch, e.g. `set_spawn_logger(Some(|event| tracing::info!(target: "unidosx", "{event}")))`.
+pub fn set_spawn_logger(logger: Option<fn(&str)>) {
+    *self::logger() = logger;
+}
+
+/// Formats the canonical event of spawning `spec` through `backend`.
+pub(crate) fn event(spec: &CommandSpec, backend: LaunchBackend) -> String {
+    format!("{backend}: {spec}")
+}
+
+/// Hands the event of spawning `spec` through `backend` to the logger, if one is set.
+pub(crate) fn log(spec: &CommandSpec, backend: LaunchBackend) {
+    // Copied out so the logger may itself launch or replace the logger without deadlocking.
+    let logger = *logger();
+
+    if let Some(logger) = logger {
+        logger(&event(spec, backend));
+    }
+}
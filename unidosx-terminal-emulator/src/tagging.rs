@@ -0,0 +1,96 @@
+//! Opt-in tagging of the launched processes.
+//!
+//! Applications that need to focus or close the window they opened must find the process behind it later. When enabled
+//! with [`tag_launches`], every launch sets [`LAUNCH_ID_VAR`] to a unique launch ID in the environment of the spawned
+//! process, inherited by its children, and [`find_launched`] finds the processes carrying it.
+
+use std::{
+    hash::{BuildHasher, RandomState},
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    time::SystemTime,
+};
+
+use crate::CommandSpec;
+
+/// The environment variable holding the launch ID of the processes spawned while tagging is enabled.
+pub const LAUNCH_ID_VAR: &str = "UNIDOSX_LAUNCH_ID";
+
+/// Whether launches are tagged with a launch ID.
+static TAG: AtomicBool = AtomicBool::new(false);
+
+/// The number of launch IDs generated during the current process.
+static GENERATED: AtomicU64 = AtomicU64::new(0);
+
+/// Starts (or stops) tagging the launched processes with a launch ID.
+///
+/// Disabled by default. When enabled, every launch made through [`Launcher::launch`](crate::Launcher::launch) and
+/// [`launch_with_fallback`](crate::launch_with_fallback) sets [`LAUNCH_ID_VAR`] to a new launch ID, returned in
+/// [`LaunchResult::launch_id`](crate::LaunchResult::launch_id). Terminal emulators spawned by a Wayland compositor
+/// (see [`Launcher::with_compositor`](crate::Launcher::with_compositor)) do not inherit it.
+pub fn tag_launches(enabled: bool) {
+    TAG.store(enabled, Ordering::Relaxed);
+}
+
+/// Tags `spec` with a new launch ID if tagging is enabled.
+pub(crate) fn tag(spec: CommandSpec) -> CommandSpec {
+    if TAG.load(Ordering::Relaxed) {
+        spec.env(LAUNCH_ID_VAR, new_launch_id())
+    } else {
+        spec
+    }
+}
+
+/// Generates a new launch ID, formatted as a random (version 4) UUID.
+pub(crate) fn new_launch_id() -> String {
+    let count = GENERATED.fetch_add(1, Ordering::Relaxed);
+    let state = RandomState::new();
+    let high = state.hash_one((std::process::id(), count, SystemTime::now()));
+    let low = state.hash_one((high, count));
+
+    // The version and variant bits of a random UUID.
+    let high = (high & !0xf000) | 0x4000;
+    let low = (low & !(0b11 << 62)) | (0b10 << 62);
+
+    format!(
+        "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+        high >> 32,
+        (high >> 16) & 0xffff,
+        high & 0xffff,
+        low >> 48,
+        low & 0xffff_ffff_ffff
+    )
+}
+
+/// Finds the processes launched with the launch ID `id`, returning their process IDs in ascending order.
+///
+/// The launched process and its children carry the launch ID, unless they cleared their environment. Only the
+/// processes readable by the current user are inspected. Only supported on Linux, other platforms find nothing.
+pub fn find_launched(id: &str) -> Vec<u32> {
+    #[cfg(target_os = "linux")]
+    {
+        let marker = format!("{LAUNCH_ID_VAR}={id}");
+        let mut found: Vec<u32> = std::fs::read_dir("/proc")
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|entry| {
+                let pid: u32 = entry.file_name().to_str()?.parse().ok()?;
+                let environ = std::fs::read(entry.path().join("environ")).ok()?;
+
+                environ
+                    .split(|&byte| byte == 0)
+                    .any(|var| var == marker.as_bytes())
+                    .then_some(pid)
+            })
+            .collect();
+
+        found.sort_unstable();
+        found
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = id;
+        Vec::new()
+    }
+}
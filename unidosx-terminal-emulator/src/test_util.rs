@@ -0,0 +1,103 @@
+//! Fake detection results for the tests of crates depending on this one.
+//!
+//! [`FakeTerminal`] builds terminal emulators without touching the filesystem, and [`FakeTerminal::scope`] or
+//! [`override_detect`] force [`detect`](crate::detect) to return a given result on the current thread, so launch logic
+//! can be tested without a real terminal emulator or mutating the process environment.
+
+use std::{cell::RefCell, path::PathBuf};
+
+use crate::{DetectionMethod, ExecutionSyntax, TerminalEmulator};
+
+thread_local! {
+    /// The result forced on [`detect`](crate::detect) on the current thread, if any.
+    static OVERRIDE: RefCell<Option<Option<TerminalEmulator>>> = const { RefCell::new(None) };
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A builder for fake terminal emulators.
+pub struct FakeTerminal {
+    /// The terminal emulator being built.
+    terminal: TerminalEmulator,
+}
+
+impl FakeTerminal {
+    /// Creates a fake terminal emulator named `name`, as if it was set through `TERMINAL_EMULATOR`.
+    ///
+    /// Known terminal emulators use their usual execution syntax, other ones the default one. The path is the name
+    /// itself and is never checked.
+    pub fn new(name: &'static str) -> Self {
+        let execution_syntax = crate::probes::lookup_known(name)
+            .map(|(_, syntax)| syntax)
+            .unwrap_or_default();
+
+        Self {
+            terminal: TerminalEmulator {
+                command_line: name.into(),
+                execution_syntax,
+                path: PathBuf::from(name),
+                method: DetectionMethod::EnvironmentVariable,
+                raw: None,
+            },
+        }
+    }
+
+    /// Sets the execution syntax.
+    pub fn execution_syntax(mut self, execution_syntax: ExecutionSyntax) -> Self {
+        self.terminal.execution_syntax = execution_syntax;
+        self
+    }
+
+    /// Sets the path to the executable.
+    pub fn path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.terminal.path = path.into();
+        self
+    }
+
+    /// Sets the detection method.
+    pub fn method(mut self, method: DetectionMethod) -> Self {
+        self.terminal.method = method;
+        self
+    }
+
+    /// Returns the fake terminal emulator.
+    pub fn build(self) -> TerminalEmulator {
+        self.terminal
+    }
+
+    /// Runs `f` with [`detect`](crate::detect) returning this terminal emulator on the current thread.
+    pub fn scope<R, F: FnOnce() -> R>(self, f: F) -> R {
+        override_detect(Some(self.build()), f)
+    }
+}
+
+impl From<FakeTerminal> for TerminalEmulator {
+    fn from(fake: FakeTerminal) -> Self {
+        fake.build()
+    }
+}
+
+/// Runs `f` with [`detect`](crate::detect) returning `result` on the current thread, `None` simulating an environment
+/// without any terminal emulator.
+///
+/// On Windows, `detect` always returns a terminal emulator, so `None` is ignored there. Scopes can be nested, the
+/// innermost result is used.
+pub fn override_detect<R, F: FnOnce() -> R>(result: Option<TerminalEmulator>, f: F) -> R {
+    /// Restores the previous override, even if `f` panics.
+    struct Restore(Option<Option<TerminalEmulator>>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            OVERRIDE.with(|current| *current.borrow_mut() = self.0.take());
+        }
+    }
+
+    let previous = OVERRIDE.with(|current| current.replace(Some(result)));
+    let _restore = Restore(previous);
+
+    f()
+}
+
+/// Returns the result forced on [`detect`](crate::detect) on the current thread, if any.
+pub(crate) fn overridden() -> Option<Option<TerminalEmulator>> {
+    OVERRIDE.with(|current| current.borrow().clone())
+}
@@ -1 +1,3628 @@
+use crate::*;
 
+#[test]
+fn gsettings_string_is_unquoted() {
+    assert_eq!(
+        probes::parse_gsettings_string("'kgx'\n"),
+        Some("kgx".to_owned())
+    );
+    assert_eq!(probes::parse_gsettings_string("''\n"), None);
+}
+
+#[test]
+fn gnome_exec_arg_keeps_unknown_flags() {
+    assert_eq!(
+        probes::exec_arg_syntax(Some("-x")),
+        ExecutionSyntax::CustomFlag {
+            flag: "-x",
+            single_argument: false
+        }
+    );
+    assert_eq!(probes::exec_arg_syntax(Some("-e")), ExecutionSyntax::E);
+    assert_eq!(
+        probes::exec_arg_syntax(Some("--")),
+        ExecutionSyntax::DoubleDash
+    );
+    assert_eq!(probes::exec_arg_syntax(None), ExecutionSyntax::Command);
+}
+
+#[test]
+fn kdeglobals_terminal_is_read_from_general_section() {
+    let contents =
+        "[KDE]\nTerminalApplication=xterm\n\n[General]\nTerminalApplication=konsole --separate\n";
+
+    assert_eq!(
+        probes::parse_kdeglobals_terminal(contents),
+        Some("konsole".to_owned())
+    );
+    assert_eq!(probes::parse_kdeglobals_terminal("[General]\n"), None);
+}
+
+#[test]
+fn known_terminals_have_their_syntax() {
+    assert_eq!(
+        probes::lookup_known("gnome-terminal"),
+        Some(("gnome-terminal", ExecutionSyntax::DoubleDash))
+    );
+    assert_eq!(
+        probes::lookup_known("kitty"),
+        Some(("kitty", ExecutionSyntax::Command))
+    );
+    assert_eq!(probes::lookup_known("not-a-terminal"), None);
+}
+
+#[test]
+fn terminal_quirks_give_the_syntax_of_aliases() {
+    // The Debian wrapper takes the `x-terminal-emulator` syntax, unlike gnome-terminal itself.
+    assert_eq!(
+        probes::lookup_known("gnome-terminal.wrapper"),
+        Some(("gnome-terminal", ExecutionSyntax::E))
+    );
+    assert_eq!(
+        probes::lookup_known("footclient"),
+        Some(("foot", ExecutionSyntax::Command))
+    );
+    assert_eq!(
+        quirks::lookup_desktop_id("org.gnome.Terminal.desktop"),
+        Some(("gnome-terminal", ExecutionSyntax::DoubleDash))
+    );
+    assert_eq!(
+        quirks::lookup_desktop_id("com.mitchellh.ghostty.desktop"),
+        Some(("ghostty", ExecutionSyntax::E))
+    );
+    assert_eq!(
+        quirks::lookup_desktop_id("org.example.Editor.desktop"),
+        None
+    );
+
+    let terminal = TerminalEmulator::new("/usr/bin/xfce4-terminal.wrapper", ExecutionSyntax::E);
+    assert_eq!(terminal.name(), "xfce4-terminal");
+}
+
+#[test]
+fn execution_syntaxes_expand_to_their_arguments() {
+    assert_eq!(ExecutionSyntax::DoubleDash.as_args(), ["--"]);
+    assert_eq!(ExecutionSyntax::SingleE.as_args(), ["-e"]);
+    assert!(ExecutionSyntax::Command.as_args().is_empty());
+    assert!(ExecutionSyntax::NativeApi.as_args().is_empty());
+    assert_eq!(ExecutionSyntax::E.as_arg(), Some("-e"));
+    assert_eq!(ExecutionSyntax::Command.as_arg(), None);
+}
+
+#[test]
+fn launcher_places_command_after_execution_syntax() {
+    let terminal = TerminalEmulator {
+        command_line: "xterm".into(),
+        execution_syntax: ExecutionSyntax::E,
+        path: "/usr/bin/xterm".into(),
+        method: DetectionMethod::HardcodedTraditional,
+        raw: None,
+    };
+
+    let launcher = Launcher::new(&terminal);
+    let mut cmd = launcher.command("htop");
+    cmd.arg("-d").arg("10");
+
+    assert_eq!(cmd.get_program(), "/usr/bin/xterm");
+    assert_eq!(
+        cmd.get_args().collect::<Vec<_>>(),
+        ["-e", "htop", "-d", "10"]
+    );
+}
+
+#[test]
+fn launcher_uses_console_for_native_api() {
+    let terminal = TerminalEmulator {
+        command_line: "".into(),
+        execution_syntax: ExecutionSyntax::NativeApi,
+        path: "".into(),
+        method: DetectionMethod::Windows,
+        raw: None,
+    };
+
+    assert_eq!(Launcher::new(&terminal), Launcher::AllocConsole);
+    assert!(build_command_in_terminal(&terminal).is_none());
+    assert_eq!(Launcher::AllocConsole.command("htop").get_program(), "htop");
+}
+
+#[test]
+fn single_argument_terminals_receive_composed_command() {
+    let terminal = TerminalEmulator {
+        command_line: "xfce4-terminal".into(),
+        execution_syntax: ExecutionSyntax::SingleE,
+        path: "/usr/bin/xfce4-terminal".into(),
+        method: DetectionMethod::HardcodedDesktopEnv,
+        raw: None,
+    };
+
+    let cmd = Launcher::new(&terminal).command_with_args("less", ["my file.txt"]);
+
+    assert_eq!(
+        cmd.get_args().collect::<Vec<_>>(),
+        ["-e", r#"sh -c 'exec "$@"' sh 'less' 'my file.txt'"#]
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn composed_command_preserves_argument_boundaries() {
+    let line = quote_command_line(
+        "printf",
+        [
+            "[%s]",
+            "two words",
+            "it's",
+            "$HOME",
+            "\"quoted\"",
+            "back\\slash",
+            "",
+            "line\nbreak",
+            "café ☕",
+        ],
+    );
+
+    let output = std::process::Command::new("sh")
+        .arg("-c")
+        .arg(line)
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "[two words][it's][$HOME][\"quoted\"][back\\slash][][line\nbreak][café ☕]"
+    );
+}
+
+#[cfg(all(unix, feature = "env-var"))]
+#[test]
+fn non_unicode_paths_and_arguments_are_kept() {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt, os::unix::fs::PermissionsExt};
+
+    let dir = std::env::temp_dir().join(format!("unidosx-non-unicode-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let terminal = dir.join(OsStr::from_bytes(b"term\xe9inal"));
+    std::fs::write(&terminal, "#!/bin/sh\n").unwrap();
+    std::fs::set_permissions(&terminal, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let found = [("TERMINAL_EMULATOR", terminal.as_os_str())]
+        .into_iter()
+        .collect::<environment::Environment>()
+        .scope(probes::check_env_var)
+        .unwrap();
+
+    assert_eq!(found.path(), terminal);
+    assert_eq!(found.execution_syntax(), ExecutionSyntax::default());
+
+    let line = quote_command_line(
+        OsStr::from_bytes(b"printf"),
+        [OsStr::from_bytes(b"[%s]"), OsStr::from_bytes(b"caf\xe9")],
+    );
+    let output = std::process::Command::new("sh")
+        .arg("-c")
+        .arg(line)
+        .output()
+        .unwrap();
+
+    assert_eq!(output.stdout, b"[caf\xe9]");
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn privileged_context_is_detected_from_ids() {
+    assert!(!privilege::is_privileged((1000, 1000, 1000, 1000), false));
+    assert!(privilege::is_privileged((1000, 0, 1000, 1000), false));
+    assert!(privilege::is_privileged((1000, 1000, 1000, 0), false));
+    assert!(!privilege::is_privileged((0, 0, 0, 0), false));
+    assert!(privilege::is_privileged((0, 0, 0, 0), true));
+}
+
+#[test]
+fn invocation_describes_the_launch() {
+    let terminal = TerminalEmulator {
+        command_line: "kitty".into(),
+        execution_syntax: ExecutionSyntax::Command,
+        path: "/usr/bin/kitty".into(),
+        method: DetectionMethod::HardcodedModern,
+        raw: None,
+    };
+
+    let invocation =
+        Launcher::new(&terminal).build_invocation("vim", ["notes.txt", "my notes.txt"]);
+
+    assert_eq!(invocation.program, "/usr/bin/kitty");
+    assert_eq!(invocation.args, ["vim", "notes.txt", "my notes.txt"]);
+    assert_eq!(invocation.cwd, None);
+    assert!(invocation.env.is_empty());
+    assert_eq!(
+        invocation.to_string(),
+        "/usr/bin/kitty vim notes.txt 'my notes.txt'"
+    );
+}
+
+#[test]
+fn custom_flags_are_placed_before_the_command() {
+    let launcher = |flag, single_argument| {
+        Launcher::new(&TerminalEmulator::new(
+            "/usr/bin/terminal",
+            ExecutionSyntax::CustomFlag {
+                flag,
+                single_argument,
+            },
+        ))
+    };
+
+    let invocation = launcher("-x", false).build_invocation("vim", ["notes.txt"]);
+    assert_eq!(invocation.args, ["-x", "vim", "notes.txt"]);
+
+    let invocation = launcher("+e", true).build_invocation("vim", ["notes.txt"]);
+    assert_eq!(
+        invocation.args,
+        ["+e", r#"sh -c 'exec "$@"' sh 'vim' 'notes.txt'"#]
+    );
+
+    let invocation = launcher("--command=", false).build_invocation("vim", ["notes.txt"]);
+    assert_eq!(
+        invocation.args,
+        [r#"--command=sh -c 'exec "$@"' sh 'vim' 'notes.txt'"#]
+    );
+
+    let syntax = ExecutionSyntax::CustomFlag {
+        flag: "--command=",
+        single_argument: true,
+    };
+    assert_eq!(syntax.id(), "single-flag:--command=");
+    assert_eq!(syntax.to_string(), "--command='[command]'");
+
+    #[cfg(any(not(windows), feature = "config-file"))]
+    {
+        assert_eq!(ExecutionSyntax::from_id(&syntax.id()), Some(syntax));
+        assert_eq!(ExecutionSyntax::from_id("flag:"), None);
+    }
+}
+
+#[cfg(all(unix, feature = "version-detection"))]
+#[test]
+fn gnome_terminal_syntax_follows_its_version() {
+    use std::{fs, os::unix::fs::PermissionsExt};
+
+    assert_eq!(
+        versions::parse_version("# GNOME Terminal 3.46.8 using VTE 0.70.6 +BIDI\n"),
+        Some(versions::Version(3, 46, 8))
+    );
+    assert_eq!(
+        versions::parse_version("# GNOME Terminal using VTE 0.44.2"),
+        Some(versions::Version(3, 20, 0))
+    );
+    assert_eq!(versions::parse_version("gnome-terminal"), None);
+
+    let root = std::env::temp_dir().join(format!("unidosx-gnome-versions-{}", std::process::id()));
+    let args = |version: &str| {
+        let dir = root.join(version);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("gnome-terminal");
+        fs::write(
+            &path,
+            format!("#!/bin/sh\necho '# GNOME Terminal {version}'\n"),
+        )
+        .unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let terminal = TerminalEmulator::new(&path, ExecutionSyntax::DoubleDash);
+        let command = build_command_in_terminal(&terminal).unwrap();
+        command
+            .get_args()
+            .map(|arg| arg.to_str().unwrap().to_owned())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(args("3.46.8"), ["--"]);
+    assert_eq!(args("3.18.3"), ["-e"]);
+    assert_eq!(
+        Launcher::new(&TerminalEmulator::new(
+            root.join("3.18.3/gnome-terminal"),
+            ExecutionSyntax::DoubleDash
+        ))
+        .build_invocation("vim", ["notes.txt"])
+        .args,
+        ["-e", r#"sh -c 'exec "$@"' sh 'vim' 'notes.txt'"#]
+    );
+
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[cfg(all(unix, feature = "version-detection", feature = "env-var"))]
+#[test]
+fn broken_versions_are_noted_in_the_report() {
+    use std::{fs, os::unix::fs::PermissionsExt};
+
+    assert_eq!(
+        versions::parse_version("alacritty 0.13.2 (bb8ea18e)"),
+        Some(versions::Version(0, 13, 2))
+    );
+    assert_eq!(
+        versions::parse_version("wezterm 20240203-110809-5046fc22"),
+        Some(versions::Version(20240203, 110809, 0))
+    );
+
+    let dir = std::env::temp_dir().join(format!("unidosx-broken-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("gnome-terminal");
+    fs::write(&path, "#!/bin/sh\necho 'GNOME Terminal 3.18.3'\n").unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let report = [("TERMINAL_EMULATOR", path.as_os_str())]
+        .into_iter()
+        .collect::<Environment>()
+        .scope(|| {
+            Detector::new()
+                .methods([DetectionMethod::EnvironmentVariable])
+                .detect_report()
+        });
+
+    assert_eq!(report.selected.unwrap().path, path);
+    assert_eq!(
+        report.notes,
+        ["gnome-terminal 3.18.3 does not understand `--`, using `-e '[command]'` instead"]
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn launch_options_become_terminal_flags_or_a_wrapper() {
+    let options = LaunchOptions::new()
+        .current_dir("/tmp")
+        .title("Logs")
+        .hold(true)
+        .env("LOG", "1");
+
+    let kitty = Launcher::new(&TerminalEmulator::new(
+        "/usr/bin/kitty",
+        ExecutionSyntax::Command,
+    ));
+    let spec = kitty.build_invocation_with_options("less", ["app.log"], &options);
+    assert_eq!(
+        spec.args,
+        [
+            "--directory",
+            "/tmp",
+            "--title",
+            "Logs",
+            "--hold",
+            "less",
+            "app.log"
+        ]
+    );
+    assert_eq!(spec.cwd.as_deref(), Some(Path::new("/tmp")));
+    assert_eq!(spec.env, [("LOG".into(), Some("1".into()))]);
+    assert_eq!(
+        kitty.build_invocation_with_options("less", ["app.log"], &LaunchOptions::new()),
+        kitty.build_invocation("less", ["app.log"])
+    );
+
+    // xterm has no flag for the working directory.
+    let xterm = Launcher::new(&TerminalEmulator::new("/usr/bin/xterm", ExecutionSyntax::E));
+    assert_eq!(
+        xterm
+            .build_invocation_with_options("less", ["app.log"], &options)
+            .args,
+        [
+            "-T",
+            "Logs",
+            "-hold",
+            "-e",
+            "sh",
+            "-c",
+            r#"cd -- "$1" || exit; shift; exec "$@""#,
+            "sh",
+            "/tmp",
+            "less",
+            "app.log"
+        ]
+    );
+
+    // The wrapper applies every option an unknown terminal emulator has no flag for.
+    let dir = std::env::temp_dir().canonicalize().unwrap();
+    let unknown = Launcher::Terminal {
+        program: "env".into(),
+        args: Vec::new(),
+        single_argument: false,
+    };
+    let output = unknown
+        .build_invocation_with_options("pwd", [""; 0], &options.current_dir(&dir))
+        .to_command()
+        .current_dir("/")
+        .stdin(std::process::Stdio::null())
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(output.status.success());
+    assert!(stdout.starts_with(&format!("\x1b]0;Logs\x07{}\n", dir.display())));
+    assert!(stdout.contains("[exited with status 0, press Enter to close]"));
+}
+
+#[test]
+fn exec_lines_are_split_like_desktop_entries() {
+    let parse = |exec| {
+        let spec = parse_exec_line(exec);
+        let mut words = vec![spec.program.into_string().unwrap()];
+        words.extend(spec.args.into_iter().map(|arg| arg.into_string().unwrap()));
+        words
+    };
+
+    assert_eq!(parse("konsole"), ["konsole"]);
+    assert_eq!(
+        parse("  kitty   --single-instance  "),
+        ["kitty", "--single-instance"]
+    );
+    assert_eq!(parse(""), [""]);
+    assert_eq!(parse("   "), [""]);
+
+    // Quotes group arguments and can be empty.
+    assert_eq!(
+        parse(r#""/opt/My Terminal/term" -e "" a"b c"d"#),
+        ["/opt/My Terminal/term", "-e", "", "ab cd"]
+    );
+    assert_eq!(
+        parse(r#"term "unterminated arg"#),
+        ["term", "unterminated arg"]
+    );
+
+    // Escapes of quoted arguments.
+    assert_eq!(
+        parse(r#"sh -c "echo \"hi\" \$HOME \`id\` \\\\ \n""#),
+        ["sh", "-c", "echo \"hi\" $HOME `id` \\ \n"]
+    );
+
+    // Escapes of string values, applied before the quotes.
+    assert_eq!(parse(r"term\sone\ttwo"), ["term", "one", "two"]);
+    assert_eq!(parse(r#""my\sterm" --flag"#), ["my term", "--flag"]);
+    assert_eq!(parse(r"C:\\bin\\term"), [r"C:\bin\term"]);
+
+    // Field codes.
+    assert_eq!(parse("foot %U"), ["foot"]);
+    assert_eq!(
+        parse("term --title=%c %i %f %F %u %k %d %D %n %N %v %m"),
+        ["term", "--title="]
+    );
+    assert_eq!(
+        parse("term --percent=100%% %%"),
+        ["term", "--percent=100%", "%"]
+    );
+    assert_eq!(parse(r#"term "%U" 50%"#), ["term", "50%"]);
+}
+
+#[test]
+fn exec_lines_round_trip() {
+    fn quote(arg: &str) -> String {
+        let mut quoted = String::from("\"");
+        for c in arg.chars() {
+            match c {
+                '"' | '`' | '$' | '\\' => quoted.push_str(&format!("\\\\{c}")),
+                '%' => quoted.push_str("%%"),
+                c => quoted.push(c),
+            }
+        }
+        quoted.push('"');
+        quoted
+    }
+
+    let commands: &[&[&str]] = &[
+        &["kitty"],
+        &["/opt/My Terminal/term", "-e", "htop"],
+        &["sh", "-c", "echo \"$HOME\" `id` 100% \\"],
+        &["term", "", " ", "\ttab"],
+    ];
+
+    for command in commands {
+        let exec: Vec<String> = command.iter().map(|arg| quote(arg)).collect();
+        let spec = parse_exec_line(&exec.join(" "));
+
+        assert_eq!(spec, CommandSpec::new(command[0]).args(&command[1..]));
+    }
+}
+
+#[test]
+fn command_spec_round_trips_through_command() {
+    let spec = CommandSpec::new("htop")
+        .args(["-d", "10"])
+        .current_dir("/tmp")
+        .env_remove("COLUMNS")
+        .env("TERM", "xterm-256color");
+
+    let cmd = spec.to_command();
+
+    assert_eq!(CommandSpec::from(&cmd), spec);
+    assert_eq!(
+        spec.to_string(),
+        "cd /tmp && env -u COLUMNS TERM=xterm-256color htop -d 10"
+    );
+}
+
+#[test]
+fn cmd_start_gets_an_empty_title() {
+    let spec = Launcher::CmdStart.build_invocation("htop", ["-d", "10"]);
+
+    assert_eq!(spec.program, "cmd");
+    assert_eq!(spec.args, ["/c", "start", "", "htop", "-d", "10"]);
+}
+
+#[test]
+fn windows_terminal_tab_targets_current_window() {
+    let launcher = Launcher::WindowsTerminalTab {
+        program: "wt.exe".into(),
+    };
+
+    let spec = launcher.build_invocation("pwsh", ["-NoExit"]);
+
+    assert_eq!(spec.program, "wt.exe");
+    assert_eq!(spec.args, ["-w", "0", "new-tab", "pwsh", "-NoExit"]);
+}
+
+#[test]
+fn shells_compose_quoted_scripts() {
+    let args = ["it's here", "\"x\""];
+
+    assert_eq!(
+        Shell::Sh.compose("ls".as_ref(), args),
+        r#"exec 'ls' 'it'\''s here' '"x"'"#
+    );
+    assert_eq!(
+        Shell::PowerShell.compose("ls".as_ref(), args),
+        r#"& 'ls' 'it''s here' '"x"'"#
+    );
+    assert_eq!(
+        Shell::Cmd.compose("dir".as_ref(), args),
+        r#""dir" "it's here" """x""""#
+    );
+    assert_eq!(
+        Shell::WindowsPowerShell.wrap("Get-Date").args,
+        ["-NoLogo", "-Command", "Get-Date"]
+    );
+}
+
+#[test]
+fn shells_hold_the_console_open() {
+    assert_eq!(
+        Shell::Cmd.hold("make".as_ref(), ["check"]),
+        CommandSpec::new("cmd").args(["/k", "make", "check"])
+    );
+    assert_eq!(
+        Shell::PowerShell.hold("make".as_ref(), ["it's"]),
+        CommandSpec::new("pwsh").args(["-NoLogo", "-NoExit", "-Command", "& 'make' 'it''s'"])
+    );
+
+    // Windows launches hold the console open through the detected command interpreter.
+    let options = LaunchOptions::new().hold(true);
+    let held = Shell::detect().hold("make".as_ref(), ["check"]);
+    let spec = Launcher::AllocConsole.build_invocation_with_options("make", ["check"], &options);
+    assert_eq!((spec.program, spec.args), (held.program, held.args));
+}
+
+#[test]
+fn virtual_terminal_switches_and_waits() {
+    let launcher = Launcher::VirtualTerminal {
+        program: "/usr/bin/openvt".into(),
+    };
+
+    let spec = launcher.build_invocation("htop", ["-d", "10"]);
+
+    assert_eq!(spec.args, ["-s", "-w", "--", "htop", "-d", "10"]);
+}
+
+#[test]
+fn virtual_consoles_are_recognized() {
+    assert!(session::is_virtual_console("/dev/tty2"));
+    assert!(session::is_virtual_console("/dev/console"));
+    assert!(!session::is_virtual_console("/dev/tty"));
+    assert!(!session::is_virtual_console("/dev/pts/3"));
+    assert!(!session::is_virtual_console("/dev/ttyS0"));
+}
+
+#[test]
+fn kmscon_runs_the_command_as_login_program() {
+    let terminal = TerminalEmulator {
+        command_line: "kmscon".into(),
+        execution_syntax: ExecutionSyntax::DoubleDash,
+        path: "/usr/bin/kmscon".into(),
+        method: DetectionMethod::HardcodedTraditional,
+        raw: None,
+    };
+
+    let spec = Launcher::new(&terminal).build_invocation("htop", ["-d", "10"]);
+
+    assert_eq!(spec.args, ["--login", "--", "htop", "-d", "10"]);
+}
+
+#[test]
+fn minimal_environment_without_home_or_xdg_is_skipped() {
+    assert_eq!(xdg::config_home_from(None, None), None);
+    assert_eq!(
+        xdg::config_home_from(Some("".into()), Some("".into())),
+        None
+    );
+    assert_eq!(
+        xdg::config_home_from(Some("relative/config".into()), None),
+        None
+    );
+    assert_eq!(
+        xdg::config_home_from(Some("".into()), Some("/home/user".into())),
+        Some("/home/user/.config".into())
+    );
+    assert_eq!(
+        xdg::config_home_from(Some("/xdg".into()), Some("/home/user".into())),
+        Some("/xdg".into())
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn empty_or_relative_path_entries_are_not_searched() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = std::env::temp_dir().join(format!("unidosx-lookup-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let terminal = dir.join("fake-terminal");
+    std::fs::write(&terminal, "#!/bin/sh\n").unwrap();
+    std::fs::set_permissions(&terminal, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    // The same directory, relative to the current working directory.
+    let depth = std::env::current_dir().unwrap().components().count() - 1;
+    let relative = "../".repeat(depth) + dir.strip_prefix("/").unwrap().to_str().unwrap();
+
+    assert_eq!(
+        lookup::search_paths("fake-terminal", dir.as_os_str()),
+        Some(terminal)
+    );
+    assert_eq!(
+        lookup::search_paths("fake-terminal", relative.as_ref()),
+        None
+    );
+    assert_eq!(lookup::search_paths("fake-terminal", "".as_ref()), None);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(all(unix, feature = "env-var"))]
+#[test]
+fn skipped_executables_are_explained() {
+    use std::{
+        fs,
+        os::unix::fs::{PermissionsExt, symlink},
+    };
+
+    let dir = std::env::temp_dir().join(format!("unidosx-rejected-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    symlink(dir.join("kitty"), dir.join("kitty")).unwrap();
+    fs::write(dir.join("foot"), "#!/bin/sh\n").unwrap();
+    fs::set_permissions(dir.join("foot"), fs::Permissions::from_mode(0o644)).unwrap();
+
+    let paths = format!("relative/bin:{}", dir.display());
+    let env = Environment::default().var("PATH", &paths);
+
+    assert_eq!(
+        env.clone()
+            .var("TERMINAL_EMULATOR", "kitty")
+            .scope(probes::check_env_var),
+        Err(SkipReason::BinaryRejected {
+            name: String::from("kitty"),
+            problems: vec![
+                LookupProblem::RelativeEntry {
+                    entry: "relative/bin".into()
+                },
+                LookupProblem::SymlinkLoop {
+                    path: dir.join("kitty")
+                },
+            ],
+        })
+    );
+    assert_eq!(
+        env.scope(|| lookup::lookup_executable("foot")),
+        Err(vec![
+            LookupProblem::RelativeEntry {
+                entry: "relative/bin".into()
+            },
+            LookupProblem::NotExecutable {
+                path: dir.join("foot")
+            },
+        ])
+    );
+    assert_eq!(
+        Environment::default()
+            .var("PATH", &dir)
+            .scope(|| lookup::lookup_executable("missing")),
+        Err(vec![])
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn pathext_extensions_are_tried() {
+    assert_eq!(
+        lookup::with_extensions("wezterm", Some(".EXE;.CMD".as_ref())),
+        ["wezterm.exe", "wezterm.cmd"]
+    );
+    assert_eq!(
+        lookup::with_extensions("wt", None),
+        ["wt.com", "wt.exe", "wt.bat", "wt.cmd"]
+    );
+    assert_eq!(
+        lookup::with_extensions("wt.exe", Some(".CMD".as_ref())),
+        ["wt.exe"]
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn app_bundle_executables_are_found_ignoring_case() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let apps = std::env::temp_dir().join(format!("unidosx-apps-{}", std::process::id()));
+    let macos = apps.join("Alacritty.app/Contents/MacOS");
+    std::fs::create_dir_all(&macos).unwrap();
+    let binary = macos.join("alacritty");
+    std::fs::write(&binary, "").unwrap();
+    std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    assert_eq!(
+        lookup::find_in_app_bundles(&apps, "alacritty"),
+        Some(binary.clone())
+    );
+    assert_eq!(
+        lookup::find_in_app_bundles(&apps, "ALACRITTY"),
+        Some(binary)
+    );
+    assert_eq!(lookup::find_in_app_bundles(&apps, "kitty"), None);
+
+    std::fs::remove_dir_all(apps).unwrap();
+}
+
+#[cfg(all(unix, feature = "env-var"))]
+#[test]
+fn executables_are_searched_in_the_active_filesystem() {
+    use std::{io, path::Path};
+
+    struct Virtual(&'static [&'static str]);
+
+    impl Filesystem for Virtual {
+        fn file_info(&self, path: &Path) -> io::Result<FileInfo> {
+            let is_dir = self.0.iter().any(|file| Path::new(file).starts_with(path));
+
+            match self.0.iter().any(|file| Path::new(file) == path) {
+                true => Ok(FileInfo {
+                    is_dir: false,
+                    is_executable: true,
+                }),
+                false if is_dir => Ok(FileInfo {
+                    is_dir: true,
+                    is_executable: false,
+                }),
+                false => Err(io::ErrorKind::NotFound.into()),
+            }
+        }
+
+        fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+            Ok(self
+                .0
+                .iter()
+                .filter_map(|file| {
+                    let relative = Path::new(file).strip_prefix(path).ok()?;
+                    Some(path.join(relative.iter().next()?))
+                })
+                .collect())
+        }
+
+        fn read_to_string(&self, _path: &Path) -> io::Result<String> {
+            Err(io::ErrorKind::NotFound.into())
+        }
+    }
+
+    let files = &[
+        "/virtual/bin/kitty",
+        "/virtual/Applications/Alacritty.app/Contents/MacOS/alacritty",
+    ];
+    let env = Environment::default()
+        .var("PATH", "/virtual/bin")
+        .var("TERMINAL_EMULATOR", "kitty");
+
+    with_filesystem(Virtual(files), || {
+        assert_eq!(
+            env.scope(|| lookup::find_executable("kitty")),
+            Some(PathBuf::from("/virtual/bin/kitty"))
+        );
+        assert_eq!(lookup::find_executable("/virtual/bin"), None);
+        assert_eq!(
+            lookup::find_in_app_bundles(Path::new("/virtual/Applications"), "alacritty"),
+            Some(PathBuf::from(files[1]))
+        );
+        assert_eq!(
+            env.scope(probe_env_var).unwrap().path,
+            Path::new("/virtual/bin/kitty")
+        );
+    });
+
+    assert_eq!(env.scope(|| lookup::find_executable("kitty")), None);
+}
+
+#[test]
+fn executable_names_follow_platform_case_rules() {
+    let case_insensitive = cfg!(any(windows, target_os = "macos"));
+
+    assert!(lookup::same_name("alacritty", "alacritty"));
+    assert_eq!(
+        lookup::same_name("Alacritty", "alacritty"),
+        case_insensitive
+    );
+    assert_eq!(
+        probes::lookup_known("Alacritty").is_some(),
+        case_insensitive
+    );
+}
+
+#[test]
+fn controlling_tty_is_mapped_through_ptmx_fdinfo() {
+    let fdinfo = "pos:\t0\nflags:\t0104002\nmnt_id:\t29\nino:\t5\ntty-index:\t3\n";
+
+    assert_eq!(current::parse_tty_index(fdinfo), Some(3));
+    assert_eq!(current::parse_tty_index("pos:\t0\n"), None);
+    assert_eq!(current::pts_index("/dev/pts/3".as_ref()), Some(3));
+    assert_eq!(current::pts_index("/dev/tty1".as_ref()), None);
+    assert_eq!(
+        current::terminal_for_process("gnome-terminal-server"),
+        "gnome-terminal"
+    );
+    assert_eq!(current::terminal_for_process("kitty"), "kitty");
+}
+
+#[test]
+fn current_terminal_is_read_from_its_variables() {
+    // An empty root hides the ancestors of the test process.
+    let root = std::env::temp_dir().join(format!("unidosx-current-{}", std::process::id()));
+    std::fs::create_dir_all(&root).unwrap();
+
+    let current = |vars: &[(&str, &str)]| {
+        with_filesystem(RootFilesystem::new(&root), || {
+            vars.iter()
+                .copied()
+                .collect::<Environment>()
+                .scope(detect_current)
+        })
+        .map(|current| {
+            assert_eq!(
+                current.terminal.detection_method(),
+                DetectionMethod::HostEnvironment
+            );
+            (current.terminal.name().to_owned(), current.confidence)
+        })
+    };
+
+    assert_eq!(current(&[]), None);
+    assert_eq!(current(&[("TERM_PROGRAM", "tmux")]), None);
+    assert_eq!(
+        current(&[("KITTY_WINDOW_ID", "1"), ("TERM_PROGRAM", "kitty")]),
+        Some(("kitty".to_owned(), Confidence::High))
+    );
+    assert_eq!(
+        current(&[("TERM_PROGRAM", "WezTerm")]),
+        Some(("wezterm".to_owned(), Confidence::Medium))
+    );
+    assert_eq!(
+        current(&[("VTE_VERSION", "7600")]),
+        Some(("gnome-terminal".to_owned(), Confidence::Low))
+    );
+    // alacritty was started from kitty, inheriting KITTY_WINDOW_ID.
+    assert_eq!(
+        current(&[
+            ("KITTY_WINDOW_ID", "1"),
+            ("ALACRITTY_SOCKET", "/tmp/socket")
+        ]),
+        Some(("kitty".to_owned(), Confidence::Medium))
+    );
+    assert_eq!(
+        current(&[
+            ("KONSOLE_VERSION", "240202"),
+            ("TMUX", "/tmp/tmux-1000/default,1,0")
+        ]),
+        Some(("konsole".to_owned(), Confidence::Medium))
+    );
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[cfg(all(target_os = "linux", feature = "process-ancestry"))]
+#[test]
+fn current_terminal_is_found_among_the_ancestors() {
+    assert_eq!(
+        current::parse_stat_parent("42 (my (odd) shell) S 7 42 42 34817 0"),
+        Some(7)
+    );
+    assert_eq!(current::parse_stat_parent("42 (sh"), None);
+
+    let root = std::env::temp_dir().join(format!("unidosx-ancestry-{}", std::process::id()));
+    let parent = root.join(format!("proc/{}", std::os::unix::process::parent_id()));
+    let terminal = root.join("proc/4242");
+    std::fs::create_dir_all(&parent).unwrap();
+    std::fs::create_dir_all(&terminal).unwrap();
+    std::fs::write(parent.join("stat"), "1 (su) S 4242 1 1 0").unwrap();
+    std::fs::write(parent.join("cmdline"), "su\0-\0").unwrap();
+    std::fs::write(terminal.join("stat"), "4242 (kitty) S 1 4242 4242 0").unwrap();
+    std::os::unix::fs::symlink("/usr/bin/kitty", terminal.join("exe")).unwrap();
+
+    let current = |vars: &[(&str, &str)]| {
+        with_filesystem(RootFilesystem::new(&root), || {
+            vars.iter()
+                .copied()
+                .collect::<Environment>()
+                .scope(detect_current)
+        })
+        .map(|current| {
+            (
+                current.terminal.name().to_owned(),
+                current.terminal.detection_method(),
+                current.confidence,
+            )
+        })
+    };
+
+    let found = with_filesystem(RootFilesystem::new(&root), probe_process_ancestry).unwrap();
+    assert_eq!(found.path(), std::path::Path::new("/usr/bin/kitty"));
+    assert_eq!(found.detection_method(), DetectionMethod::ProcessAncestry);
+
+    // The environment was scrubbed by `su`.
+    assert_eq!(
+        current(&[]),
+        Some((
+            "kitty".to_owned(),
+            DetectionMethod::ProcessAncestry,
+            Confidence::Medium
+        ))
+    );
+    assert_eq!(
+        current(&[("KITTY_WINDOW_ID", "1")]),
+        Some((
+            "kitty".to_owned(),
+            DetectionMethod::ProcessAncestry,
+            Confidence::High
+        ))
+    );
+    assert_eq!(
+        current(&[("ALACRITTY_SOCKET", "/tmp/socket")]),
+        Some((
+            "alacritty".to_owned(),
+            DetectionMethod::HostEnvironment,
+            Confidence::Medium
+        ))
+    );
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[cfg(all(target_os = "linux", feature = "focus-x11"))]
+#[test]
+fn launched_windows_are_found_by_process() {
+    let listing = "0x03a00003  0 4242   host kitty\n0x0400000a -1 1      host Desktop\n0x03a0001c  0 4242   host kitty\n";
+
+    assert_eq!(
+        focus::parse_wmctrl_windows(listing, 4242),
+        ["0x03a00003", "0x03a0001c"]
+    );
+    assert!(focus::parse_wmctrl_windows(listing, 7).is_empty());
+
+    // Without a graphical session, no tool can focus the window.
+    let result = Launcher::AllocConsole
+        .launch("true", std::iter::empty::<&str>())
+        .unwrap();
+    let error = [("PATH", "/usr/bin:/bin")]
+        .into_iter()
+        .collect::<Environment>()
+        .scope(|| focus_launched(&result))
+        .unwrap_err();
+
+    assert_eq!(error.kind(), std::io::ErrorKind::Unsupported);
+}
+
+#[cfg(unix)]
+#[test]
+fn launch_result_records_the_spawned_command() {
+    let launcher = Launcher::AllocConsole;
+
+    let mut result = launcher.launch("true", ["--ignored"]).unwrap();
+
+    let expected = CommandSpec {
+        new_console: true,
+        ..CommandSpec::new("true").arg("--ignored")
+    };
+
+    assert_eq!(result.spec, expected);
+    assert_eq!(result.backend, LaunchBackend::NewConsole);
+    let pid = result.child().id();
+    assert_eq!(result.pid, pid);
+    assert!(result.spawned_at >= result.requested_at);
+    assert!(result.child().wait().unwrap().success());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn dropped_launches_are_reaped() {
+    use std::time::{Duration, Instant};
+
+    let procs: Vec<_> = (0..4)
+        .map(|_| {
+            let result = Launcher::AllocConsole.launch("sleep", ["0.1"]).unwrap();
+
+            std::path::PathBuf::from(format!("/proc/{}", result.pid))
+        })
+        .collect();
+
+    // A zombie keeps its /proc entry until it is reaped.
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while procs.iter().any(|proc| proc.exists()) && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    assert!(procs.iter().all(|proc| !proc.exists()));
+
+    // The dropped results share a single reaper thread.
+    let reapers = std::fs::read_dir("/proc/self/task")
+        .unwrap()
+        .filter(|task| {
+            std::fs::read_to_string(task.as_ref().unwrap().path().join("comm"))
+                .is_ok_and(|name| name.trim() == "unidosx-reaper")
+        })
+        .count();
+    assert_eq!(reapers, 1);
+}
+
+#[cfg(unix)]
+#[test]
+fn batch_launches_are_staggered() {
+    use std::time::{Duration, Instant};
+
+    let dir = std::env::temp_dir();
+    let specs = [
+        CommandSpec::new("sh")
+            .args(["-c", "test \"$PWD\" = \"$1\" && exit \"$CODE\"", "sh"])
+            .arg(&dir)
+            .current_dir(&dir)
+            .env("CODE", "3"),
+        CommandSpec::new("/nonexistent/terminal"),
+        CommandSpec::new("true"),
+    ];
+
+    let started = Instant::now();
+    let results = launch_batch(&Launcher::AllocConsole, &specs, 2);
+
+    // Two launches every half second: the third one starts half a second after the first one.
+    assert!(started.elapsed() >= Duration::from_millis(500));
+    assert_eq!(results.len(), 3);
+    assert!(results[1].is_err());
+
+    let mut results: Vec<_> = results.into_iter().filter_map(Result::ok).collect();
+    assert_eq!(results[0].child().wait().unwrap().code(), Some(3));
+    assert!(results[1].child().wait().unwrap().success());
+}
+
+#[cfg(unix)]
+#[test]
+fn launches_are_restored_from_their_descriptor() {
+    let dir = std::env::temp_dir();
+    let descriptor = LaunchDescriptor {
+        spec: CommandSpec::new("sh")
+            .args(["-c", "test \"$PWD\" = \"$1\" && exit \"$CODE\"", "sh"])
+            .arg(&dir)
+            .current_dir(&dir)
+            .env("CODE", "4")
+            .env_remove("UNSET"),
+        backend: LaunchBackend::NewConsole,
+    };
+
+    let json = descriptor.to_json();
+    assert!(!json.contains('\n'));
+    assert_eq!(LaunchDescriptor::from_json(&json), Some(descriptor.clone()));
+    assert_eq!(
+        LaunchDescriptor::from_json(&json.replace("\"version\": 1", "\"version\": 99")),
+        None
+    );
+
+    let compositor = LaunchDescriptor {
+        backend: LaunchBackend::CompositorSpawn(Compositor::Hyprland),
+        ..descriptor.clone()
+    };
+    assert_eq!(
+        LaunchDescriptor::from_json(&compositor.to_json()),
+        Some(compositor)
+    );
+
+    let mut result = relaunch(&descriptor).unwrap();
+    assert_eq!(result.to_descriptor(), descriptor);
+    assert_eq!(result.child().wait().unwrap().code(), Some(4));
+}
+
+#[cfg(unix)]
+#[test]
+fn launches_are_closed_gracefully_then_killed() {
+    use std::{os::unix::process::ExitStatusExt, time::Duration};
+
+    let spawn = |trap: &str| {
+        let script = format!("trap {trap} TERM; while :; do sleep 0.01; done");
+        let result = Launcher::AllocConsole
+            .launch("sh", ["-c", &script])
+            .unwrap();
+
+        // Let the shell install the trap.
+        std::thread::sleep(Duration::from_millis(200));
+        result
+    };
+
+    let status = spawn("'exit 3'").close(Duration::from_secs(5)).unwrap();
+    assert_eq!(status.code(), Some(3));
+
+    let status = spawn("''").close(Duration::from_millis(100)).unwrap();
+    assert_eq!(status.signal(), Some(9));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn tagged_launches_can_be_found() {
+    let id = tagging::new_launch_id();
+    assert_eq!(id.len(), 36);
+    assert_eq!(id.as_bytes()[14], b'4');
+    assert_ne!(tagging::new_launch_id(), id);
+
+    let mut child = CommandSpec::new("sleep")
+        .arg("5")
+        .env(LAUNCH_ID_VAR, &id)
+        .to_command()
+        .spawn()
+        .unwrap();
+
+    // The environment of the child can briefly read empty while it is being executed.
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(2);
+    while find_launched(&id).is_empty() && std::time::Instant::now() < deadline {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+
+    assert_eq!(find_launched(&id), [child.id()]);
+    assert_eq!(find_launched(&tagging::new_launch_id()), []);
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn fallback_skips_missing_and_crashing_terminals() {
+    let candidate = |path: &str| TerminalEmulator {
+        command_line: "".into(),
+        execution_syntax: ExecutionSyntax::Command,
+        path: path.into(),
+        method: DetectionMethod::EnvironmentVariable,
+        raw: None,
+    };
+    let candidates = [
+        candidate("/nonexistent/terminal"),
+        candidate("false"),
+        candidate("true"),
+    ];
+
+    let window = std::time::Duration::from_millis(500);
+    let session = Environment::capture().var("DISPLAY", ":0");
+
+    session.scope(|| {
+        let (terminal, mut result) =
+            launch_with_fallback(&candidates, "ignored", ["args"], window).unwrap();
+
+        assert_eq!(terminal.path, std::path::Path::new("true"));
+        assert!(result.child().wait().unwrap().success());
+        assert!(launch_with_fallback(&candidates[..2], "ignored", ["args"], window).is_err());
+    });
+}
+
+#[cfg(unix)]
+#[test]
+fn early_exits_fail_the_liveness_check() {
+    let window = std::time::Duration::from_millis(500);
+    let launcher = Launcher::Terminal {
+        program: "sh".into(),
+        args: vec!["-c".into()],
+        single_argument: false,
+    };
+
+    let session = Environment::capture().var("DISPLAY", ":0");
+
+    session.scope(|| {
+        let error = launcher
+            .launch_checked("exit 3", std::iter::empty::<&str>(), window)
+            .unwrap_err();
+        assert!(error.to_string().starts_with("sh exited with"));
+
+        assert!(launcher.launch_checked("exit 0", ["sh"], window).is_ok());
+
+        let mut result = launcher.launch("sleep 1", ["sh"]).unwrap();
+        result
+            .check_liveness(std::time::Duration::from_millis(50))
+            .unwrap();
+        result.child().kill().unwrap();
+        result.child().wait().unwrap();
+    });
+}
+
+#[cfg(unix)]
+#[test]
+fn terminal_app_runs_the_command_through_applescript() {
+    let terminal = test_util::FakeTerminal::new("Terminal")
+        .path("/System/Applications/Utilities/Terminal.app")
+        .method(DetectionMethod::TerminalApp)
+        .build();
+    let launcher = Launcher::new(&terminal);
+    let script = [
+        "-e",
+        "on run argv",
+        "-e",
+        "tell application \"Terminal\"",
+        "-e",
+        "activate",
+        "-e",
+        "do script (item 1 of argv)",
+        "-e",
+        "end tell",
+        "-e",
+        "end run",
+    ];
+
+    let spec = launcher.build_invocation("printf", ["%s\\n", "two words", "it's"]);
+    assert_eq!(spec.program, std::ffi::OsStr::new("osascript"));
+    let (command, args) = spec.args.split_last().unwrap();
+    assert_eq!(args, script);
+    assert_eq!(
+        command,
+        r#"sh -c 'exec "$@"' sh 'printf' '%s\n' 'two words' 'it'\''s'"#
+    );
+
+    // The command line run by Terminal.app gets the arguments unchanged.
+    let output = std::process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .output()
+        .unwrap();
+    assert_eq!(output.stdout, b"two words\nit's\n");
+
+    // Options without a flag are applied by the `sh -c` wrapper, inside the command line.
+    let options = LaunchOptions::new().current_dir("/tmp").hold(true);
+    let spec = launcher.build_invocation_with_options("make", ["check"], &options);
+    let (command, args) = spec.args.split_last().unwrap();
+    assert_eq!(args, script);
+    let command = command.to_str().unwrap();
+    assert!(command.contains(r#"cd -- "$1""#));
+    assert!(command.contains("press Enter to close"));
+    assert!(command.ends_with(" 'sh' '/tmp' 'make' 'check'"));
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+#[test]
+fn graphical_launches_require_and_receive_the_session() {
+    let launcher = Launcher::Terminal {
+        program: "sh".into(),
+        args: vec!["-c".into()],
+        single_argument: false,
+    };
+    let script = r#"test "$DISPLAY" = ":7" && test "$XAUTHORITY" = "/run/xauth""#;
+
+    let headless = Environment::capture()
+        .remove_var("DISPLAY")
+        .remove_var("WAYLAND_DISPLAY");
+    let error = headless
+        .scope(|| launcher.launch(script, std::iter::empty::<&str>()))
+        .unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::NotConnected);
+    assert!(
+        error
+            .to_string()
+            .contains("neither DISPLAY nor WAYLAND_DISPLAY")
+    );
+
+    let session = headless
+        .clone()
+        .var("DISPLAY", ":7")
+        .var("XAUTHORITY", "/run/xauth");
+    let mut result = session
+        .scope(|| launcher.launch(script, std::iter::empty::<&str>()))
+        .unwrap();
+    assert!(result.child().wait().unwrap().success());
+    assert!(
+        result
+            .spec
+            .env
+            .contains(&("DISPLAY".into(), Some(":7".into())))
+    );
+
+    let fbterm = Launcher::Terminal {
+        program: "/nonexistent/fbterm".into(),
+        args: Vec::new(),
+        single_argument: false,
+    };
+    let error = headless
+        .scope(|| fbterm.launch("true", std::iter::empty::<&str>()))
+        .unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+}
+
+#[test]
+fn failed_terminals_are_deprioritized() {
+    let paths = ["/failure-memory/a", "/failure-memory/b"];
+
+    record_launch_failure(paths[0]);
+
+    assert!(has_launch_failed(paths[0]));
+    assert_eq!(
+        failures::prefer_working(paths, |path| path.as_ref()),
+        Some(paths[1])
+    );
+    assert_eq!(
+        failures::prefer_working([paths[0]], |path| path.as_ref()),
+        Some(paths[0])
+    );
+}
+
+#[test]
+fn compositors_spawn_the_inner_command() {
+    let inner = Launcher::Terminal {
+        program: "/usr/bin/foot".into(),
+        args: Vec::new(),
+        single_argument: false,
+    };
+    let launcher = |compositor, program: &str| Launcher::CompositorSpawn {
+        compositor,
+        program: program.into(),
+        launcher: Box::new(inner.clone()),
+    };
+
+    let niri = launcher(Compositor::Niri, "niri").build_invocation("vim", ["my notes"]);
+    let sway = launcher(Compositor::Sway, "swaymsg").build_invocation("vim", ["my notes"]);
+
+    assert_eq!(
+        niri.args,
+        [
+            "msg",
+            "action",
+            "spawn",
+            "--",
+            "/usr/bin/foot",
+            "vim",
+            "my notes"
+        ]
+    );
+    assert_eq!(sway.args, ["exec", "--", "/usr/bin/foot vim 'my notes'"]);
+}
+
+#[test]
+fn compositors_are_identified_from_desktop_names() {
+    use compositor::compositor_from_desktop;
+
+    assert_eq!(compositor_from_desktop("sway"), Some(Compositor::Sway));
+    assert_eq!(compositor_from_desktop("KDE"), Some(Compositor::KWin));
+    assert_eq!(
+        compositor_from_desktop("ubuntu:GNOME"),
+        Some(Compositor::Mutter)
+    );
+    assert_eq!(compositor_from_desktop("XFCE"), None);
+    assert_eq!(Compositor::Mutter.control_program(), None);
+}
+
+#[cfg(all(not(windows), feature = "env-var"))]
+#[test]
+fn detection_reads_the_supplied_environment() {
+    assert_eq!(
+        detect_with(&Environment::default().var("PATH", "/nonexistent")),
+        None
+    );
+
+    let env = Environment::default()
+        .var("PATH", "/usr/bin:/bin")
+        .var("TERMINAL_EMULATOR", "sh");
+    let terminal = env.scope(probe_env_var).unwrap();
+
+    assert_eq!(terminal.method, DetectionMethod::EnvironmentVariable);
+    assert_eq!(terminal.path.file_name().unwrap(), "sh");
+    assert_eq!(env.get("PATH").unwrap(), "/usr/bin:/bin");
+    assert_eq!(env.remove_var("PATH").get("PATH"), None);
+}
+
+#[cfg(all(
+    unix,
+    not(target_os = "macos"),
+    feature = "env-var",
+    feature = "kde-settings"
+))]
+#[test]
+fn raw_values_are_kept_unparsed() {
+    use std::{fs, os::unix::fs::PermissionsExt};
+
+    let root = std::env::temp_dir().join(format!("unidosx-raw-{}", std::process::id()));
+    let (bin, config) = (root.join("bin"), root.join("config"));
+    fs::create_dir_all(&bin).unwrap();
+    fs::create_dir_all(&config).unwrap();
+    fs::write(bin.join("konsole"), "#!/bin/sh\n").unwrap();
+    fs::set_permissions(bin.join("konsole"), fs::Permissions::from_mode(0o755)).unwrap();
+    fs::write(
+        config.join("kdeglobals"),
+        "[General]\nTerminalApplication=konsole --separate\n",
+    )
+    .unwrap();
+
+    let env = Environment::default()
+        .var("PATH", &bin)
+        .var("XDG_CONFIG_HOME", &config)
+        .var("XDG_CONFIG_DIRS", "/nonexistent")
+        .var("TERMINAL_EMULATOR", " konsole\n");
+
+    env.scope(|| {
+        let terminal = probe_env_var().unwrap();
+        assert_eq!(terminal.path, bin.join("konsole"));
+        assert_eq!(terminal.raw_value(), Some(" konsole\n"));
+
+        let terminal = probe_kde_settings().unwrap();
+        assert_eq!(terminal.path, bin.join("konsole"));
+        assert_eq!(terminal.raw_value(), Some("konsole --separate"));
+    });
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[cfg(all(unix, feature = "config-file", feature = "kde-settings"))]
+#[test]
+fn accessible_terminals_are_preferred_when_configured() {
+    use std::{fs, os::unix::fs::PermissionsExt};
+
+    let root = std::env::temp_dir().join(format!("unidosx-accessible-{}", std::process::id()));
+    let (bin, config) = (root.join("bin"), root.join("config"));
+    fs::create_dir_all(&bin).unwrap();
+    fs::create_dir_all(config.join("unidosx")).unwrap();
+    for name in ["kitty", "mate-terminal"] {
+        fs::write(bin.join(name), "#!/bin/sh\n").unwrap();
+        fs::set_permissions(bin.join(name), fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    fs::write(
+        config.join("kdeglobals"),
+        "[General]\nTerminalApplication=kitty\n",
+    )
+    .unwrap();
+
+    let env = Environment::default()
+        .var("PATH", &bin)
+        .var("DISPLAY", ":0")
+        .var("XDG_CONFIG_HOME", &config)
+        .var("XDG_CONFIG_DIRS", root.join("nonexistent"))
+        .var("XDG_DATA_DIRS", root.join("nonexistent"));
+
+    env.scope(|| {
+        assert!(!accessible_terminals_preferred());
+        assert_eq!(detect().unwrap().name(), "kitty");
+
+        fs::write(
+            config.join("unidosx/terminal.conf"),
+            "[Terminal]\nPreferAccessible=true\n",
+        )
+        .unwrap();
+        assert!(accessible_terminals_preferred());
+
+        let terminal = detect().unwrap();
+        assert_eq!(terminal.name(), "mate-terminal");
+        assert_eq!(terminal.method, DetectionMethod::Accessibility);
+        assert_eq!(terminal.execution_syntax, ExecutionSyntax::SingleE);
+
+        set_default_terminal(&terminal).unwrap();
+        assert!(accessible_terminals_preferred());
+    });
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[cfg(all(target_os = "linux", feature = "env-var", feature = "kde-settings"))]
+#[test]
+fn report_explains_why_each_method_found_nothing() {
+    let root = std::env::temp_dir().join(format!("unidosx-report-{}", std::process::id()));
+    let env = Environment::default()
+        .var("PATH", "/usr/bin:/bin")
+        .var("XDG_CONFIG_HOME", root.join("config"))
+        .var("XDG_CONFIG_DIRS", root.join("nonexistent"))
+        .var("XDG_DATA_DIRS", root.join("nonexistent"));
+    let reason = |report: &DetectionReport, method| {
+        report
+            .skipped()
+            .find(|&(skipped, _)| skipped == method)
+            .map(|(_, reason)| reason.clone())
+    };
+
+    let report = env.scope(detect_report);
+    assert_eq!(
+        reason(&report, DetectionMethod::EnvironmentVariable),
+        Some(SkipReason::EnvMissing {
+            name: String::from("TERMINAL_EMULATOR")
+        })
+    );
+    assert_eq!(
+        reason(&report, DetectionMethod::TerminalApp),
+        Some(SkipReason::FeatureDisabled)
+    );
+    assert_eq!(
+        reason(&report, DetectionMethod::KdeSettings),
+        Some(SkipReason::NotConfigured)
+    );
+    assert_eq!(report.entries.len(), 14);
+    assert_eq!(report.selected, env.scope(detect));
+
+    let report = env
+        .clone()
+        .var("TERMINAL_EMULATOR", "no-such-terminal")
+        .scope(detect_report);
+    assert_eq!(
+        reason(&report, DetectionMethod::EnvironmentVariable),
+        Some(SkipReason::BinaryNotFound {
+            name: String::from("no-such-terminal")
+        })
+    );
+
+    let report = env
+        .clone()
+        .var("TERMINAL_EMULATOR", "sh")
+        .scope(detect_report);
+    assert_eq!(report.entries[0].result.as_ref().unwrap().name(), "sh");
+    assert_eq!(report.selected.unwrap().name(), "sh");
+}
+
+#[cfg(all(target_os = "linux", feature = "env-var", feature = "kde-settings"))]
+#[test]
+fn try_detect_explains_why_nothing_was_found() {
+    let env = Environment::default()
+        .var("PATH", "/usr/bin:/bin")
+        .var("DISPLAY", ":0")
+        .var("TERMINAL_EMULATOR", "sh");
+    assert_eq!(env.scope(try_detect).unwrap().name(), "sh");
+
+    let config = std::env::temp_dir().join(format!("unidosx-try-detect-{}", std::process::id()));
+    std::fs::create_dir_all(config.join("kdeglobals")).unwrap();
+    let unreadable = Environment::default()
+        .var("XDG_CONFIG_HOME", &config)
+        .var("XDG_CONFIG_DIRS", config.join("nonexistent"));
+    assert!(matches!(
+        unreadable.scope(probes::check_kde_settings),
+        Err(SkipReason::Io { .. })
+    ));
+    std::fs::remove_dir_all(config).unwrap();
+
+    let not_set = (
+        DetectionMethod::EnvironmentVariable,
+        SkipReason::EnvMissing {
+            name: String::from("TERMINAL_EMULATOR"),
+        },
+    );
+    let disabled = (DetectionMethod::TerminalApp, SkipReason::FeatureDisabled);
+    let io = (
+        DetectionMethod::KdeSettings,
+        SkipReason::Io {
+            detail: String::from("kdeglobals: denied"),
+        },
+    );
+    let none = (DetectionMethod::HardcodedModern, SkipReason::NoneInstalled);
+
+    let error = DetectionError::from_skipped(vec![not_set.clone(), disabled.clone(), none.clone()]);
+    assert!(matches!(error, DetectionError::NoTerminalFound { .. }));
+    assert_eq!(error.skipped().len(), 3);
+    assert_eq!(
+        error.to_string(),
+        "no terminal emulator found; Environment Variable: TERMINAL_EMULATOR is not set; Terminal.app: disabled at \
+         compile time; Hardcoded Modern List: none installed"
+    );
+
+    let error = DetectionError::from_skipped(vec![not_set, io, none]);
+    assert_eq!(
+        error,
+        DetectionError::Io {
+            method: DetectionMethod::KdeSettings,
+            detail: String::from("kdeglobals: denied"),
+            skipped: error.skipped().to_vec(),
+        }
+    );
+
+    let error = DetectionError::from_skipped(vec![
+        disabled,
+        (
+            DetectionMethod::GnomeSettings,
+            SkipReason::PrivilegedContext,
+        ),
+    ]);
+    assert!(matches!(error, DetectionError::Unsupported { .. }));
+}
+
+#[cfg(all(target_os = "linux", feature = "env-var"))]
+#[test]
+fn headless_environments_are_detected_up_front() {
+    let ssh = Environment::default()
+        .var("PATH", "/usr/bin:/bin")
+        .var("SSH_CONNECTION", "192.0.2.1 50000 192.0.2.2 22")
+        .var("TERMINAL_EMULATOR", "sh");
+
+    let error = ssh.scope(try_detect).unwrap_err();
+    assert!(matches!(error, DetectionError::HeadlessEnvironment { .. }));
+    assert!(error.skipped().is_empty());
+    assert!(error.to_string().starts_with("no graphical session: "));
+
+    // Framebuffer terminal emulators and `openvt` serve a bare virtual console without a graphical session.
+    ssh.scope(|| {
+        assert!(report::check_headless(false).is_err());
+        assert_eq!(report::check_headless(true), Ok(()));
+    });
+    Environment::default()
+        .var("DISPLAY", ":0")
+        .scope(|| assert_eq!(report::check_headless(false), Ok(())));
+}
+
+#[cfg(all(
+    target_os = "linux",
+    feature = "x-terminal-emulator",
+    feature = "gnome-settings"
+))]
+#[test]
+fn detection_runs_offline_against_another_root() {
+    use std::{
+        fs,
+        os::unix::fs::{PermissionsExt, symlink},
+        path::Path,
+    };
+
+    let root = std::env::temp_dir().join(format!("unidosx-root-{}", std::process::id()));
+    fs::create_dir_all(root.join("usr/bin")).unwrap();
+    fs::create_dir_all(root.join("etc/alternatives")).unwrap();
+    fs::write(root.join("usr/bin/kitty"), "").unwrap();
+    fs::set_permissions(
+        root.join("usr/bin/kitty"),
+        fs::Permissions::from_mode(0o755),
+    )
+    .unwrap();
+    symlink(
+        "/usr/bin/kitty",
+        root.join("etc/alternatives/x-terminal-emulator"),
+    )
+    .unwrap();
+    symlink(
+        "/etc/alternatives/x-terminal-emulator",
+        root.join("usr/bin/x-terminal-emulator"),
+    )
+    .unwrap();
+
+    let detector = Detector::for_root(&root);
+    let terminal = detector.detect().unwrap();
+    assert_eq!(terminal.path, Path::new("/usr/bin/x-terminal-emulator"));
+    assert_eq!(terminal.method, DetectionMethod::XTerminalEmulator);
+
+    let report = detector.detect_report();
+    assert_eq!(
+        report
+            .skipped()
+            .find(|&(method, _)| method == DetectionMethod::GnomeSettings)
+            .map(|(_, reason)| reason),
+        Some(&SkipReason::SessionRequired)
+    );
+    assert_eq!(report.selected, Some(terminal));
+
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[cfg(all(
+    unix,
+    feature = "env-var",
+    feature = "hardcoded-modern",
+    feature = "hardcoded-traditional"
+))]
+#[test]
+fn every_installed_terminal_is_listed_once() {
+    use std::{
+        fs,
+        os::unix::fs::{PermissionsExt, symlink},
+    };
+
+    let root = std::env::temp_dir().join(format!("unidosx-all-{}", std::process::id()));
+    let bin = root.join("bin");
+    fs::create_dir_all(&bin).unwrap();
+
+    for name in ["kitty", "xterm"] {
+        fs::write(bin.join(name), "#!/bin/sh\n").unwrap();
+        fs::set_permissions(bin.join(name), fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    symlink(bin.join("kitty"), bin.join("alacritty")).unwrap();
+
+    let installed = [
+        ("PATH", bin.as_os_str()),
+        ("TERMINAL_EMULATOR", "alacritty".as_ref()),
+        ("HOME", root.as_os_str()),
+        ("XDG_CONFIG_HOME", root.as_os_str()),
+        ("XDG_CONFIG_DIRS", root.as_os_str()),
+        ("XDG_DATA_HOME", root.as_os_str()),
+        ("XDG_DATA_DIRS", root.as_os_str()),
+    ]
+    .into_iter()
+    .collect::<Environment>()
+    .scope(detect_all);
+
+    // kitty is found by the hardcoded lists, but alacritty, found first, links to it.
+    assert_eq!(
+        installed
+            .iter()
+            .map(|terminal| (terminal.name(), terminal.detection_method()))
+            .collect::<Vec<_>>(),
+        [
+            ("alacritty", DetectionMethod::EnvironmentVariable),
+            ("xterm", DetectionMethod::HardcodedTraditional)
+        ]
+    );
+
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn cancelled_commands_are_killed() {
+    use std::{
+        process::Command,
+        time::{Duration, Instant},
+    };
+
+    let started_at = Instant::now();
+    let timeout = CancellationToken::new().with_timeout(Duration::from_millis(50));
+    assert_eq!(
+        timeout.scope(|| cancel::output(Command::new("sleep").arg("10"))),
+        Err(SkipReason::Timeout)
+    );
+
+    let token = CancellationToken::new();
+    let canceller = token.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(50));
+        canceller.cancel();
+    });
+    assert_eq!(
+        token.scope(|| cancel::output(Command::new("sleep").arg("10"))),
+        Err(SkipReason::Cancelled)
+    );
+    assert!(started_at.elapsed() < Duration::from_secs(5));
+
+    // Commands are not started once cancelled, and run normally outside of a scope.
+    assert_eq!(
+        token.scope(|| cancel::output(&mut Command::new("true"))),
+        Err(SkipReason::Cancelled)
+    );
+    assert!(
+        cancel::output(&mut Command::new("true"))
+            .unwrap()
+            .status
+            .success()
+    );
+}
+
+#[cfg(all(unix, feature = "env-var", feature = "kde-settings"))]
+#[test]
+fn detection_methods_run_in_the_configured_order() {
+    use std::{fs, os::unix::fs::PermissionsExt};
+
+    let root = std::env::temp_dir().join(format!("unidosx-order-{}", std::process::id()));
+    let (bin, config) = (root.join("bin"), root.join("config"));
+    fs::create_dir_all(&bin).unwrap();
+    fs::create_dir_all(&config).unwrap();
+    for name in ["kitty", "konsole"] {
+        fs::write(bin.join(name), "#!/bin/sh\n").unwrap();
+        fs::set_permissions(bin.join(name), fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    fs::write(
+        config.join("kdeglobals"),
+        "[General]\nTerminalApplication=konsole\n",
+    )
+    .unwrap();
+
+    let env = Environment::default()
+        .var("PATH", &bin)
+        .var("XDG_CONFIG_HOME", &config)
+        .var("XDG_CONFIG_DIRS", "/nonexistent")
+        .var("TERMINAL_EMULATOR", "kitty");
+
+    env.scope(|| {
+        assert_eq!(Detector::new().detect().unwrap().name(), "kitty");
+
+        let detector = Detector::new().methods([
+            DetectionMethod::KdeSettings,
+            DetectionMethod::TerminalApp,
+            DetectionMethod::EnvironmentVariable,
+            DetectionMethod::KdeSettings,
+        ]);
+        let terminal = detector.detect().unwrap();
+        assert_eq!(terminal.name(), "konsole");
+        assert_eq!(terminal.detection_method(), DetectionMethod::KdeSettings);
+
+        let report = detector.detect_report();
+        assert_eq!(
+            report
+                .entries
+                .iter()
+                .map(|entry| entry.method)
+                .collect::<Vec<_>>(),
+            [
+                DetectionMethod::KdeSettings,
+                DetectionMethod::TerminalApp,
+                DetectionMethod::EnvironmentVariable
+            ]
+        );
+        assert_eq!(report.selected, Some(terminal));
+    });
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[cfg(all(unix, feature = "env-var"))]
+#[test]
+fn detection_methods_can_be_disabled_at_runtime() {
+    let env = Environment::default()
+        .var("PATH", "/usr/bin:/bin")
+        .var("TERMINAL_EMULATOR", "sh");
+
+    env.scope(|| {
+        let detector = Detector::new().disable(DetectionMethod::EnvironmentVariable);
+
+        assert_eq!(
+            Detector::new().detect().unwrap().detection_method(),
+            DetectionMethod::EnvironmentVariable
+        );
+        assert_ne!(
+            detector
+                .detect()
+                .map(|terminal| terminal.detection_method()),
+            Some(DetectionMethod::EnvironmentVariable)
+        );
+        assert_eq!(
+            detector
+                .detect_report()
+                .skipped()
+                .find(|&(method, _)| method == DetectionMethod::EnvironmentVariable)
+                .map(|(_, reason)| reason),
+            Some(&SkipReason::Disabled)
+        );
+    });
+}
+
+#[cfg(all(unix, feature = "env-var"))]
+#[test]
+fn detection_backends_run_by_priority() {
+    struct Acme(i32);
+
+    impl DetectionBackend for Acme {
+        fn name(&self) -> &'static str {
+            "acme"
+        }
+
+        fn priority(&self) -> i32 {
+            self.0
+        }
+
+        fn detect(&self, env: &Environment) -> Option<TerminalEmulator> {
+            let path = env.get("ACME_TERMINAL")?;
+
+            Some(TerminalEmulator::new(path, ExecutionSyntax::E))
+        }
+    }
+
+    let env = Environment::default()
+        .var("PATH", "/usr/bin:/bin")
+        .var("TERMINAL_EMULATOR", "sh")
+        .var("ACME_TERMINAL", "/opt/acme/bin/kitty");
+
+    env.scope(|| {
+        let terminal = Detector::new().with_backend(Acme(1)).detect().unwrap();
+        assert_eq!(terminal.name(), "kitty");
+        assert_eq!(terminal.path(), Path::new("/opt/acme/bin/kitty"));
+        assert_eq!(
+            terminal.detection_method(),
+            DetectionMethod::Backend("acme")
+        );
+
+        let detector = Detector::new().with_backend(Acme(0));
+        assert_eq!(
+            detector.detect().unwrap().detection_method(),
+            DetectionMethod::EnvironmentVariable
+        );
+        assert_eq!(
+            detector
+                .detect_report()
+                .entries
+                .last()
+                .map(|entry| entry.method),
+            Some(DetectionMethod::Backend("acme"))
+        );
+
+        let disabled = Detector::new()
+            .with_backend(Acme(1))
+            .disable(DetectionMethod::Backend("acme"));
+        assert_eq!(
+            disabled.detect().unwrap().detection_method(),
+            DetectionMethod::EnvironmentVariable
+        );
+    });
+}
+
+#[test]
+fn ini_parser_tolerates_real_world_settings_files() {
+    use ini::Ini;
+
+    let ini = Ini::parse(
+        "\u{feff}; comment\r\nTerminalEmulator=foot\r\n[General]\r\nTerminalApplication[$e]= kitty \r\n\
+         garbage line\r\n[Other][$i]\r\nkey=1\r\n[General]\r\nName[de]=Terminal\r\nTerminalApplication=konsole\r\n",
+    );
+
+    assert_eq!(ini.get("", "TerminalEmulator"), Some("foot"));
+    assert_eq!(ini.get("General", "TerminalApplication"), Some("konsole"));
+    assert_eq!(ini.get("General", "Name[de]"), Some("Terminal"));
+    assert_eq!(ini.get("General", "Name"), None);
+    assert_eq!(ini.get("Other", "key"), Some("1"));
+    assert_eq!(
+        probes::parse_xfce_helpers_terminal("TerminalEmulator=xfce4-terminal\n"),
+        Some("xfce4-terminal".to_owned())
+    );
+    assert_eq!(
+        probes::parse_lxqt_session_terminal("[Environment]\nTERM=qterminal\n"),
+        Some("qterminal".to_owned())
+    );
+}
+
+#[test]
+fn ini_parser_survives_random_input() {
+    use ini::Ini;
+
+    const ALPHABET: &[char] = &[
+        '[', ']', '=', '$', '#', ';', ' ', '\t', '\r', '\n', '\u{feff}', 'a', 'Z', '0', 'é', 'ж',
+    ];
+
+    // xorshift64, deterministic so failures are reproducible.
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+
+    for _ in 0..2000 {
+        let len = next() % 64;
+        let input: String = (0..len)
+            .map(|_| ALPHABET[(next() % ALPHABET.len() as u64) as usize])
+            .collect();
+
+        let ini = Ini::parse(&input);
+        let key = format!("k{}", next() % 1000);
+
+        // Appending a well-formed key always makes it readable, whatever came before.
+        let appended = Ini::parse(&format!("{input}\n[s]\n{key} = v\n"));
+        assert_eq!(appended.get("s", &key), Some("v"), "input: {input:?}");
+        assert_eq!(Ini::parse(&input), ini);
+    }
+}
+
+#[test]
+fn desktop_entry_names_are_localized() {
+    use ini::Ini;
+
+    let entry = Ini::parse(
+        "[Desktop Entry]\nName=Terminal\nName[ru]=Терминал\nName[pt_BR]=Terminal BR\n\
+         Name[sr@latin]=Terminal Latin\nExec=/usr/bin/gnome-terminal --window\n",
+    );
+    let name = |locale| entry.get_localized("Desktop Entry", "Name", locale);
+
+    assert_eq!(name(Some("ru_RU.UTF-8")), Some("Терминал"));
+    assert_eq!(name(Some("pt_BR.UTF-8")), Some("Terminal BR"));
+    assert_eq!(name(Some("sr_RS@latin")), Some("Terminal Latin"));
+    assert_eq!(name(Some("de_DE.UTF-8")), Some("Terminal"));
+    assert_eq!(name(None), Some("Terminal"));
+    assert!(desktop_entry::entry_runs(&entry, "gnome-terminal"));
+    assert!(!desktop_entry::entry_runs(&entry, "konsole"));
+}
+
+#[cfg(unix)]
+#[test]
+fn standard_directories_are_searched_without_path() {
+    for env in [
+        Environment::default(),
+        Environment::default().var("PATH", ""),
+    ] {
+        let sh = env.scope(|| lookup::find_executable("sh"));
+
+        assert!(sh.is_some_and(|sh| sh.starts_with("/usr") || sh.starts_with("/bin")));
+    }
+}
+
+#[cfg(all(unix, feature = "kde-settings", feature = "xdg-terminal-exec"))]
+#[test]
+fn xdg_config_and_data_directories_are_honored() {
+    use std::{fs, os::unix::fs::PermissionsExt};
+
+    let root = std::env::temp_dir().join(format!("unidosx-xdg-{}", std::process::id()));
+    let (bin, config_home, config_dir, data_home) = (
+        root.join("bin"),
+        root.join("config"),
+        root.join("etc-xdg"),
+        root.join("data"),
+    );
+    for dir in [
+        &bin,
+        &config_home,
+        &config_dir,
+        &data_home.join("applications"),
+    ] {
+        fs::create_dir_all(dir).unwrap();
+    }
+    fs::write(bin.join("foot"), "#!/bin/sh\n").unwrap();
+    fs::set_permissions(bin.join("foot"), fs::Permissions::from_mode(0o755)).unwrap();
+    fs::write(config_home.join("kdeglobals"), "[General]\nOther=1\n").unwrap();
+    fs::write(
+        config_dir.join("kdeglobals"),
+        "[General]\nTerminalApplication=foot\n",
+    )
+    .unwrap();
+    fs::write(
+        config_home.join("xdg-terminals.list"),
+        "# preferred\nfoot.desktop\n",
+    )
+    .unwrap();
+    fs::write(
+        data_home.join("applications/foot.desktop"),
+        "[Desktop Entry]\nName=Foot\nExec=foot\n",
+    )
+    .unwrap();
+
+    let env = Environment::default()
+        .var("PATH", &bin)
+        .var("XDG_CONFIG_HOME", &config_home)
+        .var("XDG_CONFIG_DIRS", &config_dir)
+        .var("XDG_DATA_HOME", &data_home)
+        .var("XDG_DATA_DIRS", "relative:/nonexistent");
+
+    env.scope(|| {
+        assert_eq!(
+            xdg::config_dirs(),
+            [config_home.clone(), config_dir.clone()]
+        );
+        assert_eq!(xdg::data_dirs(), [data_home.clone(), "/nonexistent".into()]);
+
+        let kde = probe_kde_settings().unwrap();
+        assert_eq!(kde.path, bin.join("foot"));
+        assert_eq!(kde.display_name(), "Foot");
+
+        let xdg = probe_xdg_terminal_exec().unwrap();
+        assert_eq!(xdg.path, bin.join("foot"));
+        assert_eq!(xdg.execution_syntax, ExecutionSyntax::Command);
+    });
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn xdg_terminals_list_entries_are_parsed() {
+    assert_eq!(
+        probes::parse_xdg_terminals_list(
+            "# comment\n\n-xterm.desktop\n+kitty.desktop\nfoot.desktop:server\n"
+        ),
+        ["kitty.desktop", "foot.desktop"]
+    );
+}
+
+#[cfg(all(not(windows), feature = "env-var"))]
+#[test]
+fn terminal_emulators_can_be_stored_and_shared() {
+    use std::{sync::OnceLock, thread};
+
+    fn assert_owned<T: Send + Sync + 'static>() {}
+    assert_owned::<TerminalEmulator>();
+
+    static DETECTED: OnceLock<Option<TerminalEmulator>> = OnceLock::new();
+    let env = Environment::default()
+        .var("PATH", "/usr/bin:/bin")
+        .var("TERMINAL_EMULATOR", "sh");
+
+    let detected = thread::spawn(move || env.scope(detect)).join().unwrap();
+    let stored = DETECTED.get_or_init(|| detected);
+    assert_eq!(stored.as_ref().map(TerminalEmulator::name), Some("sh"));
+}
+
+#[test]
+fn terminal_emulators_expose_their_fields() {
+    let terminal = test_util::FakeTerminal::new("wezterm")
+        .path("/usr/bin/wezterm")
+        .method(DetectionMethod::KdeSettings)
+        .build();
+
+    assert_eq!(terminal.name(), "wezterm");
+    assert_eq!(terminal.path(), Path::new("/usr/bin/wezterm"));
+    assert_eq!(terminal.execution_syntax(), ExecutionSyntax::E);
+    assert_eq!(terminal.detection_method(), DetectionMethod::KdeSettings);
+
+    let unknown = test_util::FakeTerminal::new("")
+        .path("/opt/bin/myterm")
+        .build();
+    assert_eq!(unknown.name(), "myterm");
+}
+
+#[test]
+fn terminal_emulators_are_identified_by_name() {
+    use std::collections::{BTreeSet, HashSet};
+
+    let terminal = |command_line: &'static str, path: &str, method| TerminalEmulator {
+        command_line: command_line.into(),
+        execution_syntax: ExecutionSyntax::E,
+        path: path.into(),
+        method,
+        raw: None,
+    };
+    let kitty = terminal("kitty", "/usr/bin/kitty", DetectionMethod::HardcodedModern);
+    let unknown = terminal("", "/opt/bin/myterm", DetectionMethod::EnvironmentVariable);
+
+    assert_eq!(
+        kitty,
+        terminal(
+            "kitty",
+            "/usr/local/bin/kitty",
+            DetectionMethod::KdeSettings
+        )
+    );
+    assert!(kitty < unknown);
+
+    let set: HashSet<_> = [kitty.clone(), unknown.clone(), kitty.clone()].into();
+    assert_eq!(set.len(), 2);
+    assert!(set.contains("kitty"));
+    assert!(set.contains("myterm"));
+
+    let sorted: BTreeSet<_> = [kitty, unknown].into();
+    assert_eq!(
+        sorted.get("myterm").unwrap().path,
+        PathBuf::from("/opt/bin/myterm")
+    );
+}
+
+#[test]
+fn detection_can_be_faked_in_a_scope() {
+    use test_util::FakeTerminal;
+
+    let fake = FakeTerminal::new("foot").path("/fake/foot");
+    let detected = fake.clone().scope(detect);
+
+    #[cfg(not(windows))]
+    let detected = detected.unwrap();
+    assert_eq!(detected.path, PathBuf::from("/fake/foot"));
+    assert_eq!(detected.execution_syntax, ExecutionSyntax::Command);
+
+    #[cfg(not(windows))]
+    test_util::override_detect(None, || {
+        assert_eq!(detect(), None);
+        assert_eq!(fake.scope(detect).unwrap().command_line, "foot");
+        assert_eq!(detect(), None);
+    });
+}
+
+#[test]
+fn database_is_exported_as_json() {
+    let database = export_database();
+
+    assert!(database.starts_with("{\n  \"schema_version\": 1,\n"));
+    assert!(database.contains(
+        "{\"name\": \"kmscon\", \"list\": \"traditional\", \"execution_syntax\": \"double-dash\", \
+         \"exec_args\": [\"--login\", \"--\"], \"single_argument\": false, \"framebuffer\": true}"
+    ));
+    assert!(database.contains("\"name\": \"xfce4-terminal\", \"list\": \"desktop-env\", \"execution_syntax\": \"single-e\""));
+    assert_eq!(database.matches("\"name\"").count(), 43);
+    assert_eq!(json::string("a\"b\\\n\u{1}"), r#""a\"b\\\n\u0001""#);
+}
+
+#[test]
+fn launch_history_round_trips_through_jsonl() {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let root = std::env::temp_dir().join(format!("unidosx-history-{}", std::process::id()));
+    let env = Environment::default().var("XDG_STATE_HOME", &root);
+    let path = env.scope(launch_history_path).unwrap();
+    assert_eq!(path, root.join("unidosx/launch-history.jsonl"));
+    assert!(env.scope(launch_history).unwrap().is_empty());
+
+    let spawned = LaunchRecord {
+        timestamp: UNIX_EPOCH + Duration::from_millis(1_700_000_000_123),
+        program: String::from("/usr/bin/foot"),
+        command: String::from("/usr/bin/foot htop"),
+        backend: LaunchBackend::Exec.to_string(),
+        outcome: LaunchOutcome::Spawned { pid: 4242 },
+    };
+    let failed = LaunchRecord {
+        program: String::from("/opt/my \"terminal\""),
+        outcome: LaunchOutcome::Failed {
+            error: String::from("No such file or directory\n(os error 2)"),
+        },
+        ..spawned.clone()
+    };
+
+    history::append(&path, &spawned).unwrap();
+    std::fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| std::io::Write::write_all(&mut file, b"not json\n"))
+        .unwrap();
+    history::append(&path, &failed).unwrap();
+
+    assert_eq!(env.scope(launch_history).unwrap(), [spawned, failed]);
+
+    env.scope(clear_launch_history).unwrap();
+    assert!(env.scope(launch_history).unwrap().is_empty());
+    env.scope(clear_launch_history).unwrap();
+
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[cfg(all(unix, feature = "config-file", feature = "hardcoded-modern"))]
+#[test]
+fn first_run_saves_the_chosen_terminal() {
+    use std::{fs, os::unix::fs::PermissionsExt};
+
+    struct Pick(&'static str, usize);
+
+    impl TerminalPrompt for Pick {
+        fn choose(&mut self, choices: &[TerminalEmulator]) -> std::io::Result<Option<usize>> {
+            self.1 += 1;
+            Ok(choices
+                .iter()
+                .position(|terminal| terminal.name() == self.0))
+        }
+    }
+
+    let root = std::env::temp_dir().join(format!("unidosx-first-run-{}", std::process::id()));
+    let (bin, config) = (root.join("bin"), root.join("config"));
+    fs::create_dir_all(&bin).unwrap();
+    for name in ["foot", "kitty"] {
+        fs::write(bin.join(name), "#!/bin/sh\n").unwrap();
+        fs::set_permissions(bin.join(name), fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    let env = Environment::default()
+        .var("PATH", &bin)
+        .var("HOME", &root)
+        .var("XDG_CONFIG_HOME", &config)
+        .var("XDG_CONFIG_DIRS", root.join("nonexistent"))
+        .var("XDG_DATA_DIRS", root.join("nonexistent"));
+
+    env.scope(|| {
+        let mut prompt = Pick("kitty", 0);
+
+        let chosen = first_run(&mut prompt).unwrap().unwrap();
+        assert_eq!(chosen.path, bin.join("kitty"));
+        assert_eq!(
+            fs::read_to_string(default_terminal_config_path().unwrap()).unwrap(),
+            format!(
+                "[Terminal]\nExec={}\nExecutionSyntax=command\n",
+                bin.join("kitty").display()
+            )
+        );
+
+        let configured = first_run(&mut prompt).unwrap().unwrap();
+        assert_eq!(configured, chosen);
+        assert_eq!(configured.method, DetectionMethod::ConfigFile);
+        assert_eq!(prompt.1, 1);
+        assert_eq!(detect().unwrap().method, DetectionMethod::ConfigFile);
+    });
+
+    assert_eq!(
+        config::parse_terminal_config("[Terminal]\nExec=my-term\nExecutionSyntax=single-e\n"),
+        Some((String::from("my-term"), Some(ExecutionSyntax::SingleE)))
+    );
+    assert_eq!(config::parse_terminal_config("[Terminal]\nExec=\n"), None);
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[cfg(all(unix, feature = "config-file"))]
+#[test]
+fn system_wide_default_is_read_beneath_the_user_config() {
+    use std::{fs, os::unix::fs::PermissionsExt};
+
+    let root = std::env::temp_dir().join(format!("unidosx-system-config-{}", std::process::id()));
+    let (bin, config, system) = (root.join("bin"), root.join("config"), root.join("etc-xdg"));
+    fs::create_dir_all(&bin).unwrap();
+    fs::create_dir_all(system.join("unidosx")).unwrap();
+    for name in ["foot", "kitty"] {
+        fs::write(bin.join(name), "#!/bin/sh\n").unwrap();
+        fs::set_permissions(bin.join(name), fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    fs::write(
+        system.join("unidosx/terminal.conf"),
+        "[Terminal]\nExec=foot\n",
+    )
+    .unwrap();
+
+    let env = Environment::default()
+        .var("PATH", &bin)
+        .var("XDG_CONFIG_HOME", &config)
+        .var("XDG_CONFIG_DIRS", &system);
+
+    env.scope(|| {
+        assert_eq!(probe_config_file().unwrap().path, bin.join("foot"));
+
+        fs::create_dir_all(config.join("unidosx")).unwrap();
+        fs::write(
+            config.join("unidosx/terminal.conf"),
+            "[Terminal]\nExec=kitty\n",
+        )
+        .unwrap();
+        assert_eq!(probe_config_file().unwrap().path, bin.join("kitty"));
+    });
+
+    assert!(config::parse_terminal_lock(
+        "[Terminal]\nExec=foot\nLocked=true\n"
+    ));
+    assert!(!config::parse_terminal_lock(
+        "[Terminal]\nExec=foot\nLocked=false\n"
+    ));
+    assert!(!config::parse_terminal_lock("[Other]\nLocked=true\n"));
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[cfg(all(unix, feature = "config-file"))]
+#[test]
+fn saving_the_default_terminal_keeps_other_settings() {
+    use std::fs;
+
+    let root = std::env::temp_dir().join(format!("unidosx-save-config-{}", std::process::id()));
+    let config = root.join("config");
+    fs::create_dir_all(config.join("unidosx")).unwrap();
+    fs::write(
+        config.join("unidosx/terminal.conf"),
+        "# Managed by hand\n[Terminal]\nExec=foot\nFontSize=12\nPreferAccessible=true\n\n[Other]\nExec=kept\n",
+    )
+    .unwrap();
+
+    let env = Environment::default()
+        .var("XDG_CONFIG_HOME", &config)
+        .var("XDG_CONFIG_DIRS", root.join("nonexistent"));
+
+    env.scope(|| {
+        let terminal = test_util::FakeTerminal::new("kitty")
+            .path("/usr/bin/kitty")
+            .build();
+        set_default_terminal(&terminal).unwrap();
+        set_default_terminal(&terminal).unwrap();
+    });
+
+    assert_eq!(
+        fs::read_to_string(config.join("unidosx/terminal.conf")).unwrap(),
+        "# Managed by hand\n[Terminal]\nExec=/usr/bin/kitty\nExecutionSyntax=command\nFontSize=12\n\
+         PreferAccessible=true\n\n[Other]\nExec=kept\n"
+    );
+    assert_eq!(
+        config::update_terminal_config("Key=value\n", "foot", "single-e"),
+        "Key=value\n\n[Terminal]\nExec=foot\nExecutionSyntax=single-e\n"
+    );
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[cfg(all(not(windows), feature = "config-file"))]
+#[test]
+fn stdin_prompt_asks_until_a_valid_choice() {
+    let choices = [
+        test_util::FakeTerminal::new("foot").build(),
+        test_util::FakeTerminal::new("kitty").build(),
+    ];
+    let mut output = Vec::new();
+
+    let choice = wizard::prompt(&b"3\nkitty\n2\n"[..], &mut output, &choices).unwrap();
+
+    assert_eq!(choice, Some(1));
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("  2) kitty (kitty)\n"));
+    assert!(output.contains("Invalid choice: 3\n"));
+    assert_eq!(
+        wizard::prompt(&b"\n"[..], Vec::new(), &choices).unwrap(),
+        None
+    );
+    assert_eq!(
+        wizard::prompt(&b""[..], Vec::new(), &choices).unwrap(),
+        None
+    );
+}
+
+#[cfg(not(windows))]
+#[test]
+fn selection_strategies_pick_among_candidates() {
+    use selection::pick;
+    use test_util::FakeTerminal;
+
+    let candidates = [
+        FakeTerminal::new("foot").build(),
+        FakeTerminal::new("kitty").build(),
+        FakeTerminal::new("xterm").build(),
+    ];
+
+    let rotated: Vec<_> = (0..4)
+        .map(|turn| pick(&SelectionStrategy::RoundRobin, &candidates, turn, 0))
+        .collect();
+    assert_eq!(rotated, [Some(0), Some(1), Some(2), Some(0)]);
+    assert_eq!(pick(&SelectionStrategy::Random, &candidates, 0, 7), Some(1));
+    assert_eq!(pick(&SelectionStrategy::Random, &[], 0, 7), None);
+
+    let weighted = SelectionStrategy::Weighted {
+        weights: vec![(String::from("kitty"), 3), (String::from("xterm"), 1)],
+        default_weight: 0,
+    };
+    let picked: Vec<_> = (0..4)
+        .map(|random| pick(&weighted, &candidates, 0, random))
+        .collect();
+    assert_eq!(picked, [Some(1), Some(1), Some(1), Some(2)]);
+
+    let never = SelectionStrategy::Weighted {
+        weights: Vec::new(),
+        default_weight: 0,
+    };
+    assert_eq!(pick(&never, &candidates, 0, 0), None);
+}
+
+#[cfg(all(unix, feature = "benchmark", feature = "hardcoded-modern"))]
+#[test]
+fn fastest_terminal_is_picked_from_benchmarks() {
+    use std::{fs, os::unix::fs::PermissionsExt, time::Duration};
+    use test_util::FakeTerminal;
+
+    let root = std::env::temp_dir().join(format!("unidosx-benchmark-{}", std::process::id()));
+    fs::create_dir_all(&root).unwrap();
+    for (name, status) in [("kitty", 0), ("alacritty", 1)] {
+        fs::write(root.join(name), format!("#!/bin/sh\nexit {status}\n")).unwrap();
+        fs::set_permissions(root.join(name), fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    let env = Environment::default()
+        .var("PATH", &root)
+        .var("XDG_CONFIG_HOME", root.join("nonexistent"))
+        .var("XDG_CONFIG_DIRS", root.join("nonexistent"))
+        .var("XDG_DATA_DIRS", root.join("nonexistent"));
+    let benchmarks = env.scope(|| benchmark_candidates(Duration::from_secs(5)));
+
+    assert_eq!(benchmarks.len(), 2);
+    assert_eq!(benchmarks[0].terminal.path, root.join("kitty"));
+    assert!(benchmarks[0].startup.is_some());
+    assert_eq!(benchmarks[1].startup, None);
+    assert!(benchmark::startup_time(&root.join("kitty")).is_some());
+
+    let candidates = [
+        FakeTerminal::new("unbenchmarked-foot").build(),
+        FakeTerminal::new("slow-xterm").build(),
+        FakeTerminal::new("fast-wezterm").build(),
+    ];
+    benchmark::record(&candidates[1].path, Duration::from_millis(80));
+    benchmark::record(&candidates[2].path, Duration::from_millis(20));
+
+    assert_eq!(
+        selection::pick(&SelectionStrategy::Fastest, &candidates, 0, 0),
+        Some(2)
+    );
+    assert_eq!(
+        selection::pick(&SelectionStrategy::Fastest, &candidates[..1], 0, 0),
+        Some(0)
+    );
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+#[test]
+fn wayland_only_terminals_need_a_live_socket() {
+    use std::os::unix::net::UnixListener;
+
+    let runtime_dir = std::env::temp_dir().join(format!("unidosx-wayland-{}", std::process::id()));
+    std::fs::create_dir_all(&runtime_dir).unwrap();
+    let _listener = UnixListener::bind(runtime_dir.join("wayland-1")).unwrap();
+    let paths = ["/usr/bin/foot", "/usr/bin/kitty"];
+
+    let alive = Environment::default()
+        .var("XDG_RUNTIME_DIR", &runtime_dir)
+        .var("WAYLAND_DISPLAY", "wayland-1");
+    alive.scope(|| {
+        assert!(session::wayland_socket_alive());
+        assert_eq!(
+            failures::prefer_working(paths, |path| path.as_ref()),
+            Some(paths[0])
+        );
+    });
+
+    let absolute = Environment::default().var("WAYLAND_DISPLAY", runtime_dir.join("wayland-1"));
+    assert!(absolute.scope(session::wayland_socket_alive));
+
+    let dead = alive.var("WAYLAND_DISPLAY", "wayland-0");
+    dead.scope(|| {
+        assert!(!session::wayland_socket_alive());
+        assert_eq!(
+            failures::prefer_working(paths, |path| path.as_ref()),
+            Some(paths[1])
+        );
+        assert_eq!(
+            failures::prefer_working([paths[0]], |path| path.as_ref()),
+            Some(paths[0])
+        );
+    });
+
+    std::fs::remove_dir_all(runtime_dir).unwrap();
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+#[test]
+fn terminal_environment_requirements_are_checked() {
+    use test_util::FakeTerminal;
+
+    let foot = FakeTerminal::new("foot").path("/usr/bin/foot").build();
+    let xterm = FakeTerminal::new("xterm").build();
+    let kitty = FakeTerminal::new("kitty").build();
+
+    assert_eq!(foot.required_env_vars(), ["WAYLAND_DISPLAY"]);
+    assert_eq!(xterm.required_env_vars(), ["DISPLAY"]);
+    assert!(kitty.required_env_vars().is_empty());
+
+    let unset = Environment::default()
+        .remove_var("WAYLAND_DISPLAY")
+        .remove_var("DISPLAY");
+    unset.scope(|| {
+        let error = foot.check_environment().unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::NotConnected);
+        assert_eq!(
+            error.to_string(),
+            "foot needs WAYLAND_DISPLAY, which is not set"
+        );
+        assert_eq!(
+            xterm.check_environment().unwrap_err().to_string(),
+            "xterm needs DISPLAY, which is not set"
+        );
+        assert!(kitty.check_environment().is_ok());
+    });
+
+    let dead = Environment::default()
+        .var("WAYLAND_DISPLAY", "/nonexistent/wayland-0")
+        .var("DISPLAY", "/nonexistent/x11:0");
+    dead.scope(|| {
+        assert_eq!(
+            foot.check_environment().unwrap_err().to_string(),
+            "foot needs WAYLAND_DISPLAY, but /nonexistent/wayland-0 accepts no connections"
+        );
+        assert!(xterm.check_environment().is_err());
+    });
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+#[test]
+fn x11_only_terminals_need_a_live_display() {
+    use session::{X11Address, parse_display};
+
+    assert_eq!(
+        parse_display(":1"),
+        Some(X11Address::Unix("/tmp/.X11-unix/X1".into()))
+    );
+    assert_eq!(
+        parse_display("unix:0.1"),
+        Some(X11Address::Unix("/tmp/.X11-unix/X0".into()))
+    );
+    assert_eq!(
+        parse_display("localhost:10.0"),
+        Some(X11Address::Tcp("localhost".into(), 6010))
+    );
+    assert_eq!(
+        parse_display("[::1]:2"),
+        Some(X11Address::Tcp("::1".into(), 6002))
+    );
+    assert_eq!(
+        parse_display("/private/tmp/launchd/org.xquartz:0"),
+        Some(X11Address::Unix(
+            "/private/tmp/launchd/org.xquartz:0".into()
+        ))
+    );
+    assert_eq!(parse_display("wayland-0"), None);
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let paths = ["/usr/bin/xterm", "/usr/bin/kitty"];
+
+    if let Some(display) = port.checked_sub(6000) {
+        let alive = Environment::default().var("DISPLAY", format!("127.0.0.1:{display}"));
+        assert!(alive.scope(session::x11_display_alive));
+    }
+
+    Environment::default().scope(|| {
+        assert!(!session::x11_display_alive());
+        assert_eq!(
+            failures::prefer_working(paths, |path| path.as_ref()),
+            Some(paths[1])
+        );
+    });
+}
+
+#[cfg(all(unix, feature = "env-var", not(feature = "watch")))]
+#[test]
+fn detection_cache_is_reused_until_settings_change() {
+    use std::{fs, os::unix::fs::PermissionsExt};
+
+    let root = std::env::temp_dir().join(format!("unidosx-cache-{}", std::process::id()));
+    let (bin, config) = (root.join("bin"), root.join("config"));
+    fs::create_dir_all(&bin).unwrap();
+    fs::create_dir_all(&config).unwrap();
+    for name in ["foot", "kitty"] {
+        fs::write(bin.join(name), "#!/bin/sh\n").unwrap();
+        fs::set_permissions(bin.join(name), fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    let env = Environment::default()
+        .var("PATH", &bin)
+        .var("TERMINAL_EMULATOR", "kitty")
+        .var("XDG_CACHE_HOME", root.join("cache"))
+        .var("XDG_CONFIG_HOME", &config)
+        .var("XDG_CONFIG_DIRS", root.join("nonexistent"))
+        .var("XDG_DATA_DIRS", root.join("nonexistent"));
+
+    env.scope(|| {
+        let detected = detect_cached().unwrap();
+        assert_eq!(detected.path, bin.join("kitty"));
+
+        // A tampered cache file with the same fingerprint proves the cached result is reused.
+        let cache = detection_cache_path().unwrap();
+        let contents = fs::read_to_string(&cache).unwrap();
+        assert!(contents.contains("\"command_line\": \"kitty\""));
+        fs::write(&cache, contents.replace("kitty", "foot")).unwrap();
+        let cached = detect_cached().unwrap();
+        assert_eq!(cached.path, bin.join("foot"));
+        assert_eq!(cached.execution_syntax, ExecutionSyntax::Command);
+        assert_eq!(cached.method, DetectionMethod::EnvironmentVariable);
+        assert_eq!(
+            env.clone()
+                .var("XDG_CURRENT_DESKTOP", "KDE")
+                .scope(|| detect_cached().unwrap().path),
+            bin.join("kitty")
+        );
+
+        fs::write(config.join("kdeglobals"), "[General]\n").unwrap();
+        assert_eq!(detect_cached().unwrap().path, bin.join("kitty"));
+
+        clear_detection_cache().unwrap();
+        assert!(!cache.exists());
+    });
+
+    assert!(cache::parse_cache("{\"version\": 0}", 0).is_none());
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[cfg(all(target_os = "linux", feature = "watch"))]
+#[test]
+fn watched_settings_changes_invalidate_the_memory_cache() {
+    let dir = std::env::temp_dir().join(format!("unidosx-watch-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let kitty = test_util::FakeTerminal::new("kitty").build();
+    let key = u64::MAX;
+
+    watch::watch(std::slice::from_ref(&dir));
+    watch::store(key, watch::generation(), 0, &kitty);
+    assert_eq!(watch::lookup(key, || 0), Some(kitty));
+
+    std::fs::write(dir.join("kdeglobals"), "[General]\n").unwrap();
+
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    while watch::lookup(key, || 0).is_some() {
+        assert!(
+            std::time::Instant::now() < deadline,
+            "the change was not noticed"
+        );
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(all(unix, feature = "env-var"))]
+#[test]
+fn unknown_terminal_syntax_is_inferred_from_its_help() {
+    use std::{fs, os::unix::fs::PermissionsExt};
+
+    use crate::inference::parse_help;
+
+    assert_eq!(
+        parse_help("Usage:\n  gnome-terminal [OPTION…] [-- COMMAND…]\n"),
+        Some(ExecutionSyntax::DoubleDash)
+    );
+    assert_eq!(
+        parse_help("  -e, --command <COMMAND>...  Command and args to execute\n"),
+        Some(ExecutionSyntax::E)
+    );
+    assert_eq!(
+        parse_help("  -x, --execute  Execute the remainder of the command line\n"),
+        Some(ExecutionSyntax::CustomFlag {
+            flag: "-x",
+            single_argument: false
+        })
+    );
+    assert_eq!(
+        parse_help("Usage: kitty [options] [program-to-run ...]\n"),
+        Some(ExecutionSyntax::Command)
+    );
+    assert_eq!(parse_help("Usage: myterm [OPTIONS]\n"), None);
+
+    let dir = std::env::temp_dir().join(format!("unidosx-inference-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("myterm");
+    fs::write(
+        &path,
+        "#!/bin/sh\necho 'Usage: myterm [OPTION...] [-- COMMAND...]' >&2\n",
+    )
+    .unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let detect = || {
+        [("TERMINAL_EMULATOR", path.as_os_str())]
+            .into_iter()
+            .collect::<Environment>()
+            .scope(|| {
+                Detector::new()
+                    .methods([DetectionMethod::EnvironmentVariable])
+                    .detect()
+                    .unwrap()
+                    .execution_syntax
+            })
+    };
+
+    assert_eq!(detect(), ExecutionSyntax::E);
+    probe_unknown_terminals(true);
+    let inferred = detect();
+    probe_unknown_terminals(false);
+    assert_eq!(inferred, ExecutionSyntax::DoubleDash);
+    assert_eq!(
+        infer_execution_syntax(&path),
+        Some(ExecutionSyntax::DoubleDash)
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn commands_run_in_a_new_terminal_in_one_call() {
+    use std::{fs, os::unix::fs::PermissionsExt};
+
+    let dir = std::env::temp_dir().join(format!("unidosx-run-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("myterm");
+    fs::write(&path, "#!/bin/sh\nprintf '%s\\n' \"$@\" > \"$OUT\"\n").unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    let out = dir.join("args");
+    let options = LaunchOptions::new().env("OUT", &out);
+
+    let error = test_util::override_detect(None, || {
+        run_in_new_terminal("vim", ["notes.txt"], &options).unwrap_err()
+    });
+    assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+
+    let fake = test_util::FakeTerminal::new("myterm")
+        .path(&path)
+        .execution_syntax(ExecutionSyntax::DoubleDash);
+    let status = [("DISPLAY", ":0")]
+        .into_iter()
+        .collect::<Environment>()
+        .scope(|| fake.scope(|| run_in_new_terminal("vim", ["my notes.txt"], &options)))
+        .unwrap()
+        .wait()
+        .unwrap();
+
+    assert!(status.success());
+    assert_eq!(fs::read_to_string(&out).unwrap(), "--\nvim\nmy notes.txt\n");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn spawns_are_logged_like_their_dry_run() {
+    use std::sync::Mutex;
+
+    static EVENTS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+    let kitty = Launcher::new(&TerminalEmulator::new(
+        "/usr/bin/kitty",
+        ExecutionSyntax::Command,
+    ));
+    let dry_run = [("DISPLAY", ":0")]
+        .into_iter()
+        .collect::<Environment>()
+        .scope(|| kitty.dry_run("vim", ["my notes.txt"]));
+    assert_eq!(
+        dry_run,
+        "Exec: env DISPLAY=:0 /usr/bin/kitty vim 'my notes.txt'"
+    );
+
+    let options = LaunchOptions::new()
+        .current_dir(std::env::temp_dir())
+        .env("SPAWN_LOG", "1");
+    let args = ["-c", "exit 0", "unidosx-spawn-log"];
+    let expected = Launcher::AllocConsole.dry_run_with_options("sh", args, &options);
+
+    set_spawn_logger(Some(|event| EVENTS.lock().unwrap().push(event.to_owned())));
+    let result = Launcher::AllocConsole.launch_with_options("sh", args, &options);
+    set_spawn_logger(None);
+
+    assert!(result.unwrap().into_child().wait().unwrap().success());
+    assert!(expected.starts_with("New Console: cd "));
+    assert!(EVENTS.lock().unwrap().contains(&expected));
+}
+
+#[cfg(unix)]
+#[test]
+fn spawned_terminals_can_be_killed() {
+    use std::{fs, os::unix::fs::PermissionsExt};
+
+    let dir = std::env::temp_dir().join(format!("unidosx-spawn-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("myterm");
+    fs::write(&path, "#!/bin/sh\nexec sleep 30\n").unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let terminal = TerminalEmulator::new(&path, ExecutionSyntax::E);
+    let mut child = [("DISPLAY", ":0")]
+        .into_iter()
+        .collect::<Environment>()
+        .scope(|| spawn_in_terminal(&terminal, "vim", ["notes.txt"]))
+        .unwrap();
+
+    assert_eq!(child.try_wait().unwrap(), None);
+    child.kill().unwrap();
+    assert!(!child.wait().unwrap().success());
+
+    let missing = TerminalEmulator::new(dir.join("missing"), ExecutionSyntax::E);
+    assert!(spawn_in_terminal(&missing, "vim", ["notes.txt"]).is_err());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn inner_exit_status_is_reported() {
+    use std::{fs, os::unix::fs::PermissionsExt, time::Duration};
+
+    let dir = std::env::temp_dir().join(format!("unidosx-inner-exit-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("myterm");
+    fs::write(&path, "#!/bin/sh\nexec \"$@\"\n").unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let launcher = Launcher::new(&TerminalEmulator::new(&path, ExecutionSyntax::Command));
+    let launch = |options: &LaunchOptions| {
+        [("DISPLAY", ":0")]
+            .into_iter()
+            .collect::<Environment>()
+            .scope(|| launcher.launch_with_options("sh", ["-c", "exit 7"], options))
+            .unwrap()
+    };
+
+    let tracked = LaunchOptions::new().track_exit_status(true);
+    let status_file = tracked.exit_status_file.clone().unwrap();
+    assert_eq!(
+        launch(&tracked)
+            .wait_for_inner_exit(Some(Duration::from_secs(5)))
+            .unwrap(),
+        7
+    );
+    assert!(!status_file.exists());
+
+    let untracked = launch(&LaunchOptions::new());
+    let error = untracked
+        .wait_for_inner_exit(Some(Duration::ZERO))
+        .unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    assert_eq!(untracked.into_child().wait().unwrap().code(), Some(7));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn launch_files_are_kept_in_a_private_directory() {
+    use std::{
+        fs,
+        os::unix::fs::{MetadataExt, PermissionsExt},
+    };
+
+    let runtime = std::env::temp_dir().join(format!("unidosx-private-{}", std::process::id()));
+    fs::create_dir_all(&runtime).unwrap();
+    fs::set_permissions(&runtime, fs::Permissions::from_mode(0o777)).unwrap();
+    let all = || LaunchOptions::new().capture_output(true).pipe_stdin(true);
+
+    let options = Environment::default()
+        .var("XDG_RUNTIME_DIR", &runtime)
+        .scope(all);
+    let status_file = options.exit_status_file.clone().unwrap();
+    let (stdout_file, stderr_file) = options.output_files.clone().unwrap();
+    let private = status_file.parent().unwrap().to_path_buf();
+    assert_eq!(private.parent(), Some(runtime.as_path()));
+    assert_eq!(fs::metadata(&private).unwrap().mode() & 0o777, 0o700);
+    for file in [&stdout_file, &stderr_file, &options.stdin_pipe.unwrap()] {
+        assert_eq!(file.parent(), Some(private.as_path()));
+    }
+
+    // A directory opened up to other users is replaced.
+    fs::set_permissions(&private, fs::Permissions::from_mode(0o777)).unwrap();
+    let options = Environment::default()
+        .var("XDG_RUNTIME_DIR", &runtime)
+        .scope(all);
+    let replaced = options.exit_status_file.unwrap();
+    assert_ne!(replaced.parent(), Some(private.as_path()));
+    assert_eq!(replaced.parent().unwrap().parent(), Some(runtime.as_path()));
+
+    let options = Environment::default()
+        .var("XDG_RUNTIME_DIR", runtime.join("missing"))
+        .scope(all);
+    assert_eq!(options.exit_status_file, None);
+    assert_eq!(options.output_files, None);
+    assert_eq!(options.stdin_pipe, None);
+
+    fs::remove_dir_all(&runtime).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn commands_are_waited_for() {
+    use std::{fs, os::unix::fs::PermissionsExt};
+
+    let options = LaunchOptions::new().wait(true);
+    let gnome = Launcher::new(&TerminalEmulator::new(
+        "/usr/bin/gnome-terminal",
+        ExecutionSyntax::DoubleDash,
+    ))
+    .build_invocation_with_options("make", ["check"], &options);
+    assert_eq!(gnome.args, ["--wait", "--", "make", "check"]);
+    assert_eq!(
+        Launcher::CmdStart
+            .build_invocation_with_options("make", ["check"], &options)
+            .args,
+        ["/c", "start", "", "/wait", "make", "check"]
+    );
+
+    // Hands the command over to another process and exits right away, like terminal emulator servers.
+    let dir = std::env::temp_dir().join(format!("unidosx-wait-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("myterm");
+    fs::write(&path, "#!/bin/sh\n\"$@\" &\n").unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let launcher = Launcher::new(&TerminalEmulator::new(&path, ExecutionSyntax::Command));
+    let mut launched = [("DISPLAY", ":0")]
+        .into_iter()
+        .collect::<Environment>()
+        .scope(|| launcher.launch_with_options("sh", ["-c", "sleep 0.2; exit 7"], &options))
+        .unwrap();
+    assert_eq!(launched.wait_for_command().unwrap(), Some(7));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn locale_and_encoding_reach_legacy_terminals() {
+    let build = |terminal: &str, options: &LaunchOptions| {
+        Launcher::new(&TerminalEmulator::new(terminal, ExecutionSyntax::E))
+            .build_invocation_with_options("htop", std::iter::empty::<&str>(), options)
+    };
+    let options = LaunchOptions::new()
+        .locale("en_US.UTF-8")
+        .encoding("UTF-8")
+        .env("LC_ALL", "C.UTF-8");
+
+    let xterm = build("/usr/bin/xterm", &options);
+    assert_eq!(xterm.args, ["-u8", "-e", "htop"]);
+    assert_eq!(
+        xterm.env,
+        [
+            ("LANG".into(), Some("en_US.UTF-8".into())),
+            ("LC_ALL".into(), Some("en_US.UTF-8".into())),
+            ("LC_ALL".into(), Some("C.UTF-8".into())),
+        ]
+    );
+    assert_eq!(
+        xterm
+            .to_command()
+            .get_envs()
+            .find(|(var, _)| *var == "LC_ALL"),
+        Some(("LC_ALL".as_ref(), Some("C.UTF-8".as_ref())))
+    );
+
+    let latin1 = LaunchOptions::new().encoding("ISO-8859-1");
+    assert_eq!(
+        build("/usr/bin/xterm", &latin1).args,
+        ["-en", "ISO-8859-1", "-e", "htop"]
+    );
+    assert_eq!(
+        build("/usr/bin/mlterm", &latin1).args,
+        ["--km=ISO-8859-1", "-e", "htop"]
+    );
+    assert_eq!(build("/usr/bin/kitty", &latin1).args, ["-e", "htop"]);
+}
+
+#[cfg(unix)]
+#[test]
+fn inner_output_is_captured() {
+    use std::{fs, os::unix::fs::PermissionsExt, time::Duration};
+
+    let dir = std::env::temp_dir().join(format!("unidosx-capture-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("myterm");
+    // The terminal window is simulated by discarding what it shows.
+    fs::write(&path, "#!/bin/sh\nexec \"$@\" > /dev/null 2>&1\n").unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let options = LaunchOptions::new().capture_output(true);
+    let (stdout_file, _) = options.output_files.clone().unwrap();
+    assert!(options.exit_status_file.is_some());
+
+    let launcher = Launcher::new(&TerminalEmulator::new(&path, ExecutionSyntax::Command));
+    let result = [("DISPLAY", ":0")]
+        .into_iter()
+        .collect::<Environment>()
+        .scope(|| {
+            launcher.launch_with_options(
+                "sh",
+                ["-c", "echo installed; echo warning >&2; exit 3"],
+                &options,
+            )
+        })
+        .unwrap();
+
+    let output = result
+        .wait_for_output(Some(Duration::from_secs(5)))
+        .unwrap();
+    assert_eq!(output.status, 3);
+    assert_eq!(output.stdout, b"installed\n");
+    assert_eq!(output.stderr, b"warning\n");
+    assert!(!stdout_file.exists());
+
+    let error = result.wait_for_output(Some(Duration::ZERO)).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::TimedOut);
+    assert_eq!(
+        Launcher::AllocConsole
+            .launch("true", std::iter::empty::<&str>())
+            .and_then(|result| result.wait_for_output(None))
+            .unwrap_err()
+            .kind(),
+        std::io::ErrorKind::InvalidInput
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn color_scheme_selects_terminal_flags() {
+    let args = |terminal: &str, syntax, color_scheme| {
+        Launcher::new(&TerminalEmulator::new(terminal, syntax))
+            .build_invocation_with_options(
+                "htop",
+                std::iter::empty::<&str>(),
+                &LaunchOptions::new().color_scheme(color_scheme),
+            )
+            .args
+    };
+
+    assert_eq!(
+        args(
+            "/usr/bin/gnome-terminal",
+            ExecutionSyntax::DoubleDash,
+            ColorScheme::Dark
+        ),
+        ["--profile=Dark", "--", "htop"]
+    );
+    assert_eq!(
+        args("/usr/bin/konsole", ExecutionSyntax::E, ColorScheme::Light),
+        ["--profile", "Light", "-e", "htop"]
+    );
+    assert_eq!(
+        args("/usr/bin/foot", ExecutionSyntax::Command, ColorScheme::Dark),
+        ["-o", "initial-color-theme=dark", "htop"]
+    );
+    assert_eq!(
+        args("/usr/bin/xterm", ExecutionSyntax::E, ColorScheme::Light),
+        ["-e", "htop"]
+    );
+    assert_eq!(
+        args("/usr/bin/xterm", ExecutionSyntax::E, ColorScheme::Dark),
+        ["-rv", "-e", "htop"]
+    );
+    assert_eq!(
+        args(
+            "/usr/bin/kitty",
+            ExecutionSyntax::Command,
+            ColorScheme::Dark
+        ),
+        ["htop"]
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn font_size_selects_terminal_flags() {
+    let options = LaunchOptions::new().font_size(18);
+    let args = |terminal: &str| {
+        Launcher::new(&TerminalEmulator::new(terminal, ExecutionSyntax::E))
+            .build_invocation_with_options("htop", std::iter::empty::<&str>(), &options)
+            .args
+    };
+
+    assert_eq!(args("/usr/bin/kitty"), ["-o", "font_size=18", "-e", "htop"]);
+    assert_eq!(
+        args("/usr/bin/alacritty"),
+        ["-o", "font.size=18", "-e", "htop"]
+    );
+    assert_eq!(args("/usr/bin/ghostty"), ["--font-size=18", "-e", "htop"]);
+    assert_eq!(args("/usr/bin/xterm"), ["-fs", "18", "-e", "htop"]);
+    assert_eq!(args("/usr/bin/konsole"), ["-e", "htop"]);
+}
+
+#[cfg(all(unix, feature = "daemon"))]
+#[test]
+fn daemon_answers_detect_requests() {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt, time::Duration};
+
+    let dir = std::env::temp_dir().join(format!("unidosx-daemon-{}", std::process::id()));
+    let path = dir.join("daemon.sock");
+    let served = path.clone();
+    std::thread::spawn(move || serve_daemon(&served));
+
+    for _ in 0..200 {
+        if std::os::unix::net::UnixStream::connect(&path).is_ok() {
+            break;
+        }
+
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(
+        serve_daemon(&path).unwrap_err().kind(),
+        std::io::ErrorKind::AddrInUse
+    );
+    assert_eq!(
+        detect_via_daemon(&path)
+            .unwrap()
+            .map(|terminal| terminal.path),
+        detect_cached().map(|terminal| terminal.path)
+    );
+    assert_eq!(
+        launch_via_daemon(&path, OsStr::from_bytes(b"caf\xe9"), ["x"])
+            .unwrap_err()
+            .kind(),
+        std::io::ErrorKind::InvalidInput
+    );
+    assert_eq!(
+        daemon::answer(r#"{"request": "reboot"}"#)
+            .unwrap_err()
+            .kind(),
+        std::io::ErrorKind::InvalidInput
+    );
+    assert_eq!(
+        detect_via_daemon(&dir.join("missing.sock"))
+            .unwrap_err()
+            .kind(),
+        std::io::ErrorKind::NotFound
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(all(unix, feature = "daemon"))]
+#[test]
+fn daemon_client_falls_back_in_process() {
+    use std::time::Duration;
+
+    let dir = std::env::temp_dir().join(format!("unidosx-client-{}", std::process::id()));
+    let path = dir.join("daemon.sock");
+    let served = path.clone();
+    std::thread::spawn(move || serve_daemon(&served));
+
+    for _ in 0..200 {
+        if std::os::unix::net::UnixStream::connect(&path).is_ok() {
+            break;
+        }
+
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    let client = DaemonClient::connect_to(&path).unwrap();
+    assert!(client.is_connected());
+    assert_eq!(
+        client.detect().unwrap().map(|terminal| terminal.path),
+        detect_cached().map(|terminal| terminal.path)
+    );
+
+    // A socket left behind by a daemon that exited refuses connections.
+    let stale = dir.join("stale.sock");
+    drop(std::os::unix::net::UnixListener::bind(&stale).unwrap());
+
+    for path in [stale, dir.join("missing.sock")] {
+        let client = DaemonClient::connect_to(path).unwrap();
+        assert!(!client.is_connected());
+        assert_eq!(
+            client.detect().unwrap().map(|terminal| terminal.path),
+            detect_cached().map(|terminal| terminal.path)
+        );
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(all(unix, feature = "daemon"))]
+#[test]
+fn daemon_metrics_are_served_to_prometheus() {
+    use std::io::{Read, Write};
+
+    let mut metrics = daemon::Metrics::new();
+    metrics.detections = 3;
+    metrics.launches.insert(String::from("kitty"), (2, 1));
+    metrics.launches.insert(String::from("odd\"term"), (1, 0));
+
+    let text = metrics.render(5);
+    assert!(text.contains(
+        "# TYPE unidosx_daemon_detections_total counter\nunidosx_daemon_detections_total 3\n"
+    ));
+    assert!(text.contains("\nunidosx_detection_cache_hits_total 5\n"));
+    assert!(text.contains("\nunidosx_daemon_launches_total{terminal=\"kitty\"} 2\n"));
+    assert!(text.contains("\nunidosx_daemon_launch_failures_total{terminal=\"kitty\"} 1\n"));
+    assert!(text.contains("\nunidosx_daemon_launch_failures_total{terminal=\"odd\\\"term\"} 0\n"));
+
+    let _ = daemon::answer(r#"{"request": "detect"}"#);
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    std::thread::spawn(move || serve_daemon_metrics(listener));
+
+    let mut stream = std::net::TcpStream::connect(address).unwrap();
+    stream
+        .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.contains("\r\n\r\n# HELP unidosx_daemon_detections_total "));
+    assert!(!response.contains("unidosx_daemon_detections_total 0\n"));
+}
+
+#[cfg(unix)]
+#[test]
+fn working_directory_uses_terminal_flags_or_cd() {
+    let options = LaunchOptions::new().current_dir("/srv/app");
+    let args = |terminal: &str, syntax| {
+        Launcher::new(&TerminalEmulator::new(terminal, syntax))
+            .build_invocation_with_options("htop", std::iter::empty::<&str>(), &options)
+            .args
+    };
+
+    assert_eq!(
+        args("/usr/bin/gnome-terminal", ExecutionSyntax::DoubleDash),
+        ["--working-directory=/srv/app", "--", "htop"]
+    );
+    assert_eq!(
+        args("/usr/bin/konsole", ExecutionSyntax::E),
+        ["--workdir", "/srv/app", "-e", "htop"]
+    );
+    assert_eq!(
+        args("/usr/bin/terminology", ExecutionSyntax::E),
+        ["--current-directory=/srv/app", "-e", "htop"]
+    );
+    assert_eq!(
+        args("/usr/bin/wezterm", ExecutionSyntax::E),
+        [
+            "-e",
+            "sh",
+            "-c",
+            r#"cd -- "$1" || exit; shift; exec "$@""#,
+            "sh",
+            "/srv/app",
+            "htop"
+        ]
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn title_uses_terminal_flags_or_escape() {
+    let options = LaunchOptions::new().title("Backup in progress");
+    let args = |terminal: &str| {
+        Launcher::new(&TerminalEmulator::new(terminal, ExecutionSyntax::E))
+            .build_invocation_with_options("restic", ["backup"], &options)
+            .args
+    };
+
+    assert_eq!(
+        args("/usr/bin/rxvt"),
+        ["-T", "Backup in progress", "-e", "restic", "backup"]
+    );
+    assert_eq!(
+        args("/usr/bin/st"),
+        ["-t", "Backup in progress", "-e", "restic", "backup"]
+    );
+    assert_eq!(
+        args("/usr/bin/ptyxis"),
+        [
+            "-e",
+            "sh",
+            "-c",
+            r#"printf '\033]0;%s\007' "$1"; shift; exec "$@""#,
+            "sh",
+            "Backup in progress",
+            "restic",
+            "backup"
+        ]
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn hold_uses_terminal_flags_or_waits_for_enter() {
+    let options = LaunchOptions::new().hold(true);
+    let args = |terminal: &str| {
+        Launcher::new(&TerminalEmulator::new(terminal, ExecutionSyntax::E))
+            .build_invocation_with_options("make", ["check"], &options)
+            .args
+    };
+
+    assert_eq!(
+        args("/usr/bin/terminator"),
+        ["--hold", "-e", "make", "check"]
+    );
+    assert_eq!(
+        args("/usr/bin/st"),
+        [
+            "-e",
+            "sh",
+            "-c",
+            r#""$@"; status=$?; printf '\n[exited with status %s, press Enter to close] ' "$status"; read -r _; exit "$status""#,
+            "sh",
+            "make",
+            "check"
+        ]
+    );
+}
+
+#[test]
+fn capabilities_come_from_the_database() {
+    let capabilities = |path: &str| TerminalEmulator::new(path, ExecutionSyntax::E).capabilities();
+
+    assert_eq!(
+        capabilities("/usr/bin/gnome-terminal"),
+        TerminalCapabilities {
+            supports_hold: false,
+            supports_title: true,
+            supports_working_directory: true,
+            supports_wait: true,
+            supports_tabs: true,
+            wayland_native: true,
+            truecolor: true,
+            clipboard: false,
+        }
+    );
+    assert_eq!(
+        capabilities("/usr/bin/xterm"),
+        TerminalCapabilities {
+            supports_hold: true,
+            supports_title: true,
+            supports_working_directory: false,
+            supports_wait: false,
+            supports_tabs: false,
+            wayland_native: false,
+            truecolor: false,
+            clipboard: false,
+        }
+    );
+    assert_eq!(
+        capabilities("/opt/myterm"),
+        TerminalCapabilities {
+            truecolor: true,
+            ..TerminalCapabilities::default()
+        }
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn stdin_is_piped_into_the_command() {
+    use std::{ffi::OsStr, fs, io::Write, os::unix::fs::PermissionsExt, time::Duration};
+
+    let dir = std::env::temp_dir().join(format!("unidosx-stdin-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("myterm");
+    fs::write(&path, "#!/bin/sh\nexec \"$@\"\n").unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    let output = dir.join("output");
+
+    let options = LaunchOptions::new()
+        .pipe_stdin(true)
+        .track_exit_status(true);
+    let pipe = options.stdin_pipe.clone().unwrap();
+    let launcher = Launcher::new(&TerminalEmulator::new(&path, ExecutionSyntax::Command));
+    let launched = [("DISPLAY", ":0")]
+        .into_iter()
+        .collect::<Environment>()
+        .scope(|| {
+            launcher.launch_with_options(
+                "sh",
+                [
+                    OsStr::new("-c"),
+                    OsStr::new(r#"tr a-z A-Z > "$0""#),
+                    output.as_os_str(),
+                ],
+                &options,
+            )
+        })
+        .unwrap();
+
+    let mut stdin = launched.open_stdin(Some(Duration::from_secs(5))).unwrap();
+    stdin.write_all(b"generated report\n").unwrap();
+    drop(stdin);
+
+    assert_eq!(
+        launched
+            .wait_for_inner_exit(Some(Duration::from_secs(5)))
+            .unwrap(),
+        0
+    );
+    assert_eq!(fs::read_to_string(&output).unwrap(), "GENERATED REPORT\n");
+    assert!(!pipe.exists());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn relaunched_programs_are_not_relaunched_again() {
+    [(RELAUNCHED_VAR, "1")]
+        .into_iter()
+        .collect::<Environment>()
+        .scope(|| {
+            assert!(!run::needs_relaunch());
+            relaunch_in_terminal_if_needed().unwrap();
+        });
+}
+
+#[test]
+fn clipboard_is_set_through_osc52() {
+    assert_eq!(clipboard::base64(b""), "");
+    assert_eq!(clipboard::base64(b"f"), "Zg==");
+    assert_eq!(clipboard::base64(b"fo"), "Zm8=");
+    assert_eq!(clipboard::base64(b"foo"), "Zm9v");
+    assert_eq!(clipboard::base64(b"foob"), "Zm9vYg==");
+    assert_eq!(clipboard::base64(&[0xfb, 0xff, 0xbf]), "+/+/");
+    assert_eq!(clipboard::osc52("héllo"), "\x1b]52;c;aMOpbGxv\x07");
+
+    assert!(
+        TerminalEmulator::new("/usr/bin/kitty", ExecutionSyntax::Command)
+            .capabilities()
+            .clipboard
+    );
+    assert!(
+        !TerminalEmulator::new("/usr/bin/gnome-terminal", ExecutionSyntax::DoubleDash)
+            .capabilities()
+            .clipboard
+    );
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+#[test]
+fn graphical_sessions_need_a_display() {
+    use std::io::IsTerminal;
+
+    [("WAYLAND_DISPLAY", "wayland-1")]
+        .into_iter()
+        .collect::<Environment>()
+        .scope(|| assert!(has_graphical_session()));
+
+    [("XDG_SESSION_TYPE", "wayland")]
+        .into_iter()
+        .collect::<Environment>()
+        .scope(|| {
+            assert!(!has_graphical_session());
+
+            let error = check_graphical_session().unwrap_err();
+            assert_eq!(error.kind(), std::io::ErrorKind::NotConnected);
+            assert!(
+                error
+                    .to_string()
+                    .ends_with(", although XDG_SESSION_TYPE is wayland")
+            );
+        });
+
+    assert_eq!(
+        is_attached_to_tty(),
+        std::io::stdin().is_terminal() && std::io::stdout().is_terminal()
+    );
+}
+
+#[cfg(all(unix, feature = "terminal-query"))]
+#[test]
+fn terminal_answers_are_parsed() {
+    let kitty =
+        query::parse_answers(b"\x1bP>|kitty(0.35.2)\x1b\\\x1b[>1;4000;29c\x1b[?62;c").unwrap();
+    assert_eq!(kitty.xtversion.as_deref(), Some("kitty(0.35.2)"));
+    assert_eq!(
+        (kitty.name(), kitty.version()),
+        (Some("kitty"), Some("0.35.2"))
+    );
+    assert_eq!(kitty.secondary_attributes, [1, 4000, 29]);
+    assert_eq!(kitty.primary_attributes, [62]);
+
+    let wezterm =
+        query::parse_answers(b"\x1bP>|WezTerm 20240203\x1b\\\x1b[?65;4;6;18;22c").unwrap();
+    assert_eq!(
+        (wezterm.name(), wezterm.version()),
+        (Some("WezTerm"), Some("20240203"))
+    );
+
+    // VTE ignores XTVERSION.
+    let vte = query::parse_answers(b"\x1b[>65;7600;1c\x1b[?65;1;9c").unwrap();
+    assert_eq!((vte.name(), vte.version()), (None, None));
+    assert_eq!(vte.secondary_attributes, [65, 7600, 1]);
+
+    // Incomplete until the primary attributes are answered.
+    assert_eq!(
+        query::parse_answers(b"\x1bP>|foot(1.16.2)\x1b\\\x1b[>1;11602;0c"),
+        None
+    );
+    assert_eq!(query::parse_answers(b"\x1b[?62;2"), None);
+}
+
+#[test]
+fn flatpak_launches_run_on_the_host() {
+    let spec = CommandSpec::new("kitty")
+        .args(["--", "vim", "notes.txt"])
+        .current_dir("/home/user/notes")
+        .env("DISPLAY", ":0")
+        .env_remove("LD_PRELOAD");
+
+    assert_eq!(
+        flatpak::host_command(spec),
+        CommandSpec::new("flatpak-spawn").args([
+            "--host",
+            "--directory=/home/user/notes",
+            "--env=DISPLAY=:0",
+            "--unset-env=LD_PRELOAD",
+            "kitty",
+            "--",
+            "vim",
+            "notes.txt",
+        ])
+    );
+
+    assert_eq!(
+        flatpak::parse_lookup(b"/usr/bin/kitty\n"),
+        Some(PathBuf::from("/usr/bin/kitty"))
+    );
+    // Aliases, functions and builtins are not executables.
+    assert_eq!(flatpak::parse_lookup(b"alias ll='ls -l'\n"), None);
+    assert_eq!(flatpak::parse_lookup(b""), None);
+
+    [
+        ("XDG_RUNTIME_DIR", "/run/user/1000"),
+        ("FLATPAK_ID", "org.example.App"),
+    ]
+    .into_iter()
+    .collect::<Environment>()
+    .scope(|| {
+        assert_eq!(
+            flatpak::shared_runtime_dir(),
+            Some(PathBuf::from("/run/user/1000/app/org.example.App"))
+        );
+    });
+
+    // Executables are looked up in the active filesystem, never on the host, when it is not the real one.
+    with_filesystem(RootFilesystem::new("/nonexistent"), || {
+        assert!(!flatpak::uses_host());
+    });
+}
@@ -0,0 +1,173 @@
+//! Terminal emulator versions with known launch bugs.
+//!
+//! Some releases of terminal emulators can not run commands the way the hardcoded lists expect: gnome-terminal
+//! deprecated `-e` in 3.22, printing "Option “-e” is deprecated" and expecting the command after `--`, which older
+//! releases do not understand. [`BROKEN_VERSIONS`] lists them with their workaround, an execution syntax that works, or
+//! avoids them altogether.
+//!
+//! The version is read from `<terminal> --version` (e.g. `GNOME Terminal 3.46.8 using VTE 0.70.6`), only for the
+//! terminal emulators listed, and remembered for each executable during the current process.
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+    time::Duration,
+};
+
+use crate::{CommandSpec, ExecutionSyntax, TerminalEmulator, lookup::executable_name};
+
+/// The terminal emulator releases with known launch bugs.
+const BROKEN_VERSIONS: &[BrokenVersions] = &[BrokenVersions {
+    terminal: "gnome-terminal",
+    from: Version(0, 0, 0),
+    until: Version(3, 22, 0),
+    #[cfg(not(windows))]
+    problem: "does not understand `--`",
+    workaround: Some(ExecutionSyntax::SingleE),
+}];
+
+/// How long `--version` may take before the version is considered unknown.
+const VERSION_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The versions read during the current process, by executable path.
+static VERSIONS: Mutex<BTreeMap<PathBuf, Option<Version>>> = Mutex::new(BTreeMap::new());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+/// The `major.minor.patch` version of a terminal emulator.
+pub(crate) struct Version(pub(crate) u32, pub(crate) u32, pub(crate) u32);
+
+impl Display for Version {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.0, self.1, self.2)
+    }
+}
+
+/// A range of releases of a terminal emulator with a known launch bug.
+struct BrokenVersions {
+    /// The name of the terminal emulator.
+    terminal: &'static str,
+    /// The first broken release.
+    from: Version,
+    /// The first release that is no longer broken.
+    until: Version,
+    /// The bug, following the name and version of the terminal emulator in notes.
+    #[cfg(not(windows))]
+    problem: &'static str,
+    /// The execution syntax working around the bug, or `None` if the releases are avoided.
+    workaround: Option<ExecutionSyntax>,
+}
+
+/// Locks the read versions, recovering them if a thread panicked while holding them.
+fn versions() -> MutexGuard<'static, BTreeMap<PathBuf, Option<Version>>> {
+    VERSIONS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Returns the known launch bug of the terminal emulator at `path` with its installed version, if it has one.
+///
+/// Wrappers (e.g. `gnome-terminal.wrapper`) have another executable name and translate the command themselves, they
+/// are not affected.
+fn broken_version(path: &Path) -> Option<(&'static BrokenVersions, Version)> {
+    // The executables of another system are never run.
+    if crate::filesystem::is_offline() {
+        return None;
+    }
+
+    let name = executable_name(path)?;
+    let mut entries = BROKEN_VERSIONS
+        .iter()
+        .filter(|entry| crate::lookup::same_name(entry.terminal, name))
+        .peekable();
+
+    entries.peek()?;
+
+    let cached = versions().get(path).copied();
+    let version = cached.unwrap_or_else(|| {
+        let version = read_version(path);
+        versions().insert(path.to_owned(), version);
+        version
+    })?;
+
+    entries
+        .find(|entry| (entry.from..entry.until).contains(&version))
+        .map(|entry| (entry, version))
+}
+
+/// Returns the execution syntax `terminal` expects in its installed version.
+///
+/// Releases with a known launch bug use the execution syntax working around it (e.g. gnome-terminal releases older
+/// than 3.22 take the command as a single `-e` argument instead of after `--`). The execution syntax of the other
+/// terminal emulators, or if the version is unknown, is unchanged.
+pub(crate) fn execution_syntax(terminal: &TerminalEmulator) -> ExecutionSyntax {
+    broken_version(&terminal.path)
+        .and_then(|(entry, _)| entry.workaround)
+        .unwrap_or(terminal.execution_syntax)
+}
+
+/// Returns `true` if the terminal emulator at `path` is a release to avoid, having no workaround for its launch bug.
+pub(crate) fn is_avoided(path: &Path) -> bool {
+    broken_version(path).is_some_and(|(entry, _)| entry.workaround.is_none())
+}
+
+/// Describes the known launch bug of `terminal` and how it is handled, if its version has one.
+#[cfg(not(windows))]
+pub(crate) fn note(terminal: &TerminalEmulator) -> Option<String> {
+    let (entry, version) = broken_version(&terminal.path)?;
+    let handling = match entry.workaround {
+        Some(syntax) => format!("using `{syntax}` instead"),
+        None => String::from("avoided"),
+    };
+
+    Some(format!(
+        "{} {version} {}, {handling}",
+        entry.terminal, entry.problem
+    ))
+}
+
+/// Runs `path --version`, returning the version it prints, or `None` if it fails or does not exit in time.
+fn read_version(path: &Path) -> Option<Version> {
+    let output = CommandSpec::new(path)
+        .arg("--version")
+        .output_within(VERSION_TIMEOUT)?;
+
+    parse_version(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parses the output of `--version`, taking the first word that looks like a version (e.g.
+/// `alacritty 0.13.2 (bb8ea18e)`).
+///
+/// gnome-terminal prints its version after `GNOME Terminal`, falling back to the version of VTE it was built with, as
+/// VTE 0.x releases ship with gnome-terminal 3.(x - 24) (e.g. `# GNOME Terminal 3.46.8 using VTE 0.70.6 +BIDI`).
+pub(crate) fn parse_version(output: &str) -> Option<Version> {
+    let version_after = |marker: &str| {
+        let (_, rest) = output.split_once(marker)?;
+
+        parse_word(rest.split_whitespace().next()?)
+    };
+
+    if output.contains("GNOME Terminal") {
+        return version_after("GNOME Terminal ").or_else(|| {
+            let Version(_, minor, _) = version_after("VTE ")?;
+
+            Some(Version(3, minor.checked_sub(24)?, 0))
+        });
+    }
+
+    output.split_whitespace().find_map(parse_word)
+}
+
+/// Parses a version word, `major.minor` followed by an optional `.patch` (e.g. `1.16.2`).
+fn parse_word(word: &str) -> Option<Version> {
+    let mut numbers = word.split(['.', '-']);
+    let major = numbers.next()?.parse().ok()?;
+    let minor = numbers.next()?.parse().ok()?;
+    let patch = numbers
+        .next()
+        .and_then(|patch| patch.parse().ok())
+        .unwrap_or(0);
+
+    Some(Version(major, minor, patch))
+}
@@ -0,0 +1,200 @@
+//! In-memory cache of the detected terminal emulator, invalidated by watching the settings directories.
+//!
+//! On Linux, the settings directories are watched with inotify from a background thread, and every change clears the
+//! cached results. Other platforms compare the fingerprint of the settings files (see [`crate::cache`]) on every
+//! lookup instead.
+
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    sync::{
+        Mutex, MutexGuard,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use crate::TerminalEmulator;
+
+/// A cached detection result.
+struct Entry {
+    /// The generation of the cache when the detection started.
+    generation: u64,
+    /// The fingerprint of the settings files when the detection ran.
+    fingerprint: u64,
+    /// The detected terminal emulator.
+    terminal: TerminalEmulator,
+}
+
+/// The cached detection results, by fingerprint of the environment variables.
+static CACHE: Mutex<BTreeMap<u64, Entry>> = Mutex::new(BTreeMap::new());
+
+/// Incremented whenever a settings directory changes.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Locks the cached results, recovering them if a thread panicked while holding them.
+fn cache() -> MutexGuard<'static, BTreeMap<u64, Entry>> {
+    CACHE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Returns the current generation of the cache, to be passed to [`store`] after detecting.
+pub(crate) fn generation() -> u64 {
+    GENERATION.load(Ordering::SeqCst)
+}
+
+/// Clears the cached results.
+fn invalidate() {
+    GENERATION.fetch_add(1, Ordering::SeqCst);
+    cache().clear();
+}
+
+/// Returns the cached terminal emulator detected with the environment fingerprinted as `key`.
+///
+/// Without a working watcher, the result is only returned if `fingerprint` still matches the settings files.
+pub(crate) fn lookup(key: u64, fingerprint: fn() -> u64) -> Option<TerminalEmulator> {
+    let cache = cache();
+    let entry = cache.get(&key)?;
+
+    if !watcher::active() && entry.fingerprint != fingerprint() {
+        return None;
+    }
+
+    Some(entry.terminal.clone())
+}
+
+/// Caches the terminal emulator detected with the environment fingerprinted as `key`.
+///
+/// Ignored if a settings directory changed since `generation` was read, as the result may be outdated.
+pub(crate) fn store(key: u64, generation: u64, fingerprint: u64, terminal: &TerminalEmulator) {
+    let mut cache = cache();
+
+    if GENERATION.load(Ordering::SeqCst) == generation {
+        cache.insert(
+            key,
+            Entry {
+                generation,
+                fingerprint,
+                terminal: terminal.clone(),
+            },
+        );
+    }
+
+    cache.retain(|_, entry| entry.generation == generation);
+}
+
+/// Watches `dirs` for changes, skipping the ones that do not exist or are already watched.
+pub(crate) fn watch(dirs: &[PathBuf]) {
+    watcher::watch(dirs);
+}
+
+#[cfg(target_os = "linux")]
+mod watcher {
+    use std::{
+        collections::BTreeSet,
+        ffi::{CString, c_char, c_int},
+        fs::File,
+        io::Read,
+        os::{fd::FromRawFd, unix::ffi::OsStrExt},
+        path::PathBuf,
+        sync::{
+            Mutex, OnceLock,
+            atomic::{AtomicBool, Ordering},
+        },
+        thread,
+    };
+
+    /// `IN_CLOEXEC`, closes the inotify descriptor on `exec`.
+    const IN_CLOEXEC: c_int = 0o2_000_000;
+
+    /// The events signaling a change of a settings file: modified, attributes changed, closed after writing, moved
+    /// in or out, created, deleted, and the watched directory itself deleted or moved.
+    const IN_CHANGES: u32 = 0x2 | 0x4 | 0x8 | 0x40 | 0x80 | 0x100 | 0x200 | 0x400 | 0x800;
+
+    unsafe extern "C" {
+        fn inotify_init1(flags: c_int) -> c_int;
+        fn inotify_add_watch(fd: c_int, pathname: *const c_char, mask: u32) -> c_int;
+    }
+
+    /// The inotify descriptor and the watched directories, or `None` if inotify is not available.
+    static WATCHER: OnceLock<Option<(c_int, Mutex<BTreeSet<PathBuf>>)>> = OnceLock::new();
+
+    /// Whether the watcher thread is reading events.
+    static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+    /// Returns `true` if changes of the watched directories are being reported.
+    pub(super) fn active() -> bool {
+        ACTIVE.load(Ordering::SeqCst)
+    }
+
+    /// Starts the watcher thread on first use and adds watches for `dirs`.
+    pub(super) fn watch(dirs: &[PathBuf]) {
+        // The descriptor is closed once the watcher thread stopped.
+        let Some((fd, watched)) = WATCHER.get_or_init(start).as_ref().filter(|_| active()) else {
+            return;
+        };
+        let mut watched = watched
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        for dir in dirs {
+            if watched.contains(dir) || !dir.is_dir() {
+                continue;
+            }
+
+            let Ok(path) = CString::new(dir.as_os_str().as_bytes()) else {
+                continue;
+            };
+
+            // SAFETY: `fd` is a valid inotify descriptor and `path` is a NUL-terminated string.
+            if unsafe { inotify_add_watch(*fd, path.as_ptr(), IN_CHANGES) } >= 0 {
+                watched.insert(dir.clone());
+            }
+        }
+    }
+
+    /// Creates the inotify descriptor and spawns the thread reading its events.
+    fn start() -> Option<(c_int, Mutex<BTreeSet<PathBuf>>)> {
+        // SAFETY: `inotify_init1` has no preconditions.
+        let fd = unsafe { inotify_init1(IN_CLOEXEC) };
+
+        if fd < 0 {
+            return None;
+        }
+
+        // SAFETY: `fd` is a valid descriptor owned by this thread, it is never closed elsewhere.
+        let mut events = unsafe { File::from_raw_fd(fd) };
+
+        ACTIVE.store(true, Ordering::SeqCst);
+
+        thread::Builder::new()
+            .name(String::from("unidosx-settings-watch"))
+            .spawn(move || {
+                let mut buffer = [0; 4096];
+
+                while events.read(&mut buffer).is_ok_and(|read| read > 0) {
+                    super::invalidate();
+                }
+
+                // Without events, the fingerprint of the settings files is compared on every lookup.
+                ACTIVE.store(false, Ordering::SeqCst);
+            })
+            .inspect_err(|_| ACTIVE.store(false, Ordering::SeqCst))
+            .ok()?;
+
+        Some((fd, Mutex::new(BTreeSet::new())))
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod watcher {
+    use std::path::PathBuf;
+
+    /// Returns `true` if changes of the watched directories are being reported, never on this platform.
+    pub(super) fn active() -> bool {
+        false
+    }
+
+    /// Does nothing, the fingerprint of the settings files is compared on every lookup instead.
+    pub(super) fn watch(_dirs: &[PathBuf]) {}
+}
@@ -0,0 +1,100 @@
+//! First-run selection of the default terminal emulator.
+//!
+//! [`first_run`] asks the user to choose among the installed terminal emulators when no default terminal emulator is
+//! configured, and saves the choice in the configuration file of this crate. Choices are presented through the
+//! [`TerminalPrompt`] trait, so GUI applications can provide their own picker instead of [`StdinPrompt`].
+
+use std::io::{self, BufRead, Write};
+
+use crate::{TerminalEmulator, probe_config_file, set_default_terminal};
+
+/// Presents the installed terminal emulators to the user and returns the chosen one.
+pub trait TerminalPrompt {
+    /// Asks the user to choose one of `choices`, which is never empty.
+    ///
+    /// Returns the index of the chosen terminal emulator, or `None` if the user declined to choose one.
+    fn choose(&mut self, choices: &[TerminalEmulator]) -> io::Result<Option<usize>>;
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+/// Prompts on the standard error output and reads the answer from the standard input.
+pub struct StdinPrompt;
+
+impl TerminalPrompt for StdinPrompt {
+    fn choose(&mut self, choices: &[TerminalEmulator]) -> io::Result<Option<usize>> {
+        prompt(io::stdin().lock(), io::stderr().lock(), choices)
+    }
+}
+
+/// Returns the default terminal emulator, asking the user to choose one through `prompt` if none is configured.
+///
+/// If neither the user nor the system-wide configuration file of this crate sets an installed terminal emulator, every terminal emulator found
+/// by the detection methods is presented, and the chosen one is saved with [`set_default_terminal`].
+///
+/// Returns `None` without prompting if no terminal emulator is installed, or if the user declined to choose one.
+pub fn first_run<P: TerminalPrompt + ?Sized>(
+    prompt: &mut P,
+) -> io::Result<Option<TerminalEmulator>> {
+    if let Some(terminal) = probe_config_file() {
+        return Ok(Some(terminal));
+    }
+
+    let choices = crate::selection::installed_terminals();
+
+    if choices.is_empty() {
+        return Ok(None);
+    }
+
+    let Some(terminal) = prompt
+        .choose(&choices)?
+        .and_then(|index| choices.get(index))
+    else {
+        return Ok(None);
+    };
+
+    set_default_terminal(terminal)?;
+
+    Ok(Some(terminal.clone()))
+}
+
+/// Lists `choices` on `output` and reads the number of the chosen one from `input`, asking again on invalid answers.
+///
+/// An empty answer or the end of `input` declines to choose.
+pub(crate) fn prompt<R: BufRead, W: Write>(
+    mut input: R,
+    mut output: W,
+    choices: &[TerminalEmulator],
+) -> io::Result<Option<usize>> {
+    writeln!(output, "Choose the default terminal emulator:")?;
+
+    for (number, terminal) in choices.iter().enumerate().map(|(i, t)| (i + 1, t)) {
+        writeln!(
+            output,
+            "  {number}) {} ({})",
+            terminal.display_name(),
+            terminal.path.display()
+        )?;
+    }
+
+    loop {
+        write!(output, "Terminal emulator [1-{}]: ", choices.len())?;
+        output.flush()?;
+
+        let mut answer = String::new();
+
+        if input.read_line(&mut answer)? == 0 {
+            return Ok(None);
+        }
+
+        let answer = answer.trim();
+
+        if answer.is_empty() {
+            return Ok(None);
+        }
+
+        match answer.parse::<usize>() {
+            Ok(number) if (1..=choices.len()).contains(&number) => return Ok(Some(number - 1)),
+            _ => writeln!(output, "Invalid choice: {answer}")?,
+        }
+    }
+}
@@ -0,0 +1,77 @@
+//! XDG base directory resolution.
+//!
+//! Missing, empty or relative values are ignored as required by the XDG Base Directory specification, so minimal
+//! environments without `HOME` or any `XDG_*` variable are skipped cleanly.
+
+use std::{ffi::OsString, path::PathBuf};
+
+use crate::environment;
+
+/// Returns the user configuration directory (`$XDG_CONFIG_HOME`, or `$HOME/.config`).
+pub(crate) fn config_home() -> Option<PathBuf> {
+    config_home_from(
+        environment::var_os("XDG_CONFIG_HOME"),
+        environment::var_os("HOME"),
+    )
+}
+
+/// Resolves the user configuration directory from the values of `XDG_CONFIG_HOME` and `HOME`.
+pub(crate) fn config_home_from(
+    xdg_config_home: Option<OsString>,
+    home: Option<OsString>,
+) -> Option<PathBuf> {
+    absolute(xdg_config_home).or_else(|| absolute(home).map(|home| home.join(".config")))
+}
+
+/// Returns the user and system configuration directories in order of preference (the user configuration directory,
+/// followed by `$XDG_CONFIG_DIRS`, or `/etc/xdg`).
+pub(crate) fn config_dirs() -> Vec<PathBuf> {
+    config_home()
+        .into_iter()
+        .chain(search_path("XDG_CONFIG_DIRS", "/etc/xdg"))
+        .collect()
+}
+
+/// Returns the user data directory (`$XDG_DATA_HOME`, or `$HOME/.local/share`).
+pub(crate) fn data_home() -> Option<PathBuf> {
+    absolute(environment::var_os("XDG_DATA_HOME"))
+        .or_else(|| absolute(environment::var_os("HOME")).map(|home| home.join(".local/share")))
+}
+
+/// Returns the user cache directory (`$XDG_CACHE_HOME`, or `$HOME/.cache`).
+#[cfg(not(windows))]
+pub(crate) fn cache_home() -> Option<PathBuf> {
+    absolute(environment::var_os("XDG_CACHE_HOME"))
+        .or_else(|| absolute(environment::var_os("HOME")).map(|home| home.join(".cache")))
+}
+
+/// Returns the user state directory (`$XDG_STATE_HOME`, or `$HOME/.local/state`).
+pub(crate) fn state_home() -> Option<PathBuf> {
+    absolute(environment::var_os("XDG_STATE_HOME"))
+        .or_else(|| absolute(environment::var_os("HOME")).map(|home| home.join(".local/state")))
+}
+
+/// Returns the user and system data directories in order of preference (the user data directory, followed by
+/// `$XDG_DATA_DIRS`, or `/usr/local/share:/usr/share`).
+pub(crate) fn data_dirs() -> Vec<PathBuf> {
+    data_home()
+        .into_iter()
+        .chain(search_path("XDG_DATA_DIRS", "/usr/local/share:/usr/share"))
+        .collect()
+}
+
+/// Returns the absolute directories listed in a search path variable, or in `default` if it is missing or empty.
+fn search_path(var: &str, default: &str) -> Vec<PathBuf> {
+    let dirs = environment::var_os(var)
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| default.into());
+
+    std::env::split_paths(&dirs)
+        .filter(|dir| dir.is_absolute())
+        .collect()
+}
+
+/// Converts an environment variable value into a path, ignoring missing, empty and relative values.
+pub(crate) fn absolute(value: Option<OsString>) -> Option<PathBuf> {
+    value.map(PathBuf::from).filter(|path| path.is_absolute())
+}